@@ -0,0 +1,107 @@
+use criterion::{BatchSize, criterion_group, criterion_main, Criterion};
+use rust_orca::context::Context;
+use rust_orca::operators::{
+    get_bang_operators, get_tick_operators, grid_tick, OperatorTable, default_operator_config,
+};
+
+const ROWS: usize = 200;
+const COLS: usize = 200;
+
+// a small patch of clocks, arithmetic, movement, randomness, comments and midi
+const MIXED: [&str; 6] = [
+    "D8.C4...",
+    ".*.1..R.",
+    "1A2.E...",
+    ".3......",
+    "#.comment.#.",
+    "4M5..:03C..",
+];
+
+// chains of arithmetic fed by clocks, with every other row left free for outputs
+const ARITHMETIC: [&str; 4] = [
+    "C4.3C8.1Cz..",
+    "",
+    "1A2.3M4.5B6.",
+    "",
+];
+
+// a full-width comment on every row, which locks and walks the whole row each tick
+const COMMENTS: [&str; 1] = [
+    "#.long.comment.that.runs.across.the.whole.grid.and.keeps.going.until.the.closing.marker.at.the\
+    .far.end.of.the.row.......................................................................#",
+];
+
+const MOVEMENT: [&str; 4] = [
+    "E...W...",
+    "........",
+    "..S...N.",
+    "........",
+];
+
+// a midi cell banged every tick by the delay above it
+const MIDI: [&str; 3] = [
+    "D1....",
+    ".:03C.",
+    "",
+];
+
+fn tiled_context(patch: &[&str]) -> Context {
+    let mut grid = vec!['\0'; ROWS * COLS];
+    if patch.is_empty() {
+        return Context::new(grid, COLS, ROWS, 120, 4);
+    }
+    let patch_rows = patch.len();
+    let patch_cols = patch.iter().map(|line| line.len()).max().unwrap_or(0) + 1;
+    for base_row in (0..=ROWS - patch_rows).step_by(patch_rows) {
+        for base_col in (0..=COLS.saturating_sub(patch_cols)).step_by(patch_cols) {
+            for (r, line) in patch.iter().enumerate() {
+                for (c, value) in line.chars().enumerate().take(COLS) {
+                    if value != '.' {
+                        grid[(base_row + r) * COLS + base_col + c] = value;
+                    }
+                }
+            }
+        }
+    }
+    Context::new(grid, COLS, ROWS, 120, 4)
+}
+
+fn bench_steady_state(
+    criterion: &mut Criterion,
+    name: &str,
+    patch: &[&str],
+    tick_operators: &OperatorTable,
+    bang_operators: &OperatorTable,
+) {
+    let mut context = tiled_context(patch);
+    criterion.bench_function(name, |bencher| bencher.iter(|| {
+        grid_tick(&mut context, tick_operators, bang_operators);
+        context.notes.clear();
+    }));
+}
+
+fn bench_grid_tick(criterion: &mut Criterion) {
+    // a config file that does not exist falls back to the default operator layout
+    let operator_map = default_operator_config();
+    let tick_operators = get_tick_operators(&operator_map);
+    let bang_operators = get_bang_operators(&operator_map);
+
+    bench_steady_state(criterion, "grid_tick 200x200 mixed", &MIXED, &tick_operators, &bang_operators);
+    bench_steady_state(criterion, "grid_tick 200x200 arithmetic", &ARITHMETIC, &tick_operators, &bang_operators);
+    bench_steady_state(criterion, "grid_tick 200x200 comments", &COMMENTS, &tick_operators, &bang_operators);
+    bench_steady_state(criterion, "grid_tick 200x200 midi", &MIDI, &tick_operators, &bang_operators);
+    bench_steady_state(criterion, "grid_tick 200x200 empty", &[], &tick_operators, &bang_operators);
+
+    // movers pile up against the edges after a few hundred ticks, so measure the first ticks of a
+    // fresh grid instead of the steady state
+    criterion.bench_function("grid_tick 200x200 movement", |bencher| bencher.iter_batched_ref(
+        || tiled_context(&MOVEMENT),
+        |context| for _ in 0..8 {
+            grid_tick(context, &tick_operators, &bang_operators);
+        },
+        BatchSize::LargeInput,
+    ));
+}
+
+criterion_group!(benches, bench_grid_tick);
+criterion_main!(benches);
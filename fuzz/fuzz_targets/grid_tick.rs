@@ -0,0 +1,43 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_orca::{Context, MidiCapture, default_operator_config, get_bang_operators, get_tick_operators, parse_grid, tick};
+
+// big enough for operators to reach past every edge, small enough to tick quickly
+const MAX_SIZE: usize = 24;
+const MAX_TICKS: usize = 16;
+
+// The first four bytes size the grid, count the ticks and seed the random operators, and the rest
+// is the grid's .orca text.
+fuzz_target!(|data: &[u8]| {
+    let [rows, cols, ticks, seed, text @ ..] = data else {
+        return;
+    };
+    let (rows, cols) = (*rows as usize % MAX_SIZE + 1, *cols as usize % MAX_SIZE + 1);
+    let operator_map = default_operator_config();
+    let (tick_operators, bang_operators) = (get_tick_operators(&operator_map), get_bang_operators(&operator_map));
+    let mut context = Context::new(parse_grid(&String::from_utf8_lossy(text), rows, cols), cols, rows, 120, 4);
+    context.reseed(*seed as u64);
+    let mut capture = MidiCapture::default();
+    for _ in 0..*ticks as usize % MAX_TICKS + 1 {
+        tick(&mut context, &tick_operators, &bang_operators, &mut capture);
+        check_grid(&context, rows, cols);
+    }
+    for (_, [_, note, velocity]) in &capture.messages {
+        assert!(*note <= 127 && *velocity <= 127, "note {} at velocity {} is outside MIDI", note, velocity);
+    }
+});
+
+/// Checks that every write landed inside the grid and kept the index of occupied cells in step.
+fn check_grid(context: &Context, rows: usize, cols: usize) {
+    assert_eq!((context.height, context.width, context.grid().len()), (rows, cols, rows * cols));
+    let mut occupied = Vec::new();
+    let mut index = 0;
+    while let Some((row, col)) = context.next_occupied(index) {
+        index = row as usize * cols + col as usize;
+        occupied.push(index);
+        index += 1;
+    }
+    let expected: Vec<usize> = (0..rows * cols).filter(|&index| context.grid()[index] != '\0').collect();
+    assert_eq!(occupied, expected, "the occupied cells are out of step with the grid");
+}
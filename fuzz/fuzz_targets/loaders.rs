@@ -0,0 +1,51 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_orca::fs::{parse_block, parse_header, text_size};
+use rust_orca::replay::{Replayer, parse_session};
+use rust_orca::validate::validate;
+use rust_orca::{
+    Context, MidiCapture, default_operator_config, get_bang_operators, get_tick_operators, parse_grid,
+    parse_operator_config, tick,
+};
+
+// grids bigger than this are only parsed, not run, to keep each input quick
+const MAX_RUN_CELLS: usize = 64 * 64;
+const TICKS: usize = 8;
+
+// The same text goes through every loader: as a grid with or without a header, as an operator
+// config and as a recorded session. Whatever loads is then run for a few ticks.
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    let default_map = default_operator_config();
+
+    let block = parse_block(&text);
+    assert_eq!(block.cells.len(), block.rows * block.cols);
+    let (cols, rows) = parse_header(&text).map_or(text_size(&text), |header| (header.cols, header.rows));
+    if rows * cols <= MAX_RUN_CELLS {
+        // an operator config that loads is run against the text as a grid, symbols and all
+        let operator_map = parse_operator_config(&text).unwrap_or_else(|_| default_map.clone());
+        let (tick_operators, bang_operators) = (get_tick_operators(&operator_map), get_bang_operators(&operator_map));
+        let mut context = Context::new(parse_grid(&text, rows, cols), cols, rows, 120, 4);
+        validate(&context, &tick_operators, &bang_operators);
+        let mut capture = MidiCapture::default();
+        for _ in 0..TICKS {
+            tick(&mut context, &tick_operators, &bang_operators, &mut capture);
+        }
+        assert_eq!(context.grid().len(), rows * cols);
+    }
+
+    if let Ok(session) = parse_session(&text) {
+        assert_eq!(session.grid.len(), session.rows * session.cols);
+        if session.rows * session.cols <= MAX_RUN_CELLS {
+            let (tick_operators, bang_operators) = (get_tick_operators(&default_map), get_bang_operators(&default_map));
+            let mut context = session.to_context(4);
+            let mut replayer = Replayer::new(session);
+            let mut capture = MidiCapture::default();
+            for _ in 0..TICKS {
+                replayer.before_tick(&mut context);
+                tick(&mut context, &tick_operators, &bang_operators, &mut capture);
+            }
+        }
+    }
+});
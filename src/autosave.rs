@@ -0,0 +1,91 @@
+use std::fs::{read_dir, remove_file};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::{JoinHandle, sleep};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rust_orca::context::Context;
+use rust_orca::fs::write_grid;
+use rust_orca::watch::Watched;
+
+// the name an unsaved grid is kept under, in the working directory
+const UNTITLED: &str = "untitled";
+
+/// The name an unsaved grid in the pane numbered `pane` from 0 is kept under, so that untitled
+/// panes side by side don't write over each other.
+pub fn untitled_path(pane: usize) -> PathBuf {
+    match pane {
+        0 => PathBuf::from(format!("{}.orca", UNTITLED)),
+        pane => PathBuf::from(format!("{}-{}.orca", UNTITLED, pane + 1)),
+    }
+}
+
+/// Where the grid from `path` in the pane numbered `pane` is autosaved: a sidecar file next to it,
+/// named after it.
+pub fn autosave_path(path: Option<&Path>, pane: usize) -> PathBuf {
+    let mut autosave = path.map_or_else(|| untitled_path(pane), Path::to_path_buf).into_os_string();
+    autosave.push(".autosave");
+    PathBuf::from(autosave)
+}
+
+/// Every `interval`, writes the shared context's grid in the pane numbered `pane` to the autosave
+/// file for whichever file `watched` names at the time, along with a backup stamped with the time in seconds. Only the
+/// newest `backups` backups are kept. Nothing is written while the grid is unchanged since the
+/// last autosave, so a paused grid does not churn through its backups.
+pub fn autosave(
+    watched: Arc<Mutex<Watched>>,
+    context_arc: Arc<Mutex<Context>>,
+    pane: usize,
+    interval: Duration,
+    backups: usize,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut last_saved = Vec::new();
+        loop {
+            sleep(interval);
+            let (grid, cols) = {
+                let context = context_arc.lock().unwrap();
+                (context.grid().to_vec(), context.width)
+            };
+            if grid == last_saved {
+                continue;
+            }
+            let autosave = autosave_path(watched.lock().unwrap().path.as_deref(), pane);
+            let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+            let mut backup = autosave.as_os_str().to_owned();
+            backup.push(format!(".{}", seconds));
+            // a failed autosave is retried on the next interval rather than interrupting anyone
+            if write_grid(&autosave, &grid, cols).is_ok() && write_grid(Path::new(&backup), &grid, cols).is_ok() {
+                remove_old_backups(&autosave, backups);
+                last_saved = grid;
+            }
+        }
+    })
+}
+
+/// Deletes all but the newest `keep` timestamped backups of `autosave`.
+fn remove_old_backups(autosave: &Path, keep: usize) {
+    let Some(name) = autosave.file_name().and_then(|name| name.to_str()) else {
+        return;
+    };
+    let directory = match autosave.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let prefix = format!("{}.", name);
+    let Ok(entries) = read_dir(directory) else {
+        return;
+    };
+    let mut backups: Vec<(u64, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let seconds = entry.file_name().to_str()?.strip_prefix(&prefix)?.parse().ok()?;
+            Some((seconds, entry.path()))
+        })
+        .collect();
+    backups.sort();
+    for (_, backup) in backups.iter().rev().skip(keep) {
+        let _ = remove_file(backup);
+    }
+}
@@ -0,0 +1,152 @@
+//! Runs grids inside a [Bevy](https://bevyengine.org) app, for sequencing generative visuals and
+//! games with Orca.
+//!
+//! Add [`OrcaPlugin`] along with Bevy's time plugin and insert an [`Orca`] resource holding the
+//! grid. The grid then ticks at its own tempo in `Update`, and everything it plays comes out as
+//! [`OrcaEvent`] messages for any system to read. Systems that react to a tick in the same frame
+//! go after [`OrcaTick`].
+//!
+//! ```no_run
+//! use bevy_app::{App, Update};
+//! use bevy_ecs::prelude::*;
+//! use rust_orca::bevy_orca::{Orca, OrcaEvent, OrcaPlugin, OrcaTick};
+//!
+//! fn flash(mut events: MessageReader<OrcaEvent>) {
+//!     for event in events.read() {
+//!         if let OrcaEvent::NoteOn { note, .. } = event {
+//!             println!("flash {}", note);
+//!         }
+//!     }
+//! }
+//!
+//! App::new()
+//!     .add_plugins((bevy_time::TimePlugin, OrcaPlugin))
+//!     .insert_resource(Orca::from_text("D4..\n.:03", 120))
+//!     .add_systems(Update, flash.after(OrcaTick))
+//!     .run();
+//! ```
+
+use core::time::Duration;
+
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_time::Time;
+
+use crate::context::Context;
+use crate::engine::tick;
+use crate::fs::{parse_grid, text_size};
+use crate::midi::MidiCapture;
+use crate::operators::{OperatorTable, default_operator_config, get_bang_operators, get_tick_operators};
+
+// how many ticks a slow frame catches up on before the rest are dropped, so that a long stall
+// doesn't come out as a burst of notes
+const MAX_CATCH_UP: usize = 8;
+
+/// A grid and the operators it runs with, ticking as frames go by.
+#[derive(Resource)]
+pub struct Orca {
+    pub context: Context,
+    pub tick_operators: OperatorTable,
+    pub bang_operators: OperatorTable,
+    /// Holds the grid still; notes already sounding are left to their own note offs.
+    pub paused: bool,
+    /// Time since the last tick.
+    elapsed: Duration,
+    capture: MidiCapture,
+}
+
+impl Orca {
+    /// Runs `context` with the default symbols.
+    pub fn new(context: Context) -> Orca {
+        let operator_map = default_operator_config();
+        Orca {
+            context,
+            tick_operators: get_tick_operators(&operator_map),
+            bang_operators: get_bang_operators(&operator_map),
+            paused: false,
+            elapsed: Duration::ZERO,
+            capture: MidiCapture::default(),
+        }
+    }
+
+    /// Runs .orca text at the size of the text and `bpm` beats per minute, with the default
+    /// symbols.
+    pub fn from_text(text: &str, bpm: u64) -> Orca {
+        let (cols, rows) = text_size(text);
+        Orca::new(Context::new(parse_grid(text, rows, cols), cols, rows, bpm, 4))
+    }
+
+    /// Runs one tick now, whether or not one is due, returning what it played.
+    pub fn tick(&mut self) -> Vec<OrcaEvent> {
+        let ticks = self.context.ticks;
+        self.capture.tick = ticks;
+        tick(&mut self.context, &self.tick_operators, &self.bang_operators, &mut self.capture);
+        let played = self.capture.messages.drain(..).map(|(_, message)| OrcaEvent::from_midi(message));
+        core::iter::once(OrcaEvent::Tick(ticks)).chain(played).collect()
+    }
+
+    /// How long a tick lasts at the grid's tempo.
+    fn tick_length(&self) -> Duration {
+        Duration::from_secs_f64(60.0 / (self.context.tempo * self.context.divisions) as f64)
+    }
+}
+
+/// Something a grid did on a tick. Every tick starts with a `Tick`, followed by the MIDI it
+/// played, with channels counted from 0.
+#[derive(Message, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrcaEvent {
+    /// The grid ran the tick with this number.
+    Tick(usize),
+    NoteOn { channel: u8, note: u8, velocity: u8 },
+    NoteOff { channel: u8, note: u8 },
+    ControlChange { channel: u8, controller: u8, value: u8 },
+    /// Any other MIDI message, as it was sent.
+    Midi([u8; 3]),
+}
+
+impl OrcaEvent {
+    fn from_midi(message: [u8; 3]) -> OrcaEvent {
+        let [status, number, value] = message;
+        let channel = status & 0x0f;
+        match status & 0xf0 {
+            0x90 if value > 0 => OrcaEvent::NoteOn { channel, note: number, velocity: value },
+            // a note on with zero velocity is a note off
+            0x80 | 0x90 => OrcaEvent::NoteOff { channel, note: number },
+            0xb0 => OrcaEvent::ControlChange { channel, controller: number, value },
+            _ => OrcaEvent::Midi(message),
+        }
+    }
+}
+
+/// The system that ticks the grid, for ordering other systems against.
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OrcaTick;
+
+/// Ticks the [`Orca`] resource, if there is one, on Bevy's clock and sends what it plays as
+/// [`OrcaEvent`] messages. Needs Bevy's time plugin.
+pub struct OrcaPlugin;
+
+impl Plugin for OrcaPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<OrcaEvent>()
+            .add_systems(Update, advance.in_set(OrcaTick).run_if(resource_exists::<Orca>));
+    }
+}
+
+/// Runs whichever ticks fell due during the last frame.
+fn advance(time: Res<Time>, mut orca: ResMut<Orca>, mut events: MessageWriter<OrcaEvent>) {
+    if orca.paused {
+        return;
+    }
+    orca.elapsed += time.delta();
+    for _ in 0..MAX_CATCH_UP {
+        // the tempo can change on any tick
+        let length = orca.tick_length();
+        if orca.elapsed < length {
+            return;
+        }
+        orca.elapsed -= length;
+        events.write_batch(orca.tick());
+    }
+    orca.elapsed = Duration::ZERO;
+}
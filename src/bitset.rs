@@ -0,0 +1,46 @@
+use crate::prelude::*;
+
+/// A fixed-size set of cell indices stored one bit per cell.
+#[derive(Clone, Debug)]
+pub struct Bitset {
+    words: Vec<u64>,
+}
+
+impl Bitset {
+    pub fn new(len: usize) -> Bitset {
+        Bitset { words: vec![0; len.div_ceil(64)] }
+    }
+
+    #[inline]
+    pub fn insert(&mut self, index: usize) {
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+
+    #[inline]
+    pub fn remove(&mut self, index: usize) {
+        self.words[index / 64] &= !(1 << (index % 64));
+    }
+
+    #[inline]
+    pub fn contains(&self, index: usize) -> bool {
+        self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// Returns the smallest index in the set that is at least `start`, skipping empty words 64
+    /// indices at a time.
+    pub fn next_from(&self, start: usize) -> Option<usize> {
+        let mut word_index = start / 64;
+        let mut word = *self.words.get(word_index)? & (!0 << (start % 64));
+        loop {
+            if word != 0 {
+                return Some(word_index * 64 + word.trailing_zeros() as usize);
+            }
+            word_index += 1;
+            word = *self.words.get(word_index)?;
+        }
+    }
+}
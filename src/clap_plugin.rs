@@ -0,0 +1,606 @@
+//! Runs a grid as a [CLAP](https://cleveraudio.org) plugin inside a DAW, following the host's
+//! transport and tempo and sending what the grid plays to the host as MIDI on the plugin's note
+//! output.
+//!
+//! Build the plugin with
+//! `cargo rustc --lib --crate-type cdylib --release --no-default-features --features clap` and copy
+//! the library into the host's CLAP folder, renamed to `rust-orca.clap`. A new instance starts with
+//! the grid in the file named by the `ORCA_GRID` environment variable, or an empty grid without it,
+//! and the host keeps the grid in its project from then on. On Linux the host's plugin window shows
+//! the grid as it plays: the arrow keys or a click move the cursor, a character typed goes into the
+//! cell under it and backspace or delete empties the cell.
+//!
+//! [`HostSync`] does the timekeeping without any of the plugin API, for running grids against
+//! other hosts' clocks, and [`GridView`] the editing the window does.
+
+use core::ffi::{CStr, c_char, c_void};
+use core::ptr::null;
+use std::env;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use clap_sys::entry::clap_plugin_entry;
+use clap_sys::events::{
+    CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_MIDI, CLAP_TRANSPORT_HAS_BEATS_TIMELINE, CLAP_TRANSPORT_HAS_TEMPO,
+    CLAP_TRANSPORT_IS_PLAYING, clap_event_header, clap_event_midi, clap_event_transport,
+};
+use clap_sys::ext::gui::CLAP_EXT_GUI;
+#[cfg(target_os = "linux")]
+use clap_sys::ext::gui::{CLAP_WINDOW_API_X11, clap_gui_resize_hints, clap_plugin_gui, clap_window};
+use clap_sys::ext::note_ports::{CLAP_EXT_NOTE_PORTS, CLAP_NOTE_DIALECT_MIDI, clap_note_port_info, clap_plugin_note_ports};
+use clap_sys::ext::state::{CLAP_EXT_STATE, clap_plugin_state};
+use clap_sys::factory::plugin_factory::{CLAP_PLUGIN_FACTORY_ID, clap_plugin_factory};
+use clap_sys::fixedpoint::CLAP_BEATTIME_FACTOR;
+use clap_sys::host::clap_host;
+use clap_sys::plugin::{clap_plugin, clap_plugin_descriptor};
+use clap_sys::plugin_features::{CLAP_PLUGIN_FEATURE_NOTE_EFFECT, CLAP_PLUGIN_FEATURE_UTILITY};
+use clap_sys::process::{CLAP_PROCESS_CONTINUE, clap_process, clap_process_status};
+use clap_sys::stream::{clap_istream, clap_ostream};
+use clap_sys::version::{CLAP_VERSION, clap_version_is_compatible};
+
+#[cfg(target_os = "linux")]
+use crate::clap_window::Window;
+use crate::context::Context;
+use crate::engine::tick;
+use crate::fs::{grid_to_string, parse_grid, read_file, text_size};
+use crate::midi::MidiCapture;
+use crate::operators::{OperatorTable, default_operator_config, get_bang_operators, get_tick_operators};
+
+const EMPTY_ROWS: usize = 16;
+const EMPTY_COLS: usize = 32;
+
+/// Where the host's transport is at the start of a block of audio.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HostTransport {
+    pub playing: bool,
+    /// The song position, in beats from the start of the song.
+    pub beat: f64,
+    /// Beats per minute.
+    pub tempo: f64,
+}
+
+/// A grid ticking in step with a host's song position, one block of audio at a time. The grid's
+/// tick count follows the song position, so clocks in the grid line up with the host's bars
+/// wherever playback starts.
+pub struct HostSync {
+    pub context: Context,
+    pub tick_operators: OperatorTable,
+    pub bang_operators: OperatorTable,
+    capture: MidiCapture,
+    /// Whether the host was playing during the last block, so that its notes stop when it stops.
+    playing: bool,
+}
+
+impl HostSync {
+    /// Runs `context` with the default symbols.
+    pub fn new(context: Context) -> HostSync {
+        let operator_map = default_operator_config();
+        HostSync {
+            context,
+            tick_operators: get_tick_operators(&operator_map),
+            bang_operators: get_bang_operators(&operator_map),
+            capture: MidiCapture::default(),
+            playing: false,
+        }
+    }
+
+    /// Runs the ticks due within a block of `frames` samples at `sample_rate`, returning the MIDI
+    /// they played in order, each with the sample of the block it falls on. Notes still sounding
+    /// are stopped at the start of the first block after the host stops.
+    pub fn process(&mut self, transport: HostTransport, sample_rate: f64, frames: u32) -> Vec<(u32, [u8; 3])> {
+        if !transport.playing || transport.tempo <= 0.0 {
+            if self.playing {
+                self.stop_notes();
+                self.playing = false;
+            }
+            return self.capture.messages.drain(..).map(|(_, message)| (0, message)).collect();
+        }
+        self.playing = true;
+        let tempo = (transport.tempo.round() as u64).max(1);
+        if tempo != self.context.tempo {
+            self.context.set_tempo(tempo);
+        }
+
+        let samples_per_tick = sample_rate * 60.0 / (transport.tempo * self.context.divisions as f64);
+        let start = transport.beat * self.context.divisions as f64;
+        let end = start + frames as f64 / samples_per_tick;
+        let mut played = Vec::new();
+        // ticks before the start of the song, during a count in, don't run
+        for ticks in (start.ceil().max(0.0) as usize..).take_while(|&ticks| (ticks as f64) < end) {
+            let offset = (((ticks as f64 - start) * samples_per_tick) as u32).min(frames.saturating_sub(1));
+            self.context.ticks = ticks;
+            self.capture.tick = ticks;
+            tick(&mut self.context, &self.tick_operators, &self.bang_operators, &mut self.capture);
+            played.extend(self.capture.messages.drain(..).map(|(_, message)| (offset, message)));
+        }
+        played
+    }
+
+    /// Stops every sounding note, sending the note offs with the next block.
+    pub fn stop_notes(&mut self) {
+        for note in self.context.notes.drain(..).filter(|note| note.started) {
+            note.stop(&mut self.capture);
+        }
+    }
+
+    /// Replaces the grid with .orca text at the size of the text, keeping the tempo, unless the
+    /// text has no cells or too many.
+    fn load(&mut self, text: &str) -> bool {
+        let (cols, rows) = text_size(text);
+        let grid = parse_grid(text, rows, cols);
+        match Context::try_new(grid, cols, rows, self.context.tempo, self.context.divisions) {
+            Ok(context) if cols > 0 => {
+                self.stop_notes();
+                self.context = context;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A key the plugin's window acts on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewKey {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Erase,
+}
+
+/// The grid as the plugin's window shows it, with a cursor for editing it from the keyboard.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GridView {
+    pub row: usize,
+    pub col: usize,
+}
+
+impl GridView {
+    /// Moves the cursor, which stays inside the grid, or writes to the cell under it. A `.` typed
+    /// empties the cell, as erasing does.
+    pub fn press(&mut self, key: ViewKey, context: &mut Context) {
+        match key {
+            ViewKey::Up => self.row = self.row.saturating_sub(1),
+            ViewKey::Down => self.row += 1,
+            ViewKey::Left => self.col = self.col.saturating_sub(1),
+            ViewKey::Right => self.col += 1,
+            ViewKey::Char('.') | ViewKey::Erase => context.edit(self.row as i32, self.col as i32, '\0'),
+            ViewKey::Char(c) => context.edit(self.row as i32, self.col as i32, c),
+        }
+        self.clamp(context);
+    }
+
+    /// Puts the cursor on the cell nearest to `(row, col)`.
+    pub fn click(&mut self, row: usize, col: usize, context: &Context) {
+        (self.row, self.col) = (row, col);
+        self.clamp(context);
+    }
+
+    /// Keeps the cursor inside a grid that may have shrunk since it last moved.
+    pub fn clamp(&mut self, context: &Context) {
+        self.row = self.row.min(context.height.saturating_sub(1));
+        self.col = self.col.min(context.width.saturating_sub(1));
+    }
+
+    /// The rows of the grid as they are drawn, with `.` for empty cells.
+    pub fn lines(&self, context: &Context) -> Vec<String> {
+        grid_to_string(context.grid(), context.width).lines().map(str::to_string).collect()
+    }
+}
+
+/// One instance of the plugin, reached from the host through `clap.plugin_data`.
+struct Plugin {
+    clap: clap_plugin,
+    state: Arc<Mutex<PluginState>>,
+    /// The window the grid is drawn in, while the host has one open.
+    #[cfg(target_os = "linux")]
+    window: Mutex<Option<Window>>,
+}
+
+pub(crate) struct PluginState {
+    pub(crate) sync: HostSync,
+    sample_rate: f64,
+}
+
+/// The plugin behind a pointer the host passes back.
+///
+/// # Safety
+///
+/// `clap` must be a plugin made by `create_plugin` that has not been destroyed.
+unsafe fn plugin<'a>(clap: *const clap_plugin) -> &'a Plugin {
+    unsafe { &*((*clap).plugin_data as *const Plugin) }
+}
+
+/// The starting grid: the file named by `ORCA_GRID`, or an empty grid.
+fn initial_context() -> Context {
+    let text = env::var_os("ORCA_GRID").and_then(|path| read_file(Path::new(&path)).ok());
+    match text {
+        Some(text) if text_size(&text).0 > 0 => {
+            let (cols, rows) = text_size(&text);
+            Context::try_new(parse_grid(&text, rows, cols), cols, rows, 120, 4).ok()
+        }
+        _ => None,
+    }
+    .unwrap_or_else(|| Context::new(vec!['\0'; EMPTY_ROWS * EMPTY_COLS], EMPTY_COLS, EMPTY_ROWS, 120, 4))
+}
+
+struct Features([*const c_char; 3]);
+
+// the pointers are to static strings, which any thread can read
+unsafe impl Sync for Features {}
+
+static FEATURES: Features =
+    Features([CLAP_PLUGIN_FEATURE_NOTE_EFFECT.as_ptr(), CLAP_PLUGIN_FEATURE_UTILITY.as_ptr(), null()]);
+
+static DESCRIPTOR: clap_plugin_descriptor = clap_plugin_descriptor {
+    clap_version: CLAP_VERSION,
+    id: c"com.github.kpberry.rust-orca".as_ptr(),
+    name: c"rust-orca".as_ptr(),
+    vendor: c"kpberry".as_ptr(),
+    url: c"https://github.com/kpberry/rust-orca".as_ptr(),
+    manual_url: c"".as_ptr(),
+    support_url: c"".as_ptr(),
+    version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+    description: c"An Orca grid following the host's transport, playing MIDI".as_ptr(),
+    features: FEATURES.0.as_ptr(),
+};
+
+/// The symbol hosts look up in the plugin's library.
+#[no_mangle]
+#[allow(non_upper_case_globals)]
+pub static clap_entry: clap_plugin_entry = clap_plugin_entry {
+    clap_version: CLAP_VERSION,
+    init: Some(entry_init),
+    deinit: Some(entry_deinit),
+    get_factory: Some(entry_get_factory),
+};
+
+static FACTORY: clap_plugin_factory = clap_plugin_factory {
+    get_plugin_count: Some(factory_plugin_count),
+    get_plugin_descriptor: Some(factory_plugin_descriptor),
+    create_plugin: Some(factory_create_plugin),
+};
+
+static NOTE_PORTS: clap_plugin_note_ports = clap_plugin_note_ports { count: Some(note_ports_count), get: Some(note_ports_get) };
+
+static STATE: clap_plugin_state = clap_plugin_state { save: Some(state_save), load: Some(state_load) };
+
+unsafe extern "C" fn entry_init(_plugin_path: *const c_char) -> bool {
+    true
+}
+
+unsafe extern "C" fn entry_deinit() {}
+
+unsafe extern "C" fn entry_get_factory(factory_id: *const c_char) -> *const c_void {
+    if unsafe { CStr::from_ptr(factory_id) } == CLAP_PLUGIN_FACTORY_ID {
+        &FACTORY as *const clap_plugin_factory as *const c_void
+    } else {
+        null()
+    }
+}
+
+unsafe extern "C" fn factory_plugin_count(_factory: *const clap_plugin_factory) -> u32 {
+    1
+}
+
+unsafe extern "C" fn factory_plugin_descriptor(_factory: *const clap_plugin_factory, index: u32) -> *const clap_plugin_descriptor {
+    if index == 0 { &DESCRIPTOR } else { null() }
+}
+
+unsafe extern "C" fn factory_create_plugin(
+    _factory: *const clap_plugin_factory,
+    host: *const clap_host,
+    plugin_id: *const c_char,
+) -> *const clap_plugin {
+    let (host, id) = unsafe { (&*host, CStr::from_ptr(plugin_id)) };
+    if !clap_version_is_compatible(host.clap_version) || id != unsafe { CStr::from_ptr(DESCRIPTOR.id) } {
+        return null();
+    }
+    let plugin = Box::into_raw(Box::new(Plugin {
+        clap: clap_plugin {
+            desc: &DESCRIPTOR,
+            plugin_data: core::ptr::null_mut(),
+            init: Some(plugin_init),
+            destroy: Some(plugin_destroy),
+            activate: Some(plugin_activate),
+            deactivate: Some(plugin_deactivate),
+            start_processing: Some(plugin_start_processing),
+            stop_processing: Some(plugin_stop_processing),
+            reset: Some(plugin_reset),
+            process: Some(plugin_process),
+            get_extension: Some(plugin_get_extension),
+            on_main_thread: Some(plugin_on_main_thread),
+        },
+        state: Arc::new(Mutex::new(PluginState { sync: HostSync::new(initial_context()), sample_rate: 48000.0 })),
+        #[cfg(target_os = "linux")]
+        window: Mutex::new(None),
+    }));
+    unsafe {
+        (*plugin).clap.plugin_data = plugin as *mut c_void;
+        &(*plugin).clap
+    }
+}
+
+unsafe extern "C" fn plugin_init(_clap: *const clap_plugin) -> bool {
+    true
+}
+
+unsafe extern "C" fn plugin_destroy(clap: *const clap_plugin) {
+    drop(unsafe { Box::from_raw((*clap).plugin_data as *mut Plugin) });
+}
+
+unsafe extern "C" fn plugin_activate(clap: *const clap_plugin, sample_rate: f64, _min_frames: u32, _max_frames: u32) -> bool {
+    let plugin = unsafe { plugin(clap) };
+    plugin.state.lock().unwrap_or_else(PoisonError::into_inner).sample_rate = sample_rate;
+    true
+}
+
+unsafe extern "C" fn plugin_deactivate(_clap: *const clap_plugin) {}
+
+unsafe extern "C" fn plugin_start_processing(_clap: *const clap_plugin) -> bool {
+    true
+}
+
+unsafe extern "C" fn plugin_stop_processing(_clap: *const clap_plugin) {}
+
+unsafe extern "C" fn plugin_reset(clap: *const clap_plugin) {
+    let plugin = unsafe { plugin(clap) };
+    plugin.state.lock().unwrap_or_else(PoisonError::into_inner).sync.stop_notes();
+}
+
+unsafe extern "C" fn plugin_process(clap: *const clap_plugin, process: *const clap_process) -> clap_process_status {
+    let (plugin, process) = unsafe { (plugin(clap), &*process) };
+    // the audio thread never waits; the grid being swapped out skips a block instead
+    let Ok(mut state) = plugin.state.try_lock() else {
+        return CLAP_PROCESS_CONTINUE;
+    };
+    let tempo = state.sync.context.tempo as f64;
+    let transport = match unsafe { process.transport.as_ref() } {
+        Some(transport) => host_transport(transport, tempo),
+        None => HostTransport { playing: false, beat: 0.0, tempo },
+    };
+    let sample_rate = state.sample_rate;
+    let out_events = unsafe { &*process.out_events };
+    let Some(try_push) = out_events.try_push else {
+        return CLAP_PROCESS_CONTINUE;
+    };
+    for (time, data) in state.sync.process(transport, sample_rate, process.frames_count) {
+        let event = clap_event_midi {
+            header: clap_event_header {
+                size: size_of::<clap_event_midi>() as u32,
+                time,
+                space_id: CLAP_CORE_EVENT_SPACE_ID,
+                type_: CLAP_EVENT_MIDI,
+                flags: 0,
+            },
+            port_index: 0,
+            data,
+        };
+        unsafe { try_push(out_events, &event.header) };
+    }
+    CLAP_PROCESS_CONTINUE
+}
+
+/// Reads the host's transport, counting it as stopped unless it says where it is in beats.
+fn host_transport(transport: &clap_event_transport, tempo: f64) -> HostTransport {
+    let has = |flag| transport.flags & flag != 0;
+    HostTransport {
+        playing: has(CLAP_TRANSPORT_IS_PLAYING) && has(CLAP_TRANSPORT_HAS_BEATS_TIMELINE),
+        beat: transport.song_pos_beats as f64 / CLAP_BEATTIME_FACTOR as f64,
+        tempo: if has(CLAP_TRANSPORT_HAS_TEMPO) { transport.tempo } else { tempo },
+    }
+}
+
+unsafe extern "C" fn plugin_get_extension(_clap: *const clap_plugin, id: *const c_char) -> *const c_void {
+    let id = unsafe { CStr::from_ptr(id) };
+    if id == CLAP_EXT_NOTE_PORTS {
+        &NOTE_PORTS as *const clap_plugin_note_ports as *const c_void
+    } else if id == CLAP_EXT_STATE {
+        &STATE as *const clap_plugin_state as *const c_void
+    } else if id == CLAP_EXT_GUI {
+        gui_extension()
+    } else {
+        null()
+    }
+}
+
+unsafe extern "C" fn plugin_on_main_thread(_clap: *const clap_plugin) {}
+
+unsafe extern "C" fn note_ports_count(_clap: *const clap_plugin, is_input: bool) -> u32 {
+    if is_input { 0 } else { 1 }
+}
+
+unsafe extern "C" fn note_ports_get(_clap: *const clap_plugin, index: u32, is_input: bool, info: *mut clap_note_port_info) -> bool {
+    if is_input || index != 0 {
+        return false;
+    }
+    let info = unsafe { &mut *info };
+    info.id = 0;
+    info.supported_dialects = CLAP_NOTE_DIALECT_MIDI;
+    info.preferred_dialect = CLAP_NOTE_DIALECT_MIDI;
+    info.name.fill(0);
+    for (to, &from) in info.name.iter_mut().zip(b"Orca") {
+        *to = from as c_char;
+    }
+    true
+}
+
+/// Saves the grid as .orca text.
+unsafe extern "C" fn state_save(clap: *const clap_plugin, stream: *const clap_ostream) -> bool {
+    let (plugin, stream) = unsafe { (plugin(clap), &*stream) };
+    let text = {
+        let state = plugin.state.lock().unwrap_or_else(PoisonError::into_inner);
+        grid_to_string(state.sync.context.grid(), state.sync.context.width)
+    };
+    let Some(write) = stream.write else {
+        return false;
+    };
+    let mut rest = text.as_bytes();
+    while !rest.is_empty() {
+        let written = unsafe { write(stream, rest.as_ptr() as *const c_void, rest.len() as u64) };
+        if written <= 0 {
+            return false;
+        }
+        rest = &rest[written as usize..];
+    }
+    true
+}
+
+/// Loads a grid saved by `state_save`.
+unsafe extern "C" fn state_load(clap: *const clap_plugin, stream: *const clap_istream) -> bool {
+    let (plugin, stream) = unsafe { (plugin(clap), &*stream) };
+    let Some(read) = stream.read else {
+        return false;
+    };
+    let (mut bytes, mut buffer) = (Vec::new(), [0u8; 4096]);
+    loop {
+        match unsafe { read(stream, buffer.as_mut_ptr() as *mut c_void, buffer.len() as u64) } {
+            0 => break,
+            read if read < 0 => return false,
+            read => bytes.extend_from_slice(&buffer[..read as usize]),
+        }
+    }
+    let Ok(text) = String::from_utf8(bytes) else {
+        return false;
+    };
+    plugin.state.lock().unwrap_or_else(PoisonError::into_inner).sync.load(&text)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn gui_extension() -> *const c_void {
+    null()
+}
+
+#[cfg(target_os = "linux")]
+fn gui_extension() -> *const c_void {
+    &GUI as *const clap_plugin_gui as *const c_void
+}
+
+// the window is embedded in the host's over X11, and never floats on its own
+#[cfg(target_os = "linux")]
+static GUI: clap_plugin_gui = clap_plugin_gui {
+    is_api_supported: Some(gui_is_api_supported),
+    get_preferred_api: Some(gui_get_preferred_api),
+    create: Some(gui_create),
+    destroy: Some(gui_destroy),
+    set_scale: Some(gui_set_scale),
+    get_size: Some(gui_get_size),
+    can_resize: Some(gui_can_resize),
+    get_resize_hints: Some(gui_get_resize_hints),
+    adjust_size: Some(gui_adjust_size),
+    set_size: Some(gui_set_size),
+    set_parent: Some(gui_set_parent),
+    set_transient: Some(gui_set_transient),
+    suggest_title: Some(gui_suggest_title),
+    show: Some(gui_show),
+    hide: Some(gui_hide),
+};
+
+/// Runs `action` on the plugin's window, if it has one open.
+#[cfg(target_os = "linux")]
+unsafe fn with_window<R>(clap: *const clap_plugin, action: impl FnOnce(&mut Window) -> R) -> Option<R> {
+    let plugin = unsafe { plugin(clap) };
+    plugin.window.lock().unwrap_or_else(PoisonError::into_inner).as_mut().map(action)
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn gui_is_api_supported(_clap: *const clap_plugin, api: *const c_char, is_floating: bool) -> bool {
+    !is_floating && unsafe { CStr::from_ptr(api) } == CLAP_WINDOW_API_X11
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn gui_get_preferred_api(_clap: *const clap_plugin, api: *mut *const c_char, is_floating: *mut bool) -> bool {
+    unsafe {
+        *api = CLAP_WINDOW_API_X11.as_ptr();
+        *is_floating = false;
+    }
+    true
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn gui_create(clap: *const clap_plugin, api: *const c_char, is_floating: bool) -> bool {
+    if !unsafe { gui_is_api_supported(clap, api, is_floating) } {
+        return false;
+    }
+    let plugin = unsafe { plugin(clap) };
+    // without an X server to draw on, the host goes on without a window
+    let Ok(window) = Window::open(Arc::clone(&plugin.state)) else {
+        return false;
+    };
+    *plugin.window.lock().unwrap_or_else(PoisonError::into_inner) = Some(window);
+    true
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn gui_destroy(clap: *const clap_plugin) {
+    let plugin = unsafe { plugin(clap) };
+    plugin.window.lock().unwrap_or_else(PoisonError::into_inner).take();
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn gui_set_scale(_clap: *const clap_plugin, _scale: f64) -> bool {
+    false
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn gui_get_size(clap: *const clap_plugin, width: *mut u32, height: *mut u32) -> bool {
+    let Some((cols, rows)) = (unsafe { with_window(clap, |window| window.size()) }) else {
+        return false;
+    };
+    unsafe {
+        *width = cols;
+        *height = rows;
+    }
+    true
+}
+
+// the window is as big as the grid
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn gui_can_resize(_clap: *const clap_plugin) -> bool {
+    false
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn gui_get_resize_hints(_clap: *const clap_plugin, _hints: *mut clap_gui_resize_hints) -> bool {
+    false
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn gui_adjust_size(_clap: *const clap_plugin, _width: *mut u32, _height: *mut u32) -> bool {
+    false
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn gui_set_size(clap: *const clap_plugin, width: u32, height: u32) -> bool {
+    unsafe { with_window(clap, |window| window.size() == (width, height)) }.unwrap_or(false)
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn gui_set_parent(clap: *const clap_plugin, parent: *const clap_window) -> bool {
+    let parent = unsafe { &*parent };
+    if unsafe { CStr::from_ptr(parent.api) } != CLAP_WINDOW_API_X11 {
+        return false;
+    }
+    let parent = unsafe { parent.specific.x11 } as u32;
+    unsafe { with_window(clap, |window| window.set_parent(parent).is_ok()) }.unwrap_or(false)
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn gui_set_transient(_clap: *const clap_plugin, _window: *const clap_window) -> bool {
+    false
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn gui_suggest_title(_clap: *const clap_plugin, _title: *const c_char) {}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn gui_show(clap: *const clap_plugin) -> bool {
+    unsafe { with_window(clap, |window| window.show(true).is_ok()) }.unwrap_or(false)
+}
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" fn gui_hide(clap: *const clap_plugin) -> bool {
+    unsafe { with_window(clap, |window| window.show(false).is_ok()) }.unwrap_or(false)
+}
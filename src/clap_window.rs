@@ -0,0 +1,235 @@
+//! The CLAP plugin's window: the grid drawn into a window embedded in the host's over X11, redrawn
+//! as it plays and edited from the keyboard through a [`GridView`].
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, JoinHandle, sleep};
+use std::time::Duration;
+
+use x11rb::connection::Connection;
+use x11rb::errors::{ConnectionError, ReplyError, ReplyOrIdError};
+use x11rb::protocol::Event;
+use x11rb::protocol::xproto::{
+    ConnectionExt, CreateGCAux, CreateWindowAux, EventMask, Gcontext, KeyButMask, Keycode, WindowClass,
+};
+use x11rb::rust_connection::RustConnection;
+
+use crate::clap_plugin::{GridView, PluginState, ViewKey};
+
+// how often the window redraws, to keep up with the grid playing
+const FRAME_TIME: Duration = Duration::from_millis(33);
+// pixels between the grid and the edge of the window
+const MARGIN: u16 = 8;
+
+// the keysyms of the keys that aren't characters
+const BACKSPACE: u32 = 0xff08;
+const LEFT: u32 = 0xff51;
+const UP: u32 = 0xff52;
+const RIGHT: u32 = 0xff53;
+const DOWN: u32 = 0xff54;
+const DELETE: u32 = 0xffff;
+
+/// What a key with the keysym `keysym` does in the window. Keysyms for Latin-1 characters are the
+/// characters themselves.
+fn view_key(keysym: u32) -> Option<ViewKey> {
+    match keysym {
+        BACKSPACE | DELETE => Some(ViewKey::Erase),
+        LEFT => Some(ViewKey::Left),
+        UP => Some(ViewKey::Up),
+        RIGHT => Some(ViewKey::Right),
+        DOWN => Some(ViewKey::Down),
+        0x21..=0x7e => char::from_u32(keysym).map(ViewKey::Char),
+        _ => None,
+    }
+}
+
+/// The fixed font the grid is drawn in, and the size of one of its cells.
+struct Font {
+    width: u16,
+    ascent: u16,
+    height: u16,
+}
+
+/// The window and the thread drawing into it, which stops when the window is dropped.
+pub(crate) struct Window {
+    connection: Arc<RustConnection>,
+    window: u32,
+    size: (u32, u32),
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Window {
+    /// Connects to the X server and makes a window as big as the grid in `state`, which is drawn
+    /// into from a thread of its own once the host shows it.
+    pub(crate) fn open(state: Arc<Mutex<PluginState>>) -> Result<Window, ReplyOrIdError> {
+        let (connection, screen) = x11rb::connect(None).map_err(|_| ConnectionError::UnknownError)?;
+        let connection = Arc::new(connection);
+        let screen = &connection.setup().roots[screen];
+        let (root, visual, background, foreground) =
+            (screen.root, screen.root_visual, screen.black_pixel, screen.white_pixel);
+
+        let font_id = connection.generate_id()?;
+        connection.open_font(font_id, b"fixed")?;
+        let metrics = connection.query_font(font_id)?.reply()?;
+        let font = Font {
+            width: metrics.max_bounds.character_width.max(1) as u16,
+            ascent: metrics.font_ascent.max(0) as u16,
+            height: (metrics.font_ascent + metrics.font_descent).max(1) as u16,
+        };
+
+        let (rows, cols) = {
+            let state = state.lock().unwrap_or_else(PoisonError::into_inner);
+            (state.sync.context.height as u16, state.sync.context.width as u16)
+        };
+        let width = cols.saturating_mul(font.width).saturating_add(2 * MARGIN);
+        let height = rows.saturating_mul(font.height).saturating_add(2 * MARGIN);
+        let window = connection.generate_id()?;
+        let events = EventMask::EXPOSURE | EventMask::KEY_PRESS | EventMask::BUTTON_PRESS;
+        let aux = CreateWindowAux::new().background_pixel(background).event_mask(events);
+        connection.create_window(0, window, root, 0, 0, width, height, 0, WindowClass::INPUT_OUTPUT, visual, &aux)?;
+
+        let text = connection.generate_id()?;
+        connection.create_gc(text, window, &CreateGCAux::new().foreground(foreground).background(background).font(font_id))?;
+        let cursor = connection.generate_id()?;
+        connection.create_gc(cursor, window, &CreateGCAux::new().foreground(background).background(foreground).font(font_id))?;
+        connection.close_font(font_id)?;
+        connection.flush()?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let (connection, stop) = (Arc::clone(&connection), Arc::clone(&stop));
+            let painter = Painter { connection, window, font, text, cursor, view: GridView::default() };
+            thread::spawn(move || painter.run(&state, &stop))
+        };
+        Ok(Window { connection, window, size: (width as u32, height as u32), stop, thread: Some(thread) })
+    }
+
+    /// The window's size in pixels.
+    pub(crate) fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Embeds the window in the host's window `parent`.
+    pub(crate) fn set_parent(&self, parent: u32) -> Result<(), ConnectionError> {
+        self.connection.reparent_window(self.window, parent, 0, 0)?;
+        self.connection.flush()
+    }
+
+    pub(crate) fn show(&self, shown: bool) -> Result<(), ConnectionError> {
+        if shown {
+            self.connection.map_window(self.window)?;
+        } else {
+            self.connection.unmap_window(self.window)?;
+        }
+        self.connection.flush()
+    }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        // the connection closing takes the window with it, so a failure here leaves nothing behind
+        let _ = self.connection.destroy_window(self.window);
+        let _ = self.connection.flush();
+    }
+}
+
+/// Draws the grid and handles the window's events, on the window's own thread.
+struct Painter {
+    connection: Arc<RustConnection>,
+    window: u32,
+    font: Font,
+    text: Gcontext,
+    cursor: Gcontext,
+    view: GridView,
+}
+
+impl Painter {
+    fn run(mut self, state: &Mutex<PluginState>, stop: &AtomicBool) {
+        let Ok(keys) = Keys::read(&self.connection) else {
+            return;
+        };
+        let mut drawn = (0, 0);
+        while !stop.load(Ordering::Relaxed) {
+            let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+            let context = &mut state.sync.context;
+            while let Ok(Some(event)) = self.connection.poll_for_event() {
+                match event {
+                    Event::KeyPress(event) => {
+                        if let Some(key) = keys.view_key(event.detail, event.state) {
+                            self.view.press(key, context);
+                        }
+                    }
+                    Event::ButtonPress(event) => {
+                        let row = (event.event_y.max(MARGIN as i16) as u16 - MARGIN) / self.font.height;
+                        let col = (event.event_x.max(MARGIN as i16) as u16 - MARGIN) / self.font.width;
+                        self.view.click(row as usize, col as usize, context);
+                    }
+                    _ => {}
+                }
+            }
+            // a grid loaded at another size leaves the old one's cells behind
+            if drawn != (context.width, context.height) {
+                drawn = (context.width, context.height);
+                let _ = self.connection.clear_area(false, self.window, 0, 0, 0, 0);
+            }
+            self.view.clamp(context);
+            let lines = self.view.lines(context);
+            drop(state);
+            if self.draw(&lines).is_err() {
+                return;
+            }
+            sleep(FRAME_TIME);
+        }
+    }
+
+    /// Draws each row of the grid, then the cell under the cursor in reverse.
+    fn draw(&self, lines: &[String]) -> Result<(), ConnectionError> {
+        let y = |row: usize| (MARGIN + self.font.ascent) as i16 + (row as u16 * self.font.height) as i16;
+        for (row, line) in lines.iter().enumerate() {
+            self.connection.image_text8(self.window, self.text, MARGIN as i16, y(row), &latin1(line))?;
+        }
+        let under = lines.get(self.view.row).and_then(|line| line.chars().nth(self.view.col)).unwrap_or(' ');
+        let x = MARGIN as i16 + (self.view.col as u16 * self.font.width) as i16;
+        self.connection.image_text8(self.window, self.cursor, x, y(self.view.row), &latin1(&under.to_string()))?;
+        self.connection.flush()
+    }
+}
+
+// the core fonts draw Latin-1, and anything past it shows as a question mark
+fn latin1(text: &str) -> Vec<u8> {
+    text.chars().map(|c| u8::try_from(c as u32).unwrap_or(b'?')).collect()
+}
+
+/// The keysyms each keycode stands for, unshifted and shifted.
+struct Keys {
+    first: Keycode,
+    per_keycode: usize,
+    keysyms: Vec<u32>,
+}
+
+impl Keys {
+    fn read(connection: &RustConnection) -> Result<Keys, ReplyError> {
+        let setup = connection.setup();
+        let (first, last) = (setup.min_keycode, setup.max_keycode);
+        let mapping = connection.get_keyboard_mapping(first, last - first + 1)?.reply()?;
+        Ok(Keys { first, per_keycode: mapping.keysyms_per_keycode as usize, keysyms: mapping.keysyms })
+    }
+
+    fn view_key(&self, keycode: Keycode, state: KeyButMask) -> Option<ViewKey> {
+        let shifted = state.contains(KeyButMask::SHIFT) || state.contains(KeyButMask::LOCK);
+        let index = keycode.checked_sub(self.first)? as usize * self.per_keycode;
+        let keysyms = self.keysyms.get(index..index + self.per_keycode)?;
+        // a key with nothing on its shifted level shifts the same keysym
+        let keysym = match keysyms {
+            [_, upper, ..] if shifted && *upper != 0 => *upper,
+            [keysym, ..] => *keysym,
+            [] => return None,
+        };
+        view_key(keysym)
+    }
+}
@@ -0,0 +1,353 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+pub const USAGE: &str = "usage: rust-orca [--profile] [--headless TICKS] [--metrics ADDRESS] [--ruler ROWSxCOLS] [--theme NAME] [--vim] [--screen-reader] [--gui] [--split FILE] [--autosave SECONDS] [--midi PORT] [--udp ADDRESS] [--osc] [--header] [--events OUT] [--record OUT] [--replay SESSION] [FILE]
+       rust-orca render FILE [--ticks TICKS] [--bpm BPM] [-o OUT] [--events OUT]
+       rust-orca pipe [--ticks TICKS] [--seed SEED] [--events json|csv] [--no-grid] < GRID
+       rust-orca new FILE [--size COLSxROWS] [--template NAME]
+       rust-orca validate FILE
+       rust-orca daemon SOCKET [FILE] [--midi PORT] [--metrics ADDRESS] [--udp ADDRESS] [--osc]
+       rust-orca repl SOCKET
+       rust-orca list-midi";
+
+const DEFAULT_RENDER_TICKS: usize = 4096;
+
+/// What the program was asked to do, picked by the first argument.
+#[derive(Debug)]
+pub enum Subcommand {
+    /// Edit a grid, or run it headless.
+    Edit(Args),
+    /// Bounce a grid's MIDI output to a file.
+    Render(RenderArgs),
+    /// Run a grid read from stdin and print the result to stdout.
+    Pipe(PipeArgs),
+    /// Create a grid file from a template.
+    New(NewArgs),
+    /// Check a grid for likely mistakes.
+    Validate(PathBuf),
+    /// Serve a grid on a Unix socket.
+    #[cfg(unix)]
+    Daemon(DaemonArgs),
+    /// Run commands against a daemon's grid while it plays.
+    #[cfg(unix)]
+    Repl(PathBuf),
+    /// List the MIDI ports `--midi` accepts.
+    ListMidi,
+}
+
+impl Subcommand {
+    pub fn parse(args: impl Iterator<Item=String>) -> Result<Subcommand, String> {
+        let mut args = args.peekable();
+        match args.peek().map(String::as_str) {
+            Some("render") => {
+                args.next();
+                RenderArgs::parse(args).map(Subcommand::Render)
+            }
+            Some("pipe") => {
+                args.next();
+                PipeArgs::parse(args).map(Subcommand::Pipe)
+            }
+            Some("new") => {
+                args.next();
+                NewArgs::parse(args).map(Subcommand::New)
+            }
+            #[cfg(unix)]
+            Some("daemon") => {
+                args.next();
+                DaemonArgs::parse(args).map(Subcommand::Daemon)
+            }
+            #[cfg(unix)]
+            Some("repl") => {
+                args.next();
+                match (args.next(), args.next()) {
+                    (Some(socket), None) => Ok(Subcommand::Repl(PathBuf::from(socket))),
+                    (None, _) => Err("repl needs SOCKET".to_string()),
+                    (_, Some(extra)) => Err(format!("unexpected argument {}", extra)),
+                }
+            }
+            Some("list-midi") => match args.nth(1) {
+                Some(extra) => Err(format!("unexpected argument {}", extra)),
+                None => Ok(Subcommand::ListMidi),
+            },
+            Some("validate") => {
+                args.next();
+                match (args.next(), args.next()) {
+                    (Some(path), None) => Ok(Subcommand::Validate(PathBuf::from(path))),
+                    (None, _) => Err("validate needs FILE".to_string()),
+                    (_, Some(extra)) => Err(format!("unexpected argument {}", extra)),
+                }
+            }
+            _ => Args::parse(args).map(Subcommand::Edit),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub grid_path: Option<PathBuf>,
+    /// Time every operator and print a per-operator breakdown on exit.
+    pub profile: bool,
+    /// Run this many ticks as fast as possible without a UI or MIDI device, then print the grid.
+    pub headless: Option<usize>,
+    /// Where to answer Prometheus' requests for metrics while running headless, such as
+    /// `127.0.0.1:9090`.
+    pub metrics: Option<String>,
+    /// Spacing of the guide markers drawn on empty cells.
+    pub ruler: Option<Ruler>,
+    /// The color theme to start the editor with.
+    pub theme: Option<String>,
+    /// Edit with vim-style modal keys.
+    pub vim: bool,
+    /// Announce changes as lines of text instead of drawing the grid.
+    pub screen_reader: bool,
+    /// Edit in a window instead of the terminal, when built with the gui feature.
+    pub gui: bool,
+    /// A second grid to open beside the first, playing in time with it. Layer operators read the
+    /// first grid as layer `0` and this one as layer `1`.
+    pub split_path: Option<PathBuf>,
+    /// Seconds between autosaves; 0 turns autosaving off.
+    pub autosave: Option<u64>,
+    /// The MIDI output port to play through, by index or by part of its name.
+    pub midi: Option<String>,
+    /// Where Message operators send their text over UDP, such as `127.0.0.1:57120`.
+    pub udp: Option<String>,
+    /// Send Message operators' text as OSC messages rather than as it is.
+    pub osc: bool,
+    /// Save with a header recording the grid size, tempo, seed and operator config, for keeping
+    /// grids under version control.
+    pub header: bool,
+    /// Where to write every MIDI message sent during the run once it ends, as CSV if the name
+    /// ends in .csv and as JSON otherwise.
+    pub events: Option<PathBuf>,
+    /// Where to write a session of the run once it ends: the starting grid, tempo and seed, and
+    /// every change made to the grid, each with the tick it was made on.
+    pub record: Option<PathBuf>,
+    /// A recorded session to start from and play back, making each of its changes on the tick it
+    /// was made.
+    pub replay: Option<PathBuf>,
+}
+
+impl Args {
+    pub fn parse(args: impl Iterator<Item=String>) -> Result<Args, String> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--profile" => parsed.profile = true,
+                "--headless" => parsed.headless = Some(parse_value(&arg, args.next())?),
+                "--metrics" => parsed.metrics = Some(parse_value(&arg, args.next())?),
+                "--vim" => parsed.vim = true,
+                "--screen-reader" => parsed.screen_reader = true,
+                "--gui" if cfg!(feature = "gui") => parsed.gui = true,
+                "--gui" => return Err("--gui needs rust-orca built with the gui feature".to_string()),
+                "--split" => parsed.split_path = Some(parse_value(&arg, args.next())?),
+                "--ruler" => parsed.ruler = Some(parse_value(&arg, args.next())?),
+                "--theme" => parsed.theme = Some(parse_value(&arg, args.next())?),
+                "--autosave" => parsed.autosave = Some(parse_value(&arg, args.next())?),
+                "--midi" => parsed.midi = Some(parse_value(&arg, args.next())?),
+                "--udp" => parsed.udp = Some(parse_value(&arg, args.next())?),
+                "--osc" => parsed.osc = true,
+                "--header" => parsed.header = true,
+                "--events" => parsed.events = Some(parse_value(&arg, args.next())?),
+                "--record" => parsed.record = Some(parse_value(&arg, args.next())?),
+                "--replay" => parsed.replay = Some(parse_value(&arg, args.next())?),
+                flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+                path if parsed.grid_path.is_none() => parsed.grid_path = Some(PathBuf::from(path)),
+                extra => return Err(format!("unexpected argument {}", extra)),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+#[derive(Debug)]
+pub struct RenderArgs {
+    pub grid_path: PathBuf,
+    /// How many ticks to simulate.
+    pub ticks: usize,
+    /// Overrides the grid's tempo.
+    pub bpm: Option<u64>,
+    /// Where the MIDI file goes; defaults to the grid's path with a .mid extension.
+    pub output: PathBuf,
+    /// Where to also write the MIDI messages as CSV or JSON, as with `--events` when editing.
+    pub events: Option<PathBuf>,
+}
+
+impl RenderArgs {
+    pub fn parse(args: impl Iterator<Item=String>) -> Result<RenderArgs, String> {
+        let mut grid_path = None;
+        let mut ticks = DEFAULT_RENDER_TICKS;
+        let mut bpm = None;
+        let mut output = None;
+        let mut events = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--ticks" => ticks = parse_value(&arg, args.next())?,
+                "--bpm" => bpm = Some(parse_value(&arg, args.next())?),
+                "-o" | "--output" => output = Some(parse_value(&arg, args.next())?),
+                "--events" => events = Some(parse_value(&arg, args.next())?),
+                flag if flag.starts_with('-') => return Err(format!("unknown option {}", flag)),
+                path if grid_path.is_none() => grid_path = Some(PathBuf::from(path)),
+                extra => return Err(format!("unexpected argument {}", extra)),
+            }
+        }
+        let grid_path: PathBuf = grid_path.ok_or("render needs FILE")?;
+        let output = output.unwrap_or_else(|| grid_path.with_extension("mid"));
+        Ok(RenderArgs { grid_path, ticks, bpm, output, events })
+    }
+}
+
+/// How many rows apart and how many columns apart the guide markers are drawn, written as
+/// `ROWSxCOLS`, or as one number for both. A spacing of 0 leaves out the markers along that way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "crate::config::RulerConfig")]
+pub struct Ruler {
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl std::str::FromStr for Ruler {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Ruler, ()> {
+        let (rows, cols) = value.split_once('x').unwrap_or((value, value));
+        Ok(Ruler { rows: rows.parse().map_err(|_| ())?, cols: cols.parse().map_err(|_| ())? })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventFormat {
+    Json,
+    Csv,
+}
+
+impl std::str::FromStr for EventFormat {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<EventFormat, ()> {
+        match value {
+            "json" => Ok(EventFormat::Json),
+            "csv" => Ok(EventFormat::Csv),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PipeArgs {
+    /// How many ticks to run before printing the grid.
+    pub ticks: usize,
+    /// The random seed of a grid without a header; grids with one use the seed it records.
+    pub seed: u64,
+    /// Print the MIDI messages sent during the run after the grid, in this format.
+    pub events: Option<EventFormat>,
+    /// Whether to print the grid, which can be left out to print only the events.
+    pub grid: bool,
+}
+
+impl PipeArgs {
+    pub fn parse(args: impl Iterator<Item=String>) -> Result<PipeArgs, String> {
+        let mut parsed = PipeArgs { ticks: 1, seed: 0, events: None, grid: true };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--ticks" => parsed.ticks = parse_value(&arg, args.next())?,
+                "--seed" => parsed.seed = parse_value(&arg, args.next())?,
+                "--events" => parsed.events = Some(parse_value(&arg, args.next())?),
+                "--no-grid" => parsed.grid = false,
+                flag if flag.starts_with('-') => return Err(format!("unknown option {}", flag)),
+                extra => return Err(format!("unexpected argument {}", extra)),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+#[derive(Debug)]
+pub struct NewArgs {
+    pub grid_path: PathBuf,
+    /// The size of the grid, columns first; defaults to the configured size.
+    pub size: Option<(usize, usize)>,
+    pub template: String,
+}
+
+impl NewArgs {
+    pub fn parse(args: impl Iterator<Item=String>) -> Result<NewArgs, String> {
+        let mut grid_path = None;
+        let mut size = None;
+        let mut template = "empty".to_string();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--size" => {
+                    let value: String = parse_value(&arg, args.next())?;
+                    size = Some(parse_size(&value).ok_or_else(|| format!("invalid value for {}: {}", arg, value))?);
+                }
+                "--template" => template = parse_value(&arg, args.next())?,
+                flag if flag.starts_with('-') => return Err(format!("unknown option {}", flag)),
+                path if grid_path.is_none() => grid_path = Some(PathBuf::from(path)),
+                extra => return Err(format!("unexpected argument {}", extra)),
+            }
+        }
+        let grid_path = grid_path.ok_or("new needs FILE")?;
+        Ok(NewArgs { grid_path, size, template })
+    }
+}
+
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct DaemonArgs {
+    /// Where the socket goes.
+    pub socket: PathBuf,
+    /// The grid to start with; without one the daemon starts with an empty grid.
+    pub grid_path: Option<PathBuf>,
+    /// The MIDI output port to play through, by index or by part of its name.
+    pub midi: Option<String>,
+    /// Where to answer Prometheus' requests for metrics, such as `127.0.0.1:9090`.
+    pub metrics: Option<String>,
+    /// Where Message operators send their text over UDP, such as `127.0.0.1:57120`.
+    pub udp: Option<String>,
+    /// Send Message operators' text as OSC messages rather than as it is.
+    pub osc: bool,
+}
+
+#[cfg(unix)]
+impl DaemonArgs {
+    pub fn parse(args: impl Iterator<Item=String>) -> Result<DaemonArgs, String> {
+        let mut socket = None;
+        let mut grid_path = None;
+        let mut midi = None;
+        let mut metrics = None;
+        let mut udp = None;
+        let mut osc = false;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--midi" => midi = Some(parse_value(&arg, args.next())?),
+                "--metrics" => metrics = Some(parse_value(&arg, args.next())?),
+                "--udp" => udp = Some(parse_value(&arg, args.next())?),
+                "--osc" => osc = true,
+                flag if flag.starts_with('-') => return Err(format!("unknown option {}", flag)),
+                path if socket.is_none() => socket = Some(PathBuf::from(path)),
+                path if grid_path.is_none() => grid_path = Some(PathBuf::from(path)),
+                extra => return Err(format!("unexpected argument {}", extra)),
+            }
+        }
+        let socket = socket.ok_or("daemon needs SOCKET")?;
+        Ok(DaemonArgs { socket, grid_path, midi, metrics, udp, osc })
+    }
+}
+
+/// Parses a grid size written as `COLSxROWS`, neither of which may be zero.
+fn parse_size(value: &str) -> Option<(usize, usize)> {
+    let (cols, rows) = value.split_once('x')?;
+    let (cols, rows) = (cols.parse().ok()?, rows.parse().ok()?);
+    (cols > 0 && rows > 0).then_some((cols, rows))
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} needs a value", flag))?;
+    value.parse().map_err(|_| format!("invalid value for {}: {}", flag, value))
+}
@@ -0,0 +1,149 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rust_orca::operators::{OperatorMap, is_symbol};
+
+/// The names of every command, in the order they are offered for completion.
+pub const COMMAND_NAMES: [&str; 16] = [
+    "bpm", "clip", "explain", "export", "fill", "import", "keys", "midi", "quantize", "quit", "ramp", "record", "remap",
+    "save", "theme", "write",
+];
+
+/// A command typed into a command line, such as `bpm 140` or `write 3 4 a`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Sets the tempo in beats per minute.
+    Bpm(u64),
+    /// Writes part of the recording to a file, as an animated gif if the file name ends in .gif
+    /// and as an asciinema cast otherwise. The part runs between two times into the recording, by
+    /// default from its start to its end.
+    Clip { path: PathBuf, from: Duration, to: Option<Duration> },
+    /// Explains the cell under the cursor as it moves, from what each operator read and wrote on
+    /// the last tick, or stops explaining.
+    Explain,
+    /// Saves the selection to an .orca snippet file.
+    Export(PathBuf),
+    /// Fills each row of the selection with text, repeated to the end of the row.
+    Fill(String),
+    /// Pastes the contents of an .orca file or snippet at the cursor.
+    Import(PathBuf),
+    /// Describes each key pressed in the status bar instead of acting on it, until Esc is
+    /// pressed, to check what the keyboard layout sends.
+    Keys,
+    /// Switches MIDI output to the port with this index.
+    Midi(usize),
+    /// Holds back edits until launches this many beats apart, or lets them through at once for
+    /// 0. Without a number, switches between a bar and letting them through.
+    Quantize(Option<u64>),
+    Quit,
+    /// Fills each row of the selection with base 36 values counting up from a digit.
+    Ramp(char),
+    /// Starts recording the grid as it runs, or stops if it is already recording.
+    Record,
+    /// Gives an operator, named or by its current symbol, a new symbol for the rest of the
+    /// session.
+    Remap { operator: String, symbol: char },
+    /// Saves the grid, to this path if one is given or else to the file it was loaded from.
+    Save(Option<PathBuf>),
+    /// Switches the editor to the color theme with this name.
+    Theme(String),
+    /// Writes text into the grid starting at a column and row, the same order orca uses.
+    Write { col: i32, row: i32, text: String },
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let line = line.trim().trim_start_matches(':');
+        let (name, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let mut args = rest.split_whitespace();
+        let command = match name {
+            "bpm" => Command::Bpm(parse_arg(name, "BPM", args.next())?),
+            "clip" => Command::Clip {
+                path: PathBuf::from(args.next().ok_or("clip needs FILE")?),
+                from: args.next().map_or(Ok(Duration::ZERO), |from| parse_seconds(name, "FROM", from))?,
+                to: args.next().map(|to| parse_seconds(name, "TO", to)).transpose()?,
+            },
+            "explain" => Command::Explain,
+            "export" => Command::Export(PathBuf::from(args.next().ok_or("export needs FILE")?)),
+            "fill" => Command::Fill(args.next().ok_or("fill needs TEXT")?.to_string()),
+            "import" => Command::Import(PathBuf::from(args.next().ok_or("import needs FILE")?)),
+            "keys" => Command::Keys,
+            "midi" => Command::Midi(parse_arg(name, "PORT", args.next())?),
+            "quantize" => {
+                Command::Quantize(args.next().map(|beats| parse_arg(name, "BEATS", Some(beats))).transpose()?)
+            }
+            "quit" | "q" => Command::Quit,
+            "ramp" => Command::Ramp(args.next().map_or(Ok('0'), |from| parse_arg(name, "FROM", Some(from)))?),
+            "record" => Command::Record,
+            "remap" => Command::Remap {
+                operator: args.next().ok_or("usage: remap OPERATOR SYMBOL")?.to_string(),
+                symbol: parse_arg(name, "SYMBOL", args.next())?,
+            },
+            "save" | "w" => Command::Save(args.next().map(PathBuf::from)),
+            "theme" => Command::Theme(args.next().ok_or("theme needs NAME")?.to_string()),
+            "write" => Command::Write {
+                col: parse_arg(name, "X", args.next())?,
+                row: parse_arg(name, "Y", args.next())?,
+                text: args.next().ok_or("usage: write X Y TEXT")?.to_string(),
+            },
+            "" => return Err("no command given".to_string()),
+            name => return Err(format!("unknown command {}", name)),
+        };
+        match args.next() {
+            Some(extra) => Err(format!("unexpected argument {}", extra)),
+            None => Ok(command),
+        }
+    }
+}
+
+impl Command {
+    /// Whether the command changes the grid or the tempo, and so waits for the next launch while
+    /// launches are quantized.
+    pub fn waits_for_launch(&self) -> bool {
+        matches!(
+            self,
+            Command::Bpm(_) | Command::Fill(_) | Command::Import(_) | Command::Ramp(_) | Command::Write { .. }
+        )
+    }
+}
+
+fn parse_arg<T: std::str::FromStr>(command: &str, arg: &str, value: Option<&str>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} needs {}", command, arg))?;
+    value.parse().map_err(|_| format!("invalid {} for {}: {}", arg, command, value))
+}
+
+/// Parses a non-negative number of seconds, which may have a fraction.
+fn parse_seconds(command: &str, arg: &str, value: &str) -> Result<Duration, String> {
+    let seconds: f64 = parse_arg(command, arg, Some(value))?;
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("invalid {} for {}: {}", arg, command, value))
+}
+
+/// Gives an operator, named or by its current symbol, a new symbol in `operator_map`, returning
+/// the operator's name or why it can't have the symbol.
+pub fn remap(operator_map: &mut OperatorMap, operator: &str, symbol: char) -> Result<String, String> {
+    let found = operator_map.iter().find(|(name, symbols)| {
+        name.eq_ignore_ascii_case(operator) || symbols.iter().any(|&current| operator.chars().eq([current]))
+    });
+    let Some(name) = found.map(|(name, _)| name.clone()) else {
+        return Err(format!("unknown operator {}", operator));
+    };
+    // the bang table holds every symbol in lowercase, so symbols differing only in case would
+    // collide
+    if !is_symbol(symbol) {
+        return Err(format!("{} can not be an operator", symbol));
+    }
+    let taken = operator_map
+        .iter()
+        .find(|(other, symbols)| **other != name && symbols.iter().any(|current| current.eq_ignore_ascii_case(&symbol)));
+    if let Some((other, _)) = taken {
+        return Err(format!("{} is already {}", symbol, other));
+    }
+    // the new symbol replaces the operator's aliases along with its own
+    operator_map.insert(name.clone(), vec![symbol]);
+    Ok(name)
+}
+
+/// Returns the command names that start with `prefix`.
+pub fn complete(prefix: &str) -> Vec<&'static str> {
+    COMMAND_NAMES.iter().copied().filter(|name| name.starts_with(prefix)).collect()
+}
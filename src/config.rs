@@ -0,0 +1,332 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs::read_to_string;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use rust_orca::context::Context;
+use rust_orca::operators::{
+    EvaluationMode, OperatorMap, OperatorTable, WEIGHTED_SHAPES, bind_symbol, default_operator_config,
+    get_bang_operators, get_tick_operators, is_single_width, read_operator_config,
+};
+
+use crate::cli::{Args, Ruler};
+
+// read from the working directory, on top of the global config
+pub const PROJECT_CONFIG_FILE: &str = "rust-orca.toml";
+pub const OPERATOR_CONFIG_FILE: &str = "operator_config.txt";
+
+/// Settings read from the global config file and the project's, each of which may leave out
+/// anything it doesn't change. Command line options win over both.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub grid: GridConfig,
+    pub editor: EditorConfig,
+    pub midi: MidiConfig,
+    pub udp: UdpConfig,
+    pub theme: ThemeConfig,
+    /// Key bindings on top of the defaults, as in keymap.txt: `"ctrl+s" = "save"`.
+    pub keys: BTreeMap<String, String>,
+    /// Symbols for operators by name, on top of operator_config.txt: `Add = "+"`, or
+    /// `Add = ["A", "+"]` for an operator with aliases.
+    pub operators: BTreeMap<String, Symbols>,
+    /// Values for operators' inputs to fall back on when their cells are empty, by operator and
+    /// input name: `[defaults.Random] max = "g"`.
+    pub defaults: BTreeMap<String, BTreeMap<String, char>>,
+    /// Values for the Weighted operator to draw from, by the shape symbol that picks them, with a
+    /// value given more often drawn more often: `[tables] b = "0001357"`.
+    pub tables: BTreeMap<char, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GridConfig {
+    pub rows: Option<usize>,
+    pub cols: Option<usize>,
+    /// The tempo of grids saved without one.
+    pub bpm: Option<u64>,
+    /// The frames per beat of grids saved without them.
+    pub frames: Option<u64>,
+    /// The order ticks visit cells in, by the name of its evaluation mode, for patches written for
+    /// another Orca: `evaluation = "orca-c"`.
+    pub evaluation: Option<String>,
+    /// Whether Halt, Track and Push lock and write cells as orca-c's do where rust-orca differs.
+    pub orca_c_locks: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EditorConfig {
+    pub ruler: Option<Ruler>,
+    pub vim: Option<bool>,
+    /// Seconds between autosaves; 0 turns autosaving off.
+    pub autosave: Option<u64>,
+    /// Whether grids are saved with the git-friendly header.
+    pub header: Option<bool>,
+    /// Beats between the launches that edits clearing or replacing cells wait for; 0 lets them
+    /// through at once.
+    pub quantize: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MidiConfig {
+    /// The output port, by index or by part of its name.
+    pub port: Option<PortConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UdpConfig {
+    /// Where Message operators send their text, such as `"127.0.0.1:57120"`.
+    pub target: Option<String>,
+    /// Whether the text goes out as OSC messages.
+    pub osc: Option<bool>,
+}
+
+/// The ruler's spacing, as one number for both rows and columns or as `"ROWSxCOLS"`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum RulerConfig {
+    Cells(usize),
+    Spacing(String),
+}
+
+impl TryFrom<RulerConfig> for Ruler {
+    type Error = String;
+
+    fn try_from(config: RulerConfig) -> Result<Ruler, String> {
+        match config {
+            RulerConfig::Cells(cells) => Ok(Ruler { rows: cells, cols: cells }),
+            RulerConfig::Spacing(spacing) => spacing.parse().map_err(|()| format!("invalid ruler {}", spacing)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum PortConfig {
+    Index(usize),
+    Name(String),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Symbols {
+    One(char),
+    Many(Vec<char>),
+}
+
+/// Colors on top of a built-in theme, with the same settings as theme.txt.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ThemeConfig {
+    /// The built-in theme to start from.
+    pub name: Option<String>,
+    pub crosshair: Option<String>,
+    pub selection: Option<String>,
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub lock: Option<String>,
+    pub meter_sounding: Option<String>,
+    pub meter_quiet: Option<String>,
+    pub dim_empty: Option<bool>,
+}
+
+impl ThemeConfig {
+    /// The color settings that are set, as theme.txt setting names and values.
+    pub fn settings(&self) -> Vec<(&'static str, String)> {
+        let colors = [
+            ("crosshair", &self.crosshair),
+            ("selection", &self.selection),
+            ("input", &self.input),
+            ("output", &self.output),
+            ("lock", &self.lock),
+            ("meter-sounding", &self.meter_sounding),
+            ("meter-quiet", &self.meter_quiet),
+        ];
+        let mut settings: Vec<(&str, String)> =
+            colors.into_iter().filter_map(|(setting, value)| Some((setting, value.clone()?))).collect();
+        if let Some(dim_empty) = self.dim_empty {
+            settings.push(("dim-empty", if dim_empty { "yes" } else { "no" }.to_string()));
+        }
+        settings
+    }
+
+    fn layer(self, over: ThemeConfig) -> ThemeConfig {
+        ThemeConfig {
+            name: over.name.or(self.name),
+            crosshair: over.crosshair.or(self.crosshair),
+            selection: over.selection.or(self.selection),
+            input: over.input.or(self.input),
+            output: over.output.or(self.output),
+            lock: over.lock.or(self.lock),
+            meter_sounding: over.meter_sounding.or(self.meter_sounding),
+            meter_quiet: over.meter_quiet.or(self.meter_quiet),
+            dim_empty: over.dim_empty.or(self.dim_empty),
+        }
+    }
+}
+
+/// How the project's grids run, where it differs from one Orca to another, along with the tables
+/// its Weighted operators draw from.
+#[derive(Clone, Debug, Default)]
+pub struct Semantics {
+    pub evaluation: EvaluationMode,
+    pub orca_c_locks: bool,
+    pub random_tables: HashMap<char, Vec<char>>,
+}
+
+impl Semantics {
+    pub fn apply(&self, context: &mut Context) {
+        context.evaluation = self.evaluation;
+        context.orca_c_locks = self.orca_c_locks;
+        context.random_tables = self.random_tables.clone();
+    }
+}
+
+impl Config {
+    /// Layers `over` on top of this config, so that whatever `over` sets wins.
+    fn layer(mut self, over: Config) -> Config {
+        self.keys.extend(over.keys);
+        self.operators.extend(over.operators);
+        self.tables.extend(over.tables);
+        for (operator, ports) in over.defaults {
+            self.defaults.entry(operator).or_default().extend(ports);
+        }
+        Config {
+            grid: GridConfig {
+                rows: over.grid.rows.or(self.grid.rows),
+                cols: over.grid.cols.or(self.grid.cols),
+                bpm: over.grid.bpm.or(self.grid.bpm),
+                frames: over.grid.frames.or(self.grid.frames),
+                evaluation: over.grid.evaluation.or(self.grid.evaluation),
+                orca_c_locks: over.grid.orca_c_locks.or(self.grid.orca_c_locks),
+            },
+            editor: EditorConfig {
+                ruler: over.editor.ruler.or(self.editor.ruler),
+                vim: over.editor.vim.or(self.editor.vim),
+                autosave: over.editor.autosave.or(self.editor.autosave),
+                header: over.editor.header.or(self.editor.header),
+                quantize: over.editor.quantize.or(self.editor.quantize),
+            },
+            midi: MidiConfig { port: over.midi.port.or(self.midi.port) },
+            udp: UdpConfig { target: over.udp.target.or(self.udp.target), osc: over.udp.osc.or(self.udp.osc) },
+            theme: self.theme.layer(over.theme),
+            keys: self.keys,
+            operators: self.operators,
+            defaults: self.defaults,
+            tables: self.tables,
+        }
+    }
+
+    /// How grids run, with the default evaluation mode unless one is named. Refuses a table named
+    /// by one of Weighted's own shapes, which it could never be drawn from, and tables named by or
+    /// holding anything that isn't a cell.
+    pub fn semantics(&self) -> Result<Semantics, String> {
+        let evaluation = self.grid.evaluation.as_deref().map_or(Ok(EvaluationMode::default()), |name| {
+            EvaluationMode::from_name(name).map_err(|err| format!("config grid: {}", err))
+        })?;
+        let mut random_tables = HashMap::new();
+        for (&shape, values) in &self.tables {
+            if WEIGHTED_SHAPES.contains(&shape) {
+                return Err(format!("config tables: {} is one of Weighted's own shapes", shape));
+            }
+            // as with port defaults, each has to be a cell that can be written to the grid
+            let cell = |c: char| is_single_width(c) && !c.is_whitespace() && c != '.';
+            if let Some(c) = std::iter::once(shape).chain(values.chars()).find(|&c| !cell(c)) {
+                return Err(format!("config tables: {:?} can not be a cell of table {}", c, shape));
+            }
+            random_tables.insert(shape, values.chars().collect());
+        }
+        Ok(Semantics { evaluation, orca_c_locks: self.grid.orca_c_locks.unwrap_or(false), random_tables })
+    }
+
+    /// Reads the operator symbols from the operator config file, with the symbols set here on top.
+    /// Symbols given here replace the ones the operator had in the file.
+    pub fn operator_map(&self) -> Result<OperatorMap, String> {
+        let mut operator_map = read_operator_config(OPERATOR_CONFIG_FILE).map_err(|err| err.to_string())?;
+        let known = default_operator_config();
+        for (name, symbols) in &self.operators {
+            if !known.contains_key(name) {
+                return Err(format!("unknown operator {} in config", name));
+            }
+            operator_map.remove(name);
+            let symbols = match symbols {
+                Symbols::One(symbol) => std::slice::from_ref(symbol),
+                Symbols::Many(symbols) => symbols.as_slice(),
+            };
+            for &symbol in symbols {
+                bind_symbol(&mut operator_map, name, symbol);
+            }
+        }
+        Ok(operator_map)
+    }
+
+    /// Builds the tick and bang operator tables for `operator_map`, with the inputs' defaults set
+    /// here. Also returns a description of each default that could not be set.
+    pub fn operator_tables(&self, operator_map: &OperatorMap) -> (OperatorTable, OperatorTable, Vec<String>) {
+        let mut tick_operators = get_tick_operators(operator_map);
+        let mut bang_operators = get_bang_operators(operator_map);
+        let mut errors = Vec::new();
+        for (operator, ports) in &self.defaults {
+            for (port, &value) in ports {
+                let set = tick_operators
+                    .set_port_default(operator, port, value)
+                    .and_then(|()| bang_operators.set_port_default(operator, port, value));
+                if let Err(err) = set {
+                    errors.push(format!("config defaults: {}", err));
+                }
+            }
+        }
+        (tick_operators, bang_operators, errors)
+    }
+
+    /// Fills in the options left off the command line.
+    pub fn fill_args(&self, args: &mut Args) {
+        args.ruler = args.ruler.or(self.editor.ruler);
+        args.vim |= self.editor.vim.unwrap_or(false);
+        args.autosave = args.autosave.or(self.editor.autosave);
+        args.header |= self.editor.header.unwrap_or(false);
+        args.theme = args.theme.take().or_else(|| self.theme.name.clone());
+        args.midi = args.midi.take().or_else(|| {
+            self.midi.port.as_ref().map(|port| match port {
+                PortConfig::Index(index) => index.to_string(),
+                PortConfig::Name(name) => name.clone(),
+            })
+        });
+        args.udp = args.udp.take().or_else(|| self.udp.target.clone());
+        args.osc |= self.udp.osc.unwrap_or(false);
+    }
+}
+
+/// Where the global config file is kept, following the XDG base directory spec.
+fn global_config_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("rust-orca").join("config.toml"))
+}
+
+/// Reads one config file, which need not exist.
+fn read_config_file(path: &Path) -> Result<Config, String> {
+    match read_to_string(path) {
+        Ok(text) => toml::from_str(&text).map_err(|err| format!("{}: {}", path.display(), err)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Config::default()),
+        Err(err) => Err(format!("could not read {}: {}", path.display(), err)),
+    }
+}
+
+/// Reads the global config file and then the project's on top of it, describing where the first
+/// mistake in either is.
+pub fn load_config() -> Result<Config, String> {
+    let global = match global_config_path() {
+        Some(path) => read_config_file(&path)?,
+        None => Config::default(),
+    };
+    Ok(global.layer(read_config_file(Path::new(PROJECT_CONFIG_FILE))?))
+}
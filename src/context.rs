@@ -1,88 +1,692 @@
-use std::collections::{HashMap, HashSet};
+use core::ops::RangeInclusive;
+
+use crate::bitset::Bitset;
+use crate::engine::Hooks;
+use crate::error::OrcaError;
+use crate::history::{History, StepKind};
+use crate::metrics::Metrics;
 use crate::midi::MidiNote;
+use crate::operators::EvaluationMode;
+use crate::prelude::*;
+use crate::trace::Trace;
 
 
 #[derive(Clone)]
 #[derive(Debug)]
 pub struct Port {
-    pub name: String,
+    pub name: &'static str,
     pub row: i32,
     pub col: i32,
     pub value: char,
 }
 
 impl Port {
-    pub fn new(name: &str, row: i32, col: i32, value: char) -> Port {
-        Port { name: String::from(name), row, col, value }
+    pub fn new(name: &'static str, row: i32, col: i32, value: char) -> Port {
+        Port { name, row, col, value }
     }
 }
 
+/// A rectangular region of the grid; `rows` and `cols` are always at least one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Selection {
+    pub row: i32,
+    pub col: i32,
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl Selection {
+    /// The smallest selection containing both corners, given in either order.
+    pub fn spanning(anchor: (i32, i32), corner: (i32, i32)) -> Selection {
+        Selection {
+            row: anchor.0.min(corner.0),
+            col: anchor.1.min(corner.1),
+            rows: anchor.0.abs_diff(corner.0) as usize + 1,
+            cols: anchor.1.abs_diff(corner.1) as usize + 1,
+        }
+    }
+
+    pub fn contains(&self, row: i32, col: i32) -> bool {
+        self.row <= row && row < self.row + self.rows as i32 && self.col <= col && col < self.col + self.cols as i32
+    }
+}
+
+/// Cells copied out of a selection, in row-major order.
+#[derive(Clone, Debug, Default)]
+pub struct Block {
+    pub rows: usize,
+    pub cols: usize,
+    pub cells: Vec<char>,
+}
+
+/// An edit held back until the next launch: the cells it writes, and the tempo, frames per beat
+/// and seed it sets.
+#[derive(Clone, Debug, Default)]
+pub struct Launch {
+    pub cells: Vec<(i32, i32, char)>,
+    pub tempo: Option<u64>,
+    pub divisions: Option<u64>,
+    pub seed: Option<u64>,
+    /// Whether the edit replaces the whole grid, as opening a file does, so that the history
+    /// starts over from it rather than recording it.
+    pub fresh: bool,
+}
+
+/// A copy of a grid's cells under a one-character name, for the Layer operator of another grid
+/// playing alongside it to read from.
+#[derive(Clone, Debug, Default)]
+pub struct Layer {
+    pub name: char,
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<char>,
+}
+
+impl Layer {
+    /// The cell at `(row, col)`, empty outside the grid.
+    pub fn read(&self, row: i32, col: i32) -> char {
+        if (0..self.height as i32).contains(&row) && (0..self.width as i32).contains(&col) {
+            self.cells[row as usize * self.width + col as usize]
+        } else {
+            '\0'
+        }
+    }
+}
 
 pub struct Context {
-    pub grid: Vec<Vec<char>>,
+    /// Cells in row-major order; the cell at `(row, col)` lives at `row * width + col`.
+    grid: Vec<char>,
+    /// Indices of every non-empty cell, kept in sync with `grid` on each write so that ticks only
+    /// visit cells that could hold an operator.
+    occupied: Bitset,
+    /// Cells with a bang directly above, left of, or below them; kept in sync on each write so
+    /// that bang detection is a single bit test.
+    banged: Bitset,
+    /// Indices of cells that have had a bang written to them since bangs were last cleared; some
+    /// may have been overwritten since.
+    bangs: Vec<usize>,
     pub width: usize,
     pub height: usize,
     pub notes: Vec<MidiNote>,
-    pub locks: HashSet<(i32, i32)>,
+    /// The text Message operators sent on the last tick, for whatever runs the grid to send on.
+    pub messages: Vec<String>,
+    pub locks: Bitset,
     pub variables: HashMap<char, char>,
+    /// Variables set from outside the grid, which every tick starts with instead of none; a
+    /// Variable operator writing the same name overrides one for the rest of its tick.
+    pub globals: HashMap<char, char>,
     pub ticks: usize,
     pub tempo: u64,
+    /// A tempo to switch to when the next beat starts.
+    pub next_tempo: Option<u64>,
+    /// How many beats apart launches fall. Edits held back with `hold_back` wait for the next
+    /// launch, so that they land in time with the grid; 0 lets them through on the next tick.
+    pub launch_beats: u64,
+    /// Edits waiting for the next launch, in the order they were made.
+    pub launches: Vec<Launch>,
+    /// How many held back edits have been made, for the editor to catch up on.
+    pub launched: usize,
     pub divisions: u64,
     pub tick_time: u64,
+    pub seed: u64,
+    /// The order ticks visit cells in.
+    pub evaluation: EvaluationMode,
+    /// Whether Halt, Track and Push lock and write cells exactly as orca-c's do in the edge cases
+    /// where rust-orca differs: Halt only locks the cell below rather than writing it back, and
+    /// Track and Push with no length do nothing rather than acting as if the length were 1.
+    pub orca_c_locks: bool,
+    /// Values for the Weighted operator to draw from, by the shape that picks them. A value given
+    /// several times is drawn that many times as often.
+    pub random_tables: HashMap<char, Vec<char>>,
+    /// The grids playing alongside this one as they were when the last tick ended, for the Layer
+    /// operator to read from by name; whatever runs several grids at once fills it in.
+    pub layers: Vec<Layer>,
+    pub metrics: Metrics,
+    pub history: History,
+    /// What each operator read and wrote on the last tick, when enabled.
+    pub trace: Trace,
+    /// Closures each tick runs with what it sends out.
+    pub hooks: Hooks,
+}
+
+/// The most cells a grid can have, so that a size read from a malformed file can't run the
+/// engine out of memory.
+pub const MAX_CELLS: usize = 1 << 24;
+
+/// The tempos a grid can play at, in beats per minute; others are clamped into it.
+pub const TEMPO_RANGE: RangeInclusive<u64> = 1..=1000;
+/// How many frames a beat can be split into; other counts are clamped into it.
+pub const DIVISIONS_RANGE: RangeInclusive<u64> = 1..=64;
+
+// how long a tick lasts in milliseconds, never less than one
+fn tick_time(tempo: u64, divisions: u64) -> u64 {
+    (60000 / tempo.saturating_mul(divisions).max(1)).max(1)
+}
+
+/// Whether a grid of `cols` by `rows` cells is within `MAX_CELLS`.
+pub fn fits(cols: usize, rows: usize) -> bool {
+    cols.checked_mul(rows).is_some_and(|cells| cells <= MAX_CELLS)
 }
 
 impl Context {
-    pub fn new(grid: Vec<Vec<char>>, tempo: u64, divisions: u64) -> Context {
-        let width = grid[0].len();
-        let height = grid.len();
-        Context {
+    /// Panics if the grid doesn't have `width * height` cells or has more than `MAX_CELLS`;
+    /// `try_new` describes the mistake instead.
+    pub fn new(grid: Vec<char>, width: usize, height: usize, tempo: u64, divisions: u64) -> Context {
+        Context::try_new(grid, width, height, tempo, divisions).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// A context for a row-major grid of `width` by `height` cells, as long as it has that many.
+    pub fn try_new(grid: Vec<char>, width: usize, height: usize, tempo: u64, divisions: u64) -> Result<Context, OrcaError> {
+        if !fits(width, height) {
+            return Err(OrcaError::GridTooBig { cols: width, rows: height });
+        }
+        if grid.len() != width * height {
+            return Err(OrcaError::GridSize { cols: width, rows: height, cells: grid.len() });
+        }
+        let tempo = tempo.clamp(*TEMPO_RANGE.start(), *TEMPO_RANGE.end());
+        let divisions = divisions.clamp(*DIVISIONS_RANGE.start(), *DIVISIONS_RANGE.end());
+        let occupied = Context::index_occupied(&grid);
+        let mut context = Context {
             grid,
+            occupied,
+            banged: Bitset::new(width * height),
+            bangs: Vec::new(),
             width,
             height,
             notes: Vec::new(),
-            locks: HashSet::new(),
+            messages: Vec::new(),
+            locks: Bitset::new(width * height),
             variables: HashMap::new(),
+            globals: HashMap::new(),
             ticks: 0,
             tempo,
+            next_tempo: None,
+            launch_beats: 0,
+            launches: Vec::new(),
+            launched: 0,
             divisions,
-            tick_time: 60000 / (tempo * divisions),
+            tick_time: tick_time(tempo, divisions),
+            seed: 0,
+            evaluation: EvaluationMode::default(),
+            orca_c_locks: false,
+            random_tables: HashMap::new(),
+            layers: Vec::new(),
+            metrics: Metrics::default(),
+            history: History::disabled(),
+            trace: Trace::default(),
+            hooks: Hooks::default(),
+        };
+        context.index_banged();
+        Ok(context)
+    }
+
+    fn index_occupied(grid: &[char]) -> Bitset {
+        let mut occupied = Bitset::new(grid.len());
+        for (index, &value) in grid.iter().enumerate() {
+            if value != '\0' {
+                occupied.insert(index);
+            }
         }
+        occupied
     }
+
+    fn index_banged(&mut self) {
+        self.banged.clear();
+        self.bangs.clear();
+        for index in 0..self.grid.len() {
+            if self.grid[index] == '*' {
+                self.bangs.push(index);
+                let (row, col) = ((index / self.width) as i32, (index % self.width) as i32);
+                self.mark_banged(row + 1, col);
+                self.mark_banged(row, col + 1);
+                self.mark_banged(row - 1, col);
+            }
+        }
+    }
+
+    fn mark_banged(&mut self, row: i32, col: i32) {
+        if self.contains(row, col) {
+            self.banged.insert(row as usize * self.width + col as usize);
+        }
+    }
+
+    /// Recomputes the banged bit of the cell at `(row, col)` from its neighbors.
+    fn update_banged(&mut self, row: i32, col: i32) {
+        if self.contains(row, col) {
+            let index = row as usize * self.width + col as usize;
+            if self.read(row - 1, col) == '*' || self.read(row, col - 1) == '*' || self.read(row + 1, col) == '*' {
+                self.banged.insert(index);
+            } else {
+                self.banged.remove(index);
+            }
+        }
+    }
+
+    /// Returns the position of the first cell at or after `index` in row-major order that has a
+    /// bang above, left of, or below it.
+    #[inline]
+    pub fn next_banged(&self, index: usize) -> Option<(i32, i32)> {
+        self.banged.next_from(index).map(
+            |index| ((index / self.width) as i32, (index % self.width) as i32)
+        )
+    }
+
+    /// Erases every bang on the grid, visiting only the cells bangs were written to.
+    pub fn clear_bangs(&mut self) {
+        let mut bangs = core::mem::take(&mut self.bangs);
+        for &index in &bangs {
+            if self.grid[index] == '*' {
+                self.write((index / self.width) as i32, (index % self.width) as i32, '\0');
+            }
+        }
+        // hand the buffer back so its capacity is reused on the next tick
+        bangs.clear();
+        self.bangs = bangs;
+    }
+
+    /// The cells holding bangs that `clear_bangs` would erase, by index.
+    pub fn bang_cells(&self) -> Bitset {
+        let mut cells = Bitset::new(self.grid.len());
+        for &index in self.bangs.iter().filter(|&&index| self.grid[index] == '*') {
+            cells.insert(index);
+        }
+        cells
+    }
+
+    /// Returns whether the cell at `(row, col)` has a bang above, left of, or below it.
+    #[inline]
+    pub fn is_banged(&self, row: i32, col: i32) -> bool {
+        self.contains(row, col) && self.banged.contains(row as usize * self.width + col as usize)
+    }
+
     #[allow(dead_code)]
+    #[cfg(feature = "std")]
     pub fn display(&self) {
-        let rows = self.grid.len();
-        let cols = self.grid[0].len();
-        for row in 0..rows {
-            for col in 0..cols {
-                print!("{}", self.grid[row][col]);
+        for row in self.grid.chunks(self.width) {
+            for c in row {
+                print!("{}", c);
             }
             println!();
         }
         println!("{:?}", self.notes);
     }
 
+    pub fn grid(&self) -> &[char] {
+        &self.grid
+    }
+
+    /// Returns the position of the first non-empty cell at or after `index` in row-major order.
+    #[inline]
+    pub fn next_occupied(&self, index: usize) -> Option<(i32, i32)> {
+        self.occupied.next_from(index).map(
+            |index| ((index / self.width) as i32, (index % self.width) as i32)
+        )
+    }
+
+    #[inline]
+    pub fn contains(&self, row: i32, col: i32) -> bool {
+        0 <= row && (row as usize) < self.height && 0 <= col && (col as usize) < self.width
+    }
+
+    #[inline]
     pub fn read(&self, row: i32, col: i32) -> char {
-        let row = row as usize;
-        let col = col as usize;
-        if 0 <= row && row < self.height && 0 <= col && col < self.width {
-            self.grid[row][col]
+        if self.contains(row, col) {
+            self.grid[row as usize * self.width + col as usize]
         } else {
             '\0'
         }
     }
 
-    pub fn listen(&self, name: &str, row: i32, col: i32, default: char) -> Port {
+    /// A copy of the grid for the grids playing alongside it, under `name`.
+    pub fn layer(&self, name: char) -> Layer {
+        Layer { name, width: self.width, height: self.height, cells: self.grid.clone() }
+    }
+
+    /// A copy of the grid and what ticks left around it, without the history, hooks or other
+    /// grids, for a front end to draw from while this context plays on.
+    pub fn view(&self) -> Context {
+        Context {
+            grid: self.grid.clone(),
+            occupied: self.occupied.clone(),
+            banged: self.banged.clone(),
+            bangs: self.bangs.clone(),
+            width: self.width,
+            height: self.height,
+            notes: self.notes.clone(),
+            messages: self.messages.clone(),
+            locks: self.locks.clone(),
+            variables: self.variables.clone(),
+            globals: self.globals.clone(),
+            ticks: self.ticks,
+            tempo: self.tempo,
+            next_tempo: self.next_tempo,
+            launch_beats: self.launch_beats,
+            launches: self.launches.clone(),
+            launched: self.launched,
+            divisions: self.divisions,
+            tick_time: self.tick_time,
+            seed: self.seed,
+            evaluation: self.evaluation,
+            orca_c_locks: self.orca_c_locks,
+            random_tables: self.random_tables.clone(),
+            layers: Vec::new(),
+            metrics: self.metrics.clone(),
+            history: History::disabled(),
+            trace: self.trace.clone(),
+            hooks: Hooks::default(),
+        }
+    }
+
+    pub fn listen(&self, name: &'static str, row: i32, col: i32, default: char) -> Port {
         let value = self.read(row, col);
         let value = if value == '\0' { default } else { value };
         Port::new(name, row, col, value)
     }
 
+    #[inline]
     pub fn write(&mut self, row: i32, col: i32, value: char) {
-        let row = row as usize;
-        let col = col as usize;
-        if 0 <= row && row < self.height && 0 <= col && col < self.width {
-            self.grid[row][col] = value;
+        if self.contains(row, col) {
+            let index = row as usize * self.width + col as usize;
+            let previous = core::mem::replace(&mut self.grid[index], value);
+            if value == '\0' {
+                self.occupied.remove(index);
+            } else {
+                self.occupied.insert(index);
+            }
+            self.history.record(index, previous, value);
+            if value == '*' && previous != '*' {
+                self.bangs.push(index);
+            }
+            // only the cells this one can bang are affected: below, right and above it
+            if (previous == '*') != (value == '*') {
+                self.update_banged(row + 1, col);
+                self.update_banged(row, col + 1);
+                self.update_banged(row - 1, col);
+            }
+        }
+    }
+
+    /// Writes a single cell, recorded as an edit of its own rather than as part of a tick.
+    pub fn edit(&mut self, row: i32, col: i32, value: char) {
+        self.history.begin(StepKind::Edit);
+        self.write(row, col, value);
+        self.history.end();
+    }
+
+    /// Returns a copy of the cells in `selection`; cells outside the grid are copied as empty.
+    pub fn copy(&self, selection: Selection) -> Block {
+        let mut cells = Vec::with_capacity(selection.rows * selection.cols);
+        for row in selection.row..selection.row + selection.rows as i32 {
+            for col in selection.col..selection.col + selection.cols as i32 {
+                cells.push(self.read(row, col));
+            }
+        }
+        Block { rows: selection.rows, cols: selection.cols, cells }
+    }
+
+    /// Empties every cell in `selection`, recorded as a single edit.
+    pub fn erase(&mut self, selection: Selection) {
+        self.history.begin(StepKind::Edit);
+        for row in selection.row..selection.row + selection.rows as i32 {
+            for col in selection.col..selection.col + selection.cols as i32 {
+                self.write(row, col, '\0');
+            }
+        }
+        self.history.end();
+    }
+
+    /// Writes `pattern` along each row of `selection`, repeating it to the end of the row, recorded
+    /// as a single edit. Every row starts from the beginning of the pattern, so that columns line up.
+    pub fn fill(&mut self, selection: Selection, pattern: &[char]) {
+        if pattern.is_empty() {
+            return;
+        }
+        self.history.begin(StepKind::Edit);
+        for row in selection.row..selection.row + selection.rows as i32 {
+            for (col, &value) in (selection.col..selection.col + selection.cols as i32).zip(pattern.iter().cycle()) {
+                self.write(row, col, value);
+            }
         }
+        self.history.end();
+    }
+
+    /// Comments out each row of `selection` by putting a `#` at both of its ends, or uncomments
+    /// them if the first row already starts with one. Recorded as a single edit.
+    pub fn toggle_comment(&mut self, selection: Selection) {
+        let (left, right) = (selection.col, selection.col + selection.cols as i32 - 1);
+        let value = if self.read(selection.row, left) == '#' { '\0' } else { '#' };
+        self.history.begin(StepKind::Edit);
+        for row in selection.row..selection.row + selection.rows as i32 {
+            self.write(row, left, value);
+            self.write(row, right, value);
+        }
+        self.history.end();
+    }
+
+    pub fn cut(&mut self, selection: Selection) -> Block {
+        let block = self.copy(selection);
+        self.erase(selection);
+        block
+    }
+
+    /// Writes `block` with its top left corner at `(row, col)`, clipped to the grid and recorded
+    /// as a single edit. A transparent paste skips the block's empty cells, leaving whatever is
+    /// underneath them.
+    pub fn paste(&mut self, row: i32, col: i32, block: &Block, transparent: bool) {
+        self.history.begin(StepKind::Edit);
+        for (r, cells) in block.cells.chunks(block.cols.max(1)).enumerate() {
+            for (c, &value) in cells.iter().enumerate() {
+                if !(transparent && value == '\0') {
+                    self.write(row + r as i32, col + c as i32, value);
+                }
+            }
+        }
+        self.history.end();
+    }
+
+    /// Writes `value` at `(row, col)`, first shifting the run of non-empty cells that starts there
+    /// one cell right into the empty cell after it. A run that reaches the edge of the grid loses
+    /// its last cell. Recorded as a single edit.
+    pub fn insert(&mut self, row: i32, col: i32, value: char) {
+        self.history.begin(StepKind::Edit);
+        let mut end = col;
+        while end + 1 < self.width as i32 && self.read(row, end) != '\0' {
+            end += 1;
+        }
+        for c in (col..end).rev() {
+            let shifted = self.read(row, c);
+            self.write(row, c + 1, shifted);
+        }
+        self.write(row, col, value);
+        self.history.end();
+    }
+
+    /// Removes the cell at `(row, col)`, shifting the run of non-empty cells after it one cell left.
+    /// Recorded as a single edit.
+    pub fn delete(&mut self, row: i32, col: i32) {
+        self.history.begin(StepKind::Edit);
+        let mut c = col;
+        while self.contains(row, c) && (c == col || self.read(row, c) != '\0') {
+            let shifted = self.read(row, c + 1);
+            self.write(row, c, shifted);
+            c += 1;
+        }
+        self.history.end();
+    }
+
+    /// Runs `edit`, then takes back whatever it changed in the grid, tempo and seed and holds it
+    /// for the next launch. The edit goes unrecorded until it is made at the launch.
+    pub fn hold_back<R>(&mut self, edit: impl FnOnce(&mut Context) -> R) -> R {
+        let (grid, tempo, divisions, seed) = (self.grid.clone(), self.tempo, self.divisions, self.seed);
+        let history = core::mem::take(&mut self.history);
+        let result = edit(self);
+        let changed: Vec<usize> = (0..grid.len()).filter(|&index| grid[index] != self.grid[index]).collect();
+        let mut launch = Launch::default();
+        for index in changed {
+            let (row, col) = ((index / self.width) as i32, (index % self.width) as i32);
+            launch.cells.push((row, col, self.grid[index]));
+            self.write(row, col, grid[index]);
+        }
+        if self.tempo != tempo {
+            launch.tempo = Some(self.tempo);
+            self.set_tempo(tempo);
+        }
+        if self.divisions != divisions {
+            launch.divisions = Some(self.divisions);
+            self.set_divisions(divisions);
+        }
+        if self.seed != seed {
+            launch.seed = Some(self.seed);
+            self.reseed(seed);
+        }
+        self.history = history;
+        if !launch.cells.is_empty() || launch.tempo.is_some() || launch.divisions.is_some() || launch.seed.is_some() {
+            self.launches.push(launch);
+        }
+        result
+    }
+
+    /// Whether held back edits are made on the coming tick.
+    pub fn is_launch_tick(&self) -> bool {
+        // a period too long to count up to only launches on the first tick
+        let beats = usize::try_from(self.launch_beats).unwrap_or(usize::MAX);
+        let divisions = usize::try_from(self.divisions).unwrap_or(usize::MAX);
+        self.launch_beats == 0 || self.ticks.is_multiple_of(beats.saturating_mul(divisions))
+    }
+
+    /// Makes every held back edit, each recorded as an edit of its own, besides fresh ones, which
+    /// start the history over.
+    pub fn launch(&mut self) {
+        for launch in core::mem::take(&mut self.launches) {
+            if !launch.fresh {
+                self.history.begin(StepKind::Edit);
+            }
+            for (row, col, value) in launch.cells {
+                self.write(row, col, value);
+            }
+            self.history.end();
+            if launch.fresh {
+                self.history.clear();
+            }
+            self.launched += 1;
+            if let Some(tempo) = launch.tempo {
+                self.set_tempo(tempo);
+            }
+            if let Some(divisions) = launch.divisions {
+                self.set_divisions(divisions);
+            }
+            if let Some(seed) = launch.seed {
+                self.reseed(seed);
+            }
+        }
+    }
+
+    /// Panics if the grid doesn't have as many cells as the one it replaces; `try_replace_grid`
+    /// describes the mistake instead.
+    pub fn replace_grid(&mut self, grid: Vec<char>) {
+        self.try_replace_grid(grid).unwrap_or_else(|err| panic!("{}", err));
+    }
+
+    pub fn try_replace_grid(&mut self, grid: Vec<char>) -> Result<(), OrcaError> {
+        if grid.len() != self.width * self.height {
+            return Err(OrcaError::GridSize { cols: self.width, rows: self.height, cells: grid.len() });
+        }
+        self.occupied = Context::index_occupied(&grid);
+        self.grid = grid;
+        self.index_banged();
+        self.history.clear();
+        Ok(())
+    }
+
+    /// Reverts the most recent step recorded in the history, returning what kind of step it was.
+    /// Stepping back over a tick also rewinds the tick counter.
+    pub fn step_back(&mut self) -> Option<StepKind> {
+        let (kind, deltas) = self.history.step_back()?;
+        for delta in deltas {
+            self.apply_delta(delta.index as usize, delta.xor);
+        }
+        if kind == StepKind::Tick {
+            self.ticks = self.ticks.saturating_sub(1);
+        }
+        Some(kind)
+    }
+
+    /// Re-applies the most recently reverted step, returning what kind of step it was.
+    pub fn step_forward(&mut self) -> Option<StepKind> {
+        let (kind, deltas) = self.history.step_forward()?;
+        for delta in deltas {
+            self.apply_delta(delta.index as usize, delta.xor);
+        }
+        if kind == StepKind::Tick {
+            self.ticks += 1;
+        }
+        Some(kind)
+    }
+
+    /// Steps back until the most recent edit has been reverted, rewinding any ticks that ran after
+    /// it. Returns false if there is no edit left in the history.
+    pub fn undo(&mut self) -> bool {
+        while let Some(kind) = self.step_back() {
+            if kind == StepKind::Edit {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Re-applies the most recently undone edit along with the ticks that followed it. Returns
+    /// false if there is nothing to redo, which is also the case once anything new has been
+    /// recorded since the undo.
+    pub fn redo(&mut self) -> bool {
+        if self.history.next_redo_kind() != Some(StepKind::Edit) {
+            return false;
+        }
+        self.step_forward();
+        while self.history.next_redo_kind() == Some(StepKind::Tick) {
+            self.step_forward();
+        }
+        true
+    }
+
+    fn apply_delta(&mut self, index: usize, xor: u32) {
+        let value = char::from_u32(self.grid[index] as u32 ^ xor).unwrap_or('\0');
+        self.write((index / self.width) as i32, (index % self.width) as i32, value);
+    }
+
+    /// Sets the tempo in beats per minute, along with the tick length that depends on it.
+    pub fn set_tempo(&mut self, tempo: u64) {
+        self.tempo = tempo.clamp(*TEMPO_RANGE.start(), *TEMPO_RANGE.end());
+        self.tick_time = tick_time(self.tempo, self.divisions);
+    }
+
+    /// Sets how many frames make up a beat, along with the tick length that depends on it.
+    pub fn set_divisions(&mut self, divisions: u64) {
+        self.divisions = divisions.clamp(*DIVISIONS_RANGE.start(), *DIVISIONS_RANGE.end());
+        self.tick_time = tick_time(self.tempo, self.divisions);
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// Returns a pseudo-random number for the cell at `(row, col)` on the current tick. The value
+    /// is a splitmix64 hash of the seed, tick and position rather than the next output of a
+    /// stateful generator, so it is fully determined by the seed and doesn't depend on the order
+    /// in which operators are evaluated.
+    pub fn random(&self, row: i32, col: i32) -> u64 {
+        let position = (row as u32 as u64) << 32 | col as u32 as u64;
+        splitmix(self.seed
+            .wrapping_add((self.ticks as u64).wrapping_mul(0x9e3779b97f4a7c15))
+            .wrapping_add(position.wrapping_mul(0xbf58476d1ce4e5b9)))
+    }
+
+    /// Returns the current tick's number in a stream of pseudo-random numbers of its own, given
+    /// by `seed` alone rather than by the context's seed and a cell, so that every draw with the
+    /// same seed on the same tick agrees wherever it is made.
+    pub fn random_from(&self, seed: u64) -> u64 {
+        splitmix(seed
+            .wrapping_mul(0xbf58476d1ce4e5b9)
+            .wrapping_add((self.ticks as u64).wrapping_mul(0x9e3779b97f4a7c15)))
     }
 
     pub fn write_note(&mut self, note: MidiNote) {
@@ -97,19 +701,31 @@ impl Context {
         *self.variables.get(&name).unwrap_or(&'\0')
     }
 
+    /// Forgets the variables operators wrote, leaving only the globals.
     pub fn clear_all_variables(&mut self) {
-        self.variables = HashMap::new();
+        self.variables.clear();
+        self.variables.extend(self.globals.iter().map(|(&name, &value)| (name, value)));
     }
 
+    #[inline]
     pub fn lock(&mut self, row: i32, col: i32) {
-        self.locks.insert((row, col));
+        if self.contains(row, col) {
+            self.locks.insert(row as usize * self.width + col as usize);
+        }
     }
 
+    #[inline]
     pub fn is_locked(&self, row: i32, col: i32) -> bool {
-        self.locks.contains(&(row, col))
+        self.contains(row, col) && self.locks.contains(row as usize * self.width + col as usize)
     }
 
     pub fn unlock_all(&mut self) {
-        self.locks = HashSet::new();
+        self.locks.clear();
     }
 }
+
+fn splitmix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
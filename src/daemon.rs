@@ -0,0 +1,457 @@
+//! Runs a grid in the background behind a Unix socket, for supervisors and other processes to
+//! manage long-running installations with. Each line a client sends is a JSON request, and each
+//! request is answered with a line of JSON:
+//!
+//! - `{"cmd": "load", "path": "grid.orca"}` or `{"cmd": "load", "text": "..."}` replaces the grid
+//!   with one at the size of the new one
+//! - `{"cmd": "play"}` and `{"cmd": "pause"}` start and stop the clock, and `{"cmd": "step",
+//!   "ticks": 4}` runs ticks while it is paused
+//! - `{"cmd": "bpm", "bpm": 140}` sets the tempo
+//! - `{"cmd": "write", "row": 2, "col": 3, "text": "D4"}` writes text rightwards from a cell
+//! - `{"cmd": "read"}` answers with the grid as .orca text, `{"cmd": "read", "row": 2, "col": 3}`
+//!   with one cell, and `{"cmd": "status"}` with the size of the grid and the state of the clock
+//! - `{"cmd": "variable", "name": "a", "value": "3"}` sets a variable every tick starts with, one
+//!   without a value forgets it, and `{"cmd": "variables"}` answers with those of the last tick
+//! - `{"cmd": "schedule", "tick": 64, "request": {...}}` runs a write, variable or bpm request once
+//!   the grid has run 64 ticks, or with `"after": 4` once it has run 4 more, answering with the
+//!   tick; `{"cmd": "cancel"}` forgets every request still waiting
+//! - `{"cmd": "operators"}` describes every operator: its symbols, ports and what it does
+//! - `{"cmd": "subscribe"}` sends the client every tick and MIDI message from then on
+//! - `{"cmd": "shutdown"}` stops the daemon
+//!
+//! Answers are `{"ok": true}` along with anything asked for, or `{"ok": false, "error": "..."}`.
+//! A subscribed client gets `{"tick": 4, "type": "tick"}` as each tick runs, followed by the MIDI
+//! the tick sent as `--events` writes it, and `{"error": "...", "type": "error"}` when the text of
+//! a Message operator could not be sent over UDP. A subscribed client that falls too far behind on
+//! reading its lines is hung up on.
+//!
+//! [`Daemon::serve_metrics`] also answers HTTP requests for `/metrics` with tick timings, notes
+//! played, ticks that ran late and notes sounding, for Prometheus to scrape.
+
+use std::collections::BTreeMap;
+use std::fs::remove_file;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::mem;
+use std::net::{Shutdown, SocketAddr};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::context::Context;
+use crate::engine::{Transport, tick};
+use crate::error::OrcaError;
+use crate::events::event_to_json;
+use crate::fs::{grid_to_string, parse_grid, parse_header, read_file, text_size};
+use crate::metrics::serve_metrics;
+use crate::midi::{MidiCapture, MidiSink};
+use crate::operators::{OperatorInfo, OperatorTable, PortInfo};
+use crate::scheduler::Scheduler;
+use crate::udp::UdpOutput;
+
+// how often a paused clock checks whether it has been asked to step or play
+const PAUSED_POLL_TIME: Duration = Duration::from_millis(5);
+// how many lines a client can fall behind on reading before a subscribed one is hung up on
+const CLIENT_BACKLOG: usize = 1024;
+
+/// A line sent by a client, named by its `cmd`.
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case", deny_unknown_fields)]
+enum Request {
+    Load { path: Option<PathBuf>, text: Option<String> },
+    Play,
+    Pause,
+    Step {
+        #[serde(default = "one")]
+        ticks: usize,
+    },
+    Bpm { bpm: u64 },
+    Write { row: i32, col: i32, text: String },
+    Read { row: Option<i32>, col: Option<i32> },
+    Variable { name: char, value: Option<char> },
+    Variables,
+    Schedule { tick: Option<usize>, after: Option<usize>, request: Box<Request> },
+    Cancel,
+    Status,
+    Operators,
+    Subscribe,
+    Shutdown,
+}
+
+fn one() -> usize {
+    1
+}
+
+/// The grid and everything that plays it, shared between the clock and the clients.
+struct Engine {
+    context: Context,
+    tick_operators: OperatorTable,
+    bang_operators: OperatorTable,
+    midi: Box<dyn MidiSink + Send>,
+    /// Where the text of Message operators goes, if anywhere.
+    udp: Option<UdpOutput>,
+    capture: MidiCapture,
+    /// Where subscribed clients' lines go.
+    subscribers: Vec<Subscriber>,
+    /// Requests waiting for the grid to have run as many ticks as they were scheduled for, in the
+    /// order they were made.
+    scheduled: Vec<(usize, Request)>,
+}
+
+impl Engine {
+    fn tick(&mut self) {
+        let ticks = self.context.ticks;
+        self.capture.tick = ticks;
+        tick(&mut self.context, &self.tick_operators, &self.bang_operators, &mut self.capture);
+        self.send(Some(ticks));
+        let ran = self.context.ticks;
+        if self.scheduled.iter().any(|&(tick, _)| tick <= ran) {
+            let (due, waiting) = mem::take(&mut self.scheduled).into_iter().partition(|&(tick, _)| tick <= ran);
+            self.scheduled = waiting;
+            for (_, request) in due {
+                // a request that fails now has no one left to tell
+                let _ = self.apply(request);
+            }
+        }
+    }
+
+    /// Stops every sounding note.
+    fn stop_notes(&mut self) {
+        for note in self.context.notes.drain(..).filter(|note| note.started) {
+            note.stop(&mut self.capture);
+        }
+        self.send(None);
+    }
+
+    /// Sends the captured MIDI and the tick's Message text on and to every subscriber, after a
+    /// line for the tick if it was sent on one. Subscribers that have gone away are dropped.
+    fn send(&mut self, tick: Option<usize>) {
+        let mut lines: Vec<String> = tick.map(|tick| format!("{{\"tick\": {}, \"type\": \"tick\"}}", tick)).into_iter().collect();
+        for (tick, message) in self.capture.messages.drain(..) {
+            // a message the device turns down is dropped, and playback carries on without it
+            let _ = self.midi.send(&message);
+            lines.push(event_to_json(tick, &message));
+        }
+        if let (Some(udp), Some(_)) = (&self.udp, tick) {
+            for message in &self.context.messages {
+                if let Err(err) = udp.send(message) {
+                    lines.push(json!({"type": "error", "error": format!("could not send {:?} over udp: {}", message, err)}).to_string());
+                }
+            }
+        }
+        self.subscribers.retain(|subscriber| {
+            let kept_up = lines.iter().all(|line| subscriber.lines.try_send(line.clone()).is_ok());
+            // one too slow to keep up would otherwise have its lines pile up without end
+            if !kept_up {
+                let _ = subscriber.stream.shutdown(Shutdown::Both);
+            }
+            kept_up
+        });
+    }
+
+    /// Replaces the grid with .orca text, at the size and tempo its header records if it has one
+    /// and at the size of the text otherwise.
+    fn load(&mut self, text: &str) -> Result<(), OrcaError> {
+        let header = parse_header(text);
+        let (cols, rows) = header.as_ref().map_or_else(|| text_size(text), |header| (header.cols, header.rows));
+        if cols == 0 || rows == 0 {
+            return Err(OrcaError::parse("no grid to load".to_string()));
+        }
+        let mut context = Context::try_new(parse_grid(text, rows, cols), cols, rows, self.context.tempo, self.context.divisions)?;
+        context.reseed(self.context.seed);
+        context.evaluation = self.context.evaluation;
+        context.orca_c_locks = self.context.orca_c_locks;
+        context.random_tables = mem::take(&mut self.context.random_tables);
+        context.globals = mem::take(&mut self.context.globals);
+        context.hooks = mem::take(&mut self.context.hooks);
+        // the metrics are the daemon's, and run on across grids
+        context.metrics = mem::take(&mut self.context.metrics);
+        if let Some(header) = header {
+            header.apply(&mut context);
+        }
+        self.stop_notes();
+        self.context = context;
+        Ok(())
+    }
+
+    /// Carries out a request that changes the grid, which is all a scheduled request can be.
+    fn apply(&mut self, request: Request) -> Result<(), String> {
+        match request {
+            Request::Bpm { bpm } => self.context.set_tempo(bpm),
+            Request::Write { row, col, text } => {
+                if !self.context.contains(row, col) {
+                    return Err(format!("no cell at row {} col {}", row, col));
+                }
+                for (i, value) in text.chars().enumerate() {
+                    self.context.edit(row, col + i as i32, if value == '.' { '\0' } else { value });
+                }
+            }
+            Request::Variable { name, value: Some(value) } => {
+                self.context.globals.insert(name, value);
+                self.context.set_variable(name, value);
+            }
+            Request::Variable { name, value: None } => {
+                self.context.globals.remove(&name);
+                self.context.variables.remove(&name);
+            }
+            _ => return Err("only write, variable and bpm requests can be scheduled".to_string()),
+        }
+        Ok(())
+    }
+}
+
+/// Where a client's lines go, along with its socket for hanging up on it.
+struct Subscriber {
+    lines: SyncSender<String>,
+    stream: UnixStream,
+}
+
+/// A grid waiting to be served on a socket.
+#[derive(Clone)]
+pub struct Daemon {
+    engine: Arc<Mutex<Engine>>,
+    transport: Arc<Transport>,
+}
+
+impl Daemon {
+    /// Plays `context` through `midi` with the given operators, starting as soon as it is served.
+    pub fn new(context: Context, tick_operators: OperatorTable, bang_operators: OperatorTable, midi: Box<dyn MidiSink + Send>) -> Daemon {
+        let engine = Engine { context, tick_operators, bang_operators, midi, udp: None, capture: MidiCapture::default(), subscribers: Vec::new(), scheduled: Vec::new() };
+        Daemon { engine: Arc::new(Mutex::new(engine)), transport: Arc::default() }
+    }
+
+    /// Sends the text of Message operators to `udp` from then on.
+    pub fn send_messages_to(&self, udp: UdpOutput) {
+        self.engine().udp = Some(udp);
+    }
+
+    /// Listens on a socket at `path` until a client asks for a shutdown, running the grid all the
+    /// while. A socket left behind by a daemon that is no longer running is replaced, but one
+    /// that is still answering is not.
+    pub fn serve(&self, path: &Path) -> Result<(), OrcaError> {
+        let socket_error = |source| OrcaError::Socket { address: path.display().to_string(), source };
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(socket_error(io::Error::new(ErrorKind::AddrInUse, "another daemon is listening")));
+            }
+            remove_file(path).map_err(socket_error)?;
+        }
+        let listener = UnixListener::bind(path).map_err(socket_error)?;
+
+        let daemon = self.clone();
+        let clock = thread::spawn(move || daemon.run_clock());
+        for stream in listener.incoming() {
+            if self.transport.is_stopped() {
+                break;
+            }
+            // a client that could not connect is its own problem, not the daemon's
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let (daemon, path) = (self.clone(), path.to_path_buf());
+            thread::spawn(move || daemon.serve_client(stream, &path));
+        }
+        let _ = clock.join();
+        let _ = remove_file(path);
+        Ok(())
+    }
+
+    /// Answers HTTP requests for `/metrics` at `address` from a thread of its own, returning the
+    /// address it is listening on.
+    pub fn serve_metrics(&self, address: &str) -> Result<SocketAddr, OrcaError> {
+        let daemon = self.clone();
+        serve_metrics(address, move || {
+            let engine = daemon.engine();
+            let active_notes = engine.context.notes.iter().filter(|note| note.started).count();
+            engine.context.metrics.to_prometheus(active_notes)
+        })
+    }
+
+    fn engine(&self) -> MutexGuard<'_, Engine> {
+        // a client thread that panicked leaves the grid as usable as it was
+        self.engine.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn run_clock(&self) {
+        let mut was_paused = false;
+        let mut scheduler = Scheduler::new();
+        while !self.transport.is_stopped() {
+            if !self.transport.should_tick() {
+                // silence anything still sounding when playback is paused
+                if !was_paused {
+                    self.engine().stop_notes();
+                    was_paused = true;
+                }
+                scheduler.reset();
+                sleep(PAUSED_POLL_TIME);
+                continue;
+            }
+            was_paused = self.transport.is_paused();
+            let period = {
+                let engine = self.engine();
+                Duration::from_secs_f64(60.0 / (engine.context.tempo * engine.context.divisions) as f64)
+            };
+            let jitter = scheduler.wait(period);
+            let timer = Instant::now();
+            let mut engine = self.engine();
+            engine.tick();
+            engine.context.metrics.record_jitter(jitter);
+            if timer.elapsed() > period {
+                engine.context.metrics.late_ticks += 1;
+            }
+        }
+        self.engine().stop_notes();
+    }
+
+    /// Answers a client's requests until it hangs up or asks for a shutdown.
+    fn serve_client(&self, stream: UnixStream, path: &Path) {
+        let (Ok(mut writer), Ok(hangup)) = (stream.try_clone(), stream.try_clone()) else {
+            return;
+        };
+        // answers and events share one writer, so that lines never interleave
+        let (sender, receiver) = mpsc::sync_channel::<String>(CLIENT_BACKLOG);
+        let client = Subscriber { lines: sender, stream: hangup };
+        thread::spawn(move || {
+            for line in receiver {
+                if writeln!(writer, "{}", line).is_err() {
+                    break;
+                }
+            }
+        });
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let answer = match serde_json::from_str(&line) {
+                Ok(request) => self.handle(request, &client),
+                Err(err) => Err(format!("invalid request: {}", err)),
+            };
+            let answer = match answer {
+                Ok(mut answer) => {
+                    answer["ok"] = json!(true);
+                    answer
+                }
+                Err(error) => json!({"ok": false, "error": error}),
+            };
+            if client.lines.send(answer.to_string()).is_err() {
+                break;
+            }
+            if self.transport.is_stopped() {
+                // wake the listener, which is waiting for a connection, so that it sees the stop
+                let _ = UnixStream::connect(path);
+                break;
+            }
+        }
+    }
+
+    fn handle(&self, request: Request, client: &Subscriber) -> Result<Value, String> {
+        match request {
+            Request::Load { path, text } => {
+                let text = match (path, text) {
+                    (Some(path), None) => read_file(&path).map_err(|err| err.to_string())?,
+                    (None, Some(text)) => text,
+                    _ => return Err("load needs either a path or text".to_string()),
+                };
+                self.engine().load(&text).map_err(|err| err.to_string())?;
+            }
+            Request::Play => self.transport.set_paused(false),
+            Request::Pause => self.transport.set_paused(true),
+            Request::Step { ticks } => {
+                if !self.transport.is_paused() {
+                    return Err("step needs the clock paused".to_string());
+                }
+                self.transport.step_by(ticks);
+            }
+            request @ (Request::Bpm { .. } | Request::Write { .. } | Request::Variable { .. }) => {
+                self.engine().apply(request)?;
+            }
+            Request::Read { row: None, col: None } => {
+                let engine = self.engine();
+                return Ok(json!({"grid": grid_to_string(engine.context.grid(), engine.context.width)}));
+            }
+            Request::Read { row: Some(row), col: Some(col) } => {
+                let engine = self.engine();
+                if !engine.context.contains(row, col) {
+                    return Err(format!("no cell at row {} col {}", row, col));
+                }
+                let value = engine.context.read(row, col);
+                return Ok(json!({"value": if value == '\0' { '.' } else { value }}));
+            }
+            Request::Read { .. } => return Err("read needs both a row and a col, or neither".to_string()),
+            Request::Variables => {
+                let engine = self.engine();
+                let variables: BTreeMap<String, char> =
+                    engine.context.variables.iter().map(|(name, &value)| (name.to_string(), value)).collect();
+                return Ok(json!({"variables": variables}));
+            }
+            Request::Schedule { tick, after, request } => {
+                let mut engine = self.engine();
+                let tick = match (tick, after) {
+                    (Some(tick), None) => tick,
+                    (None, Some(after)) => engine.context.ticks + after,
+                    _ => return Err("schedule needs either a tick or after".to_string()),
+                };
+                if tick <= engine.context.ticks {
+                    return Err(format!("tick {} has already passed", tick));
+                }
+                match *request {
+                    Request::Write { row, col, .. } if !engine.context.contains(row, col) => {
+                        return Err(format!("no cell at row {} col {}", row, col));
+                    }
+                    Request::Bpm { .. } | Request::Write { .. } | Request::Variable { .. } => {}
+                    _ => return Err("only write, variable and bpm requests can be scheduled".to_string()),
+                }
+                engine.scheduled.push((tick, *request));
+                return Ok(json!({"tick": tick}));
+            }
+            Request::Cancel => self.engine().scheduled.clear(),
+            Request::Status => {
+                let engine = self.engine();
+                let context = &engine.context;
+                return Ok(json!({
+                    "ticks": context.ticks,
+                    "bpm": context.tempo,
+                    "paused": self.transport.is_paused(),
+                    "rows": context.height,
+                    "cols": context.width,
+                    "scheduled": engine.scheduled.len(),
+                }));
+            }
+            Request::Operators => {
+                let registry = self.engine().tick_operators.registry();
+                return Ok(json!({"operators": registry.iter().map(operator_to_json).collect::<Vec<_>>()}));
+            }
+            Request::Subscribe => {
+                let stream = client.stream.try_clone().map_err(|err| err.to_string())?;
+                self.engine().subscribers.push(Subscriber { lines: client.lines.clone(), stream });
+            }
+            Request::Shutdown => self.transport.stop(),
+        }
+        Ok(json!({}))
+    }
+}
+
+fn operator_to_json(info: &OperatorInfo) -> Value {
+    let ports = |ports: &[PortInfo]| -> Vec<Value> {
+        ports
+            .iter()
+            .map(|port| json!({"name": port.name, "row": port.row, "col": port.col, "default": port.default}))
+            .collect()
+    };
+    json!({
+        "name": info.name,
+        "symbols": info.symbols,
+        "description": info.description,
+        "inputs": ports(&info.inputs),
+        "outputs": ports(&info.outputs),
+        "bang_only": info.bang_only,
+    })
+}
@@ -0,0 +1,167 @@
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::context::Context;
+use crate::midi::{MidiCapture, MidiNote, MidiSink, notes_tick};
+use crate::operators::{grid_tick, OperatorTable};
+use crate::prelude::*;
+
+/// What a tick sends out, for hooks to filter, change or record on its way.
+pub struct TickEvents<'a> {
+    /// The tick being run.
+    pub tick: usize,
+    /// Every note sounding, where the ones triggered on this tick have not been started yet.
+    pub notes: &'a mut Vec<MidiNote>,
+    /// The text Message operators sent on this tick.
+    pub messages: &'a mut Vec<String>,
+}
+
+type Hook = Box<dyn FnMut(&mut TickEvents) + Send + Sync>;
+
+/// Closures run around every tick with what it sends out but not the grid, so that embedders can
+/// filter, change or record a grid's output without forking the engine.
+#[derive(Default)]
+pub struct Hooks {
+    before: Vec<Hook>,
+    after: Vec<Hook>,
+}
+
+impl Hooks {
+    /// Runs `hook` before each tick, with the notes still sounding from the ticks before it and the
+    /// messages the last tick sent.
+    pub fn before_tick(&mut self, hook: impl FnMut(&mut TickEvents) + Send + Sync + 'static) {
+        self.before.push(Box::new(hook));
+    }
+
+    /// Runs `hook` after the grid has ticked and before its notes are sent, with the notes and
+    /// messages of the tick.
+    pub fn after_tick(&mut self, hook: impl FnMut(&mut TickEvents) + Send + Sync + 'static) {
+        self.after.push(Box::new(hook));
+    }
+
+    pub fn clear(&mut self) {
+        self.before.clear();
+        self.after.clear();
+    }
+}
+
+/// Advances the grid by one tick, then starts notes triggered on this tick and stops notes whose
+/// duration has run out. Edits held back for a launch are made first when one falls on the tick,
+/// and the context's hooks run on either side of the grid's tick.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(tick = context.ticks)))]
+pub fn tick(
+    context: &mut Context,
+    tick_operators: &OperatorTable,
+    bang_operators: &OperatorTable,
+    conn: &mut (impl MidiSink + ?Sized),
+) {
+    if context.ticks.is_multiple_of(context.divisions as usize) {
+        if let Some(tempo) = context.next_tempo.take() {
+            context.set_tempo(tempo);
+        }
+    }
+    if !context.launches.is_empty() && context.is_launch_tick() {
+        context.launch();
+    }
+    let ticked = context.ticks;
+    for hook in &mut context.hooks.before {
+        hook(&mut TickEvents { tick: ticked, notes: &mut context.notes, messages: &mut context.messages });
+    }
+    grid_tick(context, tick_operators, bang_operators);
+
+    let mut notes = notes_tick(&context.notes, context.tick_time);
+    for hook in &mut context.hooks.after {
+        hook(&mut TickEvents { tick: ticked, notes: &mut notes, messages: &mut context.messages });
+    }
+    for note in notes.iter_mut() {
+        if note.started && note.duration == 0 {
+            note.stop(conn);
+        } else if !note.started {
+            note.stop(conn);
+            note.start(conn);
+        }
+    }
+    context.notes = notes.iter().filter(|note| note.duration > 0).cloned().collect();
+}
+
+/// Runs `ticks` ticks back to back without waiting between them, capturing MIDI output instead of
+/// sending it.
+pub fn run_headless(
+    context: &mut Context,
+    tick_operators: &OperatorTable,
+    bang_operators: &OperatorTable,
+    ticks: usize,
+) -> MidiCapture {
+    run_headless_with(context, tick_operators, bang_operators, ticks, |_| {})
+}
+
+/// Runs ticks back to back as `run_headless` does, calling `before_tick` with the grid right
+/// before each one.
+pub fn run_headless_with(
+    context: &mut Context,
+    tick_operators: &OperatorTable,
+    bang_operators: &OperatorTable,
+    ticks: usize,
+    mut before_tick: impl FnMut(&mut Context),
+) -> MidiCapture {
+    let mut capture = MidiCapture::default();
+    for _ in 0..ticks {
+        before_tick(context);
+        capture.tick = context.ticks;
+        tick(context, tick_operators, bang_operators, &mut capture);
+    }
+    capture
+}
+
+/// Play and pause state shared between the thread that runs the clock and whatever controls it.
+#[derive(Debug, Default)]
+pub struct Transport {
+    paused: AtomicBool,
+    /// Ticks requested while paused that have not run yet.
+    steps: AtomicUsize,
+    /// Set once the clock should stop for good.
+    stopped: AtomicBool,
+}
+
+impl Transport {
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+        self.steps.store(0, Ordering::Relaxed);
+    }
+
+    /// Pauses if playing and plays if paused, returning whether it is now paused.
+    pub fn toggle(&self) -> bool {
+        let paused = !self.is_paused();
+        self.set_paused(paused);
+        paused
+    }
+
+    /// Asks the clock to stop for good, and anything watching it to shut down.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+
+    /// Asks for one more tick to run while paused.
+    pub fn step(&self) {
+        self.step_by(1);
+    }
+
+    /// Asks for `ticks` more ticks to run while paused, all at once however many there are.
+    pub fn step_by(&self, ticks: usize) {
+        // never fails, as the update always gives a value
+        let _ = self.steps.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |steps| Some(steps.saturating_add(ticks)));
+    }
+
+    /// Returns whether the clock should run a tick now, using up a requested step if paused.
+    pub fn should_tick(&self) -> bool {
+        !self.is_paused()
+            || self.steps.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |steps| steps.checked_sub(1)).is_ok()
+    }
+}
@@ -0,0 +1,107 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+
+use crate::context::MAX_CELLS;
+use crate::prelude::*;
+use crate::templates::TEMPLATES;
+
+/// Everything the engine can fail at, for embedders to report however suits them. The binary
+/// prints them as they are displayed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum OrcaError {
+    /// A file could not be read.
+    #[cfg(feature = "std")]
+    Read { path: PathBuf, source: io::Error },
+    /// A file could not be written.
+    #[cfg(feature = "std")]
+    Write { path: PathBuf, source: io::Error },
+    /// A socket could not be listened on, at a path or a network address.
+    #[cfg(feature = "std")]
+    Socket { address: String, source: io::Error },
+    /// A socket could not be connected to, or the other end stopped answering.
+    #[cfg(feature = "std")]
+    Connect { address: String, source: io::Error },
+    /// Text that was read but not understood, such as a session or an operator config, along
+    /// with the file it came from if there was one. The message says which line was wrong.
+    Parse { file: Option<String>, message: String },
+    /// The system's MIDI could not be reached, or a message could not be sent.
+    Midi(String),
+    /// There is no MIDI output port with this index or name.
+    NoMidiPort(String),
+    /// An operator config names an operator that doesn't exist.
+    UnknownOperator(String),
+    UnknownTemplate(String),
+    /// A template is bigger than the grid it was laid out in.
+    TemplateTooBig { name: &'static str, cols: usize, rows: usize },
+    /// A grid whose cells don't fill its size.
+    GridSize { cols: usize, rows: usize, cells: usize },
+    /// A grid with more than `MAX_CELLS` cells.
+    GridTooBig { cols: usize, rows: usize },
+}
+
+impl OrcaError {
+    pub(crate) fn parse(message: String) -> OrcaError {
+        OrcaError::Parse { file: None, message }
+    }
+
+    /// Names the file that text which could not be understood was read from.
+    #[cfg(feature = "files")]
+    pub(crate) fn in_file(self, path: &std::path::Path) -> OrcaError {
+        match self {
+            OrcaError::Parse { file: None, message } => {
+                OrcaError::Parse { file: Some(path.display().to_string()), message }
+            }
+            err => err,
+        }
+    }
+}
+
+impl fmt::Display for OrcaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            OrcaError::Read { path, source } => write!(f, "could not read {}: {}", path.display(), source),
+            #[cfg(feature = "std")]
+            OrcaError::Write { path, source } => write!(f, "could not write {}: {}", path.display(), source),
+            #[cfg(feature = "std")]
+            OrcaError::Socket { address, source } => write!(f, "could not listen on {}: {}", address, source),
+            #[cfg(feature = "std")]
+            OrcaError::Connect { address, source } => write!(f, "could not talk to {}: {}", address, source),
+            OrcaError::Parse { file: Some(file), message } => write!(f, "{}: {}", file, message),
+            OrcaError::Parse { file: None, message } => write!(f, "{}", message),
+            OrcaError::Midi(message) => write!(f, "midi: {}", message),
+            OrcaError::NoMidiPort(port) => write!(f, "no midi port {}", port),
+            OrcaError::UnknownOperator(name) => write!(f, "unknown operator {}", name),
+            OrcaError::UnknownTemplate(name) => {
+                let names: Vec<&str> = TEMPLATES.iter().map(|template| template.name).collect();
+                write!(f, "unknown template {}, try {}", name, names.join(", "))
+            }
+            OrcaError::TemplateTooBig { name, cols, rows } => {
+                write!(f, "the {} template needs at least {}x{}", name, cols, rows)
+            }
+            OrcaError::GridSize { cols, rows, cells } => {
+                write!(f, "a {}x{} grid has {} cells, not {}", cols, rows, cols * rows, cells)
+            }
+            OrcaError::GridTooBig { cols, rows } => {
+                write!(f, "a {}x{} grid is too big, grids have at most {} cells", cols, rows, MAX_CELLS)
+            }
+        }
+    }
+}
+
+impl core::error::Error for OrcaError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            OrcaError::Read { source, .. }
+            | OrcaError::Write { source, .. }
+            | OrcaError::Socket { source, .. }
+            | OrcaError::Connect { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,89 @@
+#[cfg(feature = "std")]
+use alloc::collections::VecDeque;
+#[cfg(feature = "std")]
+use std::sync::{Mutex, PoisonError};
+
+use crate::prelude::*;
+
+// how many events are kept before the oldest are dropped
+#[cfg(feature = "std")]
+const EVENT_LOG_LEN: usize = 256;
+
+/// The most recent events sent out of the program, each with the tick it happened on, written by
+/// whichever thread produces them and read by the editor.
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct EventLog {
+    entries: Mutex<VecDeque<(usize, String)>>,
+}
+
+#[cfg(feature = "std")]
+impl EventLog {
+    pub fn push(&self, tick: usize, event: String) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if entries.len() == EVENT_LOG_LEN {
+            entries.pop_front();
+        }
+        entries.push_back((tick, event));
+    }
+
+    /// The last `count` events, oldest first.
+    pub fn recent(&self, count: usize) -> Vec<(usize, String)> {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.iter().skip(entries.len().saturating_sub(count)).cloned().collect()
+    }
+}
+
+/// Splits a MIDI message into its kind, its channel and its two data bytes, which are the note and
+/// velocity of a note and the controller and value of a control change.
+fn decode(message: &[u8; 3]) -> (&'static str, u8, u8, u8) {
+    let [status, number, value] = *message;
+    let kind = match status & 0xf0 {
+        0x90 if value > 0 => "note_on",
+        // a note on with zero velocity is a note off
+        0x80 | 0x90 => "note_off",
+        0xb0 => "cc",
+        _ => "midi",
+    };
+    (kind, status & 0x0f, number, value)
+}
+
+/// Formats MIDI messages, each with the tick it was sent on, as a JSON array of objects like
+/// `{"tick": 4, "type": "note_on", "channel": 0, "note": 60, "velocity": 100}`. Control changes
+/// have a `controller` and `value` in place of the note and velocity.
+pub fn events_to_json(messages: &[(usize, [u8; 3])]) -> String {
+    let mut json = String::from("[\n");
+    for (i, (tick, message)) in messages.iter().enumerate() {
+        json.push_str("  ");
+        json.push_str(&event_to_json(*tick, message));
+        json.push_str(if i + 1 < messages.len() { ",\n" } else { "\n" });
+    }
+    json.push_str("]\n");
+    json
+}
+
+/// Formats one MIDI message and the tick it was sent on as a JSON object, as `events_to_json`
+/// writes each of them.
+pub fn event_to_json(tick: usize, message: &[u8; 3]) -> String {
+    let (kind, channel, number, value) = decode(message);
+    let (number_name, value_name) = match kind {
+        "note_on" | "note_off" => ("note", "velocity"),
+        "cc" => ("controller", "value"),
+        _ => ("data1", "data2"),
+    };
+    format!(
+        "{{\"tick\": {}, \"type\": \"{}\", \"channel\": {}, \"{}\": {}, \"{}\": {}}}",
+        tick, kind, channel, number_name, number, value_name, value,
+    )
+}
+
+/// Formats MIDI messages, each with the tick it was sent on, as CSV with a header row. The number
+/// column holds the note or controller, and the value column the velocity or controller value.
+pub fn events_to_csv(messages: &[(usize, [u8; 3])]) -> String {
+    let mut csv = String::from("tick,type,channel,number,value\n");
+    for (tick, message) in messages {
+        let (kind, channel, number, value) = decode(message);
+        csv.push_str(&format!("{},{},{},{},{}\n", tick, kind, channel, number, value));
+    }
+    csv
+}
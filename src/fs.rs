@@ -0,0 +1,190 @@
+#[cfg(feature = "files")]
+use std::fs::{read_to_string, rename, write};
+#[cfg(feature = "files")]
+use std::path::Path;
+
+use crate::context::{Block, Context, DIVISIONS_RANGE, TEMPO_RANGE, fits};
+#[cfg(feature = "files")]
+use crate::error::OrcaError;
+use crate::prelude::*;
+
+// header lines start with this, which grid rows never do, since grids are written without spaces
+const HEADER_PREFIX: &str = "#@ ";
+
+// headers written before the frame rate was recorded were played at orca's 4 frames per beat
+const DEFAULT_FRAMES: u64 = 4;
+
+/// What the git-friendly format records above the grid, so that a checked out composition plays
+/// back the way it was saved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub rows: usize,
+    pub cols: usize,
+    pub bpm: u64,
+    /// Frames per beat.
+    pub frames: u64,
+    pub seed: u64,
+    /// A hash of the operator config the grid was written for, from `operator_config_hash`.
+    pub operators: u64,
+}
+
+impl Header {
+    /// Plays `context` at the tempo and frame rate the header records, from its seed.
+    pub fn apply(&self, context: &mut Context) {
+        context.set_tempo(self.bpm);
+        context.set_divisions(self.frames);
+        context.reseed(self.seed);
+    }
+}
+
+/// Reads one character of .orca text as a cell. Besides `.`, the original Orca pads the snippets
+/// it copies with `⸏`, and spaces, tabs and other control characters turn up in patches passed
+/// around as plain text; all of them are empty cells.
+fn import_char(c: char) -> char {
+    match c {
+        '.' | ' ' | '⸏' => '\0',
+        c if c.is_control() => '\0',
+        c => c,
+    }
+}
+
+/// Splits .orca text into the lines of its grid, ignoring a leading byte order mark and header.
+pub fn grid_lines(text: &str) -> impl Iterator<Item=&str> {
+    text.strip_prefix('\u{feff}').unwrap_or(text).lines().skip_while(|line| line.starts_with(HEADER_PREFIX))
+}
+
+/// The size of the grid in .orca text, columns first: as many rows as it has lines and as many
+/// columns as its longest line.
+pub fn text_size(text: &str) -> (usize, usize) {
+    grid_lines(text).fold((0, 0), |(cols, rows), line| (cols.max(line.chars().count()), rows + 1))
+}
+
+/// Reads the header at the top of .orca text, if it has a complete one. Lines with settings this
+/// version doesn't know about are skipped.
+pub fn parse_header(text: &str) -> Option<Header> {
+    let (mut size, mut bpm, mut frames, mut seed, mut operators) = (None, None, None, None, None);
+    for line in text.strip_prefix('\u{feff}').unwrap_or(text).lines() {
+        let Some(setting) = line.strip_prefix(HEADER_PREFIX) else {
+            break;
+        };
+        let (name, value) = setting.split_once(' ').unwrap_or((setting, ""));
+        match name {
+            "size" => {
+                size = value
+                    .split_once('x')
+                    .and_then(|(cols, rows)| Some((cols.parse().ok()?, rows.parse().ok()?)))
+                    .filter(|&(cols, rows)| fits(cols, rows))
+            }
+            // a hand-edited header can't set a tempo the clock couldn't keep
+            "bpm" => bpm = value.parse::<u64>().ok().map(|bpm| bpm.clamp(*TEMPO_RANGE.start(), *TEMPO_RANGE.end())),
+            "frames" => {
+                frames = value.parse::<u64>().ok().filter(|&frames| frames > 0).map(|frames| frames.min(*DIVISIONS_RANGE.end()))
+            }
+            "seed" => seed = value.parse().ok(),
+            "operators" => operators = u64::from_str_radix(value, 16).ok(),
+            _ => {}
+        }
+    }
+    let (cols, rows) = size?;
+    let frames = frames.unwrap_or(DEFAULT_FRAMES);
+    Some(Header { rows, cols, bpm: bpm?, frames, seed: seed?, operators: operators? })
+}
+
+/// Formats a header as the lines written above the grid, one setting per line in a fixed order.
+pub fn header_to_string(header: &Header) -> String {
+    format!(
+        "{p}rust-orca\n{p}size {}x{}\n{p}bpm {}\n{p}frames {}\n{p}seed {}\n{p}operators {:016x}\n",
+        header.cols,
+        header.rows,
+        header.bpm,
+        header.frames,
+        header.seed,
+        header.operators,
+        p = HEADER_PREFIX,
+    )
+}
+
+/// Parses .orca text into a row-major grid with the given dimensions. Lines and rows beyond the
+/// grid are dropped, and short lines and missing rows are padded with empty cells, so trailing
+/// spaces that an editor trimmed away read back the same.
+pub fn parse_grid(text: &str, rows: usize, cols: usize) -> Vec<char> {
+    let mut lines = grid_lines(text);
+    (0..rows).flat_map(|_| {
+        let mut chars = lines.next().unwrap_or("").chars();
+        (0..cols).map(move |_| chars.next().map_or('\0', import_char))
+    }).collect()
+}
+
+/// Parses .orca text, such as a snippet copied out of the original Orca, into a block as wide as
+/// its longest line and as tall as its last line with anything on it.
+pub fn parse_block(text: &str) -> Block {
+    let lines: Vec<Vec<char>> = grid_lines(text).map(|line| line.chars().map(import_char).collect()).collect();
+    let rows = lines.iter().rposition(|line| line.iter().any(|&c| c != '\0')).map_or(0, |last| last + 1);
+    let cols = lines[..rows].iter().map(|line| line.len()).max().unwrap_or(0);
+    let cells = lines[..rows]
+        .iter()
+        .flat_map(|line| line.iter().copied().chain(core::iter::repeat('\0')).take(cols))
+        .collect();
+    Block { rows, cols, cells }
+}
+
+/// Reads an .orca text file into a block sized to fit it, as `parse_block` does.
+#[cfg(feature = "files")]
+pub fn read_block(path: &Path) -> Result<Block, OrcaError> {
+    Ok(parse_block(&read_file(path)?))
+}
+
+/// Reads an .orca text file into a row-major grid with the given dimensions, as `parse_grid` does.
+#[cfg(feature = "files")]
+pub fn read_grid(path: &Path, rows: usize, cols: usize) -> Result<Vec<char>, OrcaError> {
+    Ok(parse_grid(&read_file(path)?, rows, cols))
+}
+
+/// Reads a whole text file, naming it in the error if it could not be read.
+#[cfg(feature = "files")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", fields(path = %path.display()), skip_all, err(Display)))]
+pub fn read_file(path: &Path) -> Result<String, OrcaError> {
+    read_to_string(path).map_err(|source| OrcaError::Read { path: path.to_path_buf(), source })
+}
+
+/// Formats a row-major grid as .orca text, writing empty cells as `.`.
+pub fn grid_to_string(grid: &[char], cols: usize) -> String {
+    let mut text = String::with_capacity(grid.len() + grid.len() / cols.max(1));
+    for row in grid.chunks(cols) {
+        text.extend(row.iter().map(|&c| if c == '\0' { '.' } else { c }));
+        text.push('\n');
+    }
+    text
+}
+
+/// Writes `text` to a temporary file next to `path` and then moves it over `path`, so a crash
+/// partway through leaves the old file intact.
+#[cfg(feature = "files")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", fields(path = %path.display()), skip_all, err(Display)))]
+fn write_atomically(path: &Path, text: String) -> Result<(), OrcaError> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    write(&temporary, text)
+        .and_then(|()| rename(&temporary, path))
+        .map_err(|source| OrcaError::Write { path: path.to_path_buf(), source })
+}
+
+/// Writes a row-major grid to an .orca text file.
+#[cfg(feature = "files")]
+pub fn write_grid(path: &Path, grid: &[char], cols: usize) -> Result<(), OrcaError> {
+    write_atomically(path, grid_to_string(grid, cols))
+}
+
+/// Writes a row-major grid to an .orca text file under a header. Writing the same grid and header
+/// always gives the same bytes, so saves only show up in a diff where something changed.
+#[cfg(feature = "files")]
+pub fn write_grid_with_header(path: &Path, grid: &[char], cols: usize, header: &Header) -> Result<(), OrcaError> {
+    write_atomically(path, header_to_string(header) + &grid_to_string(grid, cols))
+}
+
+/// Writes a block to an .orca snippet file, which `read_block` reads back as the same block less
+/// any empty rows at its bottom, since those are trimmed off pasted text as well.
+#[cfg(feature = "files")]
+pub fn write_block(path: &Path, block: &Block) -> Result<(), OrcaError> {
+    write_atomically(path, grid_to_string(&block.cells, block.cols))
+}
@@ -0,0 +1,61 @@
+use eframe::egui::Color32;
+use ratatui::style::Color;
+
+// the 16 colors most terminals start out with, as xterm draws them
+const ANSI: [[u8; 3]; 16] = [
+    [0, 0, 0],
+    [205, 0, 0],
+    [0, 205, 0],
+    [205, 205, 0],
+    [0, 0, 238],
+    [205, 0, 205],
+    [0, 205, 205],
+    [229, 229, 229],
+    [127, 127, 127],
+    [255, 0, 0],
+    [0, 255, 0],
+    [255, 255, 0],
+    [92, 92, 255],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 255, 255],
+];
+// the steps of each channel in the 6x6x6 color cube of the 256-color palette
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The color a terminal would show for a theme color, or None for the terminal's own default.
+pub fn to_egui(color: Color) -> Option<Color32> {
+    let index = match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => return Some(Color32::from_rgb(r, g, b)),
+        Color::Indexed(index) => index,
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::White => 15,
+    };
+    Some(match index {
+        0..16 => {
+            let [r, g, b] = ANSI[index as usize];
+            Color32::from_rgb(r, g, b)
+        }
+        16..232 => {
+            let cube = index - 16;
+            Color32::from_rgb(CUBE[(cube / 36) as usize], CUBE[(cube / 6 % 6) as usize], CUBE[(cube % 6) as usize])
+        }
+        // the grays run from nearly black to nearly white in steps of 10
+        _ => Color32::from_gray(8 + 10 * (index - 232)),
+    })
+}
@@ -0,0 +1,103 @@
+use eframe::egui::{Align2, Color32, FontId, Pos2, Rect, Response, Sense, Stroke, Ui, Vec2};
+use ratatui::crossterm::event::{KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::style::{Color, Modifier};
+
+use crate::gui::colors::to_egui;
+use crate::tui::{App, Snapshot, cell, port_cells};
+
+const FONT_SIZE: f32 = 16.0;
+const BACKGROUND: Color32 = Color32::from_gray(16);
+const TEXT: Color32 = Color32::from_gray(220);
+// what the terminal does to dimmed and bold text, as near as a single font can
+const DIM_TEXT: Color32 = Color32::from_gray(110);
+const BOLD_TEXT: Color32 = Color32::WHITE;
+
+/// Draws the part of a grid that fits in `ui`, one monospace cell per grid cell, and handles the
+/// mouse over it: clicking moves the cursor, Alt-clicking adds or takes away an extra cursor and
+/// dragging selects. Scrolls the grid to keep the cursor in view.
+pub fn show(ui: &mut Ui, app: &mut App, snapshot: &Snapshot) -> Response {
+    let font = FontId::monospace(FONT_SIZE);
+    let cell_size = ui.fonts(|fonts| Vec2::new(fonts.glyph_width(&font, 'M'), fonts.row_height(&font)));
+    let (rect, response) = ui.allocate_exact_size(ui.available_size(), Sense::click_and_drag());
+    let view_rows = (rect.height() / cell_size.y) as usize;
+    let view_cols = (rect.width() / cell_size.x) as usize;
+    app.follow_cursor(view_rows, view_cols);
+    handle_mouse(ui, app, &response, rect.min, cell_size);
+
+    let rows = app.scroll_row..(app.scroll_row + app.view_rows).min(app.rows);
+    let cols = app.scroll_col..(app.scroll_col + app.view_cols).min(app.cols);
+    // each visible cell's character, text color, background and underline, with the ports and
+    // cursors marked over them before anything is painted
+    let mut cells = Vec::with_capacity(rows.len() * cols.len());
+    let selection = app.anchor.map(|_| app.selection());
+    for r in rows.clone() {
+        for c in cols.clone() {
+            let (value, style) = cell(app, snapshot, selection, r, c);
+            let text = match style.fg.and_then(to_egui) {
+                Some(color) => color,
+                None if style.add_modifier.contains(Modifier::BOLD) => BOLD_TEXT,
+                None if style.add_modifier.contains(Modifier::DIM) => DIM_TEXT,
+                None => TEXT,
+            };
+            let underlined = style.add_modifier.contains(Modifier::UNDERLINED);
+            cells.push((value, text, style.bg.and_then(to_egui), underlined));
+        }
+    }
+    let index = |row: i32, col: i32| {
+        let y = usize::try_from(row - rows.start as i32).ok().filter(|&y| y < rows.len())?;
+        let x = usize::try_from(col - cols.start as i32).ok().filter(|&x| x < cols.len())?;
+        Some(y * cols.len() + x)
+    };
+    for (row, col, color) in port_cells(app, snapshot) {
+        if let Some(index) = index(row, col) {
+            cells[index].1 = to_egui(Color::Black).unwrap_or(BACKGROUND);
+            cells[index].2 = to_egui(color);
+        }
+    }
+    for (row, col) in app.cursor_cells() {
+        if let Some(index) = index(row, col) {
+            let (_, text, background, _) = cells[index];
+            cells[index].1 = background.unwrap_or(BACKGROUND);
+            cells[index].2 = Some(text);
+        }
+    }
+
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, BACKGROUND);
+    for (i, (value, text, background, underlined)) in cells.into_iter().enumerate() {
+        let position = rect.min + Vec2::new((i % cols.len()) as f32 * cell_size.x, (i / cols.len()) as f32 * cell_size.y);
+        if let Some(background) = background {
+            painter.rect_filled(Rect::from_min_size(position, cell_size), 0.0, background);
+        }
+        if value != ' ' {
+            painter.text(position, Align2::LEFT_TOP, value, font.clone(), text);
+        }
+        if underlined {
+            let bottom = position.y + cell_size.y - 1.0;
+            painter.hline(position.x..=position.x + cell_size.x, bottom, Stroke::new(1.0, text));
+        }
+    }
+    response
+}
+
+/// Hands a press on the grid to the editor as the terminal's mouse event for that cell, and grows
+/// a selection from it while the pointer is dragged.
+fn handle_mouse(ui: &Ui, app: &mut App, response: &Response, origin: Pos2, cell_size: Vec2) {
+    let Some(pointer) = response.interact_pointer_pos() else {
+        return;
+    };
+    let offset = pointer - origin;
+    let (row, col) = ((offset.y / cell_size.y).max(0.0) as u16, (offset.x / cell_size.x).max(0.0) as u16);
+    if ui.input(|input| input.pointer.primary_pressed()) {
+        let modifiers = if ui.input(|input| input.modifiers.alt) { KeyModifiers::ALT } else { KeyModifiers::NONE };
+        app.handle_mouse(MouseEvent { kind: MouseEventKind::Down(MouseButton::Left), column: col, row, modifiers });
+    } else if response.dragged() && app.cursors.is_empty() {
+        let row = (app.scroll_row + row as usize).min(app.rows - 1);
+        let col = (app.scroll_col + col as usize).min(app.cols - 1);
+        if app.anchor.is_none() && (row, col) != (app.cursor_row, app.cursor_col) {
+            app.anchor = Some((app.cursor_row, app.cursor_col));
+        }
+        app.cursor_row = row;
+        app.cursor_col = col;
+    }
+}
@@ -0,0 +1,105 @@
+use eframe::egui::{Event, ImeEvent, Key, Modifiers};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rust_orca::keys::{self, KeyInput, key_presses};
+
+/// Turns what the window saw of the keyboard in a frame into the key events a terminal would have
+/// sent for it, so that the keymap, vim mode and the command line work the same in both editors.
+pub fn key_events(events: &[Event]) -> Vec<KeyEvent> {
+    let inputs = events.iter().filter_map(|event| {
+        Some(match event {
+            Event::Key { key, pressed: true, modifiers, .. } => {
+                let modifiers = key_modifiers(*modifiers);
+                let chord = modifiers.control || modifiers.alt;
+                match key_code(*key, modifiers.shift, chord)? {
+                    KeyCode::Char(c) => KeyInput::Press(keys::Key::Char(c), modifiers),
+                    code => KeyInput::Press(keys::Key::Other(code), modifiers),
+                }
+            }
+            Event::Text(text) => KeyInput::Text(text.clone()),
+            // dead keys and input methods hand over the composed character once it is done
+            Event::Ime(ImeEvent::Commit(text)) => KeyInput::Composed(text.clone()),
+            // the window takes the clipboard chords for itself, so they are handed back as the keys
+            // they were, leaving the editor to reach the clipboard its own way
+            Event::Copy => KeyInput::Press(keys::Key::Char('c'), keys::Modifiers::CONTROL),
+            Event::Cut => KeyInput::Press(keys::Key::Char('x'), keys::Modifiers::CONTROL),
+            Event::Paste(_) => KeyInput::Press(keys::Key::Char('v'), keys::Modifiers::CONTROL),
+            _ => return None,
+        })
+    });
+    key_presses(inputs)
+        .into_iter()
+        .map(|(key, modifiers)| {
+            let code = match key {
+                keys::Key::Char(c) => KeyCode::Char(c),
+                keys::Key::Other(code) => code,
+            };
+            KeyEvent::new(code, terminal_modifiers(modifiers))
+        })
+        .collect()
+}
+
+fn key_modifiers(modifiers: Modifiers) -> keys::Modifiers {
+    keys::Modifiers {
+        shift: modifiers.shift,
+        // Command stands in for Control on a Mac, as it does for the usual shortcuts there
+        control: modifiers.ctrl || modifiers.mac_cmd,
+        alt: modifiers.alt,
+    }
+}
+
+fn terminal_modifiers(modifiers: keys::Modifiers) -> KeyModifiers {
+    let mut key_modifiers = KeyModifiers::NONE;
+    for (held, modifier) in [
+        (modifiers.shift, KeyModifiers::SHIFT),
+        (modifiers.control, KeyModifiers::CONTROL),
+        (modifiers.alt, KeyModifiers::ALT),
+    ] {
+        if held {
+            key_modifiers |= modifier;
+        }
+    }
+    key_modifiers
+}
+
+/// The terminal key code for a key. Keys that type a character only count as keys in a `chord`
+/// with Control or Alt, since otherwise they come through as text.
+fn key_code(key: Key, shift: bool, chord: bool) -> Option<KeyCode> {
+    Some(match key {
+        Key::ArrowUp => KeyCode::Up,
+        Key::ArrowDown => KeyCode::Down,
+        Key::ArrowLeft => KeyCode::Left,
+        Key::ArrowRight => KeyCode::Right,
+        Key::Enter => KeyCode::Enter,
+        Key::Escape => KeyCode::Esc,
+        Key::Tab if shift => KeyCode::BackTab,
+        Key::Tab => KeyCode::Tab,
+        Key::Backspace => KeyCode::Backspace,
+        Key::Delete => KeyCode::Delete,
+        Key::Insert => KeyCode::Insert,
+        Key::Home => KeyCode::Home,
+        Key::End => KeyCode::End,
+        Key::PageUp => KeyCode::PageUp,
+        Key::PageDown => KeyCode::PageDown,
+        Key::F1 => KeyCode::F(1),
+        Key::F2 => KeyCode::F(2),
+        Key::F3 => KeyCode::F(3),
+        Key::F4 => KeyCode::F(4),
+        Key::F5 => KeyCode::F(5),
+        Key::F6 => KeyCode::F(6),
+        Key::F7 => KeyCode::F(7),
+        Key::F8 => KeyCode::F(8),
+        Key::F9 => KeyCode::F(9),
+        Key::F10 => KeyCode::F(10),
+        Key::F11 => KeyCode::F(11),
+        Key::F12 => KeyCode::F(12),
+        Key::Space if chord => KeyCode::Char(' '),
+        key if chord => {
+            let mut chars = key.symbol_or_name().chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c.to_ascii_lowercase()),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    })
+}
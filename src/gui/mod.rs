@@ -0,0 +1,419 @@
+mod colors;
+mod grid;
+mod keys;
+
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use eframe::egui::{
+    self, Align, CentralPanel, Color32, Layout, MenuBar, RichText, ScrollArea, Sense, SidePanel, TopBottomPanel, Ui,
+    Vec2, ViewportBuilder, ViewportCommand, Window,
+};
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rust_orca::context::Context;
+use rust_orca::midi::list_ports;
+
+use crate::commands::Command;
+use crate::gui::colors::to_egui;
+use crate::gui::keys::key_events;
+use crate::tui::{
+    Action, App, ConfigWatcher, METER_DECAY, Snapshot, THEMES, apply_config, help_lines, refresh_view, share_remaps,
+    status_text,
+};
+
+// how often the window redraws while nothing happens in it, to keep up with the engine
+const FRAME_TIME: Duration = Duration::from_millis(16);
+// as many events as the log keeps
+const EVENT_LOG_LINES: usize = 256;
+const METER_CHANNELS: usize = 16;
+const METER_SIZE: Vec2 = Vec2::new(8.0, 48.0);
+
+/// Which edge of the window a panel is docked against.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Dock {
+    Left,
+    Right,
+    Bottom,
+}
+
+/// A panel around the grids, which can be closed or moved to another edge of the window.
+struct Panel {
+    open: bool,
+    dock: Dock,
+}
+
+/// The editor in a window: the same panes the terminal editor shows, driven through the same keys
+/// and commands, with the event log, the themes and the MIDI ports in panels around them.
+struct Editor<'a> {
+    panes: &'a mut [(Arc<Mutex<Context>>, App)],
+    /// What each pane is drawn from, as `refresh_view` keeps it.
+    views: Vec<Option<Context>>,
+    focus: usize,
+    config: ConfigWatcher,
+    /// The event log is opened and closed with the focused pane's event log key, as it is in the
+    /// terminal.
+    events: Dock,
+    themes: Panel,
+    midi: Panel,
+    /// The MIDI output ports, as last listed.
+    ports: Vec<String>,
+}
+
+/// Runs the editor in a window until it quits, the window is closed or the engine stops.
+pub fn run(mut panes: Vec<(Arc<Mutex<Context>>, App)>) -> io::Result<()> {
+    let title = match panes[0].1.path.as_ref() {
+        Some(path) => format!("rust-orca — {}", path.display()),
+        None => "rust-orca".to_string(),
+    };
+    let options = eframe::NativeOptions {
+        viewport: ViewportBuilder::default().with_title(title).with_inner_size([1024.0, 720.0]),
+        ..Default::default()
+    };
+    let editor = Editor {
+        views: panes.iter().map(|_| None).collect(),
+        panes: &mut panes,
+        focus: 0,
+        config: ConfigWatcher::new(),
+        events: Dock::Bottom,
+        themes: Panel { open: false, dock: Dock::Left },
+        midi: Panel { open: false, dock: Dock::Right },
+        ports: output_ports(),
+    };
+    eframe::run_native("rust-orca", options, Box::new(|_| Ok(Box::new(editor))))
+        .map_err(|err| io::Error::other(err.to_string()))?;
+    // quitting is a clean exit, unlike the engine going down
+    if panes.iter().any(|(_, app)| app.quit) {
+        for (_, app) in &mut panes {
+            app.journal.discard();
+        }
+    }
+    Ok(())
+}
+
+fn output_ports() -> Vec<String> {
+    list_ports().map(|(_, outputs)| outputs).unwrap_or_default()
+}
+
+impl eframe::App for Editor<'_> {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // closing the window is as good as quitting
+        if ctx.input(|input| input.viewport().close_requested()) {
+            for (_, app) in self.panes.iter_mut() {
+                app.quit = true;
+            }
+        }
+        if self.panes.iter().any(|(_, app)| app.quit || app.transport.is_stopped()) {
+            ctx.send_viewport_cmd(ViewportCommand::Close);
+            return;
+        }
+        ctx.request_repaint_after(FRAME_TIME);
+        // pick up edits to the keymap and theme without interrupting playback
+        if self.config.changed() {
+            for (_, app) in self.panes.iter_mut() {
+                let errors = apply_config(app);
+                app.message = if errors.is_empty() { "reloaded config".to_string() } else { errors.join(", ") };
+            }
+        }
+
+        let keys = ctx.input(|input| key_events(&input.events));
+        for key in keys {
+            self.handle_key(key);
+        }
+
+        // a replayed session is replayed on the first grid
+        self.panes[0].1.follow_replay();
+        share_remaps(self.panes, 0);
+        let mut snapshots = Vec::with_capacity(self.panes.len());
+        for ((context_arc, app), view) in self.panes.iter_mut().zip(&mut self.views) {
+            // the context is only poisoned if the engine panicked mid-tick, and the editor goes down
+            // along with it
+            let Some(context) = refresh_view(view, context_arc, app) else {
+                ctx.send_viewport_cmd(ViewportCommand::Close);
+                return;
+            };
+            app.catch_up(context);
+            app.check_problems(context);
+            let snapshot = Snapshot::new(context, app);
+            app.trails.update(&snapshot.grid, snapshot.ticks);
+            if let Some(since) = app.recording_since {
+                app.recording.capture(since.elapsed(), &snapshot.grid);
+            }
+            // the window lays out its own panels around the grid
+            app.minimap_fits = false;
+            app.event_panel_fits = false;
+            snapshots.push(snapshot);
+        }
+
+        TopBottomPanel::top("menu").show(ctx, |ui| self.menu_bar(ui));
+        if self.panes[self.focus].1.event_panel {
+            let app = &self.panes[self.focus].1;
+            docked(ctx, "events", self.events, |ui| event_log(ui, app));
+        }
+        if self.themes.open {
+            let mut chosen = None;
+            docked(ctx, "themes", self.themes.dock, |ui| chosen = theme_list(ui, &self.panes[self.focus].1));
+            if let Some(name) = chosen {
+                self.run_command(Command::Theme(name.to_string()));
+            }
+        }
+        if self.midi.open {
+            let mut chosen = None;
+            let ports = &mut self.ports;
+            docked(ctx, "midi", self.midi.dock, |ui| chosen = midi_ports(ui, &self.panes[self.focus].1, ports));
+            if let Some(index) = chosen {
+                self.run_command(Command::Midi(index));
+            }
+        }
+        CentralPanel::default().show(ctx, |ui| self.grids(ui, &snapshots));
+        self.overlays(ctx);
+    }
+}
+
+impl Editor<'_> {
+    fn handle_key(&mut self, key: KeyEvent) {
+        let count = self.panes.len();
+        let (context_arc, app) = &mut self.panes[self.focus];
+        let Ok(mut context) = context_arc.lock() else {
+            return;
+        };
+        app.handle_key(key, &mut context);
+        app.update_journal(&context);
+        // edits show at once rather than on the next tick
+        self.views[self.focus] = Some(context.view());
+        let switch_pane = std::mem::take(&mut app.switch_pane);
+        drop(context);
+        share_remaps(self.panes, self.focus);
+        if switch_pane {
+            self.focus = (self.focus + 1) % count;
+        }
+    }
+
+    /// Runs a command in the focused pane, as if it had been typed on its command line.
+    fn run_command(&mut self, command: Command) {
+        let (context_arc, app) = &mut self.panes[self.focus];
+        let Ok(mut context) = context_arc.lock() else {
+            return;
+        };
+        app.message = app.run_command(command, &mut context);
+        app.update_journal(&context);
+        self.views[self.focus] = Some(context.view());
+        drop(context);
+        share_remaps(self.panes, self.focus);
+    }
+
+    /// A button that does what a key bound to `action` does in the focused pane.
+    fn action_button(&mut self, ui: &mut Ui, label: &str, action: Action) {
+        if !ui.button(label).clicked() {
+            return;
+        }
+        let (context_arc, app) = &mut self.panes[self.focus];
+        let Ok(mut context) = context_arc.lock() else {
+            return;
+        };
+        app.perform(action, false, &mut context);
+        app.update_journal(&context);
+        self.views[self.focus] = Some(context.view());
+        drop(context);
+        share_remaps(self.panes, self.focus);
+    }
+
+    fn menu_bar(&mut self, ui: &mut Ui) {
+        MenuBar::new().ui(ui, |ui| {
+            ui.menu_button("File", |ui| {
+                self.action_button(ui, "Open…", Action::Open);
+                self.action_button(ui, "Save", Action::Save);
+                self.action_button(ui, "Quit", Action::Quit);
+            });
+            ui.menu_button("Edit", |ui| {
+                self.action_button(ui, "Undo", Action::Undo);
+                self.action_button(ui, "Redo", Action::Redo);
+                ui.separator();
+                self.action_button(ui, "Copy", Action::Copy);
+                self.action_button(ui, "Cut", Action::Cut);
+                self.action_button(ui, "Paste", Action::Paste);
+                ui.separator();
+                self.action_button(ui, "Insert operator…", Action::PickOperator);
+            });
+            ui.menu_button("View", |ui| {
+                let app = &mut self.panes[self.focus].1;
+                ui.checkbox(&mut app.event_panel, "Events");
+                dock_choice(ui, "events", &mut self.events);
+                ui.separator();
+                ui.checkbox(&mut self.themes.open, "Themes");
+                dock_choice(ui, "themes", &mut self.themes.dock);
+                ui.separator();
+                if ui.checkbox(&mut self.midi.open, "MIDI").changed() {
+                    self.ports = output_ports();
+                }
+                dock_choice(ui, "midi", &mut self.midi.dock);
+            });
+            ui.menu_button("Help", |ui| self.action_button(ui, "Operators", Action::Help));
+            let label = if self.panes[self.focus].1.transport.is_paused() { "Play" } else { "Pause" };
+            self.action_button(ui, label, Action::PlayPause);
+        });
+    }
+
+    /// Lays the panes out side by side, each with its status line under it, and focuses whichever
+    /// is clicked.
+    fn grids(&mut self, ui: &mut Ui, snapshots: &[Snapshot]) {
+        let count = self.panes.len();
+        let width = (ui.available_width() - ui.spacing().item_spacing.x * (count - 1) as f32) / count as f32;
+        let height = ui.available_height();
+        ui.horizontal(|ui| {
+            for (i, ((_, app), snapshot)) in self.panes.iter_mut().zip(snapshots).enumerate() {
+                let focused = i == self.focus;
+                ui.allocate_ui_with_layout(Vec2::new(width, height), Layout::bottom_up(Align::Min), |ui| {
+                    let status = if app.command_line.active {
+                        format!(":{}", app.command_line.text)
+                    } else {
+                        status_text(app, snapshot)
+                    };
+                    let status = RichText::new(status).monospace();
+                    ui.label(if focused { status.strong() } else { status.weak() });
+                    // laid out bottom up, so the explanation sits between the grid and the status
+                    for line in snapshot.explain.iter().flatten().rev() {
+                        ui.monospace(line);
+                    }
+                    if grid::show(ui, app, snapshot).is_pointer_button_down_on() {
+                        self.focus = i;
+                    }
+                });
+            }
+        });
+    }
+
+    /// Shows the operator glossary, the open dialog and the operator picker over the grids while
+    /// the focused pane has them open. Their keys go to the editor as they do in the terminal, and
+    /// clicking an entry picks it as Enter would.
+    fn overlays(&mut self, ctx: &egui::Context) {
+        let app = &mut self.panes[self.focus].1;
+        let mut enter = false;
+        if app.help.is_some() {
+            Window::new("operators (F1 or Esc to close)").collapsible(false).show(ctx, |ui| {
+                ScrollArea::vertical().show(ui, |ui| {
+                    for line in help_lines(app) {
+                        ui.monospace(line);
+                    }
+                });
+            });
+        }
+        if let Some(browser) = &mut app.browser {
+            Window::new("open (enter to open, esc to close)").collapsible(false).show(ctx, |ui| {
+                ScrollArea::vertical().show(ui, |ui| {
+                    for (i, path) in browser.entries.iter().enumerate() {
+                        if i == 0 && browser.recent > 0 {
+                            ui.strong("recent");
+                        }
+                        if i == browser.recent {
+                            ui.strong("in this directory");
+                        }
+                        if ui.selectable_label(i == browser.selected, path.display().to_string()).clicked() {
+                            browser.selected = i;
+                            enter = true;
+                        }
+                    }
+                    if browser.entries.is_empty() {
+                        ui.label("no .orca files here");
+                    }
+                });
+            });
+        }
+        if let Some(picker) = &app.picker {
+            let matches = picker.matches(&app.tick_operators);
+            let mut clicked = None;
+            Window::new("operators (enter to place, esc to close)").collapsible(false).show(ctx, |ui| {
+                ui.monospace(format!("search: {}", picker.query));
+                ScrollArea::vertical().show(ui, |ui| {
+                    for (i, (symbol, operator)) in matches.iter().enumerate() {
+                        let text = format!("{} {}: {}", symbol, operator.name(), operator.description());
+                        if ui.selectable_label(i == picker.selected, text).clicked() {
+                            clicked = Some(i);
+                        }
+                    }
+                    if matches.is_empty() {
+                        ui.label("no matching operators");
+                    }
+                });
+            });
+            if let (Some(i), Some(picker)) = (clicked, &mut app.picker) {
+                picker.selected = i;
+                enter = true;
+            }
+        }
+        if enter {
+            self.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        }
+    }
+}
+
+/// Shows a resizable panel against the edge of the window it is docked to.
+fn docked(ctx: &egui::Context, id: &'static str, dock: Dock, contents: impl FnOnce(&mut Ui)) {
+    match dock {
+        Dock::Left => SidePanel::left(id).resizable(true).show(ctx, contents),
+        Dock::Right => SidePanel::right(id).resizable(true).show(ctx, contents),
+        Dock::Bottom => TopBottomPanel::bottom(id).resizable(true).show(ctx, contents),
+    };
+}
+
+fn dock_choice(ui: &mut Ui, id: &str, dock: &mut Dock) {
+    ui.push_id(id, |ui| {
+        ui.horizontal(|ui| {
+            ui.radio_value(dock, Dock::Left, "left");
+            ui.radio_value(dock, Dock::Right, "right");
+            ui.radio_value(dock, Dock::Bottom, "bottom");
+        });
+    });
+}
+
+/// The most recent events, newest at the bottom, each after the tick it happened on.
+fn event_log(ui: &mut Ui, app: &App) {
+    ui.heading("events");
+    ScrollArea::vertical().stick_to_bottom(true).auto_shrink(false).show(ui, |ui| {
+        for (tick, event) in app.event_log.recent(EVENT_LOG_LINES) {
+            ui.monospace(format!("{:>8} {}", tick, event));
+        }
+    });
+}
+
+/// The built-in themes, returning the one clicked.
+fn theme_list(ui: &mut Ui, app: &App) -> Option<&'static str> {
+    ui.heading("themes");
+    let mut chosen = None;
+    for theme in &THEMES {
+        if ui.selectable_label(app.theme.name == theme.name, theme.name).clicked() {
+            chosen = Some(theme.name);
+        }
+    }
+    chosen
+}
+
+/// A meter for each MIDI channel and the output ports, returning the index of the port clicked.
+fn midi_ports(ui: &mut Ui, app: &App, ports: &mut Vec<String>) -> Option<usize> {
+    ui.heading("midi");
+    ui.horizontal(|ui| {
+        for channel in 0..METER_CHANNELS {
+            let (rect, _) = ui.allocate_exact_size(METER_SIZE, Sense::hover());
+            let level = app.midi_monitor.level(channel, METER_DECAY);
+            let color = if app.midi_monitor.sounding(channel) > 0 { app.theme.meter_sounding } else { app.theme.meter_quiet };
+            let mut bar = rect;
+            bar.set_top(rect.bottom() - rect.height() * level);
+            ui.painter().rect_filled(rect, 0.0, Color32::BLACK);
+            ui.painter().rect_filled(bar, 0.0, to_egui(color).unwrap_or(Color32::GRAY));
+        }
+    });
+    ui.separator();
+    let mut chosen = None;
+    for (index, name) in ports.iter().enumerate() {
+        if ui.selectable_label(app.midi_device.as_ref() == Some(name), name).clicked() {
+            chosen = Some(index);
+        }
+    }
+    if ports.is_empty() {
+        ui.label("no midi ports");
+    }
+    if ui.button("refresh").clicked() {
+        *ports = output_ports();
+    }
+    chosen
+}
@@ -0,0 +1,169 @@
+use alloc::collections::VecDeque;
+
+use crate::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StepKind {
+    Tick,
+    Edit,
+}
+
+/// A single cell change, stored as the XOR of the old and new values so that applying it a second
+/// time undoes it.
+#[derive(Clone, Copy, Debug)]
+pub struct Delta {
+    pub index: u32,
+    pub xor: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Step {
+    kind: StepKind,
+    len: usize,
+}
+
+/// A bounded record of grid changes, grouped into steps (a tick, or a group of edits).
+///
+/// Deltas from all steps share one ring buffer; once it is full, the oldest steps are dropped to
+/// make room, so memory stays proportional to the number of cells changed rather than to the
+/// grid size. Steps that are stepped back over move onto a redo stack until something new is
+/// recorded.
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    capacity: usize,
+    max_steps: usize,
+    deltas: VecDeque<Delta>,
+    steps: VecDeque<Step>,
+    redo_deltas: Vec<Delta>,
+    redo_steps: Vec<Step>,
+    open: Option<Step>,
+}
+
+impl History {
+    /// Creates a history holding at most `capacity` cell changes across at most `max_steps` steps.
+    /// Both buffers are allocated up front so that recording never allocates.
+    pub fn new(capacity: usize, max_steps: usize) -> History {
+        History {
+            capacity,
+            max_steps,
+            deltas: VecDeque::with_capacity(capacity),
+            steps: VecDeque::with_capacity(max_steps),
+            redo_deltas: Vec::new(),
+            redo_steps: Vec::new(),
+            open: None,
+        }
+    }
+
+    /// A history that records nothing.
+    pub fn disabled() -> History {
+        History::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0 && self.max_steps > 0
+    }
+
+    #[inline]
+    pub fn is_recording(&self) -> bool {
+        self.open.is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn last_kind(&self) -> Option<StepKind> {
+        self.steps.back().map(|step| step.kind)
+    }
+
+    pub fn next_redo_kind(&self) -> Option<StepKind> {
+        self.redo_steps.last().map(|step| step.kind)
+    }
+
+    /// Starts recording a new step; does nothing if the history is disabled.
+    pub fn begin(&mut self, kind: StepKind) {
+        if self.is_enabled() {
+            self.end();
+            self.open = Some(Step { kind, len: 0 });
+        }
+    }
+
+    /// Finishes the step being recorded, if any.
+    pub fn end(&mut self) {
+        if let Some(step) = self.open.take() {
+            if self.steps.len() == self.max_steps {
+                self.evict_oldest();
+            }
+            self.steps.push_back(step);
+        }
+    }
+
+    /// Records that the cell at `index` changed from `previous` to `value` in the open step.
+    #[inline]
+    pub fn record(&mut self, index: usize, previous: char, value: char) {
+        if self.open.is_none() || previous == value {
+            return;
+        }
+        if !self.redo_steps.is_empty() {
+            self.redo_steps.clear();
+            self.redo_deltas.clear();
+        }
+        while self.deltas.len() >= self.capacity {
+            if !self.evict_oldest() {
+                // the open step alone has filled the buffer, so the oldest part of it is lost
+                self.deltas.pop_front();
+                if let Some(step) = self.open.as_mut() {
+                    step.len -= 1;
+                }
+            }
+        }
+        self.deltas.push_back(Delta { index: index as u32, xor: previous as u32 ^ value as u32 });
+        if let Some(step) = self.open.as_mut() {
+            step.len += 1;
+        }
+    }
+
+    fn evict_oldest(&mut self) -> bool {
+        match self.steps.pop_front() {
+            Some(step) => {
+                self.deltas.drain(..step.len);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes the most recent step, returning its kind and deltas (most recent first) so that the
+    /// caller can revert them. The step moves onto the redo stack.
+    pub fn step_back(&mut self) -> Option<(StepKind, Vec<Delta>)> {
+        self.end();
+        let step = self.steps.pop_back()?;
+        let deltas: Vec<Delta> = (0..step.len).filter_map(|_| self.deltas.pop_back()).collect();
+        self.redo_deltas.extend(deltas.iter().rev());
+        self.redo_steps.push(step);
+        Some((step.kind, deltas))
+    }
+
+    /// Restores the most recently stepped-back step, returning its kind and deltas in the order
+    /// they were originally applied.
+    pub fn step_forward(&mut self) -> Option<(StepKind, Vec<Delta>)> {
+        self.end();
+        let step = self.redo_steps.pop()?;
+        let deltas = self.redo_deltas.split_off(self.redo_deltas.len() - step.len);
+        self.deltas.extend(deltas.iter().copied());
+        self.steps.push_back(step);
+        Some((step.kind, deltas))
+    }
+
+    pub fn clear(&mut self) {
+        self.deltas.clear();
+        self.steps.clear();
+        self.redo_deltas.clear();
+        self.redo_steps.clear();
+        self.open = None;
+    }
+}
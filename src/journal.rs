@@ -0,0 +1,108 @@
+use std::fs::{File, read_to_string, remove_file};
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use rust_orca::context::{Context, fits};
+use rust_orca::fs::{grid_to_string, parse_grid};
+
+use crate::autosave::untitled_path;
+
+// once this many changes have been appended, the journal starts over from the whole grid
+const COMPACT_AFTER: usize = 10_000;
+
+/// Where the edits to the grid from `path` in the pane numbered `pane` are journaled: a sidecar
+/// file next to it, named after it. A journal that is still there when the editor starts was left
+/// by a session that never exited cleanly.
+pub fn journal_path(path: Option<&Path>, pane: usize) -> PathBuf {
+    let mut journal = path.map_or_else(|| untitled_path(pane), Path::to_path_buf).into_os_string();
+    journal.push(".journal");
+    PathBuf::from(journal)
+}
+
+/// Keeps a journal of unsaved work as it happens, so that a crash loses nothing: the grid as it
+/// was when it was first edited, and then after every edit the cells that changed since the last
+/// one, one to a line like `3 4 A`. Each edit is appended straight to the file.
+#[derive(Debug, Default)]
+pub struct Journal {
+    file: Option<(PathBuf, File)>,
+    last_grid: Vec<char>,
+    changes: usize,
+}
+
+impl Journal {
+    /// Brings the journal at `path` up to date with the grid, starting it if there is none yet.
+    pub fn record(&mut self, path: &Path, context: &Context) -> io::Result<()> {
+        let grid = context.grid();
+        let started = self.file.as_ref().is_some_and(|(journaled, _)| journaled == path);
+        if !started || self.changes >= COMPACT_AFTER {
+            // a journal left behind under another name, from before a save as, is kept by nothing
+            if !started {
+                self.discard();
+            }
+            let mut file = File::create(path)?;
+            file.write_all(format!("rust-orca journal\nsize {}x{}\n", context.width, context.height).as_bytes())?;
+            file.write_all(grid_to_string(grid, context.width).as_bytes())?;
+            self.file = Some((path.to_path_buf(), file));
+            self.last_grid = grid.to_vec();
+            self.changes = 0;
+            return Ok(());
+        }
+        let mut lines = String::new();
+        for (index, (&value, last)) in grid.iter().zip(self.last_grid.iter_mut()).enumerate() {
+            if value != *last {
+                *last = value;
+                let value = if value == '\0' { '.' } else { value };
+                lines.push_str(&format!("{} {} {}\n", index / context.width, index % context.width, value));
+                self.changes += 1;
+            }
+        }
+        if let Some((_, file)) = &mut self.file {
+            file.write_all(lines.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the journal, once there is nothing unsaved left in it.
+    pub fn discard(&mut self) {
+        if let Some((path, _)) = self.file.take() {
+            let _ = remove_file(path);
+        }
+    }
+}
+
+/// Reads a journal back into the grid it was keeping, describing the first line that could not be
+/// read. A line cut short by the crash ends the journal.
+pub fn read_journal(path: &Path) -> Result<(usize, usize, Vec<char>), String> {
+    let text = read_to_string(path).map_err(|err| format!("could not read {}: {}", path.display(), err))?;
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.first() != Some(&"rust-orca journal") {
+        return Err(format!("{} is not a rust-orca journal", path.display()));
+    }
+    let (cols, rows): (usize, usize) = lines
+        .get(1)
+        .and_then(|line| line.strip_prefix("size "))
+        .and_then(|size| size.split_once('x'))
+        .and_then(|(cols, rows)| Some((cols.parse().ok()?, rows.parse().ok()?)))
+        .filter(|&(cols, rows)| fits(cols, rows))
+        .ok_or_else(|| format!("{}: line 2: expected size", path.display()))?;
+    if lines.len() < 2 + rows {
+        return Err(format!("{}: the grid is cut short", path.display()));
+    }
+    let mut grid = parse_grid(&lines[2..2 + rows].join("\n"), rows, cols);
+    for line in &lines[2 + rows..] {
+        let fields: Vec<&str> = line.split(' ').collect();
+        let [row, col, value] = fields[..] else {
+            break;
+        };
+        let mut chars = value.chars();
+        let (Ok(row), Ok(col), Some(value), None) = (row.parse::<usize>(), col.parse::<usize>(), chars.next(), chars.next()) else {
+            break;
+        };
+        if row >= rows || col >= cols {
+            break;
+        }
+        grid[row * cols + col] = if value == '.' { '\0' } else { value };
+    }
+    Ok((rows, cols, grid))
+}
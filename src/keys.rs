@@ -0,0 +1,99 @@
+use crate::operators::{OperatorTable, is_single_width};
+use crate::prelude::*;
+
+/// The modifier keys held with a key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers { shift: false, control: false, alt: false };
+    pub const CONTROL: Modifiers = Modifiers { shift: false, control: true, alt: false };
+}
+
+/// A key as the editor sees it: the character it stands for, or whatever else a frontend calls
+/// keys like the arrows and function keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key<K> {
+    Char(char),
+    Other(K),
+}
+
+/// What a window saw of the keyboard, in the order it saw it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyInput<K> {
+    /// A key went down. Keys that type a character are only given when held with Control or Alt,
+    /// since otherwise they come through as text.
+    Press(Key<K>, Modifiers),
+    /// Text the keyboard layout typed.
+    Text(String),
+    /// A character finished with dead keys or an input method.
+    Composed(String),
+}
+
+/// Turns what a window saw of the keyboard into the keys a terminal would have sent for it, so
+/// that the editor works the same in both. Text typed with Alt held is dropped, as the key it
+/// came with already stood for it, unless Control was held too: that is how AltGr comes through,
+/// and the text is what the layout typed with it.
+pub fn key_presses<K>(inputs: impl IntoIterator<Item=KeyInput<K>>) -> Vec<(Key<K>, Modifiers)> {
+    let mut keys = Vec::new();
+    let mut typed_with_alt = false;
+    // a chord with both Control and Alt waits to see whether the layout typed a character with it
+    let mut altgr = None;
+    for input in inputs {
+        match input {
+            KeyInput::Press(key, modifiers) => {
+                keys.extend(altgr.take());
+                typed_with_alt = matches!(key, Key::Char(_)) && modifiers.alt;
+                if typed_with_alt && modifiers.control {
+                    altgr = Some((key, modifiers));
+                } else {
+                    keys.push((key, modifiers));
+                }
+            }
+            KeyInput::Text(text) if altgr.take().is_some() => {
+                typed_with_alt = false;
+                push_text(&mut keys, &text);
+            }
+            KeyInput::Text(_) if core::mem::take(&mut typed_with_alt) => {}
+            KeyInput::Text(text) | KeyInput::Composed(text) => push_text(&mut keys, &text),
+        }
+    }
+    keys.extend(altgr);
+    keys
+}
+
+fn push_text<K>(keys: &mut Vec<(Key<K>, Modifiers)>, text: &str) {
+    for c in text.chars() {
+        // terminals report capitals as shifted
+        keys.push((Key::Char(c), Modifiers { shift: c.is_uppercase(), ..Modifiers::NONE }));
+    }
+}
+
+/// The character a key types into the grid, if it types one. Terminals on some systems report
+/// AltGr as Control and Alt held together, so characters other than letters and digits typed
+/// with both count as typed.
+pub fn typed_char(c: char, modifiers: Modifiers) -> Option<char> {
+    let altgr = modifiers.control && modifiers.alt;
+    if !(modifiers.control || modifiers.alt) || (altgr && !c.is_ascii_alphanumeric()) {
+        Some(c)
+    } else {
+        None
+    }
+}
+
+/// Describes the character a key named `name` types, for checking what a keyboard layout sends:
+/// its code point, and whether it is too wide for a cell or is an operator.
+pub fn describe_typed(name: &str, c: char, operators: &OperatorTable) -> String {
+    let typed = format!("{} types {} (U+{:04X})", name, c, c as u32);
+    if !is_single_width(c) {
+        format!("{}, which is wider than a cell", typed)
+    } else if let Some(operator) = operators.get(c) {
+        format!("{}, the {} operator", typed, operator.name())
+    } else {
+        typed
+    }
+}
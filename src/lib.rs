@@ -0,0 +1,89 @@
+//! The rust-orca engine: a grid of cells that runs Orca's operators one tick at a time and turns
+//! what it plays into MIDI, without the editor around it.
+//!
+//! A [`Context`] holds a grid and everything that persists between ticks. Build the operator tables
+//! once with [`get_tick_operators`] and [`get_bang_operators`], from the symbols given by
+//! [`default_operator_config`] or [`parse_operator_config`], then call [`tick`] to advance the
+//! grid and send its notes to any [`MidiSink`]. [`run_headless`] runs a grid as fast as possible and
+//! captures its MIDI instead. Whatever can fail, from reading files to opening MIDI ports, returns
+//! an [`OrcaError`] rather than panicking.
+//!
+//! Everything that touches the world outside the grid is behind a feature: `files` for reading and
+//! writing grids, `midi` for the system's MIDI ports, `daemon` for serving a grid on a Unix socket
+//! and `cli` for the editor, all on by default. Without them the engine builds for
+//! `wasm32-unknown-unknown`, where the `wasm` feature adds JavaScript bindings. The `clap` feature
+//! builds the engine as a CLAP plugin for DAWs and `bevy` runs grids inside Bevy apps. The
+//! `tracing` feature adds spans and events around ticks, MIDI, commands and file operations for a
+//! [tracing](https://docs.rs/tracing) subscriber to collect. The `test-util` feature adds helpers
+//! for testing operators against the engine.
+//!
+//! Without the `std` feature, which every other feature turns on, the engine is `no_std` and
+//! needs only an allocator, for running on embedded hardware.
+//!
+//! ```no_run
+//! use rust_orca::{Context, default_operator_config, get_bang_operators, get_tick_operators, parse_grid, run_headless};
+//!
+//! let operator_map = default_operator_config();
+//! let (tick_operators, bang_operators) = (get_tick_operators(&operator_map), get_bang_operators(&operator_map));
+//! let mut context = Context::new(parse_grid("1A2", 1, 3), 3, 1, 120, 4);
+//! let capture = run_headless(&mut context, &tick_operators, &bang_operators, 16);
+//! ```
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "bevy")]
+pub mod bevy_orca;
+pub mod bitset;
+#[cfg(feature = "clap")]
+pub mod clap_plugin;
+#[cfg(all(feature = "clap", target_os = "linux"))]
+mod clap_window;
+pub mod context;
+#[cfg(all(feature = "daemon", unix))]
+pub mod daemon;
+pub mod engine;
+pub mod error;
+pub mod events;
+pub mod fs;
+pub mod history;
+pub mod keys;
+pub mod metrics;
+pub mod midi;
+pub mod notes;
+pub mod operators;
+mod prelude;
+#[cfg(feature = "std")]
+pub mod recording;
+pub mod replay;
+#[cfg(all(feature = "daemon", unix))]
+pub mod repl;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub mod scheduler;
+pub mod templates;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+mod timer;
+pub mod trace;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub mod udp;
+pub mod validate;
+#[cfg(all(feature = "files", not(target_arch = "wasm32")))]
+pub mod watch;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use context::{Block, Context, Launch, Layer, Port, Selection};
+pub use engine::{Hooks, TickEvents, Transport, run_headless, run_headless_with, tick};
+pub use error::OrcaError;
+pub use fs::{Header, grid_to_string, parse_grid};
+#[cfg(feature = "files")]
+pub use fs::{read_grid, write_grid};
+pub use midi::{MidiCapture, MidiNote, MidiSink, NoMidi};
+pub use operators::{
+    EvaluationMode, Operator, OperatorInfo, OperatorMap, OperatorTable, PortInfo, default_operator_config, get_bang_operators, get_tick_operators,
+    parse_operator_config,
+};
+#[cfg(feature = "files")]
+pub use operators::read_operator_config;
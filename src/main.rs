@@ -1,144 +1,804 @@
-mod context;
-mod midi;
-mod operators;
+mod autosave;
+mod cli;
+mod commands;
+mod config;
+#[cfg(feature = "gui")]
+mod gui;
+mod journal;
+mod output;
+mod profile;
+mod tui;
 
-use std::sync::{Arc, Mutex};
+use std::env;
+use std::fs::{read_to_string, remove_file, write};
+use std::io::{self, ErrorKind, IsTerminal, Read, Write};
+use std::path::Path;
+use std::process::exit;
+use std::sync::{Arc, Mutex, PoisonError, mpsc};
 use std::thread;
-use std::thread::sleep;
-use std::time::{Duration, Instant};
-use midir::MidiOutput;
-use pancurses::{ALL_MOUSE_EVENTS, cbreak, curs_set, getmouse, initscr, Input, mousemask, noecho, resize_term};
-use crate::context::Context;
-use crate::midi::notes_tick;
-use crate::operators::{get_bang_operators, get_tick_operators, grid_tick, read_operator_config};
+use std::thread::{JoinHandle, sleep};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use rust_orca::context::Context;
+#[cfg(unix)]
+use rust_orca::daemon::Daemon;
+#[cfg(unix)]
+use rust_orca::error::OrcaError;
+#[cfg(unix)]
+use rust_orca::repl::Repl;
+use rust_orca::engine::{run_headless, tick, Transport};
+use rust_orca::events::{events_to_csv, events_to_json};
+use rust_orca::fs::{Header, grid_to_string, header_to_string, parse_grid, parse_header, text_size, write_grid_with_header};
+use rust_orca::history::History;
+use rust_orca::metrics::{Metrics, serve_metrics};
+use rust_orca::midi::{MidiCapture, MidiSink, Monitored, NoMidi, find_output, list_ports, open_output};
+use rust_orca::operators::{OperatorMap, OperatorTable, base_36_to_char, operator_config_hash};
+use rust_orca::replay::{Replayer, SessionRecorder, read_session, write_session};
+use rust_orca::scheduler::Scheduler;
+use rust_orca::templates::find_template;
+use rust_orca::udp::UdpOutput;
+use rust_orca::validate::{validate, validate_size};
+use rust_orca::watch::watch_grid_file;
+use crate::autosave::autosave;
+use crate::cli::{EventFormat, Subcommand, USAGE};
+use crate::commands::{Command, remap};
+use crate::config::{Config, Semantics, load_config};
+use crate::journal::{journal_path, read_journal};
+use crate::output::spawn_output;
+use crate::profile::profile_table;
+
+const DEFAULT_ROWS: usize = 30;
+const DEFAULT_COLS: usize = 100;
+const DEFAULT_TEMPO: u64 = 120;
+const DEFAULT_FRAMES: u64 = 4;
+const DEFAULT_MIDI_PORT: usize = 2;
+const HISTORY_CAPACITY: usize = 1 << 20;
+const HISTORY_STEPS: usize = 1 << 12;
+const DEFAULT_AUTOSAVE_SECONDS: u64 = 60;
+// how many timestamped autosave backups are kept beside each grid
+const AUTOSAVE_BACKUPS: usize = 5;
+// how often a paused clock checks whether it has been asked to step or play
+const PAUSED_POLL_TIME: Duration = Duration::from_millis(5);
 
 fn main() {
-    let rows = 30;
-    let cols = 100;
-    let grid_row_spacing = 9;
-    let grid_col_spacing = 9;
-    let grid: Vec<Vec<char>> = (0..rows).map(|_| (0..cols).map(|_| '\0').collect()).collect();
-    let context = Context::new(grid, 120, 4);
-
-    let context_arc = Arc::new(Mutex::new(context));
-    let midi_context_arc = Arc::clone(&context_arc);
-
-    thread::spawn(move || {
-        let operator_map = read_operator_config("operator_config.txt");
-        let tick_operators = operators::get_tick_operators(&operator_map);
-        let bang_operators = operators::get_bang_operators(&operator_map);
-        let midi_out = MidiOutput::new("rust-orca").unwrap();
-        let out_ports = midi_out.ports();
-        let out_port = out_ports.get(2).unwrap();
-        let conn = &mut midi_out.connect(out_port, "rust-orca-conn").unwrap();
+    let subcommand = Subcommand::parse(env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{}\n{}", err, USAGE);
+        exit(2);
+    });
+    let config = load_config().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        exit(2);
+    });
+    let rows = config.grid.rows.unwrap_or(DEFAULT_ROWS);
+    let cols = config.grid.cols.unwrap_or(DEFAULT_COLS);
+    let tempo = config.grid.bpm.unwrap_or(DEFAULT_TEMPO);
+    let frames = config.grid.frames.unwrap_or(DEFAULT_FRAMES).max(1);
+    let semantics = config.semantics().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        exit(2);
+    });
 
-        // clear all existing midi notes
-        // TODO clear existing midi notes when program is closed as well
-        for channel in 0..16 {
-            for note in 0..128 {
-                let note_off_message = 0x80 + channel;
-                conn.send(&[note_off_message, note, 0]).unwrap();
+    let operator_map = config.operator_map().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        exit(2);
+    });
+    let (mut tick_operators, mut bang_operators, errors) = config.operator_tables(&operator_map);
+    if !errors.is_empty() {
+        eprintln!("{}", errors.join("\n"));
+        exit(2);
+    }
+    let operator_hash = operator_config_hash(&operator_map);
+
+    let mut args = match subcommand {
+        Subcommand::Edit(args) => args,
+        Subcommand::Render(args) => {
+            let (mut context, _) = read_context(Some(&args.grid_path), rows, cols, tempo, frames, &semantics);
+            if let Some(bpm) = args.bpm {
+                context.set_tempo(bpm);
+            }
+            let mut capture = run_headless(&mut context, &tick_operators, &bang_operators, args.ticks);
+            // end the bounce cleanly rather than with notes left hanging
+            capture.tick = context.ticks;
+            for note in context.notes.iter().filter(|note| note.started) {
+                note.stop(&mut capture);
             }
+            let file = capture.to_midi_file(context.divisions as u16, context.tempo);
+            if let Err(err) = write(&args.output, file) {
+                eprintln!("could not write {}: {}", args.output.display(), err);
+                exit(1);
+            }
+            eprintln!("{} ticks, {} midi messages written to {}", args.ticks, capture.messages.len(), args.output.display());
+            if let Some(path) = &args.events {
+                write_events(path, &capture.messages);
+            }
+            return;
         }
+        Subcommand::Pipe(args) => {
+            let mut text = String::new();
+            if let Err(err) = io::stdin().read_to_string(&mut text) {
+                eprintln!("could not read a grid from stdin: {}", err);
+                exit(1);
+            }
+            // without a header the grid is as big as the text, so that what comes out lines up
+            // with what went in
+            let header = parse_header(&text);
+            let (rows, cols) = match &header {
+                Some(header) => grid_size(Some(header), rows, cols),
+                None => {
+                    let (cols, rows) = text_size(&text);
+                    (rows, cols)
+                }
+            };
+            if rows == 0 || cols == 0 {
+                eprintln!("no grid on stdin");
+                exit(1);
+            }
+            let mut context = Context::try_new(parse_grid(&text, rows, cols), cols, rows, tempo, frames).unwrap_or_else(|err| {
+                eprintln!("could not load the grid on stdin: {}", err);
+                exit(1);
+            });
+            semantics.apply(&mut context);
+            context.reseed(args.seed);
+            if let Some(header) = &header {
+                header.apply(&mut context);
+            }
+            let capture = run_headless(&mut context, &tick_operators, &bang_operators, args.ticks);
+            let mut output = String::new();
+            if args.grid {
+                if let Some(header) = header {
+                    output.push_str(&header_to_string(&Header { bpm: context.tempo, frames: context.divisions, ..header }));
+                }
+                output.push_str(&grid_to_string(context.grid(), context.width));
+            }
+            if let Some(format) = args.events {
+                // a blank line ends the grid, as no grid row is ever empty
+                if args.grid {
+                    output.push('\n');
+                }
+                output.push_str(&match format {
+                    EventFormat::Json => events_to_json(&capture.messages),
+                    EventFormat::Csv => events_to_csv(&capture.messages),
+                });
+            }
+            print!("{}", output);
+            return;
+        }
+        #[cfg(unix)]
+        Subcommand::Daemon(args) => {
+            let (context, _) = read_context(args.grid_path.as_deref(), rows, cols, tempo, frames, &semantics);
+            // as when editing, play silently if there is no usable midi port, unless one was asked
+            // for by name
+            let sink: Box<dyn MidiSink + Send> = match &args.midi {
+                Some(port) => match find_output(port).and_then(open_output) {
+                    Ok((conn, _)) => Box::new(conn),
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        exit(1);
+                    }
+                },
+                None => open_output(DEFAULT_MIDI_PORT).map_or_else(|_| Box::new(NoMidi) as Box<dyn MidiSink + Send>, |(conn, _)| Box::new(conn)),
+            };
+            let daemon = Daemon::new(context, tick_operators, bang_operators, sink);
+            if let Some(target) = &args.udp {
+                match UdpOutput::open(target, args.osc) {
+                    Ok(udp) => daemon.send_messages_to(udp),
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        exit(1);
+                    }
+                }
+            }
+            if let Some(address) = &args.metrics {
+                match daemon.serve_metrics(address) {
+                    Ok(address) => eprintln!("metrics at http://{}/metrics", address),
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        exit(1);
+                    }
+                }
+            }
+            eprintln!("listening on {}", args.socket.display());
+            if let Err(err) = daemon.serve(&args.socket) {
+                eprintln!("{}", err);
+                exit(1);
+            }
+            return;
+        }
+        #[cfg(unix)]
+        Subcommand::Repl(socket) => {
+            let mut repl = Repl::connect(&socket).unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                exit(1);
+            });
+            // a script piped in gets its answers without prompts between them
+            let prompt = io::stdin().is_terminal();
+            let mut line = String::new();
+            loop {
+                if prompt {
+                    print!("> ");
+                    let _ = io::stdout().flush();
+                }
+                line.clear();
+                if !matches!(io::stdin().read_line(&mut line), Ok(read) if read > 0) {
+                    break;
+                }
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match repl.eval(&line) {
+                    Ok(shown) => println!("{}", shown),
+                    Err(err @ OrcaError::Connect { .. }) => {
+                        eprintln!("{}", err);
+                        exit(1);
+                    }
+                    Err(err) => println!("{}", err),
+                }
+                if line.trim() == "shutdown" {
+                    break;
+                }
+            }
+            return;
+        }
+        Subcommand::ListMidi => {
+            let (inputs, outputs) = list_ports().unwrap_or_else(|err| {
+                eprintln!("could not list midi ports: {}", err);
+                exit(1);
+            });
+            for (heading, ports) in [("inputs", inputs), ("outputs", outputs)] {
+                println!("{}:", heading);
+                if ports.is_empty() {
+                    println!("  none");
+                }
+                for (index, name) in ports.iter().enumerate() {
+                    println!("  {} {}", index, name);
+                }
+            }
+            return;
+        }
+        Subcommand::New(args) => {
+            let (cols, rows) = args.size.unwrap_or((cols, rows));
+            let grid = find_template(&args.template).and_then(|template| template.to_grid(rows, cols)).unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                exit(2);
+            });
+            // a new file never replaces one that is already there
+            if args.grid_path.exists() {
+                eprintln!("{} already exists", args.grid_path.display());
+                exit(1);
+            }
+            // the header keeps the size, which is what the grid is opened at
+            let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64);
+            let header = Header { rows, cols, bpm: tempo, frames, seed, operators: operator_hash };
+            if let Err(err) = write_grid_with_header(&args.grid_path, &grid, cols, &header) {
+                eprintln!("{}", err);
+                exit(1);
+            }
+            eprintln!("created {}, {}x{} from the {} template", args.grid_path.display(), cols, rows, args.template);
+            return;
+        }
+        Subcommand::Validate(path) => {
+            let text = read_to_string(&path).unwrap_or_else(|err| {
+                eprintln!("could not read grid file {}: {}", path.display(), err);
+                exit(1);
+            });
+            let (rows, cols) = grid_size(parse_header(&text).as_ref(), rows, cols);
+            let context = Context::new(parse_grid(&text, rows, cols), cols, rows, 120, 4);
+            let mut problems = validate_size(&text, rows, cols);
+            problems.extend(validate(&context, &tick_operators, &bang_operators));
+            for problem in &problems {
+                println!("{}:{}:{}: {}", path.display(), problem.row + 1, problem.col + 1, problem.message);
+            }
+            if !problems.is_empty() {
+                eprintln!("{} problem{} found", problems.len(), if problems.len() == 1 { "" } else { "s" });
+                exit(1);
+            }
+            return;
+        }
+    };
+    config.fill_args(&mut args);
+    let settings = Arc::new(config);
+    let grid_path = args.grid_path;
+    let (mut context, header) = read_context(grid_path.as_deref(), rows, cols, tempo, frames, &semantics);
+    // a replay starts from the session's grid instead of the file's, which is still where saves go
+    let mut replayer = args.replay.as_ref().map(|path| {
+        let session = read_session(path).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            exit(1);
+        });
+        if (session.rows, session.cols) != (context.height, context.width) {
+            eprintln!(
+                "{} is for a {}x{} grid, not {}x{}",
+                path.display(), session.cols, session.rows, context.width, context.height,
+            );
+            exit(1);
+        }
+        context = session.to_context(context.divisions);
+        semantics.apply(&mut context);
+        Replayer::new(session)
+    });
+    context.metrics.per_operator = args.profile;
+    let recorder = args.record.as_ref().map(|_| Arc::new(Mutex::new(SessionRecorder::new(&context))));
 
-        loop {
-            let sleep_time = {
-                let timer = Instant::now();
-
-                let mut _context = midi_context_arc.lock().unwrap();
-                grid_tick(&mut _context, &tick_operators, &bang_operators);
+    let udp = args.udp.as_deref().map(|target| UdpOutput::open(target, args.osc).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        exit(1);
+    }));
 
-                let mut notes = notes_tick(&_context.notes, _context.tick_time);
-                for note in notes.iter_mut() {
-                    if note.started && note.duration == 0 {
-                        note.stop(conn);
-                    } else if !note.started {
-                        note.stop(conn);
-                        note.start(conn);
+    if let Some(ticks) = args.headless {
+        // the metrics as of the last tick, copied out for the scrapes to read while the grid runs
+        let scraped = Arc::new(Mutex::new((Metrics::default(), 0)));
+        if let Some(address) = &args.metrics {
+            let scraped = Arc::clone(&scraped);
+            let scrape = move || {
+                let (metrics, active_notes) = &*scraped.lock().unwrap_or_else(PoisonError::into_inner);
+                metrics.to_prometheus(*active_notes)
+            };
+            match serve_metrics(address, scrape) {
+                Ok(address) => eprintln!("metrics at http://{}/metrics", address),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    exit(1);
+                }
+            }
+        }
+        let publish = |context: &Context| {
+            if args.metrics.is_some() {
+                let active_notes = context.notes.iter().filter(|note| note.started).count();
+                *scraped.lock().unwrap_or_else(PoisonError::into_inner) = (context.metrics.clone(), active_notes);
+            }
+        };
+        let mut replay_map = operator_map.clone();
+        let mut capture = MidiCapture::default();
+        for _ in 0..ticks {
+            publish(&context);
+            let mut recorder = recorder.as_ref().map(|recorder| recorder.lock().unwrap());
+            // nothing but the replay changes the grid between headless ticks, so the grid as the
+            // last tick left it is the grid as it is now
+            if let Some(recorder) = &mut recorder {
+                recorder.after_tick(&context);
+            }
+            if let Some(replayer) = &mut replayer {
+                let commands = replayer.before_tick(&mut context);
+                if let Some(tables) = replay_remaps(&commands, &mut replay_map, &settings) {
+                    (tick_operators, bang_operators) = tables;
+                }
+                if let Some(recorder) = &mut recorder {
+                    for line in &commands {
+                        recorder.command(context.ticks, line);
                     }
                 }
-                _context.notes = notes.iter().filter(|note| note.duration > 0).cloned().collect();
+            }
+            if let Some(recorder) = &mut recorder {
+                recorder.before_tick(&context);
+            }
+            // and the messages the last tick left are still there to send
+            send_messages(udp.as_ref(), &context.messages);
+            capture.tick = context.ticks;
+            tick(&mut context, &tick_operators, &bang_operators, &mut capture);
+        }
+        send_messages(udp.as_ref(), &context.messages);
+        publish(&context);
+        print!("{}", grid_to_string(context.grid(), context.width));
+        eprintln!("{} ticks, {} midi messages captured", ticks, capture.messages.len());
+        if let Some(path) = &args.events {
+            write_events(path, &capture.messages);
+        }
+        if let (Some(path), Some(recorder)) = (&args.record, &recorder) {
+            write_recording(path, &recorder.lock().unwrap());
+        }
+        if args.profile {
+            eprint!("{}", profile_table(&context.metrics));
+        }
+        return;
+    }
 
-                let elapsed = timer.elapsed().as_secs_f64();
-                60.0 / (_context.divisions * _context.tempo) as f64 - elapsed
-            };
+    let theme = args.theme.as_deref().map(|name| tui::find_theme(name).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        exit(2);
+    }));
 
-            if sleep_time > 0.0 {
-                sleep(Duration::from_secs_f64(sleep_time));
-            }
+    // run silently if there is no usable midi port, unless a port was asked for by name
+    let port = match &args.midi {
+        Some(port) => find_output(port).and_then(open_output).map_err(|err| {
+            eprintln!("{}", err);
+            exit(1);
+        }),
+        None => open_output(DEFAULT_MIDI_PORT),
+    };
+    let (sink, midi_device): (Box<dyn MidiSink + Send>, _) = match port {
+        Ok((conn, name)) => (Box::new(conn), Some(name)),
+        Err(_) => (Box::new(NoMidi), None),
+    };
+    let capture = args.events.as_ref().map(|_| Arc::new(Mutex::new(MidiCapture::default())));
+    let midi = Monitored { sink, monitor: Arc::default(), log: Arc::default(), tick: 0, capture: capture.clone() };
+    // the editor hands over a new connection when the user switches midi ports
+    let (midi_sender, midi_receiver) = mpsc::channel::<Box<dyn MidiSink + Send>>();
+    // and new operator tables when an operator is remapped or the operator config is edited
+    let (operator_sender, operator_receiver) = mpsc::channel::<(OperatorTable, OperatorTable)>();
+    let transport = Arc::new(Transport::default());
+    // and the engine hands the editor the remaps a replay makes, for it to show the same symbols
+    let (replayed_sender, replayed_receiver) = mpsc::channel::<String>();
+    let mut replayed_receiver = Some(replayed_receiver);
+
+    let mut grids = vec![(context, header, grid_path)];
+    if let Some(path) = args.split_path {
+        let (context, header) = read_context(Some(&path), rows, cols, tempo, frames, &semantics);
+        grids.push((context, header, Some(path)));
+    }
+    let mut panes = Vec::with_capacity(grids.len());
+    let mut view_senders = Vec::with_capacity(grids.len());
+    for (pane, (mut context, header, path)) in grids.into_iter().enumerate() {
+        // keep enough history for the editor to undo edits made several minutes ago on a busy grid
+        context.history = History::new(HISTORY_CAPACITY, HISTORY_STEPS);
+        context.launch_beats = settings.editor.quantize.unwrap_or(0);
+        let journal = journal_path(path.as_deref(), pane);
+        let recovered = journal.exists() && recover_journal(&journal, &mut context);
+        let (rows, cols) = (context.height, context.width);
+        let context_arc = Arc::new(Mutex::new(context));
+
+        let mut app = tui::App::new(rows, cols, tick_operators.clone(), bang_operators.clone());
+        app.pane = pane;
+        if let Some(path) = path {
+            app.set_path(path);
         }
-    });
+        // reload the grid whenever it is edited in another program
+        watch_grid_file(Arc::clone(&app.watched), Arc::clone(&context_arc));
+        // so that a crash never loses more than an interval's worth of edits
+        let autosave_seconds = args.autosave.unwrap_or(DEFAULT_AUTOSAVE_SECONDS);
+        if autosave_seconds > 0 {
+            autosave(Arc::clone(&app.watched), Arc::clone(&context_arc), pane, Duration::from_secs(autosave_seconds), AUTOSAVE_BACKUPS);
+        }
+        app.ruler = args.ruler.unwrap_or(app.ruler);
+        if args.vim {
+            app.vim = Some(tui::Vim::default());
+        }
+        if let Some(theme) = theme {
+            app.theme = theme.clone();
+        }
+        app.settings = Arc::clone(&settings);
+        app.system_clipboard = Some(tui::SystemClipboard::new());
+        app.operator_map = operator_map.clone();
+        app.operator_sender = Some(operator_sender.clone());
+        app.recorder = recorder.clone();
+        // sessions are replayed on the first grid
+        app.replayed = replayed_receiver.take();
+        let mut messages = tui::apply_config(&mut app);
+        // a grid saved with a header keeps it, and warns when it was written for other operators
+        app.header = args.header || header.is_some();
+        app.operator_hash = operator_hash;
+        if header.is_some_and(|header| header.operators != operator_hash) {
+            messages.push("grid was saved with a different operator config".to_string());
+        }
+        if recovered {
+            // the restored work is unsaved, and journaled afresh
+            app.dirty = true;
+            app.update_journal(&context_arc.lock().unwrap());
+            messages.push(format!("restored unsaved work from {}", journal.display()));
+        }
+        app.message = messages.join(", ");
+        app.midi_device = midi_device.clone();
+        app.midi_monitor = Arc::clone(&midi.monitor);
+        app.event_log = Arc::clone(&midi.log);
+        app.midi_sender = Some(midi_sender.clone());
+        app.transport = Arc::clone(&transport);
+        // the editor draws from a copy of the grid sent after each tick rather than holding the
+        // grid itself, and a copy it hasn't taken yet is kept over the next so a stalled editor
+        // never holds up the clock
+        let (view_sender, views) = mpsc::sync_channel(1);
+        app.views = Some(views);
+        view_senders.push(view_sender);
+        panes.push((context_arc, app));
+    }
+    let contexts: Vec<_> = panes.iter().map(|(context_arc, _)| Arc::clone(context_arc)).collect();
+    let engine_contexts = contexts.clone();
+    let engine_recorder = recorder.clone();
+    let engine_settings = Arc::clone(&settings);
+    let mut replay_map = operator_map.clone();
 
+    // MIDI goes out from a thread of its own, so that a slow device never holds up the clock or
+    // keeps the grid locked; the editor's port switches go straight to it
+    let sends_udp = udp.is_some();
+    let (mut output, output_thread) = spawn_output(midi, midi_receiver, udp);
+    let mut engine = EngineGuard { transport: Arc::clone(&transport), thread: None, output: Some(output_thread) };
+    engine.thread = Some(thread::spawn(move || {
+        // stop the editor as soon as the engine goes down; this guard is dropped before `output`,
+        // which ends the output thread, silencing every note, panic or not
+        let _stop = StopOnDrop(Arc::clone(&transport));
+        // clear all existing midi notes
+        output.all_notes_off();
 
-    let (mut cursor_row, mut cursor_col): (usize, usize) = (0, 0);
-
-    let mut window = initscr();
-    resize_term(rows, cols);
-    cbreak();
-    noecho();
-    curs_set(2);
-    mousemask(ALL_MOUSE_EVENTS, None);
-    window.resize(rows, cols);
-    window.keypad(true);
-    window.nodelay(true);
-    window.refresh();
-
-    loop {
-        // TODO use swap buffer with diffs to reduce latency
-        let grid = {
-            let _context = context_arc.lock().unwrap();
-            _context.grid.clone()
-        };
-        window.mv(0, 0);
-        for (r, row) in grid.iter().enumerate() {
-            for (c, &value) in row.iter().enumerate() {
-                let display_value = if value != '\0' {
-                    value
-                } else if r % grid_row_spacing == 0 && c % grid_col_spacing == 0 {
-                    '+'
-                } else {
-                    ' '
-                };
-                window.addch(display_value);
-            }
-        }
-        window.mv(cursor_row as i32, cursor_col as i32);
-
-        match window.getch() {
-            Some(input) => {
-                match input {
-                    Input::KeyUp => { cursor_row -= 1; }
-                    Input::KeyDown => { cursor_row += 1; }
-                    Input::KeyLeft => { cursor_col -= 1; }
-                    Input::KeyRight => { cursor_col += 1; }
-                    Input::KeyBackspace => {
-                        let mut _context = context_arc.lock().unwrap();
-                        _context.grid[cursor_row][cursor_col] = '\0';
+        let mut was_paused = false;
+        let mut scheduler = Scheduler::new();
+        while !transport.is_stopped() {
+            // each pane sends the tables it rebuilt, and they are all the same, as a remap in one
+            // pane is made in every other
+            while let Ok((tick_table, bang_table)) = operator_receiver.try_recv() {
+                tick_operators = tick_table;
+                bang_operators = bang_table;
+            }
+
+            if !transport.should_tick() {
+                // silence anything still sounding when playback is paused
+                if !was_paused {
+                    output.all_notes_off();
+                    for context in &engine_contexts {
+                        context.lock().unwrap().notes.clear();
                     }
-                    Input::KeyDC => {
-                        let mut _context = context_arc.lock().unwrap();
-                        _context.grid[cursor_row][cursor_col] = '\0';
+                    was_paused = true;
+                }
+                scheduler.reset();
+                sleep(PAUSED_POLL_TIME);
+                continue;
+            }
+            was_paused = transport.is_paused();
+
+            // every grid ticks on the first grid's clock, so that split panes stay in time
+            let period = {
+                let context = engine_contexts[0].lock().unwrap();
+                Duration::from_secs_f64(60.0 / (context.divisions * context.tempo) as f64)
+            };
+            let jitter = scheduler.wait(period);
+            // each grid reads the others, named by their place from 0, as the last tick left them,
+            // whichever order they tick in; the copies are only made for grids with a Layer on them
+            let reads_layers: Vec<bool> = engine_contexts
+                .iter()
+                .map(|context| engine_contexts.len() > 1 && has_layer(&context.lock().unwrap(), &tick_operators))
+                .collect();
+            let layers: Vec<_> = if reads_layers.contains(&true) {
+                let names = (0..).map(|i| base_36_to_char(i, false));
+                engine_contexts.iter().zip(names).map(|(context, name)| context.lock().unwrap().layer(name)).collect()
+            } else {
+                Vec::new()
+            };
+            for (i, context) in engine_contexts.iter().enumerate() {
+                let mut _context = context.lock().unwrap();
+                if reads_layers[i] {
+                    _context.layers.clone_from(&layers);
+                } else {
+                    _context.layers.clear();
+                }
+                // sessions are recorded and replayed on the first grid
+                let mut recorder = engine_recorder.as_ref().filter(|_| i == 0).map(|recorder| recorder.lock().unwrap());
+                if let Some(replayer) = replayer.as_mut().filter(|_| i == 0) {
+                    let commands = replayer.before_tick(&mut _context);
+                    if let Some(tables) = replay_remaps(&commands, &mut replay_map, &engine_settings) {
+                        (tick_operators, bang_operators) = tables;
                     }
-                    Input::KeyMouse => {
-                        if let Ok(mouse_event) = getmouse() {
-                            cursor_row = mouse_event.y as usize;
-                            cursor_col = mouse_event.x as usize;
+                    for line in commands {
+                        if let Some(recorder) = &mut recorder {
+                            recorder.command(_context.ticks, &line);
                         }
+                        // the editor has gone if the receiver has
+                        let _ = replayed_sender.send(line);
                     }
-                    Input::Character(mut c) => {
-                        if c == '\x08' {
-                            c = '\0';
-                        }
-                        window.addch(c);
-                        let mut _context = context_arc.lock().unwrap();
-                        _context.grid[cursor_row][cursor_col] = c;
+                }
+                if let Some(recorder) = &mut recorder {
+                    recorder.before_tick(&_context);
+                }
+                output.tick = _context.ticks;
+                tick(&mut _context, &tick_operators, &bang_operators, &mut output);
+                if sends_udp {
+                    for message in &_context.messages {
+                        output.send_text(message.clone());
                     }
-                    input => { println!("unexpected input: {:?}", input); }
                 }
+                if let Some(recorder) = &mut recorder {
+                    recorder.after_tick(&_context);
+                }
+                if i == 0 {
+                    _context.metrics.record_jitter(jitter);
+                }
+                let _ = view_senders[i].try_send(_context.view());
             }
-            None => ()
         }
+    }));
+
+    // a screen reader follows a single grid
+    let result = if args.screen_reader {
+        let (context_arc, app) = panes.swap_remove(0);
+        tui::run_screen_reader(context_arc, app)
+    } else if args.gui {
+        run_gui(panes)
+    } else {
+        tui::run(panes)
+    };
+    if let Err(err) = result {
+        eprintln!("{} error: {}", if args.gui { "Window" } else { "Terminal" }, err);
+    }
+    if engine.stop() {
+        eprintln!("the engine stopped after a panic");
+        exit(101);
+    }
+    if let (Some(path), Some(capture)) = (&args.events, capture) {
+        write_events(path, &capture.lock().unwrap().messages);
+    }
+    if let (Some(path), Some(recorder)) = (&args.record, recorder) {
+        write_recording(path, &recorder.lock().unwrap());
+    }
 
-        sleep(Duration::from_millis(10));
+    if args.profile {
+        let context = contexts[0].lock().unwrap();
+        print!("{}", profile_table(&context.metrics));
+    }
+}
+
+#[cfg(feature = "gui")]
+fn run_gui(panes: Vec<(Arc<Mutex<Context>>, tui::App)>) -> io::Result<()> {
+    gui::run(panes)
+}
+
+// the arguments turn --gui down without the gui feature, but there is still no window to open
+#[cfg(not(feature = "gui"))]
+fn run_gui(_: Vec<(Arc<Mutex<Context>>, tui::App)>) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "rust-orca was built without the gui feature"))
+}
+
+/// Whether a Layer operator is on the grid, reading the grids beside it.
+fn has_layer(context: &Context, tick_operators: &OperatorTable) -> bool {
+    let mut next = context.next_occupied(0);
+    while let Some((row, col)) = next {
+        if tick_operators.get(context.read(row, col)).is_some_and(|operator| operator.name() == "Layer") {
+            return true;
+        }
+        next = context.next_occupied(row as usize * context.width + col as usize + 1);
+    }
+    false
+}
+
+/// Sends the text of Message operators over UDP, if it is on. Running without the editor, there
+/// is a terminal to report a failed send to.
+fn send_messages(udp: Option<&UdpOutput>, messages: &[String]) {
+    let Some(udp) = udp else {
+        return;
+    };
+    for message in messages {
+        if let Err(err) = udp.send(message) {
+            eprintln!("could not send {:?} over udp: {}", message, err);
+        }
+    }
+}
+
+/// Writes MIDI messages with the ticks they were sent on to `path`, as CSV if its name ends in .csv
+/// and as JSON otherwise.
+fn write_events(path: &Path, messages: &[(usize, [u8; 3])]) {
+    let text = if path.extension().is_some_and(|extension| extension == "csv") {
+        events_to_csv(messages)
+    } else {
+        events_to_json(messages)
+    };
+    if let Err(err) = write(path, text) {
+        eprintln!("could not write {}: {}", path.display(), err);
+        exit(1);
+    }
+    eprintln!("{} events written to {}", messages.len(), path.display());
+}
+
+/// Offers to restore the unsaved work in a journal left behind by a session that did not exit
+/// cleanly, returning whether it was restored. A journal that is turned down is deleted.
+fn recover_journal(journal: &Path, context: &mut Context) -> bool {
+    let (rows, cols, grid) = match read_journal(journal) {
+        Ok(journaled) => journaled,
+        Err(err) => {
+            eprintln!("{}", err);
+            return false;
+        }
+    };
+    if (rows, cols) != (context.height, context.width) {
+        eprintln!("{} is for a {}x{} grid, not {}x{}", journal.display(), cols, rows, context.width, context.height);
+        return false;
+    }
+    // there is no one to ask when the editor is started from a script, so the journal is kept
+    if !io::stdin().is_terminal() {
+        eprintln!("{} holds unsaved work from a session that did not exit cleanly", journal.display());
+        return false;
+    }
+    eprint!("{} holds unsaved work from a session that did not exit cleanly; restore it? [y/N] ", journal.display());
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_ok() && answer.trim().eq_ignore_ascii_case("y") {
+        context.replace_grid(grid);
+        return true;
+    }
+    let _ = remove_file(journal);
+    false
+}
+
+/// Makes the remaps among the commands a replay has come to in `operator_map`, giving back the
+/// operator tables rebuilt from it if any were made.
+fn replay_remaps(commands: &[String], operator_map: &mut OperatorMap, settings: &Config) -> Option<(OperatorTable, OperatorTable)> {
+    let mut remapped = false;
+    for line in commands {
+        if let Ok(Command::Remap { operator, symbol }) = Command::parse(line) {
+            remapped |= remap(operator_map, &operator, symbol).is_ok();
+        }
+    }
+    remapped.then(|| {
+        let (tick_operators, bang_operators, _) = settings.operator_tables(operator_map);
+        (tick_operators, bang_operators)
+    })
+}
+
+/// Writes a recorded session to `path`.
+fn write_recording(path: &Path, recorder: &SessionRecorder) {
+    if let Err(err) = write_session(path, &recorder.session) {
+        eprintln!("{}", err);
+        exit(1);
+    }
+    eprintln!("session of {} changes written to {}", recorder.session.changes.len(), path.display());
+}
+
+/// Stops the transport when dropped, so that whatever watches it shuts down too.
+struct StopOnDrop(Arc<Transport>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.stop();
+    }
+}
+
+/// Stops the engine thread and waits for it and the output thread to silence their notes when
+/// dropped, so that a panic in the editor does not leave a synth droning.
+struct EngineGuard {
+    transport: Arc<Transport>,
+    thread: Option<JoinHandle<()>>,
+    output: Option<JoinHandle<()>>,
+}
+
+impl EngineGuard {
+    /// Stops the engine thread and waits for it and then the output thread, which finishes once
+    /// the engine is gone, returning whether the engine had panicked.
+    fn stop(&mut self) -> bool {
+        self.transport.stop();
+        let panicked = self.thread.take().is_some_and(|thread| thread.join().is_err());
+        if let Some(output) = self.output.take() {
+            let _ = output.join();
+        }
+        panicked
+    }
+}
+
+impl Drop for EngineGuard {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// The size to open a grid at: the one its header records, or else the configured one.
+fn grid_size(header: Option<&Header>, rows: usize, cols: usize) -> (usize, usize) {
+    header.filter(|header| header.rows > 0 && header.cols > 0).map_or((rows, cols), |header| (header.rows, header.cols))
+}
+
+/// Reads a grid from `path`, or starts an empty one if there is no path or nothing there yet, and
+/// sets up a context for it. The context takes its tempo and seed from the file's header if it has
+/// one, and otherwise plays at `tempo` and is freshly seeded.
+fn read_context(
+    path: Option<&Path>,
+    rows: usize,
+    cols: usize,
+    tempo: u64,
+    frames: u64,
+    semantics: &Semantics,
+) -> (Context, Option<Header>) {
+    let text = match path {
+        Some(path) => match read_to_string(path) {
+            Ok(text) => text,
+            // a file that does not exist yet is created by the first save
+            Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                eprintln!("could not read grid file {}: {}", path.display(), err);
+                exit(1);
+            }
+        },
+        None => String::new(),
+    };
+    let header = parse_header(&text);
+    let (rows, cols) = grid_size(header.as_ref(), rows, cols);
+    let mut context = Context::new(parse_grid(&text, rows, cols), cols, rows, tempo, frames);
+    semantics.apply(&mut context);
+    match &header {
+        Some(header) => header.apply(&mut context),
+        None => {
+            let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64);
+            context.reseed(seed);
+        }
     }
-}
\ No newline at end of file
+    (context, header)
+}
@@ -0,0 +1,166 @@
+use core::time::Duration;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use std::io::{self, BufRead, BufReader, Write};
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use std::net::{SocketAddr, TcpListener, TcpStream};
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use std::thread;
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+use crate::error::OrcaError;
+use crate::prelude::*;
+
+// how long a scraper gets to send its request and take its answer before it is hung up on, so
+// that one that goes quiet can't hold up the others
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OperatorMetrics {
+    pub invocations: u64,
+    pub time: Duration,
+}
+
+/// Timing and output statistics gathered while ticking the grid.
+#[derive(Clone, Debug, Default)]
+pub struct Metrics {
+    pub ticks: u64,
+    pub last_tick_time: Duration,
+    pub max_tick_time: Duration,
+    pub total_tick_time: Duration,
+    pub notes_emitted: u64,
+    /// Ticks that took longer to run than the time between two ticks, counted by whatever runs
+    /// the clock.
+    pub late_ticks: u64,
+    /// How late the clock woke up for ticks, past when they were due.
+    pub last_jitter: Duration,
+    pub max_jitter: Duration,
+    pub total_jitter: Duration,
+    pub jitter_samples: u64,
+    /// Whether to time every operator evaluation; this costs a couple of clock reads per operator,
+    /// so it is off unless something asks for the per-operator breakdown.
+    pub per_operator: bool,
+    pub operators: HashMap<&'static str, OperatorMetrics>,
+}
+
+impl Metrics {
+    pub fn record_tick(&mut self, time: Duration) {
+        self.ticks += 1;
+        self.last_tick_time = time;
+        self.max_tick_time = self.max_tick_time.max(time);
+        self.total_tick_time += time;
+    }
+
+    pub fn record_operator(&mut self, name: &'static str, time: Duration) {
+        let operator = self.operators.entry(name).or_default();
+        operator.invocations += 1;
+        operator.time += time;
+    }
+
+    pub fn record_jitter(&mut self, jitter: Duration) {
+        self.jitter_samples += 1;
+        self.last_jitter = jitter;
+        self.max_jitter = self.max_jitter.max(jitter);
+        self.total_jitter += jitter;
+    }
+
+    pub fn mean_jitter(&self) -> Duration {
+        if self.jitter_samples == 0 {
+            Duration::ZERO
+        } else {
+            self.total_jitter / self.jitter_samples as u32
+        }
+    }
+
+    pub fn mean_tick_time(&self) -> Duration {
+        if self.ticks == 0 {
+            Duration::ZERO
+        } else {
+            self.total_tick_time / self.ticks as u32
+        }
+    }
+
+    /// Returns the fraction of the frame deadline (the time between two ticks) that the last tick
+    /// took; values approaching 1 mean the patch is about to start missing frames.
+    pub fn load(&self, tick_time_ms: u64) -> f64 {
+        self.last_tick_time.as_secs_f64() * 1000.0 / tick_time_ms.max(1) as f64
+    }
+
+    /// Formats the metrics in Prometheus' text exposition format, along with how many notes are
+    /// sounding now, for a scraper to collect.
+    pub fn to_prometheus(&self, active_notes: usize) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, f64)]| {
+            text.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+            for (suffix, value) in samples {
+                text.push_str(&format!("{}{} {}\n", name, suffix, value));
+            }
+        };
+        metric("orca_ticks_total", "counter", "Ticks run.", &[("", self.ticks as f64)]);
+        metric("orca_tick_duration_seconds", "summary", "How long ticks took to run.", &[
+            ("_sum", self.total_tick_time.as_secs_f64()),
+            ("_count", self.ticks as f64),
+        ]);
+        metric("orca_tick_duration_max_seconds", "gauge", "The longest any tick took to run.", &[
+            ("", self.max_tick_time.as_secs_f64()),
+        ]);
+        metric("orca_late_ticks_total", "counter", "Ticks that took longer than the time between ticks.", &[
+            ("", self.late_ticks as f64),
+        ]);
+        metric("orca_tick_jitter_seconds", "summary", "How late the clock woke up for ticks.", &[
+            ("_sum", self.total_jitter.as_secs_f64()),
+            ("_count", self.jitter_samples as f64),
+        ]);
+        metric("orca_tick_jitter_max_seconds", "gauge", "The latest the clock woke up for a tick.", &[
+            ("", self.max_jitter.as_secs_f64()),
+        ]);
+        metric("orca_notes_total", "counter", "Notes played.", &[("", self.notes_emitted as f64)]);
+        metric("orca_active_notes", "gauge", "Notes sounding now.", &[("", active_notes as f64)]);
+        text
+    }
+
+    pub fn reset(&mut self) {
+        *self = Metrics { per_operator: self.per_operator, ..Metrics::default() };
+    }
+}
+
+/// Answers HTTP requests for `/metrics` at `address` from a thread of its own with the text
+/// `scrape` gives, returning the address it is listening on.
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub fn serve_metrics(address: &str, scrape: impl Fn() -> String + Send + 'static) -> Result<SocketAddr, OrcaError> {
+    let socket_error = |source| OrcaError::Socket { address: address.to_string(), source };
+    let listener = TcpListener::bind(address).map_err(socket_error)?;
+    let bound = listener.local_addr().map_err(socket_error)?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // a scraper that hangs up early or goes quiet only misses its own answer
+            let _ = answer_scrape(stream, &scrape);
+        }
+    });
+    Ok(bound)
+}
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+fn answer_scrape(mut stream: TcpStream, scrape: &impl Fn() -> String) -> io::Result<()> {
+    stream.set_read_timeout(Some(SCRAPE_TIMEOUT))?;
+    stream.set_write_timeout(Some(SCRAPE_TIMEOUT))?;
+    let mut request = String::new();
+    let mut reader = BufReader::new(stream.try_clone()?);
+    reader.read_line(&mut request)?;
+    // the headers are read and ignored
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let (status, body) = match request.split_whitespace().nth(1) {
+        Some("/metrics") => ("200 OK", scrape()),
+        _ => ("404 Not Found", "not found\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body,
+    )
+}
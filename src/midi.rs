@@ -1,12 +1,287 @@
-use std::collections::HashMap;
+#[cfg(feature = "std")]
+use core::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex, PoisonError};
+#[cfg(feature = "std")]
 use std::thread::sleep;
-use std::time::Duration;
-use midir::MidiOutputConnection;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+#[cfg(feature = "midi")]
+use midir::{MidiInput, MidiOutput, MidiOutputConnection};
 
-// c c# d d# e e# f f# g g# a a# b  b# c
-// 0 1  2 3  4 5  5 6  7 8  9 10 11 12 12
-const NATURAL_NOTES: [u8; 7] = [9, 11, 0, 2, 4, 5, 7];
-const SHARP_NOTES: [u8; 7] = [10, 12, 1, 3, 5, 6, 8];
+#[cfg(feature = "std")]
+use crate::events::EventLog;
+use crate::error::OrcaError;
+use crate::notes::note_number;
+use crate::prelude::*;
+
+pub use crate::notes::note_name;
+
+/// A destination for raw MIDI messages, such as a device connection or an in-memory capture.
+pub trait MidiSink {
+    fn send(&mut self, message: &[u8]) -> Result<(), OrcaError>;
+}
+
+#[cfg(feature = "midi")]
+impl MidiSink for MidiOutputConnection {
+    fn send(&mut self, message: &[u8]) -> Result<(), OrcaError> {
+        #[cfg(feature = "tracing")]
+        tracing::trace!(message = %describe_message(message), "midi send");
+        MidiOutputConnection::send(self, message).map_err(|err| OrcaError::Midi(err.to_string()))
+    }
+}
+
+impl<S: MidiSink + ?Sized> MidiSink for Box<S> {
+    fn send(&mut self, message: &[u8]) -> Result<(), OrcaError> {
+        (**self).send(message)
+    }
+}
+
+/// Per-channel note activity, updated from the thread sending MIDI and readable from any other.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct MidiMonitor {
+    start: Instant,
+    /// Which notes are sounding on each channel, as two 64-bit halves of a 128-bit mask.
+    sounding: [[AtomicU64; 2]; 16],
+    /// Milliseconds after `start` of the last note on for each channel.
+    last_note_on: [AtomicU64; 16],
+    last_velocity: [AtomicU8; 16],
+}
+
+#[cfg(feature = "std")]
+impl Default for MidiMonitor {
+    fn default() -> Self {
+        MidiMonitor {
+            start: Instant::now(),
+            sounding: Default::default(),
+            last_note_on: Default::default(),
+            last_velocity: Default::default(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl MidiMonitor {
+    /// Records a message on its way out. Only note on and note off messages are tracked.
+    pub fn observe(&self, message: &[u8]) {
+        let [status, note, velocity, ..] = *message else {
+            return;
+        };
+        let channel = (status & 0x0f) as usize;
+        let (half, bit) = ((note as usize / 64) & 1, 1u64 << (note % 64));
+        match status & 0xf0 {
+            0x90 if velocity > 0 => {
+                self.sounding[channel][half].fetch_or(bit, Ordering::Relaxed);
+                self.last_note_on[channel].store(self.start.elapsed().as_millis() as u64, Ordering::Relaxed);
+                self.last_velocity[channel].store(velocity, Ordering::Relaxed);
+            }
+            // a note on with zero velocity is a note off
+            0x80 | 0x90 => {
+                self.sounding[channel][half].fetch_and(!bit, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+
+    /// Forgets every sounding note, for after they have all been stopped at once.
+    pub fn clear(&self) {
+        for halves in &self.sounding {
+            for half in halves {
+                half.store(0, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// How many notes are sounding on `channel`.
+    pub fn sounding(&self, channel: usize) -> u32 {
+        self.sounding[channel].iter().map(|half| half.load(Ordering::Relaxed).count_ones()).sum()
+    }
+
+    /// The velocity of the last note started on `channel`, scaled down linearly to 0 over `decay`
+    /// after it started.
+    pub fn level(&self, channel: usize, decay: Duration) -> f32 {
+        let velocity = self.last_velocity[channel].load(Ordering::Relaxed);
+        if velocity == 0 {
+            return 0.0;
+        }
+        let now = self.start.elapsed().as_millis() as u64;
+        let since = now.saturating_sub(self.last_note_on[channel].load(Ordering::Relaxed));
+        let remaining = 1.0 - since as f32 / decay.as_millis().max(1) as f32;
+        velocity as f32 / 127.0 * remaining.max(0.0)
+    }
+}
+
+/// Passes messages on to another sink, recording note activity in a monitor and each message in
+/// an event log along the way. Every note is stopped when it is dropped, including while unwinding
+/// from a panic.
+#[cfg(feature = "std")]
+pub struct Monitored<S: MidiSink> {
+    pub sink: S,
+    pub monitor: Arc<MidiMonitor>,
+    pub log: Arc<EventLog>,
+    /// The tick stamped onto logged messages; set this before each tick.
+    pub tick: usize,
+    /// Keeps every message sent, for exporting once the run is over.
+    pub capture: Option<Arc<Mutex<MidiCapture>>>,
+}
+
+#[cfg(feature = "std")]
+impl<S: MidiSink> MidiSink for Monitored<S> {
+    fn send(&mut self, message: &[u8]) -> Result<(), OrcaError> {
+        self.monitor.observe(message);
+        self.log.push(self.tick, describe_message(message));
+        if let Some(capture) = &self.capture {
+            // a panic elsewhere while holding the capture leaves it as usable as it was
+            let mut capture = capture.lock().unwrap_or_else(PoisonError::into_inner);
+            capture.tick = self.tick;
+            capture.send(message)?;
+        }
+        self.sink.send(message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: MidiSink> Drop for Monitored<S> {
+    fn drop(&mut self) {
+        self.all_notes_off();
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: MidiSink> Monitored<S> {
+    /// Stops every note on every channel, logging it as one event rather than one per note.
+    pub fn all_notes_off(&mut self) {
+        all_notes_off(&mut self.sink);
+        self.monitor.clear();
+        self.log.push(self.tick, "all notes off".to_string());
+    }
+}
+
+/// Spells out a MIDI message, like `note on ch 2 C4 vel 100`. Channels are counted from 0, the
+/// way they are written in the grid.
+pub fn describe_message(message: &[u8]) -> String {
+    match *message {
+        [status, note, velocity, ..] if status & 0xf0 == 0x90 && velocity > 0 => {
+            format!("note on ch {} {} vel {}", status & 0x0f, note_name(note), velocity)
+        }
+        [status, note, ..] if status & 0xf0 == 0x80 || status & 0xf0 == 0x90 => {
+            format!("note off ch {} {}", status & 0x0f, note_name(note))
+        }
+        _ => {
+            let bytes: Vec<String> = message.iter().map(|byte| format!("{:02x}", byte)).collect();
+            format!("midi {}", bytes.join(" "))
+        }
+    }
+}
+
+/// Lists the names of the MIDI input ports and output ports, each in index order.
+#[cfg(feature = "midi")]
+pub fn list_ports() -> Result<(Vec<String>, Vec<String>), OrcaError> {
+    let midi_in = MidiInput::new("rust-orca").map_err(|err| OrcaError::Midi(err.to_string()))?;
+    let midi_out = MidiOutput::new("rust-orca").map_err(|err| OrcaError::Midi(err.to_string()))?;
+    let inputs = midi_in.ports().iter().map(|port| midi_in.port_name(port).unwrap_or_default()).collect();
+    let outputs = midi_out.ports().iter().map(|port| midi_out.port_name(port).unwrap_or_default()).collect();
+    Ok((inputs, outputs))
+}
+
+/// Finds the index of an output port given either the index itself or part of the port's name,
+/// ignoring case.
+#[cfg(feature = "midi")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "info", err(Display)))]
+pub fn find_output(port: &str) -> Result<usize, OrcaError> {
+    if let Ok(index) = port.parse() {
+        return Ok(index);
+    }
+    let (_, outputs) = list_ports()?;
+    let port_lower = port.to_lowercase();
+    outputs
+        .iter()
+        .position(|name| name.to_lowercase().contains(&port_lower))
+        .ok_or_else(|| OrcaError::NoMidiPort(format!("named {}", port)))
+}
+
+/// Connects to the MIDI output port at `index`, returning the connection and the port's name.
+#[cfg(feature = "midi")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "info", err(Display)))]
+pub fn open_output(index: usize) -> Result<(MidiOutputConnection, String), OrcaError> {
+    let midi_out = MidiOutput::new("rust-orca").map_err(|err| OrcaError::Midi(err.to_string()))?;
+    let out_ports = midi_out.ports();
+    let out_port = out_ports.get(index).ok_or_else(|| OrcaError::NoMidiPort(index.to_string()))?;
+    let name = midi_out.port_name(out_port).unwrap_or_default();
+    let conn = midi_out.connect(out_port, "rust-orca-conn").map_err(|err| OrcaError::Midi(err.to_string()))?;
+    Ok((conn, name))
+}
+
+/// Discards every message, for running without a MIDI device.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoMidi;
+
+impl MidiSink for NoMidi {
+    fn send(&mut self, _message: &[u8]) -> Result<(), OrcaError> {
+        Ok(())
+    }
+}
+
+/// Records messages along with the tick they were sent on instead of sending them anywhere.
+#[derive(Clone, Debug, Default)]
+pub struct MidiCapture {
+    /// The tick stamped onto messages as they arrive; set this before each tick.
+    pub tick: usize,
+    pub messages: Vec<(usize, [u8; 3])>,
+}
+
+impl MidiSink for MidiCapture {
+    fn send(&mut self, message: &[u8]) -> Result<(), OrcaError> {
+        let mut bytes = [0; 3];
+        let len = message.len().min(3);
+        bytes[..len].copy_from_slice(&message[..len]);
+        self.messages.push((self.tick, bytes));
+        Ok(())
+    }
+}
+
+impl MidiCapture {
+    /// Encodes the captured messages as a standard MIDI file with a single track, counting
+    /// `ticks_per_beat` of the capture's ticks to a beat at `tempo` beats per minute.
+    pub fn to_midi_file(&self, ticks_per_beat: u16, tempo: u64) -> Vec<u8> {
+        let mut track = Vec::new();
+        let microseconds_per_beat = (60_000_000 / tempo.max(1)).min(0xff_ffff) as u32;
+        track.extend([0, 0xff, 0x51, 0x03]);
+        track.extend(&microseconds_per_beat.to_be_bytes()[1..]);
+        let mut last_tick = 0;
+        for &(tick, message) in &self.messages {
+            push_variable_length(&mut track, (tick - last_tick) as u32);
+            track.extend(message);
+            last_tick = tick;
+        }
+        track.extend([0, 0xff, 0x2f, 0x00]);
+
+        let mut file = Vec::with_capacity(track.len() + 22);
+        file.extend(b"MThd");
+        file.extend(6u32.to_be_bytes());
+        // format 0, one track
+        file.extend(0u16.to_be_bytes());
+        file.extend(1u16.to_be_bytes());
+        file.extend(ticks_per_beat.max(1).to_be_bytes());
+        file.extend(b"MTrk");
+        file.extend((track.len() as u32).to_be_bytes());
+        file.extend(track);
+        file
+    }
+}
+
+/// Appends a standard MIDI file variable length quantity: seven bits per byte, most significant
+/// first, with the top bit set on every byte but the last.
+fn push_variable_length(bytes: &mut Vec<u8>, value: u32) {
+    let mut groups = vec![(value & 0x7f) as u8];
+    let mut rest = value >> 7;
+    while rest > 0 {
+        groups.push((rest & 0x7f) as u8 | 0x80);
+        rest >>= 7;
+    }
+    bytes.extend(groups.iter().rev());
+}
 
 #[derive(Debug)]
 #[derive(Clone)]
@@ -23,12 +298,7 @@ pub struct MidiNote {
 impl MidiNote {
     pub fn from_base_36(channel: u8, base_octave: u8, base_note: u8, sharp: bool, velocity: u8,
                         duration: u8, tick_time: u64) -> MidiNote {
-        let note_index = (base_note - 10) % 7;
-        let octave_offset = 1 + (base_note - 10) / 7;
-        let note_index = note_index as usize;
-        let note_offset = if sharp { SHARP_NOTES[note_index] } else { NATURAL_NOTES[note_index] };
-        let octave = base_octave + octave_offset;
-        let note_number = 12 * octave + note_offset;
+        let note_number = note_number(base_octave, base_note, sharp);
 
         let velocity = (velocity as f32 * (127.0 / 35.0)) as u8;
 
@@ -37,33 +307,61 @@ impl MidiNote {
     }
 
     #[allow(dead_code)]
-    pub fn play(&self, conn: &mut MidiOutputConnection) {
+    #[cfg(feature = "std")]
+    pub fn play(&self, conn: &mut (impl MidiSink + ?Sized)) {
         let note_on_message: u8 = 0x90 + self.channel;
         let note_off_message: u8 = 0x80 + self.channel;
         match conn.send(&[note_on_message, self.note_number, self.velocity]) {
             Ok(_) => {}
-            Err(err) => { println!("Midi note on send error: {}", err); }
+            Err(err) => report_send_error("note on", &err),
         };
         sleep(Duration::from_millis(self.duration));
         match conn.send(&[note_off_message, self.note_number, self.velocity]) {
             Ok(_) => {}
-            Err(err) => { println!("Midi note off send error: {}", err); }
+            Err(err) => report_send_error("note off", &err),
         };
     }
 
-    pub fn start(&mut self, conn: &mut MidiOutputConnection) {
+    pub fn start(&mut self, conn: &mut (impl MidiSink + ?Sized)) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(channel = self.channel, note = self.note_number, velocity = self.velocity, "note on");
         let note_on_message: u8 = 0x90 + self.channel;
         match conn.send(&[note_on_message, self.note_number, self.velocity]) {
             Ok(_) => { self.started = true; }
-            Err(err) => { println!("Midi note on send error: {}", err); }
+            Err(err) => report_send_error("note on", &err),
         };
     }
 
-    pub fn stop(&self, conn: &mut MidiOutputConnection) {
+    pub fn stop(&self, conn: &mut (impl MidiSink + ?Sized)) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(channel = self.channel, note = self.note_number, "note off");
         let note_off_message: u8 = 0x80 + self.channel;
         match conn.send(&[note_off_message, self.note_number, self.velocity]) {
             Ok(_) => {}
-            Err(err) => { println!("Midi note off send error: {}", err); }
+            Err(err) => report_send_error("note off", &err),
+        }
+    }
+}
+
+/// Reports a message that could not be sent, which playback carries on without, as a warning to
+/// any tracing subscriber and on stdout with std.
+fn report_send_error(kind: &str, err: &OrcaError) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(kind, %err, "midi send error");
+    #[cfg(feature = "std")]
+    println!("Midi {} send error: {}", kind, err);
+    #[cfg(not(any(feature = "std", feature = "tracing")))]
+    let _ = (kind, err);
+}
+
+/// Sends a note off for every note on every channel.
+pub fn all_notes_off(conn: &mut (impl MidiSink + ?Sized)) {
+    for channel in 0..16 {
+        for note in 0..128 {
+            let note_off_message = 0x80 + channel;
+            if let Err(err) = conn.send(&[note_off_message, note, 0]) {
+                report_send_error("note off", &err);
+            }
         }
     }
 }
@@ -76,12 +374,12 @@ pub fn notes_tick(notes: &Vec<MidiNote>, tick_time: u64) -> Vec<MidiNote> {
             let key = (note.channel, note.note_number);
             if let Some(other_note) = note_set.get(&key) {
                 if other_note.duration < duration {
-                    let mut note = note.clone();
+                    let mut note = note;
                     note.duration = duration;
                     note_set.insert(key, note);
                 }
             } else {
-                let mut note = note.clone();
+                let mut note = note;
                 note.duration = duration;
                 note_set.insert(key, note);
             }
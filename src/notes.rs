@@ -0,0 +1,38 @@
+use crate::operators::char_to_base_36;
+use crate::prelude::*;
+
+// c c# d d# e e# f f# g g# a a# b  b# c
+// 0 1  2 3  4 5  5 6  7 8  9 10 11 12 12
+const NATURAL_NOTES: [u8; 7] = [9, 11, 0, 2, 4, 5, 7];
+const SHARP_NOTES: [u8; 7] = [10, 12, 1, 3, 5, 6, 8];
+
+const NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+/// The MIDI note number the Midi operator plays for a base-36 octave and note, where notes from
+/// 10, `A`, run up through the letters and past `G` into the octaves above.
+pub fn note_number(octave: u8, note: u8, sharp: bool) -> u8 {
+    let note_index = note.saturating_sub(10) as usize % 7;
+    let octave_offset = 1 + note.saturating_sub(10) / 7;
+    let note_offset = if sharp { SHARP_NOTES[note_index] } else { NATURAL_NOTES[note_index] };
+    let octave = octave as u16 + octave_offset as u16;
+    // high octaves run past the top of the MIDI range, where they stay on its highest note
+    (12 * octave + note_offset as u16).min(127) as u8
+}
+
+/// The MIDI note number of an octave and a note as they are written in a Midi operator's cells,
+/// such as `3` and `C`, where a lowercase note is sharp. A note that isn't a letter plays nothing.
+pub fn cell_note_number(octave: char, note: char) -> Option<u8> {
+    let (octave, _) = char_to_base_36(octave);
+    let (note, upper) = char_to_base_36(note);
+    (note >= 10).then(|| note_number(octave, note, !upper))
+}
+
+/// Names a MIDI note number in scientific pitch notation, where note 60 is C4.
+pub fn note_name(note: u8) -> String {
+    format!("{}{}", NAMES[note as usize % 12], note as i32 / 12 - 1)
+}
+
+/// Names the pitch of an octave and a note written in a Midi operator's cells, as in `C#4`.
+pub fn cell_note_name(octave: char, note: char) -> Option<String> {
+    cell_note_number(octave, note).map(note_name)
+}
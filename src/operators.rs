@@ -1,21 +1,30 @@
-use std::collections::HashMap;
-use std::fs::{File, read_to_string};
-use std::hash::Hash;
+use core::ops::{Deref, Range};
+#[cfg(feature = "files")]
+use std::fs::read_to_string;
+#[cfg(feature = "files")]
+use std::io::ErrorKind;
+#[cfg(feature = "files")]
 use std::path::Path;
 
-use rand::Rng;
+use smallvec::{smallvec, SmallVec};
+use unicode_width::UnicodeWidthChar;
 
+use crate::bitset::Bitset;
 use crate::context::{Context, Port};
+use crate::error::OrcaError;
+use crate::history::StepKind;
 use crate::midi::MidiNote;
-use crate::operators::Update::Variables;
+use crate::prelude::*;
+use crate::timer::Timer;
+use crate::trace::Visit;
 
 pub fn char_to_base_36(c: char) -> (u8, bool) {
-    if c >= '0' && c <= '9' {
-        (c as u8 - '0' as u8, false)
-    } else if c >= 'a' && c <= 'z' {
-        (c as u8 + 10 - 'a' as u8, false)
-    } else if c >= 'A' && c <= 'Z' {
-        (c as u8 + 10 - 'A' as u8, true)
+    if c.is_ascii_digit() {
+        (c as u8 - b'0', false)
+    } else if c.is_ascii_lowercase() {
+        (c as u8 + 10 - b'a', false)
+    } else if c.is_ascii_uppercase() {
+        (c as u8 + 10 - b'A', true)
     } else {
         (0, false)
     }
@@ -24,38 +33,242 @@ pub fn char_to_base_36(c: char) -> (u8, bool) {
 pub fn base_36_to_char(c: u8, upper: bool) -> char {
     let c = c % 36;
     let c = if c < 10 {
-        c as u8 + '0' as u8
+        c + b'0'
     } else if upper {
-        c as u8 - 10 + 'A' as u8
+        c - 10 + b'A'
     } else {
-        c as u8 - 10 + 'a' as u8
+        c - 10 + b'a'
     };
     c as char
 }
 
+/// Whether `c` takes up exactly one column in a terminal, so that a grid holding it stays lined up.
+pub fn is_single_width(c: char) -> bool {
+    !c.is_control() && c.width() == Some(1)
+}
+
+/// Whether `c` can be an operator's symbol: a single width character that isn't a space, an empty
+/// cell or a bang.
+pub fn is_symbol(c: char) -> bool {
+    is_single_width(c) && !c.is_whitespace() && c != '.' && c != '*'
+}
+
+// updates and their ports are stored inline so that evaluating an operator doesn't allocate; only
+// unusually long tracks, queries and the like spill onto the heap
+type Ports = SmallVec<[Port; 8]>;
+type Updates = SmallVec<[Update; 3]>;
+
 enum Update {
-    Inputs(Vec<Port>),
-    Outputs(Vec<Port>),
-    Locks(Vec<Port>),
-    Notes(Vec<MidiNote>),
-    Variables(Vec<(char, char)>),
+    Inputs(Ports),
+    Outputs(Ports),
+    Locks(Ports),
+    LockRange { row: i32, cols: Range<i32> },
+    Notes(SmallVec<[MidiNote; 1]>),
+    Messages(SmallVec<[String; 1]>),
+    Variables(SmallVec<[(char, char); 1]>),
 }
 
 #[derive(Clone)]
 pub struct Operator {
-    name: String,
-    evaluate: fn(context: &Context, row: i32, col: i32) -> Vec<Update>,
+    name: &'static str,
+    description: &'static str,
+    evaluate: fn(context: &Evaluation, row: i32, col: i32) -> Updates,
+    /// Values for inputs to fall back on in place of the defaults built into `evaluate`.
+    defaults: Vec<(&'static str, char)>,
+    bang_only: bool,
+}
+
+/// The grid as an operator sees it while it is evaluated: the context, with the operator's
+/// configured defaults standing in for its built-in ones.
+struct Evaluation<'a> {
+    context: &'a Context,
+    defaults: &'a [(&'static str, char)],
+}
+
+impl Evaluation<'_> {
+    fn listen(&self, name: &'static str, row: i32, col: i32, default: char) -> Port {
+        let default = self.defaults.iter().find(|(port, _)| *port == name).map_or(default, |&(_, value)| value);
+        self.context.listen(name, row, col, default)
+    }
+}
+
+impl Deref for Evaluation<'_> {
+    type Target = Context;
+
+    fn deref(&self) -> &Context {
+        self.context
+    }
 }
 
+/// The cells an operator reads from, writes to and locks when evaluated at a given position.
+#[derive(Clone, Debug, Default)]
+pub struct PortLayout {
+    pub inputs: Vec<Port>,
+    pub outputs: Vec<Port>,
+    /// Cells locked without being read or written, such as the body of a comment.
+    pub locks: Vec<(i32, i32)>,
+}
+
+/// A port of an operator, placed relative to the operator's own cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PortInfo {
+    pub name: &'static str,
+    pub row: i32,
+    pub col: i32,
+    /// What an input reads when its cell is empty, if anything.
+    pub default: Option<char>,
+}
+
+/// What an operator is and where it reads and writes, for help, pickers and generated docs.
+#[derive(Clone, Debug)]
+pub struct OperatorInfo {
+    pub name: &'static str,
+    /// The symbols bound to the operator, in the order of the table it came from.
+    pub symbols: Vec<char>,
+    pub description: &'static str,
+    /// The operator's ports when it is placed on an empty grid.
+    pub inputs: Vec<PortInfo>,
+    pub outputs: Vec<PortInfo>,
+    /// Whether the operator only does anything on ticks it is banged.
+    pub bang_only: bool,
+}
+
+// operators are laid out at the center of an empty grid this big, which has room for the farthest
+// ports any of them have
+const LAYOUT_SIZE: usize = 17;
 
 impl Operator {
-    fn new(name: &str, evaluate: fn(&Context, i32, i32) -> Vec<Update>) -> Operator {
-        Operator { name: String::from(name), evaluate }
+    fn new(name: &'static str, description: &'static str, evaluate: fn(&Evaluation, i32, i32) -> Updates) -> Operator {
+        Operator { name, description, evaluate, defaults: Vec::new(), bang_only: false }
+    }
+
+    fn bang_only(self) -> Operator {
+        Operator { bang_only: true, ..self }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// A one-line summary of what the operator does.
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+
+    /// Whether the operator only does anything on ticks it is banged, like Midi.
+    pub fn is_bang_only(&self) -> bool {
+        self.bang_only
+    }
+
+    /// The operator's ports when placed at the center of an empty grid, and where the center is.
+    fn layout_on_empty_grid(&self) -> (PortLayout, i32) {
+        let center = (LAYOUT_SIZE / 2) as i32;
+        let context = Context::new(vec!['\0'; LAYOUT_SIZE * LAYOUT_SIZE], LAYOUT_SIZE, LAYOUT_SIZE, 120, 4);
+        (self.ports(&context, center, center), center)
+    }
+
+    fn info(&self, symbols: Vec<char>) -> OperatorInfo {
+        let (layout, center) = self.layout_on_empty_grid();
+        let port_info = |port: &Port| PortInfo {
+            name: port.name,
+            row: port.row - center,
+            col: port.col - center,
+            default: Some(port.value).filter(|&value| value != '\0'),
+        };
+        OperatorInfo {
+            name: self.name,
+            symbols,
+            description: self.description,
+            inputs: layout.inputs.iter().map(port_info).collect(),
+            outputs: layout.outputs.iter().map(|port| PortInfo { default: None, ..port_info(port) }).collect(),
+            bang_only: self.bang_only,
+        }
+    }
+
+    /// Draws where the operator reads and writes when placed on an empty grid, one string per
+    /// row: the operator is drawn as `symbol`, inputs as `i`, outputs as `o`, other locked cells
+    /// as `-`, and everything else as `.`.
+    pub fn diagram(&self, symbol: char) -> Vec<String> {
+        let (layout, center) = self.layout_on_empty_grid();
+        let mut cells = vec![(center, center, symbol)];
+        cells.extend(layout.locks.iter().map(|&(row, col)| (row, col, '-')));
+        cells.extend(layout.inputs.iter().map(|port| (port.row, port.col, 'i')));
+        cells.extend(layout.outputs.iter().map(|port| (port.row, port.col, 'o')));
+        let size = 0..LAYOUT_SIZE as i32;
+        cells.retain(|&(row, col, _)| size.contains(&row) && size.contains(&col));
+
+        let rows = cells.iter().map(|cell| cell.0);
+        let cols = cells.iter().map(|cell| cell.1);
+        let (top, bottom) = (rows.clone().min().unwrap_or(center), rows.max().unwrap_or(center));
+        let (left, right) = (cols.clone().min().unwrap_or(center), cols.max().unwrap_or(center));
+        let mut diagram = vec![vec!['.'; (right - left + 1) as usize]; (bottom - top + 1) as usize];
+        // later cells win, so an output that is also locked is drawn as an output
+        for (row, col, value) in cells.into_iter().rev() {
+            let cell = &mut diagram[(row - top) as usize][(col - left) as usize];
+            if *cell == '.' {
+                *cell = value;
+            }
+        }
+        // keep the operator itself visible over any port on the same cell
+        diagram[(center - top) as usize][(center - left) as usize] = symbol;
+        diagram.into_iter().map(|row| row.into_iter().collect()).collect()
+    }
+
+    /// Evaluates the operator at `(row, col)` against the current grid without applying it, and
+    /// returns where it would read and write. Ports that depend on other inputs (like the read
+    /// position of `O`) reflect the grid as it is now.
+    pub fn ports(&self, context: &Context, row: i32, col: i32) -> PortLayout {
+        let mut layout = PortLayout::default();
+        for update in self.evaluate(context, row, col) {
+            match update {
+                Update::Inputs(ports) => layout.inputs.extend(ports),
+                Update::Outputs(ports) => layout.outputs.extend(ports),
+                Update::Locks(ports) => layout.locks.extend(ports.iter().map(|port| (port.row, port.col))),
+                Update::LockRange { row, cols } => layout.locks.extend(cols.map(|col| (row, col))),
+                Update::Notes(_) | Update::Messages(_) | Update::Variables(_) => {}
+            }
+        }
+        layout
+    }
+
+    fn evaluate(&self, context: &Context, row: i32, col: i32) -> Updates {
+        (self.evaluate)(&Evaluation { context, defaults: &self.defaults }, row, col)
+    }
+
+    /// The names of the operator's inputs when placed on an empty grid.
+    fn input_names(&self) -> Vec<&'static str> {
+        self.layout_on_empty_grid().0.inputs.iter().map(|port| port.name).collect()
     }
 
     fn apply(&self, context: &mut Context, row: i32, col: i32) {
+        if context.metrics.per_operator && !context.is_locked(row, col) {
+            let timer = Timer::start();
+            self.apply_untimed(context, row, col);
+            context.metrics.record_operator(self.name, timer.elapsed());
+        } else {
+            self.apply_untimed(context, row, col);
+        }
+    }
+
+    /// The ports of one evaluation, as the trace records them.
+    fn visit(&self, row: i32, col: i32, updates: &Updates) -> Visit {
+        let mut visit = Visit { row, col, name: self.name, inputs: Vec::new(), outputs: Vec::new() };
+        for update in updates {
+            match update {
+                Update::Inputs(ports) => visit.inputs.extend(ports.iter().cloned()),
+                Update::Outputs(ports) => visit.outputs.extend(ports.iter().cloned()),
+                _ => {}
+            }
+        }
+        visit
+    }
+
+    fn apply_untimed(&self, context: &mut Context, row: i32, col: i32) {
         if !context.is_locked(row, col) {
-            let updates = (self.evaluate)(context, row, col);
+            let updates = self.evaluate(context, row, col);
+            if context.trace.enabled {
+                context.trace.record(self.visit(row, col, &updates));
+            }
             for update in updates {
                 match update {
                     Update::Inputs(ports) => {
@@ -74,11 +287,18 @@ impl Operator {
                             context.lock(port.row, port.col);
                         }
                     }
+                    Update::LockRange { row, cols } => {
+                        for col in cols {
+                            context.lock(row, col);
+                        }
+                    }
                     Update::Notes(notes) => {
                         for note in notes {
                             context.write_note(note);
+                            context.metrics.notes_emitted += 1;
                         }
                     }
+                    Update::Messages(messages) => context.messages.extend(messages),
                     Update::Variables(variables) => {
                         for (name, value) in variables {
                             context.set_variable(name, value);
@@ -90,8 +310,127 @@ impl Operator {
     }
 }
 
-pub fn read_operator_config(filename: &str) -> HashMap<String, char> {
-    let default_operator_config = "
+/// Operators keyed by the symbol that triggers them. ASCII symbols are stored in a flat array so
+/// that looking up the operator for a cell is a single index rather than a hash; the few symbols
+/// outside ASCII are searched for after that.
+#[derive(Clone)]
+pub struct OperatorTable {
+    operators: [Option<Operator>; 128],
+    extended: Vec<(char, Operator)>,
+}
+
+impl OperatorTable {
+    pub fn new() -> OperatorTable {
+        OperatorTable { operators: core::array::from_fn(|_| None), extended: Vec::new() }
+    }
+
+    pub fn insert(&mut self, symbol: char, operator: Operator) {
+        if symbol.is_ascii() {
+            self.operators[symbol as usize] = Some(operator);
+        } else if let Some(entry) = self.extended.iter_mut().find(|(other, _)| *other == symbol) {
+            entry.1 = operator;
+        } else {
+            self.extended.push((symbol, operator));
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, symbol: char) -> Option<&Operator> {
+        if symbol.is_ascii() {
+            self.operators[symbol as usize].as_ref()
+        } else {
+            self.extended.iter().find(|(other, _)| *other == symbol).map(|(_, operator)| operator)
+        }
+    }
+
+    /// Makes the input `port` of the operator called `operator` fall back on `value` instead of its
+    /// built-in default when its cell is empty, such as for Random to stop at `g` rather than `z`.
+    pub fn set_port_default(&mut self, operator: &str, port: &str, value: char) -> Result<(), OrcaError> {
+        if !is_single_width(value) || value.is_whitespace() || value == '.' {
+            return Err(OrcaError::parse(format!("{} can not be a default for {}", value, port)));
+        }
+        let mut found = false;
+        let operators = self.operators.iter_mut().flatten().chain(self.extended.iter_mut().map(|(_, operator)| operator));
+        for entry in operators.filter(|entry| entry.name == operator) {
+            let Some(port) = entry.input_names().into_iter().find(|name| *name == port) else {
+                return Err(OrcaError::parse(format!("{} has no input {}", operator, port)));
+            };
+            entry.defaults.retain(|(name, _)| *name != port);
+            entry.defaults.push((port, value));
+            found = true;
+        }
+        if found {
+            Ok(())
+        } else {
+            Err(OrcaError::parse(format!("unknown operator {}", operator)))
+        }
+    }
+
+    /// Describes each operator in the table once, with every symbol it is bound to.
+    pub fn registry(&self) -> Vec<OperatorInfo> {
+        let mut registry: Vec<OperatorInfo> = Vec::new();
+        for (symbol, operator) in self.iter() {
+            match registry.iter_mut().find(|info| info.name == operator.name) {
+                Some(info) => info.symbols.push(symbol),
+                None => registry.push(operator.info(vec![symbol])),
+            }
+        }
+        registry
+    }
+
+    /// Every operator with its symbol, ASCII symbols first in order.
+    pub fn iter(&self) -> impl Iterator<Item=(char, &Operator)> {
+        let ascii = self.operators.iter().enumerate().filter_map(
+            |(symbol, operator)| operator.as_ref().map(|operator| (symbol as u8 as char, operator))
+        );
+        ascii.chain(self.extended.iter().map(|(symbol, operator)| (*symbol, operator)))
+    }
+}
+
+impl Default for OperatorTable {
+    fn default() -> Self {
+        OperatorTable::new()
+    }
+}
+
+/// The symbols bound to each operator by name; the first is the one the operator is shown with,
+/// and the rest are aliases for it.
+pub type OperatorMap = HashMap<String, Vec<char>>;
+
+/// Binds `symbol` to the operator called `name` on top of its other symbols, taking it from any
+/// operator it was bound to before.
+pub fn bind_symbol(operator_map: &mut OperatorMap, name: &str, symbol: char) {
+    unbind_symbol(operator_map, symbol);
+    operator_map.entry(name.to_string()).or_default().push(symbol);
+}
+
+/// Takes `symbol` from whichever operator it is bound to, dropping operators left without any.
+pub fn unbind_symbol(operator_map: &mut OperatorMap, symbol: char) {
+    for symbols in operator_map.values_mut() {
+        symbols.retain(|&bound| bound != symbol);
+    }
+    operator_map.retain(|_, symbols| !symbols.is_empty());
+}
+
+/// Hashes an operator config with 64 bit FNV-1a over its entries in order of name, which gives the
+/// same value for the same config on every platform and every run.
+pub fn operator_config_hash(operator_map: &OperatorMap) -> u64 {
+    let mut entries: Vec<(&String, &char)> = operator_map
+        .iter()
+        .flat_map(|(name, symbols)| symbols.iter().map(move |symbol| (name, symbol)))
+        .collect();
+    entries.sort();
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for (name, symbol) in entries {
+        for byte in format!("{} {}\n", symbol, name).bytes() {
+            hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+// the symbol of each operator when there is no operator config
+const DEFAULT_OPERATOR_CONFIG: &str = "\
 A Add
 B Sub
 C Clock
@@ -119,64 +458,118 @@ X Write
 Y Jymp
 Z Interpolate
 # Comment
+| Block
 : Midi
-".trim().to_string();
-    read_to_string(filename)
-        .unwrap_or(default_operator_config)
+; Message
+^ Column
+~ Weighted
+& Seeded
+/ Divider
+@ Layer
+";
+
+/// Parses an operator config, which gives each operator its symbol with one `<symbol> <name>` per
+/// line, describing the first line that could not be read. Symbols can be any character one column
+/// wide, such as `♥` or `·`, besides a space, `.` and `*`. An operator given on several lines is
+/// bound to each of their symbols, the first of which it is shown with, and `<symbol> -` unbinds a
+/// symbol bound on an earlier line, as when a fork's symbols are appended to a config.
+pub fn parse_operator_config(text: &str) -> Result<OperatorMap, OrcaError> {
+    let known = default_operator_config();
+    let mut operator_map = OperatorMap::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (symbol, name) = line.split_once(' ').map_or((line, ""), |(symbol, name)| (symbol, name.trim()));
+        let mut chars = symbol.chars();
+        let (Some(symbol), None, false) = (chars.next(), chars.next(), name.is_empty()) else {
+            return Err(OrcaError::parse(format!("line {}: expected a symbol and an operator name", index + 1)));
+        };
+        if !known.contains_key(name) && name != "-" {
+            return Err(OrcaError::parse(format!("line {}: unknown operator {}", index + 1, name)));
+        }
+        if !is_symbol(symbol) {
+            return Err(OrcaError::parse(format!("line {}: {} can not be an operator's symbol", index + 1, symbol)));
+        }
+        if name == "-" {
+            unbind_symbol(&mut operator_map, symbol);
+        } else {
+            bind_symbol(&mut operator_map, name, symbol);
+        }
+    }
+    Ok(operator_map)
+}
+
+/// The symbol of each operator by name when there is no operator config.
+pub fn default_operator_config() -> OperatorMap {
+    DEFAULT_OPERATOR_CONFIG
         .lines()
         .filter_map(|line| line.split_once(' '))
-        .filter_map(|(symbol, name)| {
-            if let Some(symbol) = symbol.chars().next() {
-                Some((name.to_string(), symbol))
-            } else {
-                None
-            }
-        }).collect()
-}
-
-pub fn get_tick_operators(operator_map: &HashMap<String, char>) -> HashMap<char, Operator> {
-    vec![
-        Operator::new("Add", add),
-        Operator::new("Sub", sub),
-        Operator::new("Clock", clock),
-        Operator::new("Delay", delay),
-        Operator::new("East", east),
-        Operator::new("If", condition),
-        Operator::new("Generate", generate),
-        Operator::new("Halt", halt),
-        Operator::new("Increment", increment),
-        Operator::new("Jump", jump),
-        Operator::new("Concat", concat),
-        Operator::new("Lesser", lesser),
-        Operator::new("Multiply", multiply),
-        Operator::new("North", north),
-        Operator::new("Read", read),
-        Operator::new("Push", push),
-        Operator::new("Query", query),
-        Operator::new("Random", random),
-        Operator::new("South", south),
-        Operator::new("Track", track),
-        Operator::new("Euclid", euclid),
-        Operator::new("Variable", variable),
-        Operator::new("West", west),
-        Operator::new("Write", write),
-        Operator::new("Jymp", jymp),
-        Operator::new("Interpolate", interpolate),
-        Operator::new("Comment", comment),
+        .filter_map(|(symbol, name)| symbol.chars().next().map(|symbol| (name.to_string(), vec![symbol])))
+        .collect()
+}
+
+/// Reads the operator config file `filename`, or gives the default symbols if there is none.
+#[cfg(feature = "files")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", err(Display)))]
+pub fn read_operator_config(filename: &str) -> Result<OperatorMap, OrcaError> {
+    let path = Path::new(filename);
+    match read_to_string(path) {
+        Ok(text) => parse_operator_config(&text).map_err(|err| err.in_file(path)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(default_operator_config()),
+        Err(source) => Err(OrcaError::Read { path: path.to_path_buf(), source }),
+    }
+}
+
+pub fn get_tick_operators(operator_map: &OperatorMap) -> OperatorTable {
+    let mut operators = OperatorTable::new();
+    for operator in [
+        Operator::new("Add", "Outputs the sum of its inputs.", add),
+        Operator::new("Sub", "Outputs the difference of its inputs.", sub),
+        Operator::new("Clock", "Outputs the frame count divided by the rate, modulo the mod input.", clock),
+        Operator::new("Delay", "Bangs once every rate times mod frames.", delay),
+        Operator::new("Divider", "Counts the bangs it gets, banging below it on every second, fourth and so on.", divider),
+        Operator::new("East", "Moves east, or bangs when blocked.", east),
+        Operator::new("If", "Bangs if its inputs are equal.", condition),
+        Operator::new("Generate", "Writes its operands at an offset.", generate),
+        Operator::new("Halt", "Stops the operator below it.", halt),
+        Operator::new("Increment", "Increments the value below it by step, modulo mod.", increment),
+        Operator::new("Jump", "Outputs the value above it.", jump),
+        Operator::new("Concat", "Reads the values of several variables.", concat),
+        Operator::new("Lesser", "Outputs the smallest of its inputs.", lesser),
+        Operator::new("Multiply", "Outputs the product of its inputs.", multiply),
+        Operator::new("North", "Moves north, or bangs when blocked.", north),
+        Operator::new("Read", "Reads a value at an offset.", read),
+        Operator::new("Layer", "Reads the value at a column and row of another grid playing alongside.", layer),
+        Operator::new("Push", "Writes a value into a row of cells, at the key modulo the length.", push),
+        Operator::new("Query", "Reads several values at an offset.", query),
+        Operator::new("Random", "Outputs a random value between its inputs.", random),
+        Operator::new("Seeded", "Outputs a random value between its inputs, from a stream given by its seed.", seeded),
+        Operator::new("Weighted", "Outputs a random value between its inputs, drawn in the shape to its left.", weighted),
+        Operator::new("South", "Moves south, or bangs when blocked.", south),
+        Operator::new("Track", "Reads the value at the key from a row of cells.", track),
+        Operator::new("Column", "Reads the value at the key from a column of cells below it.", column),
+        Operator::new("Euclid", "Bangs on a Euclidean rhythm of step beats over max.", euclid),
+        Operator::new("Variable", "Writes a variable, or reads one when given only a name.", variable),
+        Operator::new("West", "Moves west, or bangs when blocked.", west),
+        Operator::new("Write", "Writes a value at an offset.", write),
+        Operator::new("Jymp", "Outputs the value to its left.", jymp),
+        Operator::new("Interpolate", "Steps the value below it toward a target at a rate.", interpolate),
+        Operator::new("Comment", "Halts the rest of the row up to the next comment.", comment),
+        Operator::new("Block", "Halts the rectangle between it and the next block marker below and right of it.", block),
         // the midi operator is technically operated each tick, but only produces a note on a bang
-        Operator::new("Midi", midi_note),
-    ].iter().cloned().filter_map(
-        |operator| {
-            if let Some(&symbol) = operator_map.get(&operator.name) {
-                Some((symbol, operator))
-            } else {
-                None
-            }
+        Operator::new("Midi", "Sends a MIDI note when banged.", midi_note).bang_only(),
+        Operator::new("Message", "Sends the text to its right over UDP or OSC when banged.", message).bang_only(),
+    ] {
+        for &symbol in operator_map.get(operator.name).into_iter().flatten() {
+            operators.insert(symbol, operator.clone());
         }
-    ).collect()
+    }
+    operators
 }
 
-fn add(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn add(context: &Evaluation, row: i32, col: i32) -> Updates {
     let a_port = context.listen("a", row, col - 1, '0');
     let b_port = context.listen("b", row, col + 1, '0');
 
@@ -186,30 +579,30 @@ fn add(context: &Context, row: i32, col: i32) -> Vec<Update> {
 
     let out_port = Port::new("out", row + 1, col, out);
 
-    vec![
-        Update::Inputs(vec![a_port, b_port]),
-        Update::Outputs(vec![out_port]),
+    smallvec![
+        Update::Inputs(smallvec![a_port, b_port]),
+        Update::Outputs(smallvec![out_port]),
     ]
 }
 
-fn sub(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn sub(context: &Evaluation, row: i32, col: i32) -> Updates {
     let a_port = context.listen("a", row, col - 1, '0');
     let b_port = context.listen("b", row, col + 1, '0');
 
     let (a, a_upper) = char_to_base_36(a_port.value);
     let (b, b_upper) = char_to_base_36(b_port.value);
-    let diff = if a > b { a - b } else { b - a };
+    let diff = a.abs_diff(b);
     let out = base_36_to_char(diff, a_upper || b_upper);
 
     let out_port = Port::new("out", row + 1, col, out);
 
-    vec![
-        Update::Inputs(vec![a_port, b_port]),
-        Update::Outputs(vec![out_port]),
+    smallvec![
+        Update::Inputs(smallvec![a_port, b_port]),
+        Update::Outputs(smallvec![out_port]),
     ]
 }
 
-fn delay(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn delay(context: &Evaluation, row: i32, col: i32) -> Updates {
     let rate_port = context.listen("rate", row, col - 1, '1');
     let mod_port = context.listen("mod", row, col + 1, '8');
 
@@ -219,17 +612,49 @@ fn delay(context: &Context, row: i32, col: i32) -> Vec<Update> {
     let delay_mod = delay_mod.max(1);
 
     let mut out_port = context.listen("out", row + 1, col, '\0');
-    if context.ticks % (rate as usize * delay_mod as usize) == 0 {
+    if context.ticks.is_multiple_of(rate as usize * delay_mod as usize) {
         out_port.value = '*';
     }
 
-    vec![
-        Update::Inputs(vec![rate_port, mod_port]),
-        Update::Outputs(vec![out_port]),
+    smallvec![
+        Update::Inputs(smallvec![rate_port, mod_port]),
+        Update::Outputs(smallvec![out_port]),
     ]
 }
 
-fn random(context: &Context, row: i32, col: i32) -> Vec<Update> {
+// a row of clock dividers in one: counts the bangs it gets in the cell to its left, and on every
+// 2nd, 4th, 8th and so on of them bangs the outputs below it, len of them
+fn divider(context: &Evaluation, row: i32, col: i32) -> Updates {
+    let len_port = context.listen("len", row, col + 1, '3');
+    let (len, _) = char_to_base_36(len_port.value);
+    // the count fits in a single cell, which is enough for every 32nd bang
+    let period = 1u8 << len.min(5);
+
+    let mut count_port = Port::new("count", row, col - 1, context.read(row, col - 1));
+    let banged = context.is_banged(row, col);
+    let (count, _) = char_to_base_36(count_port.value);
+    let count = if banged { (count + 1) % period } else { count % period };
+    if banged {
+        count_port.value = base_36_to_char(count, false);
+    }
+
+    let output_ports = (0..period.trailing_zeros() as i32).map(|i| {
+        let mut out_port = context.listen("out", row + 1, col + i, '\0');
+        if banged && count % (2 << i) == 0 {
+            out_port.value = '*';
+        }
+        out_port
+    });
+
+    smallvec![
+        Update::Inputs(smallvec![len_port]),
+        Update::Outputs(core::iter::once(count_port).chain(output_ports).collect()),
+        // a symbol is its own lowercase, so the bang pass would count the same bang again
+        Update::Locks(smallvec![Port::new("self", row, col, context.read(row, col))]),
+    ]
+}
+
+fn random(context: &Evaluation, row: i32, col: i32) -> Updates {
     let min_port = context.listen("min", row, col - 1, '0');
     let max_port = context.listen("max", row, col + 1, 'z');
 
@@ -237,19 +662,80 @@ fn random(context: &Context, row: i32, col: i32) -> Vec<Update> {
     let (max, max_upper) = char_to_base_36(max_port.value);
     let max = max.max(min + 1); // wow this looks like trash
 
-    let mut rng = rand::thread_rng();
-    let r = rng.gen_range(min..max);
+    let r = min + (context.random(row, col) % (max - min) as u64) as u8;
+    let out = base_36_to_char(r, min_upper || max_upper);
+
+    let out_port = Port::new("out", row + 1, col, out);
+
+    smallvec![
+        Update::Inputs(smallvec![min_port, max_port]),
+        Update::Outputs(smallvec![out_port]),
+    ]
+}
+
+// random with a stream of its own: two with the same seed output the same values together, whatever
+// the grid's seed
+fn seeded(context: &Evaluation, row: i32, col: i32) -> Updates {
+    let seed_port = context.listen("seed", row, col - 2, '0');
+    let min_port = context.listen("min", row, col - 1, '0');
+    let max_port = context.listen("max", row, col + 1, 'z');
+
+    let (seed, _) = char_to_base_36(seed_port.value);
+    let (min, min_upper) = char_to_base_36(min_port.value);
+    let (max, max_upper) = char_to_base_36(max_port.value);
+    let max = max.max(min + 1);
+
+    let r = min + (context.random_from(seed as u64) % (max - min) as u64) as u8;
     let out = base_36_to_char(r, min_upper || max_upper);
 
     let out_port = Port::new("out", row + 1, col, out);
 
-    vec![
-        Update::Inputs(vec![min_port, max_port]),
-        Update::Outputs(vec![out_port]),
+    smallvec![
+        Update::Inputs(smallvec![seed_port, min_port, max_port]),
+        Update::Outputs(smallvec![out_port]),
     ]
 }
 
-fn midi_note(context: &Context, row: i32, col: i32) -> Vec<Update> {
+// the shapes weighted draws in: toward the low or the high end, bunched in the middle, or evenly;
+// any other shape names one of the context's random tables
+const LOW: char = 'l';
+const HIGH: char = 'h';
+const MIDDLE: char = 'm';
+const EVEN: char = 'e';
+
+/// The shapes Weighted draws in besides the random tables, which can't be named by them.
+pub const WEIGHTED_SHAPES: [char; 4] = [LOW, HIGH, MIDDLE, EVEN];
+
+fn weighted(context: &Evaluation, row: i32, col: i32) -> Updates {
+    let shape_port = context.listen("shape", row, col - 2, LOW);
+    let min_port = context.listen("min", row, col - 1, '0');
+    let max_port = context.listen("max", row, col + 1, 'z');
+
+    let (min, min_upper) = char_to_base_36(min_port.value);
+    let (max, max_upper) = char_to_base_36(max_port.value);
+    let max = max.max(min + 1);
+    let span = (max - min) as u64;
+
+    // one hash holds the three draws the shapes need
+    let random = context.random(row, col);
+    let draws = [random & 0x1fffff, random >> 21 & 0x1fffff, random >> 42].map(|draw| draw % span);
+    let out = match (shape_port.value, context.random_tables.get(&shape_port.value)) {
+        (_, Some(table)) if !table.is_empty() => table[(random % table.len() as u64) as usize],
+        (HIGH, _) => base_36_to_char(min + draws[0].max(draws[1]) as u8, min_upper || max_upper),
+        (MIDDLE, _) => base_36_to_char(min + (draws.iter().sum::<u64>() / 3) as u8, min_upper || max_upper),
+        (EVEN, _) => base_36_to_char(min + draws[0] as u8, min_upper || max_upper),
+        _ => base_36_to_char(min + draws[0].min(draws[1]) as u8, min_upper || max_upper),
+    };
+
+    let out_port = Port::new("out", row + 1, col, out);
+
+    smallvec![
+        Update::Inputs(smallvec![shape_port, min_port, max_port]),
+        Update::Outputs(smallvec![out_port]),
+    ]
+}
+
+fn midi_note(context: &Evaluation, row: i32, col: i32) -> Updates {
     let channel_port = context.listen("channel", row, col + 1, '0');
     let octave_port = context.listen("octave", row, col + 2, '0');
     let note_port = context.listen("note", row, col + 3, '0');
@@ -262,26 +748,54 @@ fn midi_note(context: &Context, row: i32, col: i32) -> Vec<Update> {
     let (velocity, _) = char_to_base_36(velocity_port.value);
     let (duration, _) = char_to_base_36(duration_port.value);
 
-    let midi_notes = if note >= 10 && (
-        context.read(row - 1, col) == '*'
-            || context.read(row, col - 1) == '*'
-            || context.read(row + 1, col) == '*'
-    ) {
-        vec![MidiNote::from_base_36(
+    let midi_notes = if note >= 10 && context.is_banged(row, col) {
+        smallvec![MidiNote::from_base_36(
             channel, octave, note, !note_upper,
             velocity, duration, context.tick_time,
         )]
     } else {
-        vec![]
+        smallvec![]
     };
 
-    vec![
-        Update::Inputs(vec![channel_port, octave_port, note_port, velocity_port, duration_port]),
+    smallvec![
+        Update::Inputs(smallvec![channel_port, octave_port, note_port, velocity_port, duration_port]),
         Update::Notes(midi_notes),
     ]
 }
 
-fn clock(context: &Context, row: i32, col: i32) -> Vec<Update> {
+// the text is `len` cells long, or runs up to the first empty cell without a length; an empty cell
+// inside it is a space, and a cell naming a variable set this tick is replaced by its value
+fn message(context: &Evaluation, row: i32, col: i32) -> Updates {
+    let len_port = context.listen("len", row, col - 1, '\0');
+    let len = if len_port.value == '\0' {
+        (col + 1..context.width as i32).take_while(|&c| context.read(row, c) != '\0').count() as i32
+    } else {
+        char_to_base_36(len_port.value).0 as i32
+    };
+    let text_ports: Ports = (0..len).map(|i| context.listen("text", row, col + 1 + i, '\0')).collect();
+
+    let (messages, locks) = if len > 0 && context.is_banged(row, col) {
+        let text = text_ports.iter().map(|port| match (port.value, context.read_variable(port.value)) {
+            ('\0', _) => ' ',
+            (value, '\0') => value,
+            (_, variable) => variable,
+        });
+        // locked once it has sent, so that it is not sent again when banged in the same tick
+        (smallvec![text.collect()], smallvec![Port::new("locked", row, col, context.read(row, col))])
+    } else {
+        (smallvec![], smallvec![])
+    };
+
+    let mut input_ports = text_ports;
+    input_ports.insert(0, len_port);
+    smallvec![
+        Update::Inputs(input_ports),
+        Update::Messages(messages),
+        Update::Locks(locks),
+    ]
+}
+
+fn clock(context: &Evaluation, row: i32, col: i32) -> Updates {
     let rate_port = context.listen("rate", row, col - 1, '1');
     let mod_port = context.listen("mod", row, col + 1, '8');
 
@@ -294,18 +808,22 @@ fn clock(context: &Context, row: i32, col: i32) -> Vec<Update> {
 
     let out_port = Port::new("out", row + 1, col, out);
 
-    vec![
-        Update::Inputs(vec![rate_port, mod_port]),
-        Update::Outputs(vec![out_port]),
+    smallvec![
+        Update::Inputs(smallvec![rate_port, mod_port]),
+        Update::Outputs(smallvec![out_port]),
     ]
 }
 
-fn track(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn track(context: &Evaluation, row: i32, col: i32) -> Updates {
     let key_port = context.listen("key", row, col - 2, '0');
     let len_port = context.listen("len", row, col - 1, '1');
 
     let (key, _) = char_to_base_36(key_port.value);
     let (len, _) = char_to_base_36(len_port.value);
+    // orca-c reads and locks nothing more with no length, leaving the cell below free to run
+    if len == 0 && context.orca_c_locks {
+        return smallvec![Update::Inputs(smallvec![key_port, len_port])];
+    }
     let len = len.max(1);
     let val_port = context.listen("val", row, col + 1 + (key % len) as i32, '\0');
     let out = val_port.value;
@@ -315,95 +833,123 @@ fn track(context: &Context, row: i32, col: i32) -> Vec<Update> {
         |i| Port::new("locked", row, col + 1 + i, '\0')
     ).collect();
 
-    vec![
-        Update::Inputs(vec![key_port, len_port, val_port]),
-        Update::Outputs(vec![out_port]),
+    smallvec![
+        Update::Inputs(smallvec![key_port, len_port, val_port]),
+        Update::Outputs(smallvec![out_port]),
         Update::Locks(locks)
     ]
 }
 
-fn halt(context: &Context, row: i32, col: i32) -> Vec<Update> {
+// track turned on its side, for narrow grids: the values run down from below it and it outputs to
+// its right
+fn column(context: &Evaluation, row: i32, col: i32) -> Updates {
+    let key_port = context.listen("key", row, col - 2, '0');
+    let len_port = context.listen("len", row, col - 1, '1');
+
+    let (key, _) = char_to_base_36(key_port.value);
+    let (len, _) = char_to_base_36(len_port.value);
+    let len = len.max(1);
+    let val_port = context.listen("val", row + 1 + (key % len) as i32, col, '\0');
+    let out = val_port.value;
+
+    let out_port = Port::new("out", row, col + 1, out);
+    let locks = (0..(len as i32)).map(
+        |i| Port::new("locked", row + 1 + i, col, '\0')
+    ).collect();
+
+    smallvec![
+        Update::Inputs(smallvec![key_port, len_port, val_port]),
+        Update::Outputs(smallvec![out_port]),
+        Update::Locks(locks)
+    ]
+}
+
+fn halt(context: &Evaluation, row: i32, col: i32) -> Updates {
+    // orca-c only locks the cell, where it is otherwise read and written back unchanged
+    if context.orca_c_locks {
+        return smallvec![Update::Locks(smallvec![Port::new("locked", row + 1, col, context.read(row + 1, col))])];
+    }
     let output_port = context.listen("out", row + 1, col, '\0');
-    vec![
-        Update::Inputs(vec![output_port.clone()]),
-        Update::Outputs(vec![output_port.clone()]),
-        Update::Locks(vec![output_port]),
+    smallvec![
+        Update::Inputs(smallvec![output_port.clone()]),
+        Update::Outputs(smallvec![output_port.clone()]),
+        Update::Locks(smallvec![output_port]),
     ]
 }
 
-fn east(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn east(context: &Evaluation, row: i32, col: i32) -> Updates {
     let mut input_port = context.listen("", row, col, '\0');
     let mut output_port = context.listen("", row, col + 1, '\0');
     if output_port.value == '\0' {
         output_port.value = input_port.value;
         input_port.value = '\0';
-        vec![
-            Update::Outputs(vec![input_port, output_port.clone()]),
-            Update::Locks(vec![output_port]),
+        smallvec![
+            Update::Outputs(smallvec![input_port, output_port.clone()]),
+            Update::Locks(smallvec![output_port]),
         ]
     } else {
         input_port.value = '*';
-        vec![
-            Update::Outputs(vec![input_port])
+        smallvec![
+            Update::Outputs(smallvec![input_port])
         ]
     }
 }
 
-fn west(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn west(context: &Evaluation, row: i32, col: i32) -> Updates {
     let mut input_port = context.listen("", row, col, '\0');
     let mut output_port = context.listen("", row, col - 1, '\0');
     if output_port.value == '\0' {
         output_port.value = input_port.value;
         input_port.value = '\0';
-        vec![
-            Update::Outputs(vec![input_port, output_port.clone()]),
-            Update::Locks(vec![output_port]),
+        smallvec![
+            Update::Outputs(smallvec![input_port, output_port.clone()]),
+            Update::Locks(smallvec![output_port]),
         ]
     } else {
         input_port.value = '*';
-        vec![
-            Update::Outputs(vec![input_port])
+        smallvec![
+            Update::Outputs(smallvec![input_port])
         ]
     }
 }
 
-fn north(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn north(context: &Evaluation, row: i32, col: i32) -> Updates {
     let mut input_port = context.listen("", row, col, '\0');
     let mut output_port = context.listen("", row - 1, col, '\0');
     if output_port.value == '\0' {
         output_port.value = input_port.value;
         input_port.value = '\0';
-        vec![
-            Update::Outputs(vec![input_port, output_port.clone()]),
-            Update::Locks(vec![output_port]),
+        smallvec![
+            Update::Outputs(smallvec![input_port, output_port.clone()]),
+            Update::Locks(smallvec![output_port]),
         ]
     } else {
         input_port.value = '*';
-        vec![
-            Update::Outputs(vec![input_port])
+        smallvec![
+            Update::Outputs(smallvec![input_port])
         ]
     }
 }
 
-fn south(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn south(context: &Evaluation, row: i32, col: i32) -> Updates {
     let mut input_port = context.listen("", row, col, '\0');
     let mut output_port = context.listen("", row + 1, col, '\0');
     if output_port.value == '\0' {
         output_port.value = input_port.value;
         input_port.value = '\0';
-        vec![
-            Update::Outputs(vec![input_port, output_port.clone()]),
-            Update::Locks(vec![output_port]),
+        smallvec![
+            Update::Outputs(smallvec![input_port, output_port.clone()]),
+            Update::Locks(smallvec![output_port]),
         ]
     } else {
         input_port.value = '*';
-        vec![
-            Update::Outputs(vec![input_port])
+        smallvec![
+            Update::Outputs(smallvec![input_port])
         ]
     }
 }
 
-fn condition(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn condition(context: &Evaluation, row: i32, col: i32) -> Updates {
     let a_port = context.listen("a", row, col - 1, '\0');
     let b_port = context.listen("b", row, col + 1, '\0');
 
@@ -414,13 +960,13 @@ fn condition(context: &Context, row: i32, col: i32) -> Vec<Update> {
         out_port.value = '*';
     }
 
-    vec![
-        Update::Inputs(vec![a_port, b_port]),
-        Update::Outputs(vec![out_port]),
+    smallvec![
+        Update::Inputs(smallvec![a_port, b_port]),
+        Update::Outputs(smallvec![out_port]),
     ]
 }
 
-fn increment(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn increment(context: &Evaluation, row: i32, col: i32) -> Updates {
     let step_port = context.listen("step", row, col - 1, '1');
     let mod_port = context.listen("mod", row, col + 1, 'z');
 
@@ -432,33 +978,33 @@ fn increment(context: &Context, row: i32, col: i32) -> Vec<Update> {
     let out = (out + step) % increment_mod;
     out_port.value = base_36_to_char(out, mod_upper);
 
-    vec![
-        Update::Inputs(vec![step_port, mod_port]),
-        Update::Outputs(vec![out_port]),
+    smallvec![
+        Update::Inputs(smallvec![step_port, mod_port]),
+        Update::Outputs(smallvec![out_port]),
     ]
 }
 
-fn jump(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn jump(context: &Evaluation, row: i32, col: i32) -> Updates {
     let input_port = context.listen("input", row - 1, col, '\0');
     let output_port = Port::new("output", row + 1, col, input_port.value);
 
-    vec![
-        Update::Inputs(vec![input_port]),
-        Update::Outputs(vec![output_port]),
+    smallvec![
+        Update::Inputs(smallvec![input_port]),
+        Update::Outputs(smallvec![output_port]),
     ]
 }
 
-fn jymp(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn jymp(context: &Evaluation, row: i32, col: i32) -> Updates {
     let input_port = context.listen("input", row, col - 1, '\0');
     let output_port = Port::new("output", row, col + 1, input_port.value);
 
-    vec![
-        Update::Inputs(vec![input_port]),
-        Update::Outputs(vec![output_port]),
+    smallvec![
+        Update::Inputs(smallvec![input_port]),
+        Update::Outputs(smallvec![output_port]),
     ]
 }
 
-fn lesser(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn lesser(context: &Evaluation, row: i32, col: i32) -> Updates {
     let a_port = context.listen("a", row, col - 1, '\0');
     let b_port = context.listen("b", row, col + 1, '\0');
 
@@ -473,13 +1019,13 @@ fn lesser(context: &Context, row: i32, col: i32) -> Vec<Update> {
 
     let out_port = Port::new("out", row + 1, col, out);
 
-    vec![
-        Update::Inputs(vec![a_port, b_port]),
-        Update::Outputs(vec![out_port]),
+    smallvec![
+        Update::Inputs(smallvec![a_port, b_port]),
+        Update::Outputs(smallvec![out_port]),
     ]
 }
 
-fn multiply(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn multiply(context: &Evaluation, row: i32, col: i32) -> Updates {
     let a_port = context.listen("a", row, col - 1, '0');
     let b_port = context.listen("b", row, col + 1, '0');
 
@@ -489,13 +1035,13 @@ fn multiply(context: &Context, row: i32, col: i32) -> Vec<Update> {
 
     let out_port = Port::new("out", row + 1, col, out);
 
-    vec![
-        Update::Inputs(vec![a_port, b_port]),
-        Update::Outputs(vec![out_port]),
+    smallvec![
+        Update::Inputs(smallvec![a_port, b_port]),
+        Update::Outputs(smallvec![out_port]),
     ]
 }
 
-fn read(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn read(context: &Evaluation, row: i32, col: i32) -> Updates {
     let x_port = context.listen("x", row, col - 2, '0');
     let y_port = context.listen("y", row, col - 1, '0');
 
@@ -506,20 +1052,43 @@ fn read(context: &Context, row: i32, col: i32) -> Vec<Update> {
 
     let out_port = Port::new("out", row + 1, col, out);
 
-    vec![
-        Update::Inputs(vec![x_port, y_port, val_port]),
-        Update::Outputs(vec![out_port]),
+    smallvec![
+        Update::Inputs(smallvec![x_port, y_port, val_port]),
+        Update::Outputs(smallvec![out_port]),
+    ]
+}
+
+// a bang in the other grid reads as a bang, so a conductor grid can gate this one with its clocks
+fn layer(context: &Evaluation, row: i32, col: i32) -> Updates {
+    let layer_port = context.listen("layer", row, col - 3, '0');
+    let x_port = context.listen("x", row, col - 2, '0');
+    let y_port = context.listen("y", row, col - 1, '0');
+
+    let (x, _) = char_to_base_36(x_port.value);
+    let (y, _) = char_to_base_36(y_port.value);
+    let layer = context.layers.iter().find(|layer| layer.name == layer_port.value);
+    let out = layer.map_or('\0', |layer| layer.read(y as i32, x as i32));
+
+    let out_port = Port::new("out", row + 1, col, out);
+
+    smallvec![
+        Update::Inputs(smallvec![layer_port, x_port, y_port]),
+        Update::Outputs(smallvec![out_port]),
     ]
 }
 
-fn push(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn push(context: &Evaluation, row: i32, col: i32) -> Updates {
     let key_port = context.listen("key", row, col - 2, '0');
     let len_port = context.listen("len", row, col - 1, '1');
 
     let (key, _) = char_to_base_36(key_port.value);
     let (len, _) = char_to_base_36(len_port.value);
-    let len = len.max(1);
     let val_port = context.listen("val", row, col + 1, '\0');
+    // as with track, orca-c writes and locks nothing with no length
+    if len == 0 && context.orca_c_locks {
+        return smallvec![Update::Inputs(smallvec![key_port, len_port, val_port])];
+    }
+    let len = len.max(1);
     let out = val_port.value;
 
     let out_port = Port::new("out", row + 1, col + (key % len) as i32, out);
@@ -527,14 +1096,14 @@ fn push(context: &Context, row: i32, col: i32) -> Vec<Update> {
         |i| Port::new("locked", row + 1, col + i, '\0')
     ).collect();
 
-    vec![
-        Update::Inputs(vec![key_port, len_port, val_port]),
-        Update::Outputs(vec![out_port]),
+    smallvec![
+        Update::Inputs(smallvec![key_port, len_port, val_port]),
+        Update::Outputs(smallvec![out_port]),
         Update::Locks(locks)
     ]
 }
 
-fn query(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn query(context: &Evaluation, row: i32, col: i32) -> Updates {
     let x_port = context.listen("x", row, col - 3, '0');
     let y_port = context.listen("y", row, col - 2, '0');
     let len_port = context.listen("len", row, col - 1, '1');
@@ -543,21 +1112,21 @@ fn query(context: &Context, row: i32, col: i32) -> Vec<Update> {
     let (y, _) = char_to_base_36(y_port.value);
     let (len, _) = char_to_base_36(len_port.value);
     let len = len.max(1);
-    let mut input_ports: Vec<Port> = (0..len).map(|i| context.listen(
-        &format!("in-{}", i), row + y as i32, col + 1 + x as i32 + i as i32, '\0',
+    let mut input_ports: Ports = (0..len).map(|i| context.listen(
+        "in", row + y as i32, col + 1 + x as i32 + i as i32, '\0',
     )).collect();
     let output_ports = input_ports.iter().enumerate().map(|(i, port)| Port::new(
-        &format!("out-{}", i), row + 1, col + 1 + i as i32 - len as i32, port.value,
+        "out", row + 1, col + 1 + i as i32 - len as i32, port.value,
     )).collect();
 
-    input_ports.extend(vec![x_port, y_port]);
-    vec![
+    input_ports.extend([x_port, y_port]);
+    smallvec![
         Update::Inputs(input_ports),
         Update::Outputs(output_ports),
     ]
 }
 
-fn generate(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn generate(context: &Evaluation, row: i32, col: i32) -> Updates {
     let x_port = context.listen("x", row, col - 3, '0');
     let y_port = context.listen("y", row, col - 2, '0');
     let len_port = context.listen("len", row, col - 1, '1');
@@ -566,21 +1135,21 @@ fn generate(context: &Context, row: i32, col: i32) -> Vec<Update> {
     let (y, _) = char_to_base_36(y_port.value);
     let (len, _) = char_to_base_36(len_port.value);
     let len = len.max(1);
-    let mut input_ports: Vec<Port> = (0..len).map(|i| context.listen(
-        &format!("in-{}", i), row, col + 1 + i as i32, '\0',
+    let mut input_ports: Ports = (0..len).map(|i| context.listen(
+        "in", row, col + 1 + i as i32, '\0',
     )).collect();
     let output_ports = input_ports.iter().enumerate().map(|(i, port)| Port::new(
-        &format!("out-{}", i), row + 1 + y as i32, col + i as i32 + x as i32, port.value,
+        "out", row + 1 + y as i32, col + i as i32 + x as i32, port.value,
     )).collect();
 
-    input_ports.extend(vec![x_port, y_port]);
-    vec![
+    input_ports.extend([x_port, y_port]);
+    smallvec![
         Update::Inputs(input_ports),
         Update::Outputs(output_ports),
     ]
 }
 
-fn write(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn write(context: &Evaluation, row: i32, col: i32) -> Updates {
     let x_port = context.listen("x", row, col - 2, '0');
     let y_port = context.listen("y", row, col - 1, '0');
 
@@ -591,13 +1160,13 @@ fn write(context: &Context, row: i32, col: i32) -> Vec<Update> {
 
     let out_port = Port::new("out", row + 1 + y as i32, col + x as i32, out);
 
-    vec![
-        Update::Inputs(vec![x_port, y_port, val_port]),
-        Update::Outputs(vec![out_port]),
+    smallvec![
+        Update::Inputs(smallvec![x_port, y_port, val_port]),
+        Update::Outputs(smallvec![out_port]),
     ]
 }
 
-fn interpolate(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn interpolate(context: &Evaluation, row: i32, col: i32) -> Updates {
     let rate_port = context.listen("rate", row, col - 1, '1');
     let target_port = context.listen("target", row, col + 1, 'z');
 
@@ -608,13 +1177,13 @@ fn interpolate(context: &Context, row: i32, col: i32) -> Vec<Update> {
     let out = (out + rate).min(target);
     out_port.value = base_36_to_char(out, target_upper);
 
-    vec![
-        Update::Inputs(vec![rate_port, target_port]),
-        Update::Outputs(vec![out_port]),
+    smallvec![
+        Update::Inputs(smallvec![rate_port, target_port]),
+        Update::Outputs(smallvec![out_port]),
     ]
 }
 
-fn euclid(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn euclid(context: &Evaluation, row: i32, col: i32) -> Updates {
     let step_port = context.listen("step", row, col - 1, '1');
     let max_port = context.listen("max", row, col + 1, '8');
 
@@ -627,112 +1196,215 @@ fn euclid(context: &Context, row: i32, col: i32) -> Vec<Update> {
         out_port.value = '*';
     }
 
-    vec![
-        Update::Inputs(vec![step_port, max_port]),
-        Update::Outputs(vec![out_port]),
+    smallvec![
+        Update::Inputs(smallvec![step_port, max_port]),
+        Update::Outputs(smallvec![out_port]),
     ]
 }
 
-fn comment(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn comment(context: &Evaluation, row: i32, col: i32) -> Updates {
     let width = context.width as i32;
     let mut c = col + 1;
-    for i in c..width {
-        c = i;
-        if context.read(row, c) == '#' {
-            break;
-        }
+    while c < width - 1 && context.read(row, c) != '#' {
+        c += 1;
     }
-    let locks = (col..(c + 1)).map(|l| Port::new("locked", row, l, '\0')).collect();
-    vec![
-        Update::Locks(locks)
+    smallvec![
+        Update::LockRange { row, cols: col..(c + 1) }
     ]
 }
 
-fn variable(context: &Context, row: i32, col: i32) -> Vec<Update> {
+// a block comment runs from its marker at the top left to the next marker found reading row by
+// row from there, in any column from the marker's rightwards, which is its bottom right corner;
+// with no closing marker it halts the rest of the row as a comment does
+fn block(context: &Evaluation, row: i32, col: i32) -> Updates {
+    let (width, height) = (context.width as i32, context.height as i32);
+    let marker = context.read(row, col);
+    let corner = (row..height)
+        .flat_map(|r| (if r == row { col + 1 } else { col }..width).map(move |c| (r, c)))
+        .find(|&(r, c)| context.read(r, c) == marker);
+    let (bottom, right) = corner.unwrap_or((row, width - 1));
+    (row..=bottom).map(|row| Update::LockRange { row, cols: col..(right + 1) }).collect()
+}
+
+fn variable(context: &Evaluation, row: i32, col: i32) -> Updates {
     let write_port = context.listen("write", row, col - 1, '\0');
     let read_port = context.listen("read", row, col + 1, '\0');
 
     if write_port.value == '\0' {
         let out_port = Port::new("out", row + 1, col, context.read_variable(read_port.value));
-        vec![
-            Update::Inputs(vec![write_port, read_port]),
-            Update::Outputs(vec![out_port]),
+        smallvec![
+            Update::Inputs(smallvec![write_port, read_port]),
+            Update::Outputs(smallvec![out_port]),
         ]
     } else {
         let value = read_port.value;
-        vec![
-            Update::Inputs(vec![read_port]),
-            Update::Variables(vec![(write_port.value, value)]),
+        smallvec![
+            Update::Inputs(smallvec![read_port]),
+            Update::Variables(smallvec![(write_port.value, value)]),
         ]
     }
 }
 
-fn concat(context: &Context, row: i32, col: i32) -> Vec<Update> {
+fn concat(context: &Evaluation, row: i32, col: i32) -> Updates {
     let len_port = context.listen("len", row, col - 1, '1');
 
     let (len, _) = char_to_base_36(len_port.value);
     let output_ports = (0..(len as i32)).map(
-        |i| Port::new(&format!("out-{}", i), row + 1, col + i + 1,
+        |i| Port::new("out", row + 1, col + i + 1,
                       context.read_variable(context.read(row, col + i + 1)))
     ).collect();
     let locks = (0..(len as i32)).map(
         |i| Port::new("locked", row, col + 1 + i, '\0')
     ).collect();
-    vec![
-        Update::Inputs(vec![len_port]),
+    smallvec![
+        Update::Inputs(smallvec![len_port]),
         Update::Outputs(output_ports),
         Update::Locks(locks),
     ]
 }
 
-pub fn get_bang_operators(operator_map: &HashMap<String, char>) -> HashMap<char, Operator> {
-    let mut operators: HashMap<char, Operator> = HashMap::new();
-    for (c, operator) in get_tick_operators(operator_map) {
-        operators.insert(c.to_ascii_lowercase(), operator);
+pub fn get_bang_operators(operator_map: &OperatorMap) -> OperatorTable {
+    let mut operators = OperatorTable::new();
+    for (c, operator) in get_tick_operators(operator_map).iter() {
+        operators.insert(c.to_ascii_lowercase(), operator.clone());
     }
     operators
 }
 
+/// The order a tick visits cells in, which decides what each operator sees of what the others
+/// wrote earlier in the same tick. Patches written for another Orca can depend on its order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EvaluationMode {
+    /// Every operator in row-major order, then every banged lowercase operator, so that a bang
+    /// reaches the lowercase operators next to it on the tick it is written wherever they are.
+    #[default]
+    RustOrca,
+    /// orca-c's single row-major pass, in which a lowercase operator acts if a bang is next to it
+    /// when the scan reaches it and a bang is only erased when the scan reaches the bang. A bang
+    /// written after the scan has passed the operator above it reaches the operator on the next
+    /// tick.
+    OrcaC,
+    /// The lowercase operators next to the bangs left by the last tick act before anything else
+    /// runs, so that nothing moves into or out of a bang's way before it lands, and then the rest
+    /// of the operators run in row-major order.
+    BangFirst,
+}
+
+/// Every evaluation mode with the name it is chosen by.
+pub const EVALUATION_MODES: [(&str, EvaluationMode); 3] = [
+    ("rust-orca", EvaluationMode::RustOrca),
+    ("orca-c", EvaluationMode::OrcaC),
+    ("bang-first", EvaluationMode::BangFirst),
+];
+
+impl EvaluationMode {
+    pub fn name(self) -> &'static str {
+        EVALUATION_MODES.iter().find(|(_, mode)| *mode == self).map_or("", |(name, _)| name)
+    }
+
+    pub fn from_name(name: &str) -> Result<EvaluationMode, OrcaError> {
+        EVALUATION_MODES.iter().find(|(mode, _)| *mode == name).map(|&(_, mode)| mode).ok_or_else(|| {
+            let names: Vec<&str> = EVALUATION_MODES.iter().map(|(name, _)| *name).collect();
+            OrcaError::parse(format!("unknown evaluation mode {}, expected one of {}", name, names.join(", ")))
+        })
+    }
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
 pub fn grid_tick(
     context: &mut Context,
-    tick_operators: &HashMap<char, Operator>,
-    bang_operators: &HashMap<char, Operator>,
+    tick_operators: &OperatorTable,
+    bang_operators: &OperatorTable,
 ) {
-    let rows = context.height as i32;
-    let cols = context.width as i32;
+    let timer = Timer::start();
+    context.history.begin(StepKind::Tick);
+    context.trace.begin(context.ticks);
+    context.messages.clear();
     context.unlock_all();
     context.clear_all_variables();
 
-    // clear previous bangs
-    for row in 0..rows {
-        for col in 0..cols {
-            if context.read(row, col) == '*' {
-                context.write(row, col, '\0');
+    match context.evaluation {
+        EvaluationMode::RustOrca => {
+            context.clear_bangs();
+            tick_pass(context, tick_operators);
+            bang_pass(context, bang_operators);
+        }
+        EvaluationMode::OrcaC => {
+            let last_bangs = context.bang_cells();
+            context.clear_bangs();
+            single_pass(context, tick_operators, bang_operators, &last_bangs);
+        }
+        EvaluationMode::BangFirst => {
+            // the bangs are erased before the operators they reach act, so that what those
+            // operators write is not erased with them; operators that run on every tick anyway
+            // are left to run with the rest
+            let mut banged = Vec::new();
+            let mut next = context.next_banged(0);
+            while let Some((row, col)) = next {
+                banged.push((row, col));
+                next = context.next_banged(row as usize * context.width + col as usize + 1);
+            }
+            context.clear_bangs();
+            for (row, col) in banged {
+                let value = context.read(row, col);
+                if let (None, Some(operator)) = (tick_operators.get(value), bang_operators.get(value)) {
+                    operator.apply(context, row, col);
+                }
             }
+            tick_pass(context, tick_operators);
         }
     }
 
-    // apply grid operators (which may produce new bangs)
-    for row in 0..rows {
-        for col in 0..cols {
-            if let Some(operator) = tick_operators.get(&context.read(row, col)) {
-                operator.apply(context, row, col);
-            }
+    context.ticks += 1;
+    context.history.end();
+    context.metrics.record_tick(timer.elapsed());
+}
+
+// the passes walk an index in row-major order, re-querying it as they go so that cells written
+// ahead of the scan are still visited, exactly like a full grid scan
+
+/// Applies every tick operator, which may write new bangs.
+fn tick_pass(context: &mut Context, tick_operators: &OperatorTable) {
+    let cols = context.width as i32;
+    let mut next = context.next_occupied(0);
+    while let Some((row, col)) = next {
+        if let Some(operator) = tick_operators.get(context.read(row, col)) {
+            operator.apply(context, row, col);
         }
+        next = context.next_occupied((row * cols + col) as usize + 1);
     }
+}
 
-    // apply bang operators on current bangs
-    for row in 0..rows {
-        for col in 0..cols {
-            if let Some(operator) = bang_operators.get(&context.read(row, col)) {
-                if context.read(row - 1, col) == '*'
-                    || context.read(row, col - 1) == '*'
-                    || context.read(row + 1, col) == '*' {
-                    operator.apply(context, row, col);
-                }
-            }
+/// Applies the bang operators next to the bangs on the grid.
+fn bang_pass(context: &mut Context, bang_operators: &OperatorTable) {
+    let cols = context.width as i32;
+    let mut next = context.next_banged(0);
+    while let Some((row, col)) = next {
+        if let Some(operator) = bang_operators.get(context.read(row, col)) {
+            operator.apply(context, row, col);
         }
+        next = context.next_banged((row * cols + col) as usize + 1);
     }
+}
 
-    context.ticks += 1;
+/// Applies tick operators and banged bang operators in one scan, the way orca-c does.
+/// `last_bangs` holds the cells the last tick's bangs were in, which orca-c only erases when its
+/// scan reaches them, so that the operator above one still sees it.
+fn single_pass(context: &mut Context, tick_operators: &OperatorTable, bang_operators: &OperatorTable, last_bangs: &Bitset) {
+    let cols = context.width as i32;
+    let mut next = context.next_occupied(0);
+    while let Some((row, col)) = next {
+        let value = context.read(row, col);
+        if let Some(operator) = tick_operators.get(value) {
+            operator.apply(context, row, col);
+        } else if let Some(operator) = bang_operators.get(value) {
+            // a bang left below is gone unless nothing has been written over it yet
+            let below = (row + 1) as usize * context.width + col as usize;
+            let bang_below = context.contains(row + 1, col) && last_bangs.contains(below) && context.read(row + 1, col) == '\0';
+            if context.is_banged(row, col) || bang_below {
+                operator.apply(context, row, col);
+            }
+        }
+        next = context.next_occupied((row * cols + col) as usize + 1);
+    }
 }
@@ -0,0 +1,89 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use rust_orca::error::OrcaError;
+use rust_orca::midi::{MidiSink, Monitored};
+use rust_orca::udp::UdpOutput;
+
+// how often the output thread checks for a new port while nothing is being sent
+const PORT_POLL_TIME: Duration = Duration::from_millis(5);
+
+/// What the clock hands over to the output thread.
+pub enum Output {
+    Message { tick: usize, message: [u8; 3] },
+    AllNotesOff,
+    /// The text of a Message operator, for the UDP output.
+    Text { tick: usize, text: String },
+}
+
+/// The clock's end of the channel to the output thread, so that a tick never waits on a MIDI
+/// device or the network. Messages are sent on in the order they arrive.
+pub struct OutputSink {
+    sender: Sender<Output>,
+    /// The tick stamped onto messages; set this before each tick.
+    pub tick: usize,
+}
+
+impl MidiSink for OutputSink {
+    fn send(&mut self, message: &[u8]) -> Result<(), OrcaError> {
+        let mut bytes = [0; 3];
+        let len = message.len().min(3);
+        bytes[..len].copy_from_slice(&message[..len]);
+        self.sender
+            .send(Output::Message { tick: self.tick, message: bytes })
+            .map_err(|_| OrcaError::Midi("the output thread has stopped".to_string()))
+    }
+}
+
+impl OutputSink {
+    pub fn all_notes_off(&self) {
+        // with the output thread gone there is nothing left sounding
+        let _ = self.sender.send(Output::AllNotesOff);
+    }
+
+    /// Hands the text of a Message operator to the output thread, which sends it over UDP.
+    pub fn send_text(&self, text: String) {
+        // with the output thread gone there is nowhere left to send it
+        let _ = self.sender.send(Output::Text { tick: self.tick, text });
+    }
+}
+
+/// Starts the thread that sends MIDI to `midi`, switching to each new port sent through `ports`,
+/// and the text of Message operators to `udp`. The thread runs until the returned sink is dropped,
+/// then silences every note as `midi` is dropped.
+pub fn spawn_output(
+    mut midi: Monitored<Box<dyn MidiSink + Send>>,
+    ports: Receiver<Box<dyn MidiSink + Send>>,
+    udp: Option<UdpOutput>,
+) -> (OutputSink, JoinHandle<()>) {
+    let (sender, receiver) = mpsc::channel();
+    let thread = thread::spawn(move || loop {
+        if let Ok(sink) = ports.try_recv() {
+            midi.all_notes_off();
+            midi.sink = sink;
+        }
+        match receiver.recv_timeout(PORT_POLL_TIME) {
+            Ok(Output::Message { tick, message }) => {
+                midi.tick = tick;
+                // the editor owns the terminal, so failures go to the event log it shows
+                if let Err(err) = midi.send(&message) {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(%err, "midi send error");
+                    midi.log.push(tick, format!("send error: {}", err));
+                }
+            }
+            Ok(Output::AllNotesOff) => midi.all_notes_off(),
+            Ok(Output::Text { tick, text }) => {
+                if let Some(Err(err)) = udp.as_ref().map(|udp| udp.send(&text)) {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(%err, "udp send error");
+                    midi.log.push(tick, format!("udp send error: {}", err));
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    });
+    (OutputSink { sender, tick: 0 }, thread)
+}
@@ -0,0 +1,12 @@
+//! What the standard prelude brings in that the engine uses, taken from `alloc` so that every
+//! module reads the same with or without std, along with the hash map for the build.
+
+pub use alloc::boxed::Box;
+pub use alloc::format;
+pub use alloc::string::{String, ToString};
+pub use alloc::vec;
+pub use alloc::vec::Vec;
+#[cfg(not(feature = "std"))]
+pub use hashbrown::{HashMap, HashSet};
+#[cfg(feature = "std")]
+pub use std::collections::{HashMap, HashSet};
@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use rust_orca::metrics::Metrics;
+
+/// Formats the per-operator timings as a table sorted by total time, most expensive first.
+pub fn profile_table(metrics: &Metrics) -> String {
+    let mut operators: Vec<_> = metrics.operators.iter().collect();
+    operators.sort_by_key(|(_, operator)| std::cmp::Reverse(operator.time));
+
+    let total_operator_time: Duration = operators.iter().map(|(_, operator)| operator.time).sum();
+    let mut table = format!(
+        "{} ticks, mean {:.1} us, max {:.1} us\n",
+        metrics.ticks,
+        metrics.mean_tick_time().as_secs_f64() * 1e6,
+        metrics.max_tick_time.as_secs_f64() * 1e6,
+    );
+    // only a clock running in real time measures how late it wakes up
+    if metrics.jitter_samples > 0 {
+        table.push_str(&format!(
+            "jitter mean {:.1} us, max {:.1} us\n",
+            metrics.mean_jitter().as_secs_f64() * 1e6,
+            metrics.max_jitter.as_secs_f64() * 1e6,
+        ));
+    }
+    table.push_str(&format!(
+        "{:<12} {:>12} {:>12} {:>10} {:>7}\n",
+        "operator", "invocations", "total ms", "mean us", "share",
+    ));
+    for (name, operator) in operators {
+        let mean = operator.time.as_secs_f64() / operator.invocations.max(1) as f64;
+        let share = operator.time.as_secs_f64() / total_operator_time.as_secs_f64().max(f64::EPSILON);
+        table.push_str(&format!(
+            "{:<12} {:>12} {:>12.3} {:>10.3} {:>6.1}%\n",
+            name, operator.invocations, operator.time.as_secs_f64() * 1e3, mean * 1e6, share * 100.0,
+        ));
+    }
+    table
+}
@@ -0,0 +1,327 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+// how many frames are kept before the oldest are dropped, about a quarter of an hour at 120 bpm
+const RECORDING_LEN: usize = 8192;
+
+// each cell is drawn as a 3x5 glyph with a pixel of space to its right and below it, scaled up
+const CELL_WIDTH: usize = 4;
+const CELL_HEIGHT: usize = 6;
+const GIF_SCALE: usize = 2;
+
+// how long the last frame of a gif is held when the clip ends on it
+const LAST_FRAME_TIME: Duration = Duration::from_secs(1);
+
+// background, empty cells, operators, lowercase operators, values, bangs and comments, in the
+// colors of the original Orca
+const PALETTE: [[u8; 3]; 8] = [
+    [0x00, 0x00, 0x00],
+    [0x44, 0x44, 0x44],
+    [0xff, 0xff, 0xff],
+    [0xaa, 0xaa, 0xaa],
+    [0x72, 0xde, 0xc2],
+    [0xff, 0xb5, 0x45],
+    [0x77, 0x77, 0x77],
+    [0x00, 0x00, 0x00],
+];
+
+// rows of three pixels, top first, with the leftmost pixel in the highest bit
+const GLYPHS: [(char, [u8; 5]); 47] = [
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b011, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b010, 0b010, 0b010]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    ('*', [0b000, 0b101, 0b010, 0b101, 0b000]),
+    ('#', [0b101, 0b111, 0b101, 0b111, 0b101]),
+    (':', [0b000, 0b010, 0b000, 0b010, 0b000]),
+    ('%', [0b101, 0b001, 0b010, 0b100, 0b101]),
+    ('/', [0b001, 0b001, 0b010, 0b100, 0b100]),
+    ('!', [0b010, 0b010, 0b010, 0b000, 0b010]),
+    ('?', [0b110, 0b001, 0b010, 0b000, 0b010]),
+    ('=', [0b000, 0b111, 0b000, 0b111, 0b000]),
+    (';', [0b000, 0b010, 0b000, 0b010, 0b100]),
+    ('$', [0b011, 0b110, 0b010, 0b011, 0b110]),
+    ('\0', [0b000, 0b000, 0b000, 0b000, 0b010]),
+];
+
+// drawn for characters without a glyph of their own
+const UNKNOWN_GLYPH: [u8; 5] = [0b111, 0b111, 0b111, 0b111, 0b111];
+
+/// Snapshots of a running grid, each taken when it changed and stamped with how long into the
+/// recording that was, which can be cut into clips for sharing as asciinema casts or animated gifs.
+#[derive(Debug, Default)]
+pub struct Recording {
+    pub rows: usize,
+    pub cols: usize,
+    frames: VecDeque<(Duration, Vec<char>)>,
+}
+
+impl Recording {
+    pub fn new(rows: usize, cols: usize) -> Recording {
+        Recording { rows, cols, frames: VecDeque::new() }
+    }
+
+    /// Adds a snapshot of `grid` taken `time` into the recording, unless it is the same as the last.
+    pub fn capture(&mut self, time: Duration, grid: &[char]) {
+        if self.frames.back().is_some_and(|(_, last)| last == grid) {
+            return;
+        }
+        if self.frames.len() == RECORDING_LEN {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((time, grid.to_vec()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// How far into the recording the last snapshot was taken.
+    pub fn duration(&self) -> Duration {
+        self.frames.back().map_or(Duration::ZERO, |&(time, _)| time)
+    }
+
+    /// The frames shown between `from` and `to`, starting with the one already showing at `from`,
+    /// with their times counted from `from`.
+    fn clip(&self, from: Duration, to: Duration) -> Vec<(Duration, &[char])> {
+        let first = self.frames.iter().rposition(|&(time, _)| time <= from).unwrap_or(0);
+        self.frames
+            .iter()
+            .skip(first)
+            .take_while(|&&(time, _)| time <= to)
+            .map(|(time, grid)| (time.saturating_sub(from), grid.as_slice()))
+            .collect()
+    }
+
+    /// Writes the frames between `from` and `to` as an asciinema v2 cast, each one redrawing the
+    /// whole grid from the top left of the terminal.
+    pub fn to_cast(&self, from: Duration, to: Duration) -> String {
+        let mut cast = format!("{{\"version\": 2, \"width\": {}, \"height\": {}}}\n", self.cols, self.rows);
+        for (i, (time, grid)) in self.clip(from, to).into_iter().enumerate() {
+            // the first frame clears whatever was on the screen before the cast started
+            let mut screen = String::from(if i == 0 { "\x1b[2J\x1b[H" } else { "\x1b[H" });
+            for (row, cells) in grid.chunks(self.cols.max(1)).enumerate() {
+                if row > 0 {
+                    screen.push_str("\r\n");
+                }
+                screen.extend(cells.iter().map(|&c| if c == '\0' { '.' } else { c }));
+            }
+            cast.push_str(&format!("[{:.6}, \"o\", {}]\n", time.as_secs_f64(), json_string(&screen)));
+        }
+        cast
+    }
+
+    /// Writes the frames between `from` and `to` as a looping animated gif, drawing each cell with
+    /// a small bitmap font.
+    pub fn to_gif(&self, from: Duration, to: Duration) -> Vec<u8> {
+        let width = self.cols * CELL_WIDTH * GIF_SCALE;
+        let height = self.rows * CELL_HEIGHT * GIF_SCALE;
+        let mut gif = Vec::new();
+        gif.extend(b"GIF89a");
+        gif.extend((width as u16).to_le_bytes());
+        gif.extend((height as u16).to_le_bytes());
+        // a global color table of 2^(2 + 1) colors, with background color 0
+        gif.extend([0xf2, 0, 0]);
+        gif.extend(PALETTE.iter().flatten());
+        // the netscape extension, looping forever
+        gif.extend(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+
+        let frames = self.clip(from, to);
+        let clip_length = to.saturating_sub(from);
+        for (i, &(time, grid)) in frames.iter().enumerate() {
+            let next = frames.get(i + 1).map_or(clip_length, |&(next, _)| next);
+            // delays are rounded from the start of the clip, so that they don't drift apart from it
+            let mut delay = (next.as_millis() / 10).saturating_sub(time.as_millis() / 10) as u16;
+            if i + 1 == frames.len() && delay == 0 {
+                delay = (LAST_FRAME_TIME.as_millis() / 10) as u16;
+            }
+            gif.extend([0x21, 0xf9, 0x04, 0x00]);
+            gif.extend(delay.to_le_bytes());
+            gif.extend([0x00, 0x00]);
+            gif.push(0x2c);
+            gif.extend([0, 0, 0, 0]);
+            gif.extend((width as u16).to_le_bytes());
+            gif.extend((height as u16).to_le_bytes());
+            gif.push(0);
+            gif.push(GIF_CODE_SIZE);
+            let pixels = self.draw(grid, width, height);
+            for block in lzw_compress(&pixels).chunks(255) {
+                gif.push(block.len() as u8);
+                gif.extend(block);
+            }
+            gif.push(0);
+        }
+        gif.push(0x3b);
+        gif
+    }
+
+    /// Draws a frame as palette indices, one per pixel in row-major order.
+    fn draw(&self, grid: &[char], width: usize, height: usize) -> Vec<u8> {
+        let mut pixels = vec![0; width * height];
+        for (index, &c) in grid.iter().enumerate() {
+            let (row, col) = (index / self.cols.max(1), index % self.cols.max(1));
+            let color = cell_color(c);
+            let glyph = GLYPHS
+                .iter()
+                .find(|&&(symbol, _)| symbol == c.to_ascii_uppercase())
+                .map_or(UNKNOWN_GLYPH, |&(_, glyph)| glyph);
+            for (y, bits) in glyph.iter().enumerate() {
+                for x in 0..3 {
+                    if bits & (0b100 >> x) == 0 {
+                        continue;
+                    }
+                    let top = (row * CELL_HEIGHT + y) * GIF_SCALE;
+                    let left = (col * CELL_WIDTH + x) * GIF_SCALE;
+                    for dy in 0..GIF_SCALE {
+                        let start = (top + dy) * width + left;
+                        pixels[start..start + GIF_SCALE].fill(color);
+                    }
+                }
+            }
+        }
+        pixels
+    }
+}
+
+/// The palette index a cell is drawn in.
+fn cell_color(c: char) -> u8 {
+    match c {
+        '\0' => 1,
+        '*' => 5,
+        '#' => 6,
+        '0'..='9' => 4,
+        c if c.is_lowercase() => 3,
+        _ => 2,
+    }
+}
+
+/// Quotes `text` as a JSON string.
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// the palette has 8 colors, so pixels start out as 3 bit codes
+const GIF_CODE_SIZE: u8 = 3;
+const GIF_MAX_CODE: u16 = 4096;
+
+/// Compresses palette indices of at most 3 bits with the variable length LZW that gifs use,
+/// returning the packed codes without the sub-block framing.
+pub fn lzw_compress(pixels: &[u8]) -> Vec<u8> {
+    let clear = 1u16 << GIF_CODE_SIZE;
+    let end = clear + 1;
+    let mut writer = BitWriter::default();
+    let mut size = GIF_CODE_SIZE as u32 + 1;
+    let mut next = end + 1;
+    let mut codes: HashMap<(u16, u8), u16> = HashMap::new();
+
+    writer.write(clear, size);
+    let Some((&first, rest)) = pixels.split_first() else {
+        writer.write(end, size);
+        return writer.finish();
+    };
+    let mut prefix = first as u16;
+    for &pixel in rest {
+        if let Some(&code) = codes.get(&(prefix, pixel)) {
+            prefix = code;
+            continue;
+        }
+        writer.write(prefix, size);
+        if next < GIF_MAX_CODE {
+            codes.insert((prefix, pixel), next);
+            next += 1;
+            // the decoder grows its codes one step behind, once the table outgrows them
+            if next > 1 << size && size < 12 {
+                size += 1;
+            }
+        } else {
+            writer.write(clear, size);
+            codes.clear();
+            next = end + 1;
+            size = GIF_CODE_SIZE as u32 + 1;
+        }
+        prefix = pixel as u16;
+    }
+    writer.write(prefix, size);
+    // the decoder adds an entry for the last code too, which can grow its codes before the end
+    if next == 1 << size && size < 12 {
+        size += 1;
+    }
+    writer.write(end, size);
+    writer.finish()
+}
+
+/// Packs codes into bytes least significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u32) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
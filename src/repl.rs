@@ -0,0 +1,168 @@
+//! Livecoding a grid served by the daemon from a prompt or a script. Each line is a command,
+//! sent to the daemon as a request while the grid plays on, and its answer comes back as a line
+//! of text:
+//!
+//! - `read` gives the grid, and `read ROW COL` the value of one cell
+//! - `write ROW COL TEXT` writes text rightwards from a cell, where `.` is an empty cell
+//! - `set NAME VALUE` sets a variable that every tick starts with, `unset NAME` forgets it, and
+//!   `vars` gives the variables of the last tick
+//! - `bpm BPM`, `play`, `pause`, `step [TICKS]`, `status`, `load PATH` and `shutdown` do as the
+//!   daemon's requests of the same names do
+//! - `at TICK COMMAND` runs a `write`, `set`, `unset` or `bpm` command once the grid has run that
+//!   many ticks, and `in TICKS COMMAND` once it has run that many more; `cancel` forgets every
+//!   command still waiting
+//!
+//! Rows and columns count from 0. A line starting with `{` is sent as it is, as a request in the
+//! daemon's own JSON.
+
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
+use serde_json::{Value, json};
+
+use crate::error::OrcaError;
+
+/// How each command is written, as `help` lists them.
+pub const COMMANDS: [&str; 16] = [
+    "read [ROW COL]",
+    "write ROW COL TEXT",
+    "set NAME VALUE",
+    "unset NAME",
+    "vars",
+    "bpm BPM",
+    "play",
+    "pause",
+    "step [TICKS]",
+    "status",
+    "load PATH",
+    "at TICK COMMAND",
+    "in TICKS COMMAND",
+    "cancel",
+    "shutdown",
+    "help",
+];
+
+/// Turns a line into the daemon request it stands for, describing how the command is written if
+/// it isn't written that way.
+pub fn parse_command(line: &str) -> Result<Value, OrcaError> {
+    let line = line.trim();
+    if line.starts_with('{') {
+        let request: Value =
+            serde_json::from_str(line).map_err(|err| OrcaError::parse(format!("invalid request: {}", err)))?;
+        // the answer to each request is read as the next line, which events would get in front of
+        if request["cmd"] == "subscribe" {
+            return Err(OrcaError::parse("subscribe is not available here".to_string()));
+        }
+        return Ok(request);
+    }
+    let words: Vec<&str> = line.split_whitespace().collect();
+    request(&words).ok_or_else(|| {
+        let name = words.first().copied().unwrap_or_default();
+        match COMMANDS.iter().find(|usage| usage.split(' ').next() == Some(name)) {
+            Some(usage) => OrcaError::parse(format!("usage: {}", usage)),
+            None => OrcaError::parse(format!("unknown command {}, try help", name)),
+        }
+    })
+}
+
+fn request(words: &[&str]) -> Option<Value> {
+    let request = match words {
+        ["read"] => json!({"cmd": "read"}),
+        ["read", row, col] => json!({"cmd": "read", "row": row.parse::<i32>().ok()?, "col": col.parse::<i32>().ok()?}),
+        ["write", row, col, text] => {
+            json!({"cmd": "write", "row": row.parse::<i32>().ok()?, "col": col.parse::<i32>().ok()?, "text": text})
+        }
+        ["set", name, value] => json!({"cmd": "variable", "name": cell(name)?, "value": cell(value)?}),
+        ["unset", name] => json!({"cmd": "variable", "name": cell(name)?}),
+        ["vars"] => json!({"cmd": "variables"}),
+        ["bpm", bpm] => json!({"cmd": "bpm", "bpm": bpm.parse::<u64>().ok()?}),
+        ["step"] => json!({"cmd": "step"}),
+        ["step", ticks] => json!({"cmd": "step", "ticks": ticks.parse::<usize>().ok()?}),
+        ["load", path] => json!({"cmd": "load", "path": path}),
+        ["at", tick, command @ ..] => {
+            json!({"cmd": "schedule", "tick": tick.parse::<usize>().ok()?, "request": request(command)?})
+        }
+        ["in", ticks, command @ ..] => {
+            json!({"cmd": "schedule", "after": ticks.parse::<usize>().ok()?, "request": request(command)?})
+        }
+        [name @ ("play" | "pause" | "status" | "cancel" | "shutdown")] => json!({"cmd": name}),
+        _ => return None,
+    };
+    Some(request)
+}
+
+// variable names and values are single cells
+fn cell(word: &str) -> Option<char> {
+    let mut chars = word.chars();
+    chars.next().filter(|_| chars.next().is_none())
+}
+
+/// Puts an answer from the daemon as a line of text: the error if there was one, the grid, cell
+/// or variables asked for, and otherwise whatever else came back, or `ok` if nothing did.
+pub fn show_answer(answer: &Value) -> String {
+    if answer["ok"] == false {
+        return format!("error: {}", answer["error"].as_str().unwrap_or("unknown"));
+    }
+    if let Some(grid) = answer["grid"].as_str() {
+        return grid.trim_end().to_string();
+    }
+    if let Some(value) = answer["value"].as_str() {
+        return value.to_string();
+    }
+    if let Some(variables) = answer["variables"].as_object() {
+        if variables.is_empty() {
+            return "no variables".to_string();
+        }
+        let lines: Vec<String> = variables.iter().map(|(name, value)| format!("{} {}", name, shown(value))).collect();
+        return lines.join("\n");
+    }
+    let fields: Vec<String> = answer
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(key, _)| *key != "ok")
+        .map(|(key, value)| format!("{} {}", key, shown(value)))
+        .collect();
+    if fields.is_empty() { "ok".to_string() } else { fields.join(", ") }
+}
+
+// strings go without their quotes
+fn shown(value: &Value) -> String {
+    value.as_str().map_or_else(|| value.to_string(), str::to_string)
+}
+
+/// A connection to a daemon's socket that commands are run over.
+pub struct Repl {
+    address: String,
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Repl {
+    pub fn connect(path: &Path) -> Result<Repl, OrcaError> {
+        let address = path.display().to_string();
+        let connect_error = |source| OrcaError::Connect { address: address.clone(), source };
+        let writer = UnixStream::connect(path).map_err(connect_error)?;
+        let reader = BufReader::new(writer.try_clone().map_err(connect_error)?);
+        Ok(Repl { address, reader, writer })
+    }
+
+    /// Runs one line, giving what to show for it. A command the daemon turned down is shown
+    /// rather than being an error, which is kept for lines that aren't commands and for losing
+    /// the daemon.
+    pub fn eval(&mut self, line: &str) -> Result<String, OrcaError> {
+        if line.trim() == "help" {
+            return Ok(COMMANDS.join("\n"));
+        }
+        let request = parse_command(line)?;
+        let connect_error = |source| OrcaError::Connect { address: self.address.clone(), source };
+        writeln!(self.writer, "{}", request).map_err(connect_error)?;
+        let mut answer = String::new();
+        if self.reader.read_line(&mut answer).map_err(connect_error)? == 0 {
+            return Err(connect_error(io::Error::new(ErrorKind::UnexpectedEof, "the daemon hung up")));
+        }
+        let answer = serde_json::from_str(&answer).map_err(|err| OrcaError::parse(format!("invalid answer: {}", err)))?;
+        Ok(show_answer(&answer))
+    }
+}
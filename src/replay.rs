@@ -0,0 +1,265 @@
+#[cfg(feature = "files")]
+use std::fs::write;
+#[cfg(feature = "files")]
+use std::path::Path;
+
+use crate::context::{Context, fits};
+use crate::error::OrcaError;
+#[cfg(feature = "files")]
+use crate::fs::read_file;
+use crate::fs::{grid_to_string, parse_grid};
+use crate::history::StepKind;
+use crate::prelude::*;
+
+/// Something done to a running grid between two ticks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// A cell was set to a value, `\0` for empty.
+    Cell { row: usize, col: usize, value: char },
+    Bpm(u64),
+    /// The frames per beat changed.
+    Frames(u64),
+    /// A command that changes how the grid plays without changing the grid, like a remap, which
+    /// whatever replays the session runs again.
+    Command(String),
+}
+
+/// A recorded performance: the grid, tempo and seed it started from, and every change made to the
+/// grid along the way, each stamped with the tick it was made before.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Session {
+    pub rows: usize,
+    pub cols: usize,
+    pub bpm: u64,
+    /// The frames per beat it started at, which sessions recorded before they were kept leave to
+    /// whatever replays them.
+    pub frames: Option<u64>,
+    pub seed: u64,
+    /// The tick the grid was on when recording started.
+    pub tick: usize,
+    pub grid: Vec<char>,
+    pub changes: Vec<(usize, Change)>,
+}
+
+impl Session {
+    /// Starts a session from the grid as it is now, with nothing changed yet.
+    pub fn start(context: &Context) -> Session {
+        Session {
+            rows: context.height,
+            cols: context.width,
+            bpm: context.tempo,
+            frames: Some(context.divisions),
+            seed: context.seed,
+            tick: context.ticks,
+            grid: context.grid().to_vec(),
+            changes: Vec::new(),
+        }
+    }
+
+    /// Sets up a context the way the session started out, ready to be replayed, at `divisions`
+    /// frames per beat if the session doesn't say.
+    pub fn to_context(&self, divisions: u64) -> Context {
+        let divisions = self.frames.unwrap_or(divisions);
+        let mut context = Context::new(self.grid.clone(), self.cols, self.rows, self.bpm, divisions);
+        context.reseed(self.seed);
+        context.ticks = self.tick;
+        context
+    }
+}
+
+/// Records a session by comparing the grid before each tick with how the previous tick left it,
+/// so that whatever changed in between is kept however it was changed: typed, pasted, undone or
+/// reloaded from disk.
+#[derive(Debug)]
+pub struct SessionRecorder {
+    pub session: Session,
+    last_grid: Vec<char>,
+    last_tempo: u64,
+    last_divisions: u64,
+}
+
+impl SessionRecorder {
+    pub fn new(context: &Context) -> SessionRecorder {
+        SessionRecorder {
+            session: Session::start(context),
+            last_grid: context.grid().to_vec(),
+            last_tempo: context.tempo,
+            last_divisions: context.divisions,
+        }
+    }
+
+    /// Records a command run before the tick `tick`, for a replay to run again.
+    pub fn command(&mut self, tick: usize, line: &str) {
+        self.session.changes.push((tick, Change::Command(line.trim().to_string())));
+    }
+
+    /// Records what changed since the last tick; call this right before each tick.
+    pub fn before_tick(&mut self, context: &Context) {
+        let tick = context.ticks;
+        if context.tempo != self.last_tempo {
+            self.last_tempo = context.tempo;
+            self.session.changes.push((tick, Change::Bpm(context.tempo)));
+        }
+        if context.divisions != self.last_divisions {
+            self.last_divisions = context.divisions;
+            self.session.changes.push((tick, Change::Frames(context.divisions)));
+        }
+        for (index, (&value, &last)) in context.grid().iter().zip(&self.last_grid).enumerate() {
+            if value != last {
+                let (row, col) = (index / context.width, index % context.width);
+                self.session.changes.push((tick, Change::Cell { row, col, value }));
+            }
+        }
+    }
+
+    /// Remembers how the tick left the grid; call this right after each tick. A tempo change the
+    /// tick made itself, from a tapped tempo, is recorded before the next tick.
+    pub fn after_tick(&mut self, context: &Context) {
+        self.last_grid.copy_from_slice(context.grid());
+    }
+}
+
+/// Plays a session back by making each of its changes before the tick it was recorded on.
+#[derive(Debug)]
+pub struct Replayer {
+    session: Session,
+    next: usize,
+}
+
+impl Replayer {
+    pub fn new(session: Session) -> Replayer {
+        Replayer { session, next: 0 }
+    }
+
+    /// Makes the changes due before the tick the grid is about to run, recorded as a single edit,
+    /// and gives back the commands due for the caller to run.
+    pub fn before_tick(&mut self, context: &mut Context) -> Vec<String> {
+        let due = self.session.changes[self.next..].iter().take_while(|(tick, _)| *tick <= context.ticks).count();
+        let mut commands = Vec::new();
+        if due == 0 {
+            return commands;
+        }
+        context.history.begin(StepKind::Edit);
+        for (_, change) in &self.session.changes[self.next..self.next + due] {
+            match change {
+                Change::Cell { row, col, value } => context.write(*row as i32, *col as i32, *value),
+                Change::Bpm(bpm) => context.set_tempo(*bpm),
+                Change::Frames(frames) => context.set_divisions(*frames),
+                Change::Command(line) => commands.push(line.clone()),
+            }
+        }
+        context.history.end();
+        self.next += due;
+        commands
+    }
+}
+
+/// Formats a session as text: its settings one per line, then the starting grid after a `grid`
+/// line, then after a `changes` line one change per line, like `12 cell 3 4 A`, `40 bpm 140`,
+/// `40 frames 8` or `52 command remap Add +`, each starting with its tick. Empty cells are written
+/// as `.`.
+pub fn session_to_string(session: &Session) -> String {
+    let mut text = format!("rust-orca session\nsize {}x{}\nbpm {}\n", session.cols, session.rows, session.bpm);
+    if let Some(frames) = session.frames {
+        text.push_str(&format!("frames {}\n", frames));
+    }
+    text.push_str(&format!("seed {}\ntick {}\ngrid\n", session.seed, session.tick));
+    text.push_str(&grid_to_string(&session.grid, session.cols));
+    text.push_str("changes\n");
+    for (tick, change) in &session.changes {
+        match *change {
+            Change::Cell { row, col, value } => {
+                text.push_str(&format!("{} cell {} {} {}\n", tick, row, col, if value == '\0' { '.' } else { value }));
+            }
+            Change::Bpm(bpm) => text.push_str(&format!("{} bpm {}\n", tick, bpm)),
+            Change::Frames(frames) => text.push_str(&format!("{} frames {}\n", tick, frames)),
+            Change::Command(ref line) => text.push_str(&format!("{} command {}\n", tick, line)),
+        }
+    }
+    text
+}
+
+/// Reads a session written by `session_to_string`, describing the first line that could not be read.
+pub fn parse_session(text: &str) -> Result<Session, OrcaError> {
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.first() != Some(&"rust-orca session") {
+        return Err(OrcaError::parse("not a rust-orca session".to_string()));
+    }
+    let setting = |index: usize, name: &str| -> Result<&str, OrcaError> {
+        let line = lines.get(index).ok_or_else(|| OrcaError::parse(format!("missing {}", name)))?;
+        line.strip_prefix(name)
+            .and_then(|value| value.strip_prefix(' '))
+            .ok_or_else(|| OrcaError::parse(format!("line {}: expected {}", index + 1, name)))
+    };
+    let number = |index: usize, name: &str| -> Result<u64, OrcaError> {
+        let value = setting(index, name)?;
+        value.parse().map_err(|_| OrcaError::parse(format!("line {}: invalid {} {}", index + 1, name, value)))
+    };
+    let size = setting(1, "size")?;
+    let (cols, rows): (usize, usize) = size
+        .split_once('x')
+        .and_then(|(cols, rows)| Some((cols.parse().ok()?, rows.parse().ok()?)))
+        .ok_or_else(|| OrcaError::parse(format!("line 2: invalid size {}", size)))?;
+    if !fits(cols, rows) {
+        return Err(OrcaError::parse(format!("line 2: a {}x{} grid is too big", cols, rows)));
+    }
+    let bpm = number(2, "bpm")?;
+    // sessions recorded before the frames were kept go without them
+    let frames = lines.get(3).is_some_and(|line| line.starts_with("frames ")).then(|| number(3, "frames")).transpose()?;
+    let skipped = frames.is_some() as usize;
+    let seed = number(3 + skipped, "seed")?;
+    let tick = number(4 + skipped, "tick")? as usize;
+    let grid_line = 5 + skipped;
+    if lines.get(grid_line) != Some(&"grid") {
+        return Err(OrcaError::parse(format!("line {}: expected grid", grid_line + 1)));
+    }
+    // the grid is written a row to a line, so the changes start right after its last row
+    let changes_line = grid_line + 1 + rows;
+    if lines.get(changes_line) != Some(&"changes") {
+        let message = format!("line {}: expected changes after {} rows of grid", changes_line + 1, rows);
+        return Err(OrcaError::parse(message));
+    }
+    let grid = parse_grid(&lines[grid_line + 1..changes_line].join("\n"), rows, cols);
+
+    let mut changes = Vec::new();
+    for (index, line) in lines.iter().enumerate().skip(changes_line + 1) {
+        let invalid = || OrcaError::parse(format!("line {}: invalid change {}", index + 1, line));
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (tick, change) = match fields[..] {
+            [] => continue,
+            [tick, "cell", row, col, value] => {
+                let mut chars = value.chars();
+                let value = match (chars.next(), chars.next()) {
+                    (Some('.'), None) => '\0',
+                    (Some(value), None) => value,
+                    _ => return Err(invalid()),
+                };
+                let row: usize = row.parse().map_err(|_| invalid())?;
+                let col: usize = col.parse().map_err(|_| invalid())?;
+                if row >= rows || col >= cols {
+                    let message = format!("line {}: cell {} {} is outside the grid", index + 1, row, col);
+                    return Err(OrcaError::parse(message));
+                }
+                (tick, Change::Cell { row, col, value })
+            }
+            [tick, "bpm", bpm] => (tick, Change::Bpm(bpm.parse().map_err(|_| invalid())?)),
+            [tick, "frames", frames] => (tick, Change::Frames(frames.parse().map_err(|_| invalid())?)),
+            [tick, "command", _, ..] => (tick, Change::Command(fields[2..].join(" "))),
+            _ => return Err(invalid()),
+        };
+        changes.push((tick.parse().map_err(|_| invalid())?, change));
+    }
+    Ok(Session { rows, cols, bpm, frames, seed, tick, grid, changes })
+}
+
+#[cfg(feature = "files")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", fields(path = %path.display()), skip_all, err(Display)))]
+pub fn write_session(path: &Path, session: &Session) -> Result<(), OrcaError> {
+    write(path, session_to_string(session)).map_err(|source| OrcaError::Write { path: path.to_path_buf(), source })
+}
+
+/// Reads a session file, describing what went wrong if it could not be read.
+#[cfg(feature = "files")]
+pub fn read_session(path: &Path) -> Result<Session, OrcaError> {
+    parse_session(&read_file(path)?).map_err(|err| err.in_file(path))
+}
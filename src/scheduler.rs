@@ -0,0 +1,48 @@
+use std::hint::spin_loop;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+// how long before a deadline sleeping hands over to spinning; the system's sleeps can overshoot by
+// about this much
+const SPIN_TIME: Duration = Duration::from_millis(2);
+
+/// Paces the clock against absolute deadlines, each a period after the one before, so that the
+/// time ticks take to run and any oversleeping never add up into drift. Waits sleep until shortly
+/// before the deadline and spin through the rest of the way, which keeps ticks within a few
+/// microseconds of when they are due even when they are only milliseconds apart.
+#[derive(Clone, Debug, Default)]
+pub struct Scheduler {
+    deadline: Option<Instant>,
+}
+
+impl Scheduler {
+    pub fn new() -> Scheduler {
+        Scheduler::default()
+    }
+
+    /// Forgets the last deadline, so that the next tick is due straight away; for after a pause,
+    /// which would otherwise leave the clock behind.
+    pub fn reset(&mut self) {
+        self.deadline = None;
+    }
+
+    /// Waits until the next tick is due, `period` after the last one, and returns how late it
+    /// woke up. The first tick, and one that falls more than a whole period behind, is due at
+    /// once and the deadlines carry on from there, since rushing through the missed ticks would
+    /// only garble them.
+    pub fn wait(&mut self, period: Duration) -> Duration {
+        let now = Instant::now();
+        let deadline = match self.deadline {
+            Some(last) if last + period * 2 > now => last + period,
+            _ => now,
+        };
+        self.deadline = Some(deadline);
+        if let Some(coarse) = deadline.checked_sub(SPIN_TIME).filter(|coarse| *coarse > now) {
+            sleep(coarse - now);
+        }
+        while Instant::now() < deadline {
+            spin_loop();
+        }
+        Instant::now() - deadline
+    }
+}
@@ -0,0 +1,59 @@
+use crate::context::fits;
+use crate::error::OrcaError;
+use crate::fs::{parse_block, parse_grid};
+use crate::prelude::*;
+
+/// A grid to start a new piece from.
+pub struct Template {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The template as .orca text, laid out at the top left of the new grid.
+    pub text: &'static str,
+}
+
+pub const TEMPLATES: [Template; 3] = [
+    Template { name: "empty", description: "Nothing but empty cells.", text: "" },
+    // a kick, snare and hat on the General MIDI drum channel, each on its own delay
+    Template {
+        name: "drums",
+        description: "Kick, snare and hat on MIDI channel 10.",
+        text: "\
+#.drums.#..............
+.......................
+.D4......D8.......D2...
+..:92C....:92D.....:92f
+",
+    },
+    // every other frame the clock steps the track to the next note of a chord and the delay plays it
+    Template {
+        name: "arp",
+        description: "An arpeggio stepping through a C major seventh chord.",
+        text: "\
+#.arp.#...
+..........
+.2C4......
+D2.4TCEGB.
+.:03......
+",
+    },
+];
+
+/// Finds a template by name.
+pub fn find_template(name: &str) -> Result<&'static Template, OrcaError> {
+    TEMPLATES.iter().find(|template| template.name == name).ok_or_else(|| OrcaError::UnknownTemplate(name.to_string()))
+}
+
+impl Template {
+    /// Lays the template out at the top left of an otherwise empty grid, describing how much room
+    /// it needs if the grid is too small for it.
+    pub fn to_grid(&self, rows: usize, cols: usize) -> Result<Vec<char>, OrcaError> {
+        if !fits(cols, rows) {
+            return Err(OrcaError::GridTooBig { cols, rows });
+        }
+        let block = parse_block(self.text);
+        if block.rows > rows || block.cols > cols {
+            return Err(OrcaError::TemplateTooBig { name: self.name, cols: block.cols, rows: block.rows });
+        }
+        Ok(parse_grid(self.text, rows, cols))
+    }
+}
@@ -0,0 +1,79 @@
+//! Helpers for testing operators against the engine, as the crate's own tests do: building a grid
+//! from .orca text, running it, and checking the invariants every tick must keep. Plugin authors
+//! can pass their own operator tables wherever the defaults are used here.
+
+use crate::context::Context;
+use crate::engine::run_headless;
+use crate::fs::{grid_to_string, parse_grid, text_size};
+use crate::operators::{EvaluationMode, OperatorTable, default_operator_config, get_bang_operators, get_tick_operators, grid_tick};
+use crate::prelude::*;
+
+/// The tick and bang operator tables with the default symbols.
+pub fn default_tables() -> (OperatorTable, OperatorTable) {
+    let operator_map = default_operator_config();
+    (get_tick_operators(&operator_map), get_bang_operators(&operator_map))
+}
+
+/// A context holding .orca text, at the size of the text.
+pub fn context_from(text: &str) -> Context {
+    let (cols, rows) = text_size(text);
+    Context::new(parse_grid(text, rows, cols), cols, rows, 120, 4)
+}
+
+/// Runs .orca text for `ticks` ticks with the given operators, returning the grid as .orca text.
+pub fn run_patch(text: &str, ticks: usize, tick_operators: &OperatorTable, bang_operators: &OperatorTable) -> String {
+    run_patch_in(EvaluationMode::default(), text, ticks, tick_operators, bang_operators)
+}
+
+/// Runs .orca text for `ticks` ticks as `run_patch` does, with the default operators.
+pub fn run_default(text: &str, ticks: usize) -> String {
+    let (tick_operators, bang_operators) = default_tables();
+    run_patch(text, ticks, &tick_operators, &bang_operators)
+}
+
+/// Runs a patch as `run_patch` does, visiting cells in the order `evaluation` visits them.
+pub fn run_patch_in(
+    evaluation: EvaluationMode,
+    text: &str,
+    ticks: usize,
+    tick_operators: &OperatorTable,
+    bang_operators: &OperatorTable,
+) -> String {
+    let mut context = context_from(text);
+    context.evaluation = evaluation;
+    run_headless(&mut context, tick_operators, bang_operators, ticks);
+    grid_to_string(context.grid(), context.width)
+}
+
+/// Panics unless the grid still has `width * height` cells and the index of occupied cells
+/// matches them, which a write outside the grid or around `Context::write` would break.
+pub fn assert_grid_intact(context: &Context) {
+    let cells = context.width * context.height;
+    assert_eq!(context.grid().len(), cells, "the grid has {} cells, not {}", context.grid().len(), cells);
+    let mut occupied = Vec::new();
+    let mut index = 0;
+    while let Some((row, col)) = context.next_occupied(index) {
+        index = row as usize * context.width + col as usize;
+        occupied.push(index);
+        index += 1;
+    }
+    let expected: Vec<usize> = (0..cells).filter(|&index| context.grid()[index] != '\0').collect();
+    assert_eq!(occupied, expected, "the occupied cells are out of step with the grid");
+}
+
+/// Panics unless the next tick of `context` gives the same grid as the next tick of a fresh
+/// context holding the same grid, so that nothing a tick leaves behind, such as locks, carries
+/// into the one after it. Runs that tick on `context`.
+pub fn assert_tick_is_fresh(context: &mut Context, tick_operators: &OperatorTable, bang_operators: &OperatorTable) {
+    let mut fresh = Context::new(context.grid().to_vec(), context.width, context.height, context.tempo, context.divisions);
+    fresh.ticks = context.ticks;
+    fresh.reseed(context.seed);
+    grid_tick(context, tick_operators, bang_operators);
+    grid_tick(&mut fresh, tick_operators, bang_operators);
+    assert_eq!(
+        grid_to_string(context.grid(), context.width),
+        grid_to_string(fresh.grid(), fresh.width),
+        "tick {} ran differently with what the tick before it left behind",
+        fresh.ticks - 1,
+    );
+}
@@ -0,0 +1,38 @@
+pub use clock::Timer;
+
+#[cfg(all(feature = "std", not(all(target_arch = "wasm32", target_os = "unknown"))))]
+mod clock {
+    use core::time::Duration;
+    use std::time::Instant;
+
+    /// Measures how long something took.
+    pub struct Timer(Instant);
+
+    impl Timer {
+        pub fn start() -> Timer {
+            Timer(Instant::now())
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            self.0.elapsed()
+        }
+    }
+}
+
+// without std, or on a bare wasm32 target, there is no clock to read, so everything takes no time
+#[cfg(not(all(feature = "std", not(all(target_arch = "wasm32", target_os = "unknown")))))]
+mod clock {
+    use core::time::Duration;
+
+    pub struct Timer;
+
+    impl Timer {
+        pub fn start() -> Timer {
+            Timer
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            Duration::ZERO
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::context::Port;
+use crate::prelude::*;
+
+/// One operator evaluated during a tick: where it sits and what its ports read and wrote.
+#[derive(Clone, Debug)]
+pub struct Visit {
+    pub row: i32,
+    pub col: i32,
+    pub name: &'static str,
+    pub inputs: Vec<Port>,
+    pub outputs: Vec<Port>,
+}
+
+/// What every operator read and wrote during the last tick, in the order they ran, for explaining
+/// how a cell came to hold what it does. Recording copies each operator's ports, so it is off
+/// unless something asks for it.
+#[derive(Clone, Debug, Default)]
+pub struct Trace {
+    pub enabled: bool,
+    /// The tick the visits were recorded on.
+    pub tick: usize,
+    visits: Vec<Visit>,
+}
+
+// how many operators back a chain of writes is followed before it is cut short
+const MAX_DEPTH: usize = 8;
+
+impl Trace {
+    /// Forgets the visits of the tick before, ready to record `tick`.
+    pub fn begin(&mut self, tick: usize) {
+        self.visits.clear();
+        self.tick = tick;
+    }
+
+    pub fn record(&mut self, visit: Visit) {
+        self.visits.push(visit);
+    }
+
+    pub fn visits(&self) -> &[Visit] {
+        &self.visits
+    }
+
+    /// The operators that wrote to the cell at `(row, col)`, in the order they ran, each with its
+    /// place in that order; the last one's value is the one left in the cell.
+    pub fn writers(&self, row: i32, col: i32) -> impl Iterator<Item = (usize, &Visit)> {
+        self.visits.iter().enumerate().filter(move |(_, visit)| touches(&visit.outputs, row, col))
+    }
+
+    /// The operators that read the cell at `(row, col)`, in the order they ran.
+    pub fn readers(&self, row: i32, col: i32) -> impl Iterator<Item = (usize, &Visit)> {
+        self.visits.iter().enumerate().filter(move |(_, visit)| touches(&visit.inputs, row, col))
+    }
+
+    /// Explains the cell at `(row, col)` a line at a time: which operators wrote it during the
+    /// last tick and from which inputs, following each input back through the operators that
+    /// wrote it earlier in the same tick, then which operators read it.
+    pub fn explain(&self, row: i32, col: i32) -> Vec<String> {
+        let mut lines = vec![format!("row {} column {} on tick {}", row + 1, col + 1, self.tick)];
+        let before = self.visits.len();
+        if self.writers(row, col).next().is_none() {
+            lines.push("  nothing wrote to it".to_string());
+        }
+        self.explain_writes(row, col, before, 1, &mut lines);
+        let readers: Vec<_> = self.readers(row, col).collect();
+        if readers.is_empty() {
+            lines.push("  nothing read it".to_string());
+        }
+        for (_, visit) in readers {
+            let port = visit.inputs.iter().find(|port| port.row == row && port.col == col);
+            let name = port.map_or("", |port| port.name);
+            lines.push(format!("  read by {} as {}", place(visit), if name.is_empty() { "input" } else { name }));
+        }
+        lines
+    }
+
+    /// Adds a line for each operator that ran before the one at `before` and wrote to the cell,
+    /// followed by the writes behind its inputs, indented a level further.
+    fn explain_writes(&self, row: i32, col: i32, before: usize, depth: usize, lines: &mut Vec<String>) {
+        let indent = "  ".repeat(depth);
+        for (index, visit) in self.writers(row, col).filter(|&(index, _)| index < before) {
+            let output = visit.outputs.iter().rev().find(|port| port.row == row && port.col == col);
+            let value = output.map_or('.', |port| shown(port.value));
+            let inputs: Vec<String> = visit.inputs.iter().map(|port| {
+                let name = if port.name.is_empty() { "input" } else { port.name };
+                format!("{} {} at row {} column {}", name, shown(port.value), port.row + 1, port.col + 1)
+            }).collect();
+            if inputs.is_empty() {
+                lines.push(format!("{}{} written by {}", indent, value, place(visit)));
+            } else {
+                lines.push(format!("{}{} written by {} from {}", indent, value, place(visit), inputs.join(", ")));
+            }
+            if depth == MAX_DEPTH {
+                lines.push(format!("{}  ...", indent));
+                continue;
+            }
+            for port in &visit.inputs {
+                self.explain_writes(port.row, port.col, index, depth + 1, lines);
+            }
+        }
+    }
+}
+
+fn touches(ports: &[Port], row: i32, col: i32) -> bool {
+    ports.iter().any(|port| port.row == row && port.col == col)
+}
+
+fn place(visit: &Visit) -> String {
+    format!("{} at row {} column {}", visit.name, visit.row + 1, visit.col + 1)
+}
+
+fn shown(value: char) -> char {
+    if value == '\0' { '.' } else { value }
+}
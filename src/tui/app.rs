@@ -0,0 +1,1256 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{read_to_string, write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use rust_orca::context::{Block, Context, Selection, TEMPO_RANGE};
+use rust_orca::engine::Transport;
+use rust_orca::events::EventLog;
+use rust_orca::fs::{
+    Header, grid_to_string, parse_block, parse_grid, parse_header, read_block, write_block, write_grid,
+    write_grid_with_header,
+};
+use rust_orca::keys::describe_typed;
+use rust_orca::midi::{MidiMonitor, MidiSink, open_output};
+use rust_orca::operators::{
+    Operator, OperatorMap, OperatorTable, PortLayout, base_36_to_char, char_to_base_36, is_single_width,
+    operator_config_hash,
+};
+use rust_orca::recording::Recording;
+use rust_orca::replay::SessionRecorder;
+use rust_orca::validate::validate;
+use rust_orca::watch::Watched;
+
+use crate::cli::Ruler;
+use crate::commands::{Command, remap};
+use crate::config::Config;
+use crate::journal::{Journal, journal_path};
+use crate::tui::browser::{Browser, remember};
+use crate::tui::clipboard::SystemClipboard;
+use crate::tui::keymap::{Action, Keymap, action_name, key_name, typed_char};
+use crate::tui::macros::Macros;
+use crate::tui::picker::Picker;
+use crate::tui::prompt::CommandLine;
+use crate::tui::theme::{THEMES, Theme, find_theme};
+use crate::tui::trails::Trails;
+use crate::tui::vim::Vim;
+
+const DEFAULT_RULER: Ruler = Ruler { rows: 9, cols: 9 };
+
+// columns taken by the minimap, including the line separating it from the grid
+pub const MINIMAP_WIDTH: usize = 25;
+
+// rows taken by the event log panel, including the line separating it from the grid
+pub const EVENT_LOG_HEIGHT: usize = 8;
+
+// how many taps of the tap tempo key the tempo is averaged over, and how long a gap between taps
+// starts counting again
+const TAP_COUNT: usize = 4;
+const TAP_TIMEOUT: Duration = Duration::from_secs(2);
+
+// how close the cursor may get to the edge of the view before it scrolls
+const SCROLL_MARGIN: usize = 4;
+
+// the launch quantum :quantize turns on without one given, a bar of 4/4
+const LAUNCH_BEATS: u64 = 4;
+
+/// An edit of this pane's waiting for the next launch, for its bookkeeping to be done once the
+/// edit is made.
+enum Queued {
+    Edit,
+    Open { path: PathBuf, header: bool },
+}
+
+/// What the find keys search for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FindTarget {
+    Char(char),
+    /// Any cell holding an operator.
+    Operator,
+}
+
+/// Editor state that lives outside the engine: the cursor, the selection and clipboard, what is
+/// shown in the status bar, and whether the user asked to quit.
+pub struct App {
+    pub rows: usize,
+    pub cols: usize,
+    pub cursor_row: usize,
+    pub cursor_col: usize,
+    /// The grid position shown at the top left of the screen.
+    pub scroll_row: usize,
+    pub scroll_col: usize,
+    /// How many grid rows and columns fit on screen, as of the last frame.
+    pub view_rows: usize,
+    pub view_cols: usize,
+    /// The vim-style key scheme's state, if it is turned on.
+    pub vim: Option<Vim>,
+    /// Whether typing shifts the rest of the row right instead of overwriting the cell.
+    pub insert_mode: bool,
+    /// The corner the selection was started from; the cursor is the opposite corner.
+    pub anchor: Option<(usize, usize)>,
+    /// Where the extra cursors are, relative to the main one; they move along with it, and typing
+    /// writes at every cursor at once.
+    pub cursors: Vec<(i32, i32)>,
+    pub clipboard: Block,
+    /// The operating system's clipboard, which copies also go to and pastes come from once
+    /// something was copied in another program.
+    pub system_clipboard: Option<SystemClipboard>,
+    pub tick_operators: OperatorTable,
+    pub bang_operators: OperatorTable,
+    /// Spacing of the guide markers down the rows and across the columns.
+    pub ruler: Ruler,
+    pub trails: Trails,
+    pub theme: Theme,
+    /// Whether the downsampled overview of the whole grid is shown beside it.
+    pub minimap: bool,
+    /// Whether the minimap is open and the pane is currently wide enough to fit it.
+    pub minimap_fits: bool,
+    /// The file the grid was loaded from.
+    pub path: Option<PathBuf>,
+    /// The file the grid watcher reloads the grid from, kept the same as `path`.
+    pub watched: Arc<Mutex<Watched>>,
+    /// Whether the grid has been edited since it was loaded.
+    pub dirty: bool,
+    /// What the linter found wrong as of the last edit, by cell; these cells are underlined.
+    pub problems: HashMap<(i32, i32), String>,
+    /// Whether the grid has been edited since the linter last looked at it.
+    problems_stale: bool,
+    /// Unsaved edits, kept on disk as they are made so that a crash doesn't lose them.
+    pub journal: Journal,
+    /// Which pane the grid is in, numbered from 0, which keeps untitled grids' journals apart.
+    pub pane: usize,
+    /// Whether saves write the header of the git-friendly format above the grid.
+    pub header: bool,
+    /// The operator symbols by name that the tables were built from.
+    pub operator_map: OperatorMap,
+    /// Symbols given to operators with the remap command, kept on top of the operator config
+    /// when it is reloaded.
+    pub operator_remaps: BTreeMap<String, char>,
+    /// Hands rebuilt tick and bang operator tables to the engine thread.
+    pub operator_sender: Option<Sender<(OperatorTable, OperatorTable)>>,
+    /// The session being recorded, which keeps the remaps made in the editor.
+    pub recorder: Option<Arc<Mutex<SessionRecorder>>>,
+    /// The commands a replayed session has run on the engine, for the editor to follow.
+    pub replayed: Option<Receiver<String>>,
+    /// The hash of the operator config, recorded in headers.
+    pub operator_hash: u64,
+    pub midi_device: Option<String>,
+    /// Note activity on each MIDI channel, fed by the engine thread.
+    pub midi_monitor: Arc<MidiMonitor>,
+    /// Recent MIDI messages and commands, for the event log panel.
+    pub event_log: Arc<EventLog>,
+    /// Whether the event log panel is shown below the grid.
+    pub event_panel: bool,
+    /// Whether the event log panel is open and the pane is currently tall enough to fit it.
+    pub event_panel_fits: bool,
+    /// Hands a new MIDI connection to the engine thread.
+    pub midi_sender: Option<Sender<Box<dyn MidiSink + Send>>>,
+    pub transport: Arc<Transport>,
+    /// Copies of the grid the engine sends after each tick, for the editor to draw from without
+    /// holding the grid while it plays.
+    pub views: Option<Receiver<Context>>,
+    /// When the editor started, for the session clock.
+    pub started: Instant,
+    /// The grid as it ran while recording, kept after recording stops so that clips can be cut
+    /// from it.
+    pub recording: Recording,
+    /// When recording started, while it is still going.
+    pub recording_since: Option<Instant>,
+    /// How many edits each undo step covers, most recent last; runs of typing are undone together.
+    undo_groups: Vec<usize>,
+    redo_groups: Vec<usize>,
+    /// This pane's edits waiting for a launch, in the order they were held back.
+    queued: Vec<Queued>,
+    /// How many held back edits the context had made when the pane last caught up with it.
+    launched: usize,
+    /// Set after the find key is pressed, until the character to find is typed.
+    pub finding: bool,
+    pub last_find: Option<FindTarget>,
+    /// Whether key presses are described in the status bar instead of acting, to show what the
+    /// keyboard layout sends for each key.
+    pub key_test: bool,
+    /// Whether the cell under the cursor is explained under the grid: which operators wrote and
+    /// read it on the last tick.
+    pub explain: bool,
+    /// Whether the last key typed a character, so that the next one joins the same undo step.
+    typing: bool,
+    /// When the tap tempo key was last pressed, oldest first.
+    taps: Vec<Instant>,
+    pub command_line: CommandLine,
+    pub macros: Macros,
+    pub keymap: Keymap,
+    /// The settings from config.toml, whose keys and colors are applied under the keymap and
+    /// theme files each time those are read.
+    pub settings: Arc<Config>,
+    /// How far the operator glossary is scrolled, if it is open.
+    pub help: Option<usize>,
+    pub browser: Option<Browser>,
+    pub picker: Option<Picker>,
+    /// The outcome of the last action, shown at the end of the status bar.
+    pub message: String,
+    /// Set when the user asks to move to the other pane, until the panes are switched.
+    pub switch_pane: bool,
+    /// Set when an operator is remapped, until the other panes have been given the same symbols.
+    pub remapped: bool,
+    pub quit: bool,
+}
+
+impl App {
+    pub fn new(rows: usize, cols: usize, tick_operators: OperatorTable, bang_operators: OperatorTable) -> App {
+        App {
+            rows,
+            cols,
+            cursor_row: 0,
+            cursor_col: 0,
+            scroll_row: 0,
+            scroll_col: 0,
+            view_rows: rows,
+            view_cols: cols,
+            vim: None,
+            insert_mode: false,
+            anchor: None,
+            cursors: Vec::new(),
+            clipboard: Block::default(),
+            system_clipboard: None,
+            tick_operators,
+            bang_operators,
+            ruler: DEFAULT_RULER,
+            trails: Trails::default(),
+            theme: THEMES[0].clone(),
+            minimap: false,
+            minimap_fits: false,
+            path: None,
+            watched: Arc::default(),
+            dirty: false,
+            problems: HashMap::new(),
+            problems_stale: true,
+            journal: Journal::default(),
+            pane: 0,
+            header: false,
+            operator_map: OperatorMap::new(),
+            operator_remaps: BTreeMap::new(),
+            operator_sender: None,
+            recorder: None,
+            replayed: None,
+            operator_hash: 0,
+            midi_device: None,
+            midi_monitor: Arc::default(),
+            event_log: Arc::default(),
+            event_panel: false,
+            event_panel_fits: false,
+            midi_sender: None,
+            transport: Arc::default(),
+            views: None,
+            started: Instant::now(),
+            recording: Recording::default(),
+            recording_since: None,
+            undo_groups: Vec::new(),
+            redo_groups: Vec::new(),
+            queued: Vec::new(),
+            launched: 0,
+            typing: false,
+            taps: Vec::new(),
+            finding: false,
+            last_find: None,
+            key_test: false,
+            explain: false,
+            command_line: CommandLine::default(),
+            macros: Macros::default(),
+            keymap: Keymap::default(),
+            settings: Arc::default(),
+            help: None,
+            browser: None,
+            picker: None,
+            message: String::new(),
+            switch_pane: false,
+            remapped: false,
+            quit: false,
+        }
+    }
+
+    /// Rebuilds the operator tables from the symbols in `operator_map`, handing them to the engine
+    /// as well, which switches over between two ticks.
+    pub fn set_operator_map(&mut self, operator_map: OperatorMap) {
+        // the defaults were checked against the same operators when the editor started
+        let (tick_operators, bang_operators, _) = self.settings.operator_tables(&operator_map);
+        self.tick_operators = tick_operators;
+        self.bang_operators = bang_operators;
+        self.operator_hash = operator_config_hash(&operator_map);
+        self.operator_map = operator_map;
+        self.problems_stale = true;
+        if let Some(sender) = &self.operator_sender {
+            let _ = sender.send((self.tick_operators.clone(), self.bang_operators.clone()));
+        }
+    }
+
+    /// Gives an operator a new symbol in every table, returning its name.
+    fn remap(&mut self, operator: &str, symbol: char) -> Result<String, String> {
+        let mut operator_map = self.operator_map.clone();
+        let name = remap(&mut operator_map, operator, symbol)?;
+        self.operator_remaps.insert(name.clone(), symbol);
+        self.set_operator_map(operator_map);
+        self.remapped = true;
+        Ok(name)
+    }
+
+    /// Follows the remaps a replayed session has made on the engine, so that the editor shows and
+    /// edits with the same symbols.
+    pub fn follow_replay(&mut self) {
+        let commands: Vec<String> = self.replayed.as_ref().map_or_else(Vec::new, |replayed| replayed.try_iter().collect());
+        for line in commands {
+            if let Ok(Command::Remap { operator, symbol }) = Command::parse(&line) {
+                self.message = match self.remap(&operator, symbol) {
+                    Ok(name) => format!("replayed: {} is now {}", name, symbol),
+                    Err(err) => format!("replayed: {}", err),
+                };
+            }
+        }
+    }
+
+    /// Puts the clipboard on the system clipboard as .orca text.
+    fn copy_to_system(&mut self) {
+        if let Some(system_clipboard) = &mut self.system_clipboard {
+            system_clipboard.copy(grid_to_string(&self.clipboard.cells, self.clipboard.cols));
+        }
+    }
+
+    /// Pastes the clipboard at the top left of the selection; a transparent paste leaves what is
+    /// under the clipboard's empty cells.
+    fn paste(&mut self, transparent: bool, context: &mut Context) {
+        let selection = self.selection();
+        context.paste(selection.row, selection.col, &self.clipboard, transparent);
+        self.edited(false);
+    }
+
+    /// Pastes .orca text that the terminal pasted in, such as a snippet from another program,
+    /// keeping it as the clipboard.
+    pub fn paste_text(&mut self, text: &str, context: &mut Context) {
+        self.catch_up(context);
+        self.clipboard = parse_block(text);
+        self.paste(false, context);
+        self.message = format!("pasted {}x{}", self.clipboard.cols, self.clipboard.rows);
+    }
+
+    /// The operator under the cursor, if there is one.
+    pub fn cursor_operator(&self, context: &Context) -> Option<&Operator> {
+        let symbol = context.read(self.cursor_row as i32, self.cursor_col as i32);
+        self.tick_operators.get(symbol).or_else(|| self.bang_operators.get(symbol))
+    }
+
+    /// The ports of the operator under the cursor, if there is one.
+    pub fn cursor_ports(&self, context: &Context) -> Option<PortLayout> {
+        self.cursor_operator(context)
+            .map(|operator| operator.ports(context, self.cursor_row as i32, self.cursor_col as i32))
+    }
+
+    /// The selected block, which is just the cell under the cursor when nothing is selected.
+    pub fn selection(&self) -> Selection {
+        let cursor = (self.cursor_row as i32, self.cursor_col as i32);
+        match self.anchor {
+            Some((row, col)) => Selection::spanning((row as i32, col as i32), cursor),
+            None => Selection::spanning(cursor, cursor),
+        }
+    }
+
+    /// The cells under the main cursor and every extra cursor that is on the grid, main cursor first.
+    pub fn cursor_cells(&self) -> Vec<(i32, i32)> {
+        let (row, col) = (self.cursor_row as i32, self.cursor_col as i32);
+        let extra = self.cursors.iter()
+            .map(|&(rows, cols)| (row + rows, col + cols))
+            .filter(|&(row, col)| (0..self.rows as i32).contains(&row) && (0..self.cols as i32).contains(&col));
+        std::iter::once((row, col)).chain(extra).collect()
+    }
+
+    /// Moves the cursor, growing the selection from where it started if `extend` is set and
+    /// dropping it otherwise.
+    fn move_selecting(&mut self, rows: i32, cols: i32, extend: bool) {
+        if !extend {
+            self.anchor = None;
+        } else if self.anchor.is_none() {
+            self.anchor = Some((self.cursor_row, self.cursor_col));
+        }
+        self.move_cursor(rows, cols);
+    }
+
+    pub fn move_cursor(&mut self, rows: i32, cols: i32) {
+        self.cursor_row = (self.cursor_row as i32 + rows).clamp(0, self.rows as i32 - 1) as usize;
+        self.cursor_col = (self.cursor_col as i32 + cols).clamp(0, self.cols as i32 - 1) as usize;
+    }
+
+    /// How many grid rows and columns each minimap cell covers.
+    pub fn minimap_scale(&self) -> (usize, usize) {
+        (self.rows.div_ceil(self.view_rows), self.cols.div_ceil(MINIMAP_WIDTH - 1))
+    }
+
+    /// Lays out a pane of `height` by `width` cells: the bottom line is the status bar, and the
+    /// open panels take their share of the rest before the grid gets what is left. A panel that
+    /// would leave the grid less room than it takes itself is hidden until the pane grows again,
+    /// so that shrinking the terminal never squeezes the grid out entirely.
+    pub fn fit(&mut self, height: usize, width: usize) {
+        let height = height.saturating_sub(1);
+        self.minimap_fits = self.minimap && width >= 2 * MINIMAP_WIDTH;
+        self.event_panel_fits = self.event_panel && height >= 2 * EVENT_LOG_HEIGHT;
+        self.follow_cursor(
+            height - if self.event_panel_fits { EVENT_LOG_HEIGHT } else { 0 },
+            width - if self.minimap_fits { MINIMAP_WIDTH } else { 0 },
+        );
+    }
+
+    /// Sets how much of the grid fits on screen, then scrolls so that the cursor stays at least
+    /// `SCROLL_MARGIN` cells away from the edges of the view where the grid allows it.
+    pub fn follow_cursor(&mut self, view_rows: usize, view_cols: usize) {
+        self.view_rows = view_rows.max(1);
+        self.view_cols = view_cols.max(1);
+        self.scroll_row = scroll_to(self.scroll_row, self.cursor_row, self.view_rows, self.rows);
+        self.scroll_col = scroll_to(self.scroll_col, self.cursor_col, self.view_cols, self.cols);
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent, context: &mut Context) {
+        self.catch_up(context);
+        if key.kind == KeyEventKind::Release {
+            return;
+        }
+        if self.key_test {
+            if key.code == KeyCode::Esc {
+                self.key_test = false;
+                self.message = "key test off".to_string();
+            } else {
+                self.message = self.describe_key(key);
+            }
+            return;
+        }
+        if let Some(scroll) = self.help {
+            self.help = match key.code {
+                KeyCode::Esc | KeyCode::F(1) => None,
+                KeyCode::Up => Some(scroll.saturating_sub(1)),
+                KeyCode::Down => Some(scroll + 1),
+                KeyCode::PageUp => Some(scroll.saturating_sub(self.view_rows)),
+                KeyCode::PageDown => Some(scroll + self.view_rows),
+                _ => Some(scroll),
+            };
+            return;
+        }
+        if self.browser.is_some() {
+            self.handle_browser_key(key, context);
+            return;
+        }
+        if self.picker.is_some() {
+            self.handle_picker_key(key, context);
+            return;
+        }
+        if !self.command_line.active && self.handle_macro_key(key, context) {
+            return;
+        }
+        self.macros.record(key);
+        if self.command_line.active {
+            self.handle_command_key(key, context);
+            return;
+        }
+        if std::mem::take(&mut self.finding) {
+            let target = match key.code {
+                KeyCode::Tab => Some(FindTarget::Operator),
+                KeyCode::Char(c) => Some(FindTarget::Char(c)),
+                _ => None,
+            };
+            if let Some(target) = target {
+                self.last_find = Some(target);
+                self.find(target, true, context);
+            } else {
+                self.message.clear();
+            }
+            return;
+        }
+        if self.handle_vim_key(key, context) {
+            self.typing = false;
+            return;
+        }
+        let continues_typing = std::mem::take(&mut self.typing);
+        if let Some((action, extend)) = self.keymap.get(key) {
+            self.perform(action, extend, context);
+            return;
+        }
+        if let Some(c) = typed_char(key) {
+            // a character wider than a cell would push the rest of its row out of line
+            if !is_single_width(c) {
+                self.message = format!("{} is wider than a cell", c);
+                return;
+            }
+            // '.' is how empty cells are written in .orca files, so typing it clears the cell
+            let value = if c == '.' { '\0' } else { c };
+            for (i, (row, col)) in self.cursor_cells().into_iter().enumerate() {
+                if self.insert_mode {
+                    context.insert(row, col, value);
+                } else {
+                    context.edit(row, col, value);
+                }
+                self.edited(continues_typing || i > 0);
+            }
+            if self.insert_mode {
+                self.move_cursor(0, 1);
+            }
+            self.typing = true;
+        }
+    }
+
+    /// What `key` does: the action it is bound to, or the character it types.
+    fn describe_key(&self, key: KeyEvent) -> String {
+        let name = key_name(key);
+        if let Some((action, extend)) = self.keymap.get(key) {
+            let extend = if extend { ", extending the selection" } else { "" };
+            return format!("{} is {}{}", name, action_name(action), extend);
+        }
+        match typed_char(key) {
+            Some(c) => describe_typed(&name, c, &self.tick_operators),
+            None => format!("{} does nothing", name),
+        }
+    }
+
+    /// Does what a key bound to `action` does. Movement grows the selection if `extend` is set.
+    /// While launches are quantized, edits that clear or replace cells wait for the next launch.
+    pub fn perform(&mut self, action: Action, extend: bool, context: &mut Context) {
+        self.catch_up(context);
+        if context.launch_beats > 0 && action.waits_for_launch() {
+            if self.hold_back(context, |app, context| app.perform_now(action, extend, context)) {
+                self.message = format!("{} (queued)", self.message);
+            }
+        } else {
+            self.perform_now(action, extend, context);
+        }
+    }
+
+    fn perform_now(&mut self, action: Action, extend: bool, context: &mut Context) {
+        let (row, col) = (self.cursor_row as i32, self.cursor_col as i32);
+        let (rows, cols) = (self.rows as i32, self.cols as i32);
+        let (view_rows, view_cols) = (self.view_rows as i32, self.view_cols as i32);
+        match action {
+            Action::Quit => self.quit = true,
+            Action::Help => self.help = Some(0),
+            Action::Open => self.browser = Some(Browser::new()),
+            Action::Save => self.message = self.run_command(Command::Save(None), context),
+            Action::PickOperator => self.picker = Some(Picker::default()),
+            Action::Minimap => self.minimap = !self.minimap,
+            Action::EventLog => self.event_panel = !self.event_panel,
+            Action::SwitchPane => self.switch_pane = true,
+            Action::CommandLine => self.command_line.open(),
+            Action::ExportSelection => self.command_line.open_with("export "),
+            Action::ImportSnippet => self.command_line.open_with("import "),
+            Action::Copy => {
+                self.clipboard = context.copy(self.selection());
+                self.copy_to_system();
+                self.message = format!("copied {}x{}", self.clipboard.cols, self.clipboard.rows);
+            }
+            Action::Cut => {
+                self.clipboard = context.cut(self.selection());
+                self.copy_to_system();
+                self.edited(false);
+                self.message = format!("cut {}x{}", self.clipboard.cols, self.clipboard.rows);
+            }
+            Action::Paste | Action::PasteTransparent => {
+                if let Some(text) = self.system_clipboard.as_mut().and_then(SystemClipboard::paste) {
+                    self.clipboard = parse_block(&text);
+                }
+                self.paste(action == Action::PasteTransparent, context);
+            }
+            Action::Find => {
+                self.finding = true;
+                self.message = "find: type a character, or tab for any operator".to_string();
+            }
+            // jump to the next or previous match of the last search
+            Action::FindNext | Action::FindPrevious => match self.last_find {
+                Some(target) => self.find(target, action == Action::FindNext, context),
+                None => self.message = "nothing to find".to_string(),
+            },
+            Action::Undo => self.undo(context),
+            Action::Redo => self.redo(context),
+            Action::Select if self.anchor.is_some() => self.anchor = None,
+            Action::Select => self.anchor = Some((self.cursor_row, self.cursor_col)),
+            Action::ClearSelection => {
+                self.anchor = None;
+                self.cursors.clear();
+            }
+            // one cursor on each selected row, in the cursor's column
+            Action::CursorPerRow => match self.anchor {
+                Some(_) => {
+                    let selection = self.selection();
+                    self.cursors = (selection.row..selection.row + selection.rows as i32)
+                        .map(|r| (r - row, 0))
+                        .filter(|&offset| offset != (0, 0))
+                        .collect();
+                    self.anchor = None;
+                    self.message = format!("{} cursors", self.cursors.len() + 1);
+                }
+                None if !self.cursors.is_empty() => {
+                    self.cursors.clear();
+                    self.message = "one cursor".to_string();
+                }
+                None => self.message = "select rows to put cursors on".to_string(),
+            },
+            // the engine reads the tempo and frame rate between ticks, so changes to them take
+            // effect on the next tick
+            Action::BpmUp | Action::BpmDown | Action::BpmUpTen | Action::BpmDownTen => {
+                let tempo = match action {
+                    Action::BpmUp => context.tempo + 1,
+                    Action::BpmUpTen => context.tempo + 10,
+                    Action::BpmDown => context.tempo.saturating_sub(1),
+                    _ => context.tempo.saturating_sub(10),
+                };
+                context.set_tempo(tempo);
+                self.message = format!("bpm {}", context.tempo);
+            }
+            Action::TapTempo => self.tap_tempo(context),
+            Action::FramesUp | Action::FramesDown => {
+                let divisions = if action == Action::FramesUp {
+                    context.divisions + 1
+                } else {
+                    context.divisions - 1
+                };
+                context.set_divisions(divisions);
+                self.message = format!("{} frames per beat", context.divisions);
+            }
+            Action::PlayPause => {
+                let paused = self.transport.toggle();
+                self.message = if paused { "paused" } else { "playing" }.to_string();
+            }
+            // stepping pauses playback first, so that only the requested ticks run
+            Action::Step => {
+                if !self.transport.is_paused() {
+                    self.transport.set_paused(true);
+                }
+                self.transport.step();
+            }
+            Action::ResetFrame => {
+                context.ticks = 0;
+                self.message = "frame reset".to_string();
+            }
+            Action::MoveUp => self.move_selecting(-1, 0, extend),
+            Action::MoveDown => self.move_selecting(1, 0, extend),
+            Action::MoveLeft => self.move_selecting(0, -1, extend),
+            Action::MoveRight => self.move_selecting(0, 1, extend),
+            // paging moves a screen's height, or a screen's width sideways
+            Action::PageUp => self.move_selecting(-view_rows, 0, extend),
+            Action::PageDown => self.move_selecting(view_rows, 0, extend),
+            Action::PageLeft => self.move_selecting(0, -view_cols, extend),
+            Action::PageRight => self.move_selecting(0, view_cols, extend),
+            Action::RowStart => self.move_selecting(0, -cols, extend),
+            Action::RowEnd => self.move_selecting(0, cols, extend),
+            Action::GridStart => self.move_selecting(-rows, -cols, extend),
+            Action::GridEnd => self.move_selecting(rows, cols, extend),
+            Action::InsertMode => self.insert_mode = !self.insert_mode,
+            // in insert mode, erasing pulls the rest of the row left: erase-left removes the cell
+            // before each cursor and erase the cell under it
+            Action::EraseLeft | Action::Erase if self.anchor.is_none() => {
+                let left = action == Action::EraseLeft && self.insert_mode;
+                if left && col == 0 {
+                    return;
+                }
+                let cells = self.cursor_cells().into_iter().filter(|&(_, col)| !left || col > 0);
+                for (i, (row, col)) in cells.enumerate() {
+                    match (self.insert_mode, left) {
+                        (true, true) => context.delete(row, col - 1),
+                        (true, false) => context.delete(row, col),
+                        _ => context.edit(row, col, '\0'),
+                    }
+                    self.edited(i > 0);
+                }
+                if left {
+                    self.move_cursor(0, -1);
+                }
+            }
+            Action::EraseLeft | Action::Erase => {
+                context.erase(self.selection());
+                self.edited(false);
+            }
+            Action::Bang => {
+                for (i, (row, col)) in self.cursor_cells().into_iter().enumerate() {
+                    context.edit(row, col, '*');
+                    self.edited(i > 0);
+                }
+            }
+            Action::Comment => {
+                context.toggle_comment(self.selection());
+                self.edited(false);
+            }
+            // handled along with the other macro keys, before a key is recorded
+            Action::RecordMacro => {}
+        }
+    }
+
+    /// Handles a key while the open dialog is up: up and down choose a file, enter opens it and
+    /// escape closes the dialog.
+    fn handle_browser_key(&mut self, key: KeyEvent, context: &mut Context) {
+        let Some(browser) = self.browser.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.browser = None,
+            KeyCode::Up => browser.select(-1),
+            KeyCode::Down => browser.select(1),
+            KeyCode::PageUp => browser.select(-(self.view_rows as isize)),
+            KeyCode::PageDown => browser.select(self.view_rows as isize),
+            KeyCode::Enter => {
+                let Some(path) = browser.entries.get(browser.selected).cloned() else {
+                    return;
+                };
+                if self.dirty && !browser.discard {
+                    browser.discard = true;
+                    self.message = "unsaved changes: press enter again to discard them".to_string();
+                    return;
+                }
+                self.browser = None;
+                self.message = self.open(path, context);
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles a key while the operator picker is up: typing searches, up and down choose an
+    /// operator, enter puts it under the cursor and tab does the same but also fills its empty
+    /// inputs with placeholder values.
+    fn handle_picker_key(&mut self, key: KeyEvent, context: &mut Context) {
+        let Some(picker) = self.picker.as_mut() else {
+            return;
+        };
+        let matches = picker.matches(&self.tick_operators);
+        match key.code {
+            KeyCode::Esc => self.picker = None,
+            KeyCode::Up => picker.select(-1, matches.len()),
+            KeyCode::Down => picker.select(1, matches.len()),
+            KeyCode::Backspace => {
+                picker.query.pop();
+                picker.selected = 0;
+            }
+            KeyCode::Enter | KeyCode::Tab => {
+                let Some(&(symbol, operator)) = matches.get(picker.selected) else {
+                    return;
+                };
+                self.message = format!("{} {}", symbol, operator.name());
+                self.picker = None;
+                self.place_operator(symbol, key.code == KeyCode::Tab, context);
+            }
+            _ => {
+                if let Some(c) = typed_char(key) {
+                    picker.query.push(c);
+                    picker.selected = 0;
+                }
+            }
+        }
+    }
+
+    /// Writes `symbol` under the cursor, and if `stamp` is set, writes each of its empty inputs'
+    /// default value into it, or `0` for inputs without one. Undone as a single step.
+    fn place_operator(&mut self, symbol: char, stamp: bool, context: &mut Context) {
+        let (row, col) = (self.cursor_row as i32, self.cursor_col as i32);
+        context.edit(row, col, symbol);
+        self.edited(false);
+        let Some(operator) = self.tick_operators.get(symbol).filter(|_| stamp) else {
+            return;
+        };
+        for port in operator.ports(context, row, col).inputs {
+            if context.contains(port.row, port.col) && context.read(port.row, port.col) == '\0' {
+                let value = if port.value == '\0' { '0' } else { port.value };
+                context.edit(port.row, port.col, value);
+                self.edited(true);
+            }
+        }
+    }
+
+    /// Replaces the grid with the one in the file at `path`, starting a fresh undo history, and takes
+    /// the tempo, frame rate and seed from its header if it has one. While launches are quantized,
+    /// the switch is held back for a launch like any other edit, and the pane goes on saving to the
+    /// old file until then.
+    fn open(&mut self, path: PathBuf, context: &mut Context) -> String {
+        let text = match read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) => return format!("could not open {}: {}", path.display(), err),
+        };
+        let (grid, header) = (parse_grid(&text, self.rows, self.cols), parse_header(&text));
+        let message = format!("opened {}", path.display());
+        let queued = context.launches.len();
+        let replace = |context: &mut Context| {
+            context.replace_grid(grid);
+            if let Some(header) = &header {
+                header.apply(context);
+            }
+        };
+        if context.launch_beats == 0 {
+            replace(context);
+        } else {
+            context.hold_back(replace);
+        }
+        if let Some(launch) = context.launches.get_mut(queued) {
+            launch.fresh = true;
+            self.queued.push(Queued::Open { path, header: header.is_some() });
+            return format!("{} (queued)", message);
+        }
+        // a grid the same as the one open is switched to at once
+        context.history.clear();
+        self.opened(path, header.is_some());
+        message
+    }
+
+    /// Takes up a grid just opened from `path`, with nothing to undo and nothing unsaved.
+    fn opened(&mut self, path: PathBuf, header: bool) {
+        if header {
+            self.header = true;
+        }
+        self.undo_groups.clear();
+        self.redo_groups.clear();
+        self.anchor = None;
+        self.dirty = false;
+        self.problems_stale = true;
+        self.set_path(path);
+    }
+
+    /// Sets the file the grid belongs to, which is also the file it is reloaded from when it
+    /// changes on disk, and moves it to the top of the recent files.
+    pub fn set_path(&mut self, path: PathBuf) {
+        remember(&path);
+        self.watched.lock().unwrap().path = Some(path.clone());
+        self.path = Some(path);
+    }
+
+    /// Journals the edits made since the last call while the grid has unsaved work, and deletes
+    /// the journal once it doesn't. Called after every key press.
+    pub fn update_journal(&mut self, context: &Context) {
+        if !self.dirty {
+            self.journal.discard();
+        } else if let Err(err) = self.journal.record(&journal_path(self.path.as_deref(), self.pane), context) {
+            self.message = format!("could not write journal: {}", err);
+        }
+    }
+
+    /// Handles the keys that record and replay macros, returning whether `key` was one of them.
+    /// The record-macro key (Alt-R by default) followed by a number starts recording into that slot
+    /// and the record-macro key again stops it; Alt and a number replays the slot from the cursor.
+    fn handle_macro_key(&mut self, key: KeyEvent, context: &mut Context) -> bool {
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let slot = match key.code {
+            KeyCode::Char(c) => c.to_digit(10).map(|slot| slot as usize),
+            _ => None,
+        };
+        if self.macros.armed {
+            self.macros.armed = false;
+            if let Some(slot) = slot.filter(|_| !alt) {
+                self.macros.start(slot);
+                self.message = format!("recording macro {}", slot);
+                return true;
+            }
+        }
+        if self.keymap.get(key).is_some_and(|(action, _)| action == Action::RecordMacro) {
+            match self.macros.stop() {
+                Some((slot, len)) => self.message = format!("recorded {} keys to macro {}", len, slot),
+                None => {
+                    self.macros.armed = true;
+                    self.message = "record macro to slot 0-9".to_string();
+                }
+            }
+            return true;
+        }
+        match slot {
+            Some(slot) if alt => {
+                if self.macros.recording == Some(slot) {
+                    self.message = format!("macro {} is being recorded", slot);
+                } else {
+                    for key in self.macros.keys(slot) {
+                        self.handle_key(key, context);
+                    }
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves the cursor to the next match of `target` after it in reading order, or the previous
+    /// one before it, wrapping around the grid.
+    fn find(&mut self, target: FindTarget, forward: bool, context: &Context) {
+        let len = self.rows * self.cols;
+        let cursor = self.cursor_row * self.cols + self.cursor_col;
+        let matches = |index: usize| {
+            let value = context.grid()[index];
+            match target {
+                FindTarget::Char(c) => value == c,
+                FindTarget::Operator => {
+                    self.tick_operators.get(value).is_some() || self.bang_operators.get(value).is_some()
+                }
+            }
+        };
+        let found = (1..=len)
+            .map(|offset| if forward { (cursor + offset) % len } else { (cursor + len - offset) % len })
+            .find(|&index| matches(index));
+        let description = match target {
+            FindTarget::Char(c) => format!("'{}'", c),
+            FindTarget::Operator => "operators".to_string(),
+        };
+        match found {
+            Some(index) => {
+                self.anchor = None;
+                self.cursor_row = index / self.cols;
+                self.cursor_col = index % self.cols;
+                self.message = format!("found {}", description);
+            }
+            None => self.message = format!("no {} found", description),
+        }
+    }
+
+    /// Marks the grid as changed by one more edit, which joins the previous undo step if
+    /// `continues_typing` is set.
+    pub fn edited(&mut self, continues_typing: bool) {
+        self.dirty = true;
+        self.problems_stale = true;
+        self.redo_groups.clear();
+        match self.undo_groups.last_mut() {
+            Some(edits) if continues_typing => *edits += 1,
+            _ => self.undo_groups.push(1),
+        }
+    }
+
+    pub fn undo(&mut self, context: &mut Context) {
+        self.forget_cleared_redos(context);
+        let edits = self.undo_groups.pop().unwrap_or(1);
+        let undone = (0..edits).take_while(|_| context.undo()).count();
+        if undone == 0 {
+            self.message = "nothing to undo".to_string();
+        } else {
+            self.redo_groups.push(undone);
+            self.dirty = true;
+            self.problems_stale = true;
+            self.message = format!("undid {} edit{}", undone, if undone == 1 { "" } else { "s" });
+        }
+    }
+
+    pub fn redo(&mut self, context: &mut Context) {
+        self.forget_cleared_redos(context);
+        let edits = self.redo_groups.pop().unwrap_or(1);
+        let redone = (0..edits).take_while(|_| context.redo()).count();
+        if redone == 0 {
+            self.message = "nothing to redo".to_string();
+        } else {
+            self.undo_groups.push(redone);
+            self.dirty = true;
+            self.problems_stale = true;
+            self.message = format!("redid {} edit{}", redone, if redone == 1 { "" } else { "s" });
+        }
+    }
+
+    /// Runs the linter over `context` again if the grid has been edited since it last did, rather
+    /// than on every frame. `context` is meant to be a copy of the grid, so that the engine isn't
+    /// held up while the linter runs.
+    pub fn check_problems(&mut self, context: &Context) {
+        if !std::mem::take(&mut self.problems_stale) {
+            return;
+        }
+        self.problems.clear();
+        for problem in validate(context, &self.tick_operators, &self.bang_operators) {
+            self.problems.entry((problem.row, problem.col)).or_insert(problem.message);
+        }
+    }
+
+    // a tick recorded since the last undo clears the context's redo stack, and the groups of
+    // edits on it go with it
+    fn forget_cleared_redos(&mut self, context: &Context) {
+        if context.history.next_redo_kind().is_none() {
+            self.redo_groups.clear();
+        }
+    }
+
+    /// Counts a press of the tap tempo key. Once enough taps have come in, their average spacing
+    /// becomes the tempo from the next beat on, so that playback does not jump mid-beat.
+    fn tap_tempo(&mut self, context: &mut Context) {
+        let now = Instant::now();
+        if self.taps.last().is_some_and(|&last| now - last > TAP_TIMEOUT) {
+            self.taps.clear();
+        }
+        self.taps.push(now);
+        if self.taps.len() > TAP_COUNT {
+            self.taps.remove(0);
+        }
+        if self.taps.len() < TAP_COUNT {
+            self.message = format!("tap {}/{}", self.taps.len(), TAP_COUNT);
+            return;
+        }
+        let beat = (now - self.taps[0]) / (TAP_COUNT - 1) as u32;
+        // taps too close together to time would otherwise give a tempo no clock could keep
+        let tempo = ((60.0 / beat.as_secs_f64()).round() as u64).clamp(*TEMPO_RANGE.start(), *TEMPO_RANGE.end());
+        context.next_tempo = Some(tempo);
+        self.message = format!("bpm {} from the next beat", tempo);
+    }
+
+    fn handle_command_key(&mut self, key: KeyEvent, context: &mut Context) {
+        match key.code {
+            KeyCode::Esc => self.command_line.close(),
+            KeyCode::Enter => {
+                let line = self.command_line.submit();
+                self.message = match Command::parse(&line) {
+                    Ok(command) => {
+                        self.event_log.push(context.ticks, format!(":{}", line.trim().trim_start_matches(':')));
+                        self.run_command(command, context)
+                    }
+                    Err(err) => err,
+                };
+            }
+            KeyCode::Tab => {
+                let candidates = self.command_line.complete();
+                if candidates.len() > 1 {
+                    self.message = candidates.join(" ");
+                }
+            }
+            KeyCode::Up => self.command_line.previous(),
+            KeyCode::Down => self.command_line.next(),
+            KeyCode::Backspace => {
+                self.command_line.text.pop();
+            }
+            KeyCode::Char(c) => self.command_line.text.push(c),
+            _ => {}
+        }
+    }
+
+    /// Carries out a command, returning a message describing the result. While launches are
+    /// quantized, commands that change the grid or the tempo wait for the next launch.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self, context), ret(Display)))]
+    pub fn run_command(&mut self, command: Command, context: &mut Context) -> String {
+        self.catch_up(context);
+        if context.launch_beats > 0 && command.waits_for_launch() {
+            let mut message = String::new();
+            if self.hold_back(context, |app, context| message = app.run_command_now(command, context)) {
+                message.push_str(" (queued)");
+            }
+            return message;
+        }
+        self.run_command_now(command, context)
+    }
+
+    /// Makes an edit with the context holding it back for the next launch, returning whether it
+    /// was queued. The edit's undo steps and unsaved changes are only counted once it is made.
+    fn hold_back(&mut self, context: &mut Context, edit: impl FnOnce(&mut App, &mut Context)) -> bool {
+        let (undo_groups, redo_groups, dirty) = (self.undo_groups.clone(), self.redo_groups.clone(), self.dirty);
+        let queued = context.launches.len();
+        context.hold_back(|context| edit(self, context));
+        (self.undo_groups, self.redo_groups, self.dirty) = (undo_groups, redo_groups, dirty);
+        if context.launches.len() == queued {
+            return false;
+        }
+        self.queued.push(Queued::Edit);
+        true
+    }
+
+    /// Does the bookkeeping for the edits of this pane's that were held back and have since been
+    /// made at a launch. Called before anything reads or changes the pane.
+    pub fn catch_up(&mut self, context: &Context) {
+        let made = context.launched.saturating_sub(self.launched).min(self.queued.len());
+        self.launched = context.launched;
+        let made: Vec<Queued> = self.queued.drain(..made).collect();
+        for queued in made {
+            match queued {
+                Queued::Edit => self.edited(false),
+                Queued::Open { path, header } => self.opened(path, header),
+            }
+        }
+    }
+
+    fn run_command_now(&mut self, command: Command, context: &mut Context) -> String {
+        match command {
+            Command::Bpm(bpm) => {
+                context.set_tempo(bpm);
+                format!("bpm {}", context.tempo)
+            }
+            Command::Fill(text) => {
+                let pattern: Vec<char> = text.chars().map(|c| if c == '.' { '\0' } else { c }).collect();
+                let selection = self.selection();
+                context.fill(selection, &pattern);
+                self.edited(false);
+                format!("filled {}x{} with {}", selection.cols, selection.rows, text)
+            }
+            Command::Clip { path, from, to } => {
+                if self.recording.is_empty() {
+                    return "nothing recorded, start with :record".to_string();
+                }
+                let to = to.unwrap_or_else(|| self.recording.duration());
+                let bytes = if path.extension().is_some_and(|extension| extension == "gif") {
+                    self.recording.to_gif(from, to)
+                } else {
+                    self.recording.to_cast(from, to).into_bytes()
+                };
+                match write(&path, bytes) {
+                    Ok(()) => format!("wrote {:.1}s clip to {}", to.saturating_sub(from).as_secs_f64(), path.display()),
+                    Err(err) => format!("could not write {}: {}", path.display(), err),
+                }
+            }
+            Command::Export(path) => {
+                let block = context.copy(self.selection());
+                match write_block(&path, &block) {
+                    Ok(()) => format!("exported {}x{} to {}", block.cols, block.rows, path.display()),
+                    Err(err) => err.to_string(),
+                }
+            }
+            Command::Import(path) => match read_block(&path) {
+                Ok(block) => {
+                    context.paste(self.cursor_row as i32, self.cursor_col as i32, &block, false);
+                    self.edited(false);
+                    format!("imported {}x{} from {}", block.cols, block.rows, path.display())
+                }
+                Err(err) => err.to_string(),
+            },
+            Command::Midi(index) => match open_output(index) {
+                Ok((conn, name)) => {
+                    if let Some(sender) = &self.midi_sender {
+                        // the engine thread only goes away when the program exits
+                        let _ = sender.send(Box::new(conn));
+                    }
+                    self.midi_device = Some(name.clone());
+                    format!("midi {}", name)
+                }
+                Err(err) => err.to_string(),
+            },
+            Command::Quit => {
+                self.quit = true;
+                String::new()
+            }
+            Command::Quantize(beats) => {
+                context.launch_beats = beats.unwrap_or(if context.launch_beats == 0 { LAUNCH_BEATS } else { 0 });
+                match context.launch_beats {
+                    0 => "edits land at once".to_string(),
+                    1 => "edits land on the next beat".to_string(),
+                    beats => format!("edits land every {} beats", beats),
+                }
+            }
+            // the ramp keeps the case of the digit it starts from, so that it can count up operators'
+            // uppercase inputs as well
+            Command::Ramp(from) => {
+                let (start, upper) = char_to_base_36(from);
+                let pattern: Vec<char> = (0..36).map(|i| base_36_to_char(start + i, upper)).collect();
+                let selection = self.selection();
+                context.fill(selection, &pattern);
+                self.edited(false);
+                format!("filled {}x{} with a ramp from {}", selection.cols, selection.rows, from)
+            }
+            Command::Explain => {
+                self.explain = !self.explain;
+                context.trace.enabled = self.explain;
+                if self.explain { "explaining the cell under the cursor" } else { "stopped explaining" }.to_string()
+            }
+            Command::Keys => {
+                self.key_test = true;
+                "key test: press keys to see what they send, Esc to stop".to_string()
+            }
+            Command::Record => match self.recording_since.take() {
+                Some(_) => format!(
+                    "recorded {:.1}s, {} frames",
+                    self.recording.duration().as_secs_f64(),
+                    self.recording.len(),
+                ),
+                None => {
+                    self.recording = Recording::new(context.height, context.width);
+                    self.recording_since = Some(Instant::now());
+                    "recording".to_string()
+                }
+            },
+            Command::Remap { operator, symbol } => match self.remap(&operator, symbol) {
+                Ok(name) => {
+                    if let Some(recorder) = &self.recorder {
+                        recorder.lock().unwrap().command(context.ticks, &format!("remap {} {}", name, symbol));
+                    }
+                    format!("{} is now {}", name, symbol)
+                }
+                Err(err) => err,
+            },
+            Command::Save(path) => {
+                let Some(path) = path.or_else(|| self.path.clone()) else {
+                    return "save needs a file name".to_string();
+                };
+                let written = if self.header {
+                    let header = Header {
+                        rows: context.height,
+                        cols: context.width,
+                        bpm: context.tempo,
+                        frames: context.divisions,
+                        seed: context.seed,
+                        operators: self.operator_hash,
+                    };
+                    write_grid_with_header(&path, context.grid(), context.width, &header)
+                } else {
+                    write_grid(&path, context.grid(), context.width)
+                };
+                match written {
+                    Ok(()) => {
+                        self.dirty = false;
+                        let message = format!("saved {}", path.display());
+                        if self.path.as_ref() != Some(&path) {
+                            self.set_path(path);
+                        }
+                        // the watcher would otherwise load the grid back, losing the undo history
+                        self.watched.lock().unwrap().saved();
+                        message
+                    }
+                    Err(err) => err.to_string(),
+                }
+            }
+            Command::Theme(name) => match find_theme(&name) {
+                Ok(theme) => {
+                    self.theme = theme.clone();
+                    format!("theme {}", name)
+                }
+                Err(err) => err,
+            },
+            Command::Write { col, row, text } => {
+                let cells: Vec<char> = text.chars().map(|c| if c == '.' { '\0' } else { c }).collect();
+                let block = Block { rows: 1, cols: cells.len(), cells };
+                context.paste(row, col, &block, false);
+                self.edited(false);
+                format!("wrote {}", text)
+            }
+        }
+    }
+
+    pub fn handle_mouse(&mut self, mouse: MouseEvent) {
+        if let MouseEventKind::Down(MouseButton::Left) = mouse.kind {
+            // clicking the minimap jumps to the middle of the part of the grid it shows there
+            if self.minimap_fits && mouse.column as usize > self.view_cols && (mouse.row as usize) < self.view_rows {
+                let (scale_rows, scale_cols) = self.minimap_scale();
+                let minimap_col = mouse.column as usize - self.view_cols - 1;
+                self.anchor = None;
+                self.cursor_row = (mouse.row as usize * scale_rows + scale_rows / 2).min(self.rows - 1);
+                self.cursor_col = (minimap_col * scale_cols + scale_cols / 2).min(self.cols - 1);
+                return;
+            }
+            let row = self.scroll_row + mouse.row as usize;
+            let col = self.scroll_col + mouse.column as usize;
+            if (mouse.row as usize) < self.view_rows && (mouse.column as usize) < self.view_cols && row < self.rows && col < self.cols {
+                self.anchor = None;
+                // alt-clicking adds an extra cursor, or takes away the one already there
+                if mouse.modifiers.contains(KeyModifiers::ALT) {
+                    let offset = (row as i32 - self.cursor_row as i32, col as i32 - self.cursor_col as i32);
+                    match self.cursors.iter().position(|&cursor| cursor == offset) {
+                        Some(index) => {
+                            self.cursors.remove(index);
+                        }
+                        None if offset != (0, 0) => self.cursors.push(offset),
+                        None => {}
+                    }
+                    return;
+                }
+                self.cursors.clear();
+                self.cursor_row = row;
+                self.cursor_col = col;
+            }
+        }
+    }
+}
+
+/// Returns the scroll offset along one axis that keeps `cursor` inside a view of `view` cells over
+/// `len` cells, moving the current offset `scroll` as little as possible.
+fn scroll_to(scroll: usize, cursor: usize, view: usize, len: usize) -> usize {
+    let margin = SCROLL_MARGIN.min(view.saturating_sub(1) / 2);
+    let max_scroll = len.saturating_sub(view);
+    let scroll = if cursor < scroll + margin {
+        cursor.saturating_sub(margin)
+    } else if cursor + margin >= scroll + view {
+        cursor + margin + 1 - view
+    } else {
+        scroll
+    };
+    scroll.min(max_scroll)
+}
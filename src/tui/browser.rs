@@ -0,0 +1,67 @@
+use std::env;
+use std::fs::{read_dir, read_to_string, write};
+use std::path::{Path, PathBuf};
+
+// how many recently opened files are remembered
+const RECENT_FILES: usize = 10;
+
+/// The open dialog: recently opened files, then the .orca files in the working directory.
+pub struct Browser {
+    pub entries: Vec<PathBuf>,
+    /// How many of `entries` come from the recent files list.
+    pub recent: usize,
+    pub selected: usize,
+    /// Set after trying to open a file over unsaved changes, so that trying again discards them.
+    pub discard: bool,
+}
+
+impl Browser {
+    pub fn new() -> Browser {
+        let recent = read_recent();
+        let mut local: Vec<PathBuf> = read_dir(".")
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.extension().is_some_and(|extension| extension == "orca"))
+                    .filter(|path| path.canonicalize().map_or(true, |path| !recent.contains(&path)))
+                    .map(|path| path.strip_prefix(".").map(PathBuf::from).unwrap_or(path))
+                    .collect()
+            })
+            .unwrap_or_default();
+        local.sort();
+        Browser { recent: recent.len(), entries: recent.into_iter().chain(local).collect(), selected: 0, discard: false }
+    }
+
+    pub fn select(&mut self, offset: isize) {
+        self.discard = false;
+        self.selected = self.selected.saturating_add_signed(offset).min(self.entries.len().saturating_sub(1));
+    }
+}
+
+/// Where the recent files list is kept, if there is a home directory to keep it in.
+fn recent_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".rust-orca-recent"))
+}
+
+/// Reads the recent files list, most recent first, leaving out files that no longer exist.
+pub fn read_recent() -> Vec<PathBuf> {
+    recent_path()
+        .and_then(|path| read_to_string(path).ok())
+        .map(|text| text.lines().map(PathBuf::from).filter(|path| path.is_file()).collect())
+        .unwrap_or_default()
+}
+
+/// Moves `path` to the top of the recent files list.
+pub fn remember(path: &Path) {
+    let Some(recent_path) = recent_path() else {
+        return;
+    };
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let mut recent = read_recent();
+    recent.retain(|recent| *recent != path);
+    recent.insert(0, path);
+    recent.truncate(RECENT_FILES);
+    let text: String = recent.iter().map(|path| format!("{}\n", path.display())).collect();
+    // the list is a convenience, so failing to save it is not worth interrupting anyone for
+    let _ = write(recent_path, text);
+}
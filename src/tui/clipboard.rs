@@ -0,0 +1,67 @@
+use std::env;
+use std::io::{Write, stdout};
+
+use arboard::Clipboard;
+
+const BASE64_DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The operating system's clipboard, so that selections can be copied to and pasted from other
+/// programs. Over SSH, or where there is no clipboard to reach, copies go through the terminal as
+/// OSC 52 escape sequences instead, which most terminals pass on to the clipboard of the machine
+/// they run on; pasting then goes through the terminal's own paste.
+pub struct SystemClipboard {
+    clipboard: Option<Clipboard>,
+    /// The text the editor last copied, so that a paste only reads the clipboard once something
+    /// else was copied since.
+    last_copied: Option<String>,
+}
+
+impl SystemClipboard {
+    pub fn new() -> SystemClipboard {
+        // the clipboard of the machine the editor runs on is no use to someone logged in over SSH
+        let remote = env::var_os("SSH_CONNECTION").is_some() || env::var_os("SSH_TTY").is_some();
+        let clipboard = if remote { None } else { Clipboard::new().ok() };
+        SystemClipboard { clipboard, last_copied: None }
+    }
+
+    /// Puts text on the clipboard.
+    pub fn copy(&mut self, text: String) {
+        let copied = match &mut self.clipboard {
+            Some(clipboard) => clipboard.set_text(text.as_str()).is_ok(),
+            None => false,
+        };
+        if !copied {
+            // a terminal that doesn't understand the sequence ignores it
+            let mut out = stdout();
+            let _ = write!(out, "\x1b]52;c;{}\x07", base64(text.as_bytes()));
+            let _ = out.flush();
+        }
+        self.last_copied = Some(text);
+    }
+
+    /// The text on the clipboard, if something other than the editor put it there since the
+    /// editor last copied.
+    pub fn paste(&mut self) -> Option<String> {
+        let text = self.clipboard.as_mut()?.get_text().ok()?;
+        if self.last_copied.as_ref() == Some(&text) {
+            return None;
+        }
+        self.last_copied = Some(text.clone());
+        Some(text)
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| group | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_DIGITS[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
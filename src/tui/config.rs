@@ -0,0 +1,85 @@
+use std::fs::metadata;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::OPERATOR_CONFIG_FILE;
+use crate::tui::App;
+use crate::tui::keymap::{Keymap, read_keymap};
+use crate::tui::theme::{THEMES, apply_setting, find_theme, read_theme};
+
+pub const KEYMAP_FILE: &str = "keymap.txt";
+pub const THEME_FILE: &str = "theme.txt";
+
+// how often the config files are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Reads the keymap and theme files into `app`, on top of the keys and colors from its config.toml
+/// settings. The colors are applied on top of the built-in theme `app` is using. The operator
+/// config is read as well, and the operator tables rebuilt if any symbol changed, keeping the
+/// symbols given with the remap command. Returns a description of each problem found in the
+/// settings and files.
+pub fn apply_config(app: &mut App) -> Vec<String> {
+    let mut keymap = Keymap::default();
+    let settings_keymap_errors = keymap.bind_pairs(app.settings.keys.iter().map(|(chord, name)| (chord.as_str(), name.as_str())));
+    let (keymap, keymap_errors) = read_keymap(KEYMAP_FILE, keymap);
+
+    let mut base = find_theme(app.theme.name).unwrap_or(&THEMES[0]).clone();
+    let settings_theme_errors: Vec<String> = app
+        .settings
+        .theme
+        .settings()
+        .into_iter()
+        .filter_map(|(setting, value)| apply_setting(&mut base, setting, &value).err())
+        .collect();
+    let (theme, theme_errors) = read_theme(THEME_FILE, &base);
+    app.keymap = keymap;
+    app.theme = theme;
+
+    let mut operator_errors = Vec::new();
+    match app.settings.operator_map() {
+        Ok(mut operator_map) => {
+            operator_map.extend(app.operator_remaps.iter().map(|(name, &symbol)| (name.clone(), vec![symbol])));
+            if operator_map != app.operator_map {
+                app.set_operator_map(operator_map);
+            }
+        }
+        Err(err) => operator_errors.push(err),
+    }
+
+    let settings_keymap_errors = settings_keymap_errors.into_iter().map(|err| format!("config keys: {}", err));
+    let settings_theme_errors = settings_theme_errors.into_iter().map(|err| format!("config theme: {}", err));
+    let keymap_errors = keymap_errors.into_iter().map(|err| format!("{} {}", KEYMAP_FILE, err));
+    let theme_errors = theme_errors.into_iter().map(|err| format!("{} {}", THEME_FILE, err));
+    settings_keymap_errors
+        .chain(settings_theme_errors)
+        .chain(keymap_errors)
+        .chain(theme_errors)
+        .chain(operator_errors)
+        .collect()
+}
+
+/// Notices when the config files are created, edited or removed.
+pub struct ConfigWatcher {
+    modified: [Option<SystemTime>; 3],
+    checked_at: Instant,
+}
+
+impl ConfigWatcher {
+    pub fn new() -> ConfigWatcher {
+        ConfigWatcher { modified: modified_times(), checked_at: Instant::now() }
+    }
+
+    /// Whether any config file has changed since the last call that returned true. The files are
+    /// only looked at every `POLL_INTERVAL`, so this is cheap enough to call every frame.
+    pub fn changed(&mut self) -> bool {
+        if self.checked_at.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+        self.checked_at = Instant::now();
+        let modified = modified_times();
+        modified != std::mem::replace(&mut self.modified, modified)
+    }
+}
+
+fn modified_times() -> [Option<SystemTime>; 3] {
+    [KEYMAP_FILE, THEME_FILE, OPERATOR_CONFIG_FILE].map(|file| metadata(file).and_then(|metadata| metadata.modified()).ok())
+}
@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::fs::read_to_string;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rust_orca::keys::{self, Modifiers};
+
+/// Something the editor can do from a single key chord.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Help,
+    /// Opens the dialog for choosing a file to open.
+    Open,
+    Save,
+    /// Opens the searchable list of operators to put one under the cursor.
+    PickOperator,
+    Minimap,
+    EventLog,
+    /// Moves to the other grid when two are open side by side.
+    SwitchPane,
+    CommandLine,
+    /// Starts an `export` command for saving the selection as a snippet.
+    ExportSelection,
+    /// Starts an `import` command for pasting a snippet.
+    ImportSnippet,
+    Copy,
+    Cut,
+    Paste,
+    PasteTransparent,
+    Find,
+    FindNext,
+    FindPrevious,
+    Undo,
+    Redo,
+    /// Starts a selection at the cursor, or drops the current one.
+    Select,
+    ClearSelection,
+    /// Puts a cursor on each selected row, or goes back to a single cursor.
+    CursorPerRow,
+    BpmUp,
+    BpmDown,
+    BpmUpTen,
+    BpmDownTen,
+    /// Sets the tempo from the time between presses.
+    TapTempo,
+    FramesUp,
+    FramesDown,
+    PlayPause,
+    Step,
+    ResetFrame,
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    PageUp,
+    PageDown,
+    PageLeft,
+    PageRight,
+    RowStart,
+    RowEnd,
+    GridStart,
+    GridEnd,
+    InsertMode,
+    EraseLeft,
+    Erase,
+    /// Puts a bang under the cursor.
+    Bang,
+    /// Toggles `#` comments around the selection.
+    Comment,
+    RecordMacro,
+}
+
+impl Action {
+    /// Whether the action clears or replaces cells, or sets the tempo, and so waits for the next
+    /// launch while launches are quantized. Typing goes through at once.
+    pub fn waits_for_launch(self) -> bool {
+        matches!(
+            self,
+            Action::Cut
+                | Action::Paste
+                | Action::PasteTransparent
+                | Action::EraseLeft
+                | Action::Erase
+                | Action::Bang
+                | Action::Comment
+                | Action::BpmUp
+                | Action::BpmDown
+                | Action::BpmUpTen
+                | Action::BpmDownTen
+        )
+    }
+}
+
+const ACTION_NAMES: [(&str, Action); 51] = [
+    ("quit", Action::Quit),
+    ("help", Action::Help),
+    ("open", Action::Open),
+    ("save", Action::Save),
+    ("pick-operator", Action::PickOperator),
+    ("minimap", Action::Minimap),
+    ("event-log", Action::EventLog),
+    ("switch-pane", Action::SwitchPane),
+    ("command-line", Action::CommandLine),
+    ("export-selection", Action::ExportSelection),
+    ("import-snippet", Action::ImportSnippet),
+    ("copy", Action::Copy),
+    ("cut", Action::Cut),
+    ("paste", Action::Paste),
+    ("paste-transparent", Action::PasteTransparent),
+    ("find", Action::Find),
+    ("find-next", Action::FindNext),
+    ("find-previous", Action::FindPrevious),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+    ("select", Action::Select),
+    ("clear-selection", Action::ClearSelection),
+    ("cursor-per-row", Action::CursorPerRow),
+    ("bpm-up", Action::BpmUp),
+    ("bpm-down", Action::BpmDown),
+    ("bpm-up-10", Action::BpmUpTen),
+    ("bpm-down-10", Action::BpmDownTen),
+    ("tap-tempo", Action::TapTempo),
+    ("frames-up", Action::FramesUp),
+    ("frames-down", Action::FramesDown),
+    ("play-pause", Action::PlayPause),
+    ("step", Action::Step),
+    ("reset-frame", Action::ResetFrame),
+    ("move-up", Action::MoveUp),
+    ("move-down", Action::MoveDown),
+    ("move-left", Action::MoveLeft),
+    ("move-right", Action::MoveRight),
+    ("page-up", Action::PageUp),
+    ("page-down", Action::PageDown),
+    ("page-left", Action::PageLeft),
+    ("page-right", Action::PageRight),
+    ("row-start", Action::RowStart),
+    ("row-end", Action::RowEnd),
+    ("grid-start", Action::GridStart),
+    ("grid-end", Action::GridEnd),
+    ("insert-mode", Action::InsertMode),
+    ("erase-left", Action::EraseLeft),
+    ("erase", Action::Erase),
+    ("bang", Action::Bang),
+    ("comment", Action::Comment),
+    ("record-macro", Action::RecordMacro),
+];
+
+// the bindings used when there is no keymap file; ':' is the midi operator, so the command line
+// has a key of its own
+const DEFAULT_KEYMAP: &str = "
+ctrl+q quit
+f1 help
+ctrl+o open
+ctrl+s save
+ctrl+t pick-operator
+f2 minimap
+f4 event-log
+f3 switch-pane
+ctrl+k command-line
+alt+e export-selection
+alt+i import-snippet
+ctrl+c copy
+ctrl+x cut
+ctrl+v paste
+alt+v paste-transparent
+ctrl+g find
+ctrl+n find-next
+ctrl+p find-previous
+ctrl+z undo
+ctrl+y redo
+esc clear-selection
+alt+c cursor-per-row
+ctrl+up bpm-up
+ctrl+down bpm-down
+ctrl+shift+up bpm-up-10
+ctrl+shift+down bpm-down-10
+alt+t tap-tempo
+ctrl+right frames-up
+ctrl+left frames-down
+space play-pause
+ctrl+f step
+ctrl+r reset-frame
+up move-up
+down move-down
+left move-left
+right move-right
+pageup page-up
+pagedown page-down
+ctrl+pageup page-left
+ctrl+pagedown page-right
+home row-start
+end row-end
+ctrl+home grid-start
+ctrl+end grid-end
+insert insert-mode
+backspace erase-left
+delete erase
+ctrl+b bang
+alt+/ comment
+alt+r record-macro
+";
+
+/// A key and the modifiers held with it. Shift is folded into the character for character keys,
+/// since that is how terminals report it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Chord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Chord {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Chord {
+        match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => Chord {
+                code: KeyCode::Char(shifted(c)),
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            _ => Chord { code, modifiers },
+        }
+    }
+
+    /// Parses chords like `ctrl+shift+up`, `alt+v`, `f1` or `space`.
+    fn parse(text: &str) -> Option<Chord> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = text;
+        loop {
+            let (modifier, rest) = match key.split_once('+') {
+                Some((modifier, rest)) if !rest.is_empty() => (modifier, rest),
+                _ => break,
+            };
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return None,
+            };
+            key = rest;
+        }
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "insert" => KeyCode::Insert,
+                "delete" => KeyCode::Delete,
+                "backspace" => KeyCode::Backspace,
+                "enter" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "esc" => KeyCode::Esc,
+                name => KeyCode::F(name.strip_prefix('f')?.parse().ok()?),
+            },
+        };
+        Some(Chord::new(code, modifiers))
+    }
+
+    /// Writes the chord the way `parse` reads it.
+    fn name(&self) -> String {
+        let mut name = String::new();
+        for (modifier, prefix) in [(KeyModifiers::CONTROL, "ctrl+"), (KeyModifiers::ALT, "alt+"), (KeyModifiers::SHIFT, "shift+")] {
+            if self.modifiers.contains(modifier) {
+                name.push_str(prefix);
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => name.push_str("space"),
+            KeyCode::Char(c) => name.push(c),
+            KeyCode::F(n) => name.push_str(&format!("f{}", n)),
+            KeyCode::PageUp => name.push_str("pageup"),
+            KeyCode::PageDown => name.push_str("pagedown"),
+            code => name.push_str(&format!("{:?}", code).to_ascii_lowercase()),
+        }
+        name
+    }
+}
+
+// a shifted letter with no single capital, such as ß, is left as it is
+fn shifted(c: char) -> char {
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(upper), None) => upper,
+        _ => c,
+    }
+}
+
+/// The character `key` types into the grid, if it types one.
+pub fn typed_char(key: KeyEvent) -> Option<char> {
+    let KeyCode::Char(c) = key.code else {
+        return None;
+    };
+    let modifiers = Modifiers {
+        shift: key.modifiers.contains(KeyModifiers::SHIFT),
+        control: key.modifiers.contains(KeyModifiers::CONTROL),
+        alt: key.modifiers.contains(KeyModifiers::ALT),
+    };
+    keys::typed_char(c, modifiers)
+}
+
+/// Names `key` the way keymap files write chords, such as `ctrl+alt+q`.
+pub fn key_name(key: KeyEvent) -> String {
+    Chord::new(key.code, key.modifiers).name()
+}
+
+/// The name keymap files use for `action`.
+pub fn action_name(action: Action) -> &'static str {
+    ACTION_NAMES.iter().find(|(_, named)| *named == action).map_or("", |(name, _)| name)
+}
+
+/// Which action each key chord performs.
+#[derive(Clone)]
+pub struct Keymap {
+    bindings: HashMap<Chord, Action>,
+}
+
+impl Keymap {
+    /// The action bound to `key`, and whether shift was held on top of the chord it is bound to,
+    /// which makes movement extend the selection.
+    pub fn get(&self, key: KeyEvent) -> Option<(Action, bool)> {
+        let chord = Chord::new(key.code, key.modifiers);
+        if let Some(&action) = self.bindings.get(&chord) {
+            return Some((action, false));
+        }
+        if !chord.modifiers.contains(KeyModifiers::SHIFT) {
+            return None;
+        }
+        let unshifted = Chord { code: chord.code, modifiers: chord.modifiers - KeyModifiers::SHIFT };
+        self.bindings.get(&unshifted).map(|&action| (action, true))
+    }
+
+    /// Adds the bindings in `text`, one `<chord> <action>` per line. An action bound in `text`
+    /// loses the chords it was bound to before. Returns a description of each line that could not
+    /// be read.
+    fn bind(&mut self, text: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut rebound = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let result = match line.split_once(' ') {
+                Some((chord, name)) => self.bind_chord(chord, name.trim(), &mut rebound),
+                None => Err("expected a key and an action".to_string()),
+            };
+            if let Err(err) = result {
+                errors.push(format!("line {}: {}", number + 1, err));
+            }
+        }
+        errors
+    }
+
+    /// Adds bindings of chords to action names, as `bind` does for the lines of a keymap file.
+    /// Returns a description of each binding that could not be made.
+    pub fn bind_pairs<'a>(&mut self, bindings: impl IntoIterator<Item=(&'a str, &'a str)>) -> Vec<String> {
+        let mut rebound = Vec::new();
+        bindings
+            .into_iter()
+            .filter_map(|(chord, name)| self.bind_chord(chord, name, &mut rebound).err())
+            .collect()
+    }
+
+    /// Binds `chord` to the action called `name`. The first time an action is bound among the
+    /// bindings `rebound` keeps track of, it loses the chords it was bound to before.
+    fn bind_chord(&mut self, chord: &str, name: &str, rebound: &mut Vec<Action>) -> Result<(), String> {
+        let Some(parsed) = Chord::parse(chord) else {
+            return Err(format!("unknown key {}", chord));
+        };
+        let Some(&(_, action)) = ACTION_NAMES.iter().find(|(action_name, _)| *action_name == name) else {
+            return Err(format!("unknown action {}", name));
+        };
+        if !rebound.contains(&action) {
+            rebound.push(action);
+            self.bindings.retain(|_, bound| *bound != action);
+        }
+        self.bindings.insert(parsed, action);
+        Ok(())
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Keymap { bindings: HashMap::new() };
+        keymap.bind(DEFAULT_KEYMAP);
+        keymap
+    }
+}
+
+/// Reads key bindings from `filename` on top of `keymap`, so that the file only needs to list the
+/// actions it moves. A missing file leaves `keymap` as it is. Also returns a description of each
+/// line that could not be read.
+pub fn read_keymap(filename: &str, mut keymap: Keymap) -> (Keymap, Vec<String>) {
+    let errors = read_to_string(filename)
+        .map(|text| keymap.bind(&text))
+        .unwrap_or_default();
+    (keymap, errors)
+}
@@ -0,0 +1,35 @@
+use ratatui::crossterm::event::KeyEvent;
+
+/// Keystroke macros stored in ten slots, one for each number key.
+#[derive(Default)]
+pub struct Macros {
+    slots: [Vec<KeyEvent>; 10],
+    /// The slot keys are being recorded into.
+    pub recording: Option<usize>,
+    /// Set after the record key is pressed, until the slot number is chosen.
+    pub armed: bool,
+}
+
+impl Macros {
+    /// Starts recording into `slot`, replacing whatever it held.
+    pub fn start(&mut self, slot: usize) {
+        self.armed = false;
+        self.slots[slot].clear();
+        self.recording = Some(slot);
+    }
+
+    /// Stops recording, returning the slot that was recorded and how many keys it holds.
+    pub fn stop(&mut self) -> Option<(usize, usize)> {
+        self.recording.take().map(|slot| (slot, self.slots[slot].len()))
+    }
+
+    pub fn record(&mut self, key: KeyEvent) {
+        if let Some(slot) = self.recording {
+            self.slots[slot].push(key);
+        }
+    }
+
+    pub fn keys(&self, slot: usize) -> Vec<KeyEvent> {
+        self.slots[slot].clone()
+    }
+}
@@ -0,0 +1,273 @@
+mod app;
+mod browser;
+mod clipboard;
+mod config;
+mod keymap;
+mod macros;
+mod picker;
+mod prompt;
+mod reader;
+mod theme;
+mod trails;
+mod view;
+mod vim;
+
+use std::io;
+use std::io::{Write, stdout};
+use std::panic;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use ratatui::Terminal;
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::cursor::Show;
+use ratatui::crossterm::event::{
+    self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, MouseEventKind,
+};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    self, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode, is_raw_mode_enabled,
+};
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+use rust_orca::context::Context;
+
+pub use crate::tui::app::App;
+pub use crate::tui::clipboard::SystemClipboard;
+pub use crate::tui::config::apply_config;
+pub use crate::tui::theme::find_theme;
+pub use crate::tui::vim::Vim;
+pub use crate::tui::config::ConfigWatcher;
+pub use crate::tui::view::Snapshot;
+// what the windowed editor draws with, so that it looks and reads the same as the terminal one
+#[cfg(feature = "gui")]
+pub use crate::tui::keymap::Action;
+#[cfg(feature = "gui")]
+pub use crate::tui::theme::THEMES;
+#[cfg(feature = "gui")]
+pub use crate::tui::view::{METER_DECAY, cell, help_lines, port_cells, status_text};
+use crate::tui::reader::Announcer;
+
+// how long to wait for input before redrawing, which also bounds the redraw rate
+const FRAME_TIME: Duration = Duration::from_millis(16);
+
+/// Puts the terminal back the way the editor found it: out of raw mode and, if it was entered, the
+/// alternate screen, with mouse reporting and bracketed paste off and the cursor showing. Does nothing once the
+/// terminal has been restored, so it is safe to call from both a panic hook and a drop.
+fn restore_terminal(alternate_screen: bool) {
+    if !is_raw_mode_enabled().unwrap_or(false) {
+        return;
+    }
+    // there is nowhere left to report a failure to restore the terminal
+    let _ = disable_raw_mode();
+    if alternate_screen {
+        let _ = execute!(stdout(), DisableMouseCapture, DisableBracketedPaste, LeaveAlternateScreen, Show);
+    }
+}
+
+/// Restores the terminal when dropped, however the editor exits.
+struct TerminalGuard {
+    alternate_screen: bool,
+}
+
+impl TerminalGuard {
+    /// Sets up a panic hook that restores the terminal before the panic is reported, from whichever
+    /// thread panics, since the report would be unreadable otherwise.
+    fn new(alternate_screen: bool) -> TerminalGuard {
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_terminal(alternate_screen);
+            hook(info);
+        }));
+        TerminalGuard { alternate_screen }
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal(self.alternate_screen);
+    }
+}
+
+/// Brings `view` up to date for drawing `app`'s pane: the newest copy of the grid the engine sent
+/// after a tick or, while it is paused and the lock can't hold up the clock, a copy taken from the
+/// context itself. Gives `None` if the engine panicked holding the context.
+pub fn refresh_view<'a>(view: &'a mut Option<Context>, context_arc: &Mutex<Context>, app: &App) -> Option<&'a Context> {
+    if let Some(newest) = app.views.as_ref().and_then(|views| views.try_iter().last()) {
+        *view = Some(newest);
+    } else if view.is_none() || app.views.is_none() || app.transport.is_paused() {
+        *view = Some(context_arc.lock().ok()?.view());
+    }
+    view.as_ref()
+}
+
+/// Gives every pane the operator symbols of the pane at `from` once it has remapped one, since
+/// the engine runs every grid with the same operator tables.
+pub fn share_remaps(panes: &mut [(Arc<Mutex<Context>>, App)], from: usize) {
+    if !std::mem::take(&mut panes[from].1.remapped) {
+        return;
+    }
+    let (operator_map, remaps) = (panes[from].1.operator_map.clone(), panes[from].1.operator_remaps.clone());
+    for (i, (_, app)) in panes.iter_mut().enumerate() {
+        if i != from {
+            app.operator_remaps.clone_from(&remaps);
+            app.set_operator_map(operator_map.clone());
+        }
+    }
+}
+
+/// Runs the interactive editor until the user quits or the engine stops, with each grid and its
+/// editor state shown in a pane of its own side by side. Keys go to the focused pane; clicking a
+/// pane focuses it.
+pub fn run(mut panes: Vec<(Arc<Mutex<Context>>, App)>) -> io::Result<()> {
+    let _guard = TerminalGuard::new(true);
+    enable_raw_mode()?;
+    // bracketed paste hands over text pasted into the terminal in one piece, instead of as keys
+    execute!(stdout(), EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+    let mut focus = 0;
+    let mut config = ConfigWatcher::new();
+    let mut views: Vec<Option<Context>> = panes.iter().map(|_| None).collect();
+
+    while !panes.iter().any(|(_, app)| app.quit || app.transport.is_stopped()) {
+        // pick up edits to the keymap and theme without interrupting playback
+        if config.changed() {
+            for (_, app) in &mut panes {
+                let errors = apply_config(app);
+                app.message = if errors.is_empty() { "reloaded config".to_string() } else { errors.join(", ") };
+            }
+        }
+
+        // panes are a column apart
+        let size = terminal.size()?;
+        let areas = Layout::horizontal(vec![Constraint::Fill(1); panes.len()])
+            .spacing(1)
+            .split(Rect::new(0, 0, size.width, size.height));
+        // a replayed session is replayed on the first grid
+        panes[0].1.follow_replay();
+        share_remaps(&mut panes, 0);
+        let mut snapshots = Vec::with_capacity(panes.len());
+        for (((context_arc, app), area), view) in panes.iter_mut().zip(areas.iter()).zip(&mut views) {
+            // the size is checked every frame, so a resized terminal is reflowed on the next one
+            // without the engine noticing
+            app.fit(area.height as usize, area.width as usize);
+            // the context is only poisoned if the engine panicked mid-tick, and the editor goes
+            // down along with it
+            let Some(context) = refresh_view(view, context_arc, app) else {
+                return Ok(());
+            };
+            app.catch_up(context);
+            app.check_problems(context);
+            let snapshot = Snapshot::new(context, app);
+            app.trails.update(&snapshot.grid, snapshot.ticks);
+            if let Some(since) = app.recording_since {
+                app.recording.capture(since.elapsed(), &snapshot.grid);
+            }
+            snapshots.push(snapshot);
+        }
+        terminal.draw(|frame| {
+            for (i, ((_, app), snapshot)) in panes.iter().zip(&snapshots).enumerate() {
+                view::draw(frame, areas[i], app, snapshot, i == focus);
+            }
+        })?;
+
+        if event::poll(FRAME_TIME)? {
+            match event::read()? {
+                Event::Key(key) => {
+                    let count = panes.len();
+                    let (context_arc, app) = &mut panes[focus];
+                    let Ok(mut context) = context_arc.lock() else {
+                        return Ok(());
+                    };
+                    app.handle_key(key, &mut context);
+                    app.update_journal(&context);
+                    // edits show at once rather than on the next tick
+                    views[focus] = Some(context.view());
+                    let switch_pane = std::mem::take(&mut app.switch_pane);
+                    drop(context);
+                    share_remaps(&mut panes, focus);
+                    if switch_pane {
+                        focus = (focus + 1) % count;
+                    }
+                }
+                Event::Paste(text) => {
+                    let (context_arc, app) = &mut panes[focus];
+                    let Ok(mut context) = context_arc.lock() else {
+                        return Ok(());
+                    };
+                    app.paste_text(&text, &mut context);
+                    app.update_journal(&context);
+                    views[focus] = Some(context.view());
+                }
+                Event::Mouse(mut mouse) => {
+                    if let Some(pane) = areas.iter().position(|area| area.contains(Position::new(mouse.column, mouse.row))) {
+                        if let MouseEventKind::Down(_) = mouse.kind {
+                            focus = pane;
+                        }
+                        mouse.column -= areas[pane].x;
+                        panes[pane].1.handle_mouse(mouse);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    // quitting is a clean exit, unlike the engine going down
+    if panes.iter().any(|(_, app)| app.quit) {
+        for (_, app) in &mut panes {
+            app.journal.discard();
+        }
+    }
+    Ok(())
+}
+
+/// Runs the editor without drawing the grid, for use with a screen reader: instead of redrawing
+/// the screen, each key press prints a line for every change it made to the cursor position, the
+/// cell under the cursor, the selection, the editing mode, the command being typed or the status
+/// message.
+pub fn run_screen_reader(context_arc: Arc<Mutex<Context>>, mut app: App) -> io::Result<()> {
+    let _guard = TerminalGuard::new(false);
+    enable_raw_mode()?;
+    let mut announcer = Announcer::default();
+    let mut config = ConfigWatcher::new();
+    let mut view = None;
+
+    let mut out = stdout();
+    while !app.quit && !app.transport.is_stopped() {
+        if config.changed() {
+            let errors = apply_config(&mut app);
+            app.message = if errors.is_empty() { "reloaded config".to_string() } else { errors.join(", ") };
+        }
+        // paging still moves by a screen's worth of cells
+        let (width, height) = terminal::size()?;
+        app.follow_cursor(height as usize, width as usize);
+        let Some(context) = refresh_view(&mut view, &context_arc, &app) else {
+            return Ok(());
+        };
+        app.follow_replay();
+        app.catch_up(context);
+        let lines = announcer.announce(context, &app);
+        for line in lines {
+            // raw mode leaves carriage returns to us
+            write!(out, "{}\r\n", line)?;
+        }
+        out.flush()?;
+
+        // wake up now and then to notice config changes
+        if !event::poll(FRAME_TIME)? {
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            let Ok(mut context) = context_arc.lock() else {
+                return Ok(());
+            };
+            app.handle_key(key, &mut context);
+            app.update_journal(&context);
+            view = Some(context.view());
+        }
+    }
+    // quitting is a clean exit, unlike the engine going down
+    if app.quit {
+        app.journal.discard();
+    }
+    Ok(())
+}
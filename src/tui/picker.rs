@@ -0,0 +1,40 @@
+use rust_orca::operators::{Operator, OperatorTable};
+
+/// The operator picker: a search box over the operator glossary.
+#[derive(Default)]
+pub struct Picker {
+    pub query: String,
+    pub selected: usize,
+}
+
+impl Picker {
+    /// The operators matching the query, best matches first: the operator whose symbol is the
+    /// query, then names starting with it, then names containing its letters in order.
+    pub fn matches<'a>(&self, operators: &'a OperatorTable) -> Vec<(char, &'a Operator)> {
+        let query = self.query.to_lowercase();
+        let mut matches: Vec<(usize, char, &Operator)> = operators.iter().filter_map(|(symbol, operator)| {
+            let name = operator.name().to_lowercase();
+            let rank = if query.chars().eq(symbol.to_lowercase()) {
+                0
+            } else if name.starts_with(&query) {
+                1
+            } else if is_subsequence(&query, &name) {
+                2
+            } else {
+                return None;
+            };
+            Some((rank, symbol, operator))
+        }).collect();
+        matches.sort_by_key(|&(rank, _, _)| rank);
+        matches.into_iter().map(|(_, symbol, operator)| (symbol, operator)).collect()
+    }
+
+    pub fn select(&mut self, offset: isize, count: usize) {
+        self.selected = self.selected.saturating_add_signed(offset).min(count.saturating_sub(1));
+    }
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut haystack = haystack.chars();
+    needle.chars().all(|c| haystack.any(|h| h == c))
+}
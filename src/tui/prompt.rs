@@ -0,0 +1,72 @@
+use crate::commands::complete;
+
+/// The text being typed into the command line, along with previously entered commands.
+#[derive(Default)]
+pub struct CommandLine {
+    pub active: bool,
+    pub text: String,
+    history: Vec<String>,
+    /// Index into `history` of the entry being shown, or `history.len()` for a fresh line.
+    position: usize,
+}
+
+impl CommandLine {
+    pub fn open(&mut self) {
+        self.active = true;
+        self.text.clear();
+        self.position = self.history.len();
+    }
+
+    /// Opens the line with the start of a command already typed.
+    pub fn open_with(&mut self, text: &str) {
+        self.open();
+        self.text.push_str(text);
+    }
+
+    pub fn close(&mut self) {
+        self.active = false;
+        self.text.clear();
+    }
+
+    /// Closes the line and returns what was typed, remembering it in the history.
+    pub fn submit(&mut self) -> String {
+        let text = std::mem::take(&mut self.text);
+        if !text.trim().is_empty() && self.history.last() != Some(&text) {
+            self.history.push(text.clone());
+        }
+        self.active = false;
+        text
+    }
+
+    pub fn previous(&mut self) {
+        if self.position > 0 {
+            self.position -= 1;
+            self.text = self.history[self.position].clone();
+        }
+    }
+
+    pub fn next(&mut self) {
+        if self.position < self.history.len() {
+            self.position += 1;
+            self.text = self.history.get(self.position).cloned().unwrap_or_default();
+        }
+    }
+
+    /// Completes the command name being typed as far as it is unambiguous, returning the
+    /// candidates when there is more than one.
+    pub fn complete(&mut self) -> Vec<&'static str> {
+        if self.text.contains(' ') {
+            return Vec::new();
+        }
+        let candidates = complete(&self.text);
+        if let [name] = candidates[..] {
+            self.text = format!("{} ", name);
+        } else if let Some(first) = candidates.first() {
+            let common = candidates.iter().fold(first.len(), |len, name| {
+                first.chars().zip(name.chars()).take(len).take_while(|(a, b)| a == b).count()
+            });
+            self.text = first[..common].to_string();
+        }
+        candidates
+    }
+}
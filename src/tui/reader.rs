@@ -0,0 +1,106 @@
+use rust_orca::context::Context;
+
+use crate::tui::App;
+
+/// Describes what changed in the editor since it was last asked, one line per change, for the
+/// screen reader mode. Only the editor's own state is followed, not the running grid, so that a
+/// busy patch does not drown out everything else.
+#[derive(Default)]
+pub struct Announcer {
+    started: bool,
+    cursor: (usize, usize),
+    cell: char,
+    selection: Option<(usize, usize)>,
+    insert_mode: bool,
+    vim_insert: bool,
+    command_line: bool,
+    command_text: String,
+    message: String,
+}
+
+impl Announcer {
+    pub fn announce(&mut self, context: &Context, app: &App) -> Vec<String> {
+        let mut lines = Vec::new();
+        let first = !std::mem::replace(&mut self.started, true);
+        if first {
+            let name = app.path.as_ref().map_or("untitled".to_string(), |path| path.display().to_string());
+            lines.push(format!("{}, {} by {}, {} bpm", name, app.cols, app.rows, context.tempo));
+        }
+
+        let cursor = (app.cursor_row, app.cursor_col);
+        let cell = context.read(cursor.0 as i32, cursor.1 as i32);
+        if first || cursor != self.cursor {
+            lines.push(format!("row {} column {}: {}", cursor.0, cursor.1, describe(cell, app)));
+        } else if cell != self.cell {
+            lines.push(format!("now {}", describe(cell, app)));
+        }
+        self.cursor = cursor;
+        self.cell = cell;
+
+        let selection = app.anchor.map(|_| {
+            let selection = app.selection();
+            (selection.cols, selection.rows)
+        });
+        if selection != self.selection {
+            lines.push(match selection {
+                Some((cols, rows)) => format!("selected {} by {}", cols, rows),
+                None => "selection cleared".to_string(),
+            });
+            self.selection = selection;
+        }
+
+        if app.insert_mode != self.insert_mode {
+            self.insert_mode = app.insert_mode;
+            lines.push(format!("insert mode {}", if app.insert_mode { "on" } else { "off" }));
+        }
+        let vim_insert = app.vim.as_ref().is_some_and(|vim| vim.insert);
+        if vim_insert != self.vim_insert {
+            self.vim_insert = vim_insert;
+            lines.push(if vim_insert { "insert" } else { "normal" }.to_string());
+        }
+
+        if app.command_line.active != self.command_line {
+            self.command_line = app.command_line.active;
+            if app.command_line.active {
+                lines.push(format!("command {}", app.command_line.text).trim_end().to_string());
+            }
+            self.command_text.clone_from(&app.command_line.text);
+        }
+        // raw mode leaves nothing echoed as it is typed, so each change to the command is read out
+        let text = &app.command_line.text;
+        if app.command_line.active && *text != self.command_text {
+            if let Some(typed) = text.strip_prefix(self.command_text.as_str()) {
+                lines.push(spoken(typed));
+            } else if let Some(deleted) = self.command_text.strip_prefix(text.as_str()) {
+                lines.push(format!("deleted {}", spoken(deleted)));
+            } else {
+                // a command from the history or a completion replaces the whole line
+                lines.push(text.clone());
+            }
+            self.command_text.clone_from(text);
+        }
+        if app.message != self.message {
+            if !app.message.is_empty() {
+                lines.push(app.message.clone());
+            }
+            self.message = app.message.clone();
+        }
+        lines
+    }
+}
+
+/// Reads out a cell's value, along with the name of the operator it holds if it holds one.
+fn describe(cell: char, app: &App) -> String {
+    if cell == '\0' {
+        return "empty".to_string();
+    }
+    match app.tick_operators.get(cell).or_else(|| app.bang_operators.get(cell)) {
+        Some(operator) => format!("{} {}", cell, operator.name()),
+        None => cell.to_string(),
+    }
+}
+
+/// Reads out typed text, naming a space that would otherwise go unheard.
+fn spoken(text: &str) -> String {
+    if text == " " { "space".to_string() } else { text.to_string() }
+}
@@ -0,0 +1,131 @@
+use std::fs::read_to_string;
+
+use ratatui::style::Color;
+
+use crate::tui::trails::TRAIL_TICKS;
+
+/// The colors the editor is drawn with.
+#[derive(Clone)]
+pub struct Theme {
+    pub name: &'static str,
+    /// Background of the row and column that run through the cursor.
+    pub crosshair: Color,
+    pub selection: Color,
+    /// Backgrounds for the inputs, outputs and other locked cells of the operator under the cursor.
+    pub input: Color,
+    pub output: Color,
+    pub lock: Color,
+    /// Backgrounds for cells that recently held a bang and that recently changed, brightest first.
+    pub bang_trail: [Color; TRAIL_TICKS],
+    pub change_trail: [Color; TRAIL_TICKS],
+    /// MIDI meter bars for channels with notes sounding and for quiet ones.
+    pub meter_sounding: Color,
+    pub meter_quiet: Color,
+    /// Whether empty cells and their guide markers are drawn dimmed.
+    pub dim_empty: bool,
+}
+
+pub const THEMES: [Theme; 3] = [
+    Theme {
+        name: "default",
+        crosshair: Color::Indexed(235),
+        selection: Color::Blue,
+        input: Color::Cyan,
+        output: Color::White,
+        lock: Color::DarkGray,
+        bang_trail: [Color::Indexed(220), Color::Indexed(178), Color::Indexed(136), Color::Indexed(94)],
+        change_trail: [Color::Indexed(240), Color::Indexed(239), Color::Indexed(238), Color::Indexed(237)],
+        meter_sounding: Color::Green,
+        meter_quiet: Color::DarkGray,
+        dim_empty: true,
+    },
+    // for low vision: nothing is dimmed, and every highlight is a bright color that black text
+    // stands out against
+    Theme {
+        name: "high-contrast",
+        crosshair: Color::Indexed(238),
+        selection: Color::Magenta,
+        input: Color::Yellow,
+        output: Color::White,
+        lock: Color::Indexed(250),
+        bang_trail: [Color::White, Color::Indexed(252), Color::Indexed(248), Color::Indexed(244)],
+        change_trail: [Color::Indexed(246), Color::Indexed(244), Color::Indexed(242), Color::Indexed(240)],
+        meter_sounding: Color::White,
+        meter_quiet: Color::Indexed(244),
+        dim_empty: false,
+    },
+    // for deuteranopia and protanopia: the Okabe-Ito blues, oranges and yellows, which stay
+    // distinct without telling red from green
+    Theme {
+        name: "colorblind",
+        crosshair: Color::Indexed(235),
+        selection: Color::Indexed(25),
+        input: Color::Indexed(74),
+        output: Color::Indexed(214),
+        lock: Color::DarkGray,
+        bang_trail: [Color::Indexed(227), Color::Indexed(185), Color::Indexed(143), Color::Indexed(101)],
+        change_trail: [Color::Indexed(240), Color::Indexed(239), Color::Indexed(238), Color::Indexed(237)],
+        meter_sounding: Color::Indexed(74),
+        meter_quiet: Color::DarkGray,
+        dim_empty: true,
+    },
+];
+
+/// Finds a built-in theme by name.
+pub fn find_theme(name: &str) -> Result<&'static Theme, String> {
+    THEMES.iter().find(|theme| theme.name == name).ok_or_else(|| {
+        let names: Vec<&str> = THEMES.iter().map(|theme| theme.name).collect();
+        format!("unknown theme {}, try {}", name, names.join(", "))
+    })
+}
+
+/// Reads color settings from `filename` on top of `base`, one `<setting> <value>` per line. A
+/// `theme <name>` line starts over from that built-in theme; the other settings are `crosshair`,
+/// `selection`, `input`, `output`, `lock`, `meter-sounding` and `meter-quiet`, which take a color
+/// name, a 256-color index or a `#rrggbb` value, and `dim-empty`, which takes `yes` or `no`. A
+/// missing file leaves `base` as it is. Also returns a description of each line that could not be
+/// read.
+pub fn read_theme(filename: &str, base: &Theme) -> (Theme, Vec<String>) {
+    let mut theme = base.clone();
+    let mut errors = Vec::new();
+    let text = read_to_string(filename).unwrap_or_default();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (setting, value) = line.split_once(' ').unwrap_or((line, ""));
+        if let Err(err) = apply_setting(&mut theme, setting, value.trim()) {
+            errors.push(format!("line {}: {}", number + 1, err));
+        }
+    }
+    (theme, errors)
+}
+
+/// Changes one setting of `theme`, taking the same settings and values as a theme file.
+pub fn apply_setting(theme: &mut Theme, setting: &str, value: &str) -> Result<(), String> {
+    let slot = match setting {
+        "theme" => {
+            *theme = find_theme(value)?.clone();
+            return Ok(());
+        }
+        "dim-empty" => {
+            theme.dim_empty = match value {
+                "yes" => true,
+                "no" => false,
+                _ => return Err(format!("dim-empty takes yes or no, not {}", value)),
+            };
+            return Ok(());
+        }
+        "crosshair" => &mut theme.crosshair,
+        "selection" => &mut theme.selection,
+        "input" => &mut theme.input,
+        "output" => &mut theme.output,
+        "lock" => &mut theme.lock,
+        "meter-sounding" => &mut theme.meter_sounding,
+        "meter-quiet" => &mut theme.meter_quiet,
+        _ => return Err(format!("unknown setting {}", setting)),
+    };
+    *slot = value.parse::<Color>().map_err(|_| format!("unknown color {}", value))?;
+    Ok(())
+}
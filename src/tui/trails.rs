@@ -0,0 +1,52 @@
+/// Remembers when each cell last changed and last held a bang, so that recent activity can be
+/// drawn fading out over a few ticks.
+#[derive(Default)]
+pub struct Trails {
+    previous: Vec<char>,
+    changed_at: Vec<usize>,
+    banged_at: Vec<usize>,
+}
+
+/// How many ticks a highlight takes to fade out.
+pub const TRAIL_TICKS: usize = 4;
+
+impl Trails {
+    /// Compares `grid` with the grid seen on the last call, stamping cells that differ (and cells
+    /// holding bangs) with the current tick.
+    pub fn update(&mut self, grid: &[char], ticks: usize) {
+        if self.previous.len() != grid.len() {
+            // start from a quiet state rather than flashing the whole grid
+            self.previous = grid.to_vec();
+            self.changed_at = vec![usize::MAX; grid.len()];
+            self.banged_at = vec![usize::MAX; grid.len()];
+            return;
+        }
+        for (index, (&value, previous)) in grid.iter().zip(self.previous.iter_mut()).enumerate() {
+            if value != *previous {
+                self.changed_at[index] = ticks;
+                *previous = value;
+            }
+            if value == '*' {
+                self.banged_at[index] = ticks;
+            }
+        }
+    }
+
+    /// How strongly the cell at `index` changed recently, from `TRAIL_TICKS` on the tick it changed
+    /// down to 0 once it has faded.
+    pub fn change_level(&self, index: usize, ticks: usize) -> usize {
+        level(self.changed_at.get(index).copied(), ticks)
+    }
+
+    /// How recently the cell at `index` held a bang, on the same scale as `change_level`.
+    pub fn bang_level(&self, index: usize, ticks: usize) -> usize {
+        level(self.banged_at.get(index).copied(), ticks)
+    }
+}
+
+fn level(at: Option<usize>, ticks: usize) -> usize {
+    match at {
+        Some(at) if at <= ticks => TRAIL_TICKS.saturating_sub(ticks - at),
+        _ => 0,
+    }
+}
@@ -0,0 +1,486 @@
+use std::time::Duration;
+
+use ratatui::Frame;
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Position, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Clear, Paragraph};
+
+use rust_orca::context::{Context, Selection};
+use rust_orca::notes::cell_note_name;
+use rust_orca::operators::PortLayout;
+
+use crate::tui::App;
+use crate::tui::browser::Browser;
+use crate::tui::picker::Picker;
+use crate::tui::app::{EVENT_LOG_HEIGHT, MINIMAP_WIDTH};
+use crate::tui::trails::TRAIL_TICKS;
+
+// how long a channel's meter takes to fall back to nothing after a note starts
+pub const METER_DECAY: Duration = Duration::from_millis(400);
+const METER_BARS: [char; 9] = ['·', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const METER_WIDTH: u16 = 16;
+// the status bar counts bars as if every piece were in 4/4
+const BEATS_PER_BAR: usize = 4;
+
+/// What the editor needs from the shared context to draw a frame, copied out so that the lock is
+/// not held while drawing.
+pub struct Snapshot {
+    pub grid: Vec<char>,
+    pub ports: Option<PortLayout>,
+    /// The name and ports of the operator under the cursor, spelled out for the status bar.
+    pub hover: Option<String>,
+    /// How the cell under the cursor came to hold what it does, while the editor explains cells.
+    pub explain: Option<Vec<String>>,
+    pub ticks: usize,
+    pub tempo: u64,
+    pub divisions: u64,
+    /// How many edits are waiting for the next launch.
+    pub queued: usize,
+}
+
+impl Snapshot {
+    pub fn new(context: &Context, app: &App) -> Snapshot {
+        let ports = app.cursor_ports(context);
+        let hover = app.cursor_operator(context).zip(ports.as_ref()).map(|(operator, ports)| {
+            describe_ports(operator.name(), app.cursor_row as i32, app.cursor_col as i32, ports)
+        });
+        Snapshot {
+            grid: context.grid().to_vec(),
+            ports,
+            hover,
+            explain: app.explain.then(|| context.trace.explain(app.cursor_row as i32, app.cursor_col as i32)),
+            ticks: context.ticks,
+            tempo: context.tempo,
+            divisions: context.divisions,
+            queued: context.launches.len(),
+        }
+    }
+}
+
+/// Spells out an operator's ports by where they are relative to it, for example
+/// `Add — a:left b:right out:below`.
+fn describe_ports(name: &str, row: i32, col: i32, ports: &PortLayout) -> String {
+    let inputs = ports.inputs.iter().map(|port| (port, "in"));
+    let outputs = ports.outputs.iter().map(|port| (port, "out"));
+    let mut text = format!("{} —", name);
+    for (port, role) in inputs.chain(outputs) {
+        let name = if port.name.is_empty() { role } else { port.name };
+        text.push_str(&format!(" {}:{}", name, direction(port.row - row, port.col - col)));
+    }
+    // a note is named by the pitch it plays rather than left as its cells' characters
+    let input = |name| ports.inputs.iter().find(|port| port.name == name).map(|port| port.value);
+    if let Some(pitch) = input("octave").zip(input("note")).and_then(|(octave, note)| cell_note_name(octave, note)) {
+        text.push_str(&format!(" — plays {}", pitch));
+    }
+    text
+}
+
+/// Names an offset from a cell, like `left`, `below` or `2 right`.
+fn direction(rows: i32, cols: i32) -> String {
+    let part = |distance: i32, negative: &str, positive: &str| {
+        let word = if distance < 0 { negative } else { positive };
+        match distance.abs() {
+            0 => None,
+            1 => Some(word.to_string()),
+            distance => Some(format!("{} {}", distance, word)),
+        }
+    };
+    let parts: Vec<String> = [part(rows, "above", "below"), part(cols, "left", "right")].into_iter().flatten().collect();
+    if parts.is_empty() { "here".to_string() } else { parts.join(" ") }
+}
+
+/// Draws one grid's pane into `area`. Only the focused pane shows the terminal cursor and a
+/// highlighted status bar.
+pub fn draw(frame: &mut Frame, area: Rect, app: &App, snapshot: &Snapshot, focused: bool) {
+    // the bottom line is kept for the status bar
+    let grid_height = area.height.saturating_sub(1);
+    let selection = app.anchor.map(|_| app.selection());
+    let buffer = frame.buffer_mut();
+    let visible_rows = app.scroll_row..(app.scroll_row + grid_height as usize).min(app.rows);
+    let visible_cols = app.scroll_col..(app.scroll_col + app.view_cols).min(app.cols);
+    for r in visible_rows {
+        for c in visible_cols.clone() {
+            let (display_value, style) = cell(app, snapshot, selection, r, c);
+            if let Some(cell) = screen_position(area, app, r as i32, c as i32).and_then(|position| buffer.cell_mut(position)) {
+                cell.set_char(display_value).set_style(style);
+            }
+        }
+    }
+
+    for (row, col, color) in port_cells(app, snapshot) {
+        if let Some(cell) = screen_position(area, app, row, col).and_then(|position| buffer.cell_mut(position)) {
+            cell.set_bg(color).set_fg(Color::Black);
+        }
+    }
+
+    if app.minimap_fits {
+        draw_minimap(buffer, area, app, snapshot);
+    }
+    if app.event_panel_fits {
+        draw_event_log(buffer, area, app);
+    }
+    if let Some(lines) = &snapshot.explain {
+        draw_explain(frame, area, lines);
+    }
+    let buffer = frame.buffer_mut();
+    draw_status(buffer, area, app, snapshot, focused);
+    if let Some(scroll) = app.help {
+        draw_help(frame, area, app, scroll);
+    }
+    if let Some(browser) = &app.browser {
+        draw_browser(frame, area, browser);
+    }
+    if let Some(picker) = &app.picker {
+        draw_picker(frame, area, app, picker);
+    }
+    let buffer = frame.buffer_mut();
+
+    for (row, col) in app.cursor_cells() {
+        if let Some(cell) = screen_position(area, app, row, col).and_then(|position| buffer.cell_mut(position)) {
+            cell.set_style(Style::new().add_modifier(Modifier::REVERSED));
+        }
+    }
+    if !focused {
+        return;
+    }
+    if app.command_line.active {
+        let column = 1 + app.command_line.text.chars().count() as u16;
+        frame.set_cursor_position(Position::new(
+            area.x + column.min(area.width.saturating_sub(1)),
+            area.y + area.height.saturating_sub(1),
+        ));
+    } else if let Some(cursor) = screen_position(area, app, app.cursor_row as i32, app.cursor_col as i32) {
+        frame.set_cursor_position(cursor);
+    }
+}
+
+/// What the cell at `(r, c)` shows and how it is styled, before the ports of the operator under
+/// the cursor and the cursors themselves are marked over it.
+pub fn cell(app: &App, snapshot: &Snapshot, selection: Option<Selection>, r: usize, c: usize) -> (char, Style) {
+    let theme = &app.theme;
+    let value = snapshot.grid[r * app.cols + c];
+    let on_row = app.ruler.rows > 0 && r.is_multiple_of(app.ruler.rows);
+    let on_col = app.ruler.cols > 0 && c.is_multiple_of(app.ruler.cols);
+    let display_value = if value != '\0' {
+        value
+    } else if on_row && on_col {
+        '+'
+    } else if (on_row || on_col) && (r == app.cursor_row || c == app.cursor_col) {
+        // ticks along the crosshair make it easy to count out distances from the cursor
+        '·'
+    } else {
+        ' '
+    };
+    let mut style = if value == '*' {
+        Style::new().add_modifier(Modifier::BOLD)
+    } else if value == '\0' && theme.dim_empty {
+        Style::new().add_modifier(Modifier::DIM)
+    } else {
+        Style::new()
+    };
+    let bang_level = app.trails.bang_level(r * app.cols + c, snapshot.ticks);
+    let change_level = app.trails.change_level(r * app.cols + c, snapshot.ticks);
+    if bang_level > 0 {
+        style = style.bg(theme.bang_trail[TRAIL_TICKS - bang_level]).fg(Color::Black);
+    } else if change_level > 0 {
+        style = style.bg(theme.change_trail[TRAIL_TICKS - change_level]);
+    } else if r == app.cursor_row || c == app.cursor_col {
+        style = style.bg(theme.crosshair);
+    }
+    if selection.is_some_and(|selection| selection.contains(r as i32, c as i32)) {
+        style = style.bg(theme.selection);
+    }
+    if app.problems.contains_key(&(r as i32, c as i32)) {
+        style = style.add_modifier(Modifier::UNDERLINED);
+    }
+    (display_value, style)
+}
+
+/// The cells of the ports of the operator under the cursor, each with the background it is
+/// marked with. They are drawn with black text.
+pub fn port_cells<'a>(app: &'a App, snapshot: &'a Snapshot) -> impl Iterator<Item = (i32, i32, Color)> + 'a {
+    let theme = &app.theme;
+    snapshot.ports.iter().flat_map(move |ports| {
+        // outputs come last since an output cell is also locked
+        let locks = ports.locks.iter().map(move |&(row, col)| (row, col, theme.lock));
+        let inputs = ports.inputs.iter().map(move |port| (port.row, port.col, theme.input));
+        let outputs = ports.outputs.iter().map(move |port| (port.row, port.col, theme.output));
+        locks.chain(inputs).chain(outputs)
+    })
+}
+
+/// Where the cell at `(row, col)` appears on screen, or None if it is scrolled out of the part of
+/// `area` the grid is shown in.
+fn screen_position(area: Rect, app: &App, row: i32, col: i32) -> Option<Position> {
+    let y = usize::try_from(row - app.scroll_row as i32).ok().filter(|&y| y < app.view_rows)?;
+    let x = usize::try_from(col - app.scroll_col as i32).ok().filter(|&x| x < app.view_cols)?;
+    Some(Position::new(area.x + x as u16, area.y + y as u16))
+}
+
+/// Draws an overview of the whole grid to the right of it, shading each cell by how full the
+/// part of the grid it covers is and marking the part currently on screen.
+fn draw_minimap(buffer: &mut Buffer, area: Rect, app: &App, snapshot: &Snapshot) {
+    let (scale_rows, scale_cols) = app.minimap_scale();
+    let left = area.x + app.view_cols as u16;
+    let visible_rows = app.scroll_row..app.scroll_row + app.view_rows;
+    let visible_cols = app.scroll_col..app.scroll_col + app.view_cols;
+    for y in 0..app.view_rows {
+        if let Some(cell) = buffer.cell_mut(Position::new(left, area.y + y as u16)) {
+            cell.set_char('│').set_style(Style::new());
+        }
+        for x in 0..MINIMAP_WIDTH - 1 {
+            let rows = (y * scale_rows).min(app.rows)..((y + 1) * scale_rows).min(app.rows);
+            let cols = (x * scale_cols).min(app.cols)..((x + 1) * scale_cols).min(app.cols);
+            if rows.is_empty() || cols.is_empty() {
+                continue;
+            }
+            let mut filled = 0;
+            let mut bang = false;
+            for r in rows.clone() {
+                for &value in &snapshot.grid[r * app.cols + cols.start..r * app.cols + cols.end] {
+                    filled += (value != '\0') as usize;
+                    bang |= value == '*';
+                }
+            }
+            let density = filled as f32 / (rows.len() * cols.len()) as f32;
+            let shade = if bang {
+                '*'
+            } else if filled == 0 {
+                ' '
+            } else if density < 0.25 {
+                '░'
+            } else if density < 0.5 {
+                '▒'
+            } else if density < 0.75 {
+                '▓'
+            } else {
+                '█'
+            };
+            let on_screen = rows.start < visible_rows.end && visible_rows.start < rows.end
+                && cols.start < visible_cols.end && visible_cols.start < cols.end;
+            let style = if on_screen { Style::new().bg(app.theme.crosshair) } else { Style::new() };
+            if let Some(cell) = buffer.cell_mut(Position::new(left + 1 + x as u16, area.y + y as u16)) {
+                cell.set_char(shade).set_style(style);
+            }
+        }
+    }
+}
+
+/// Draws the most recent events under the grid, newest at the bottom, each after the tick it
+/// happened on.
+fn draw_event_log(buffer: &mut Buffer, area: Rect, app: &App) {
+    let top = area.y + app.view_rows as u16;
+    let width = area.width as usize;
+    let title = format!("{:─<width$}", "─ events ", width = width);
+    buffer.set_stringn(area.x, top, title, width, Style::new());
+    let events = app.event_log.recent(EVENT_LOG_HEIGHT - 1);
+    for (i, (tick, event)) in events.iter().enumerate() {
+        let line = format!("{:>8} {}", tick, event);
+        buffer.set_stringn(area.x, top + 1 + i as u16, line, width, Style::new());
+    }
+}
+
+/// Draws the explanation of the cell under the cursor at the bottom of the grid, taking up at
+/// most half of it so that the cursor can still be moved around.
+fn draw_explain(frame: &mut Frame, area: Rect, lines: &[String]) {
+    let grid_height = area.height.saturating_sub(1);
+    let height = (lines.len() as u16 + 2).min(grid_height / 2);
+    let explain_area = Rect::new(area.x, area.y + grid_height - height, area.width, height);
+    let lines: Vec<Line> = lines.iter().map(|line| Line::from(line.as_str())).collect();
+    let block = Block::bordered().title(" explain (:explain to close) ");
+    frame.render_widget(Clear, explain_area);
+    frame.render_widget(Paragraph::new(lines).block(block), explain_area);
+}
+
+/// The operator glossary, a line at a time: each operator's port diagram next to its symbols,
+/// name and description, with a blank line after each.
+pub fn help_lines(app: &App) -> Vec<String> {
+    let mut lines = Vec::new();
+    for info in app.tick_operators.registry() {
+        let symbol = info.symbols[0];
+        let Some(operator) = app.tick_operators.get(symbol) else {
+            continue;
+        };
+        let diagram = operator.diagram(symbol);
+        // the description goes on the operator's own row, after every symbol bound to it
+        let symbols: Vec<String> = info.symbols.iter().map(char::to_string).collect();
+        let operator_row = diagram.iter().position(|row| row.contains(symbol)).unwrap_or(0);
+        for (i, row) in diagram.iter().enumerate() {
+            let text = if i == operator_row {
+                format!("{:<12}{} {}: {}", row, symbols.join(" "), info.name, info.description)
+            } else {
+                row.clone()
+            };
+            lines.push(text);
+        }
+        lines.push(String::new());
+    }
+    lines
+}
+
+/// Draws the operator glossary over the grid: each operator's port diagram next to its symbol,
+/// name and description.
+fn draw_help(frame: &mut Frame, area: Rect, app: &App, scroll: usize) {
+    let lines: Vec<Line> = help_lines(app).into_iter().map(Line::from).collect();
+    let help_area = Rect::new(
+        area.x + 2,
+        area.y + 1,
+        area.width.saturating_sub(4),
+        area.height.saturating_sub(3),
+    );
+    let scroll = scroll.min(lines.len().saturating_sub(1)) as u16;
+    let block = Block::bordered().title(" operators: i input, o output, - locked (F1 or Esc to close) ");
+    frame.render_widget(Clear, help_area);
+    frame.render_widget(Paragraph::new(lines).block(block).scroll((scroll, 0)), help_area);
+}
+
+/// Draws the open dialog over the grid, with the recent files above the ones in the working
+/// directory.
+fn draw_browser(frame: &mut Frame, area: Rect, browser: &Browser) {
+    let mut lines = Vec::new();
+    let mut selected_line: usize = 0;
+    for (i, path) in browser.entries.iter().enumerate() {
+        if i == 0 && browser.recent > 0 {
+            lines.push(Line::styled("recent", Style::new().add_modifier(Modifier::BOLD)));
+        }
+        if i == browser.recent {
+            lines.push(Line::styled("in this directory", Style::new().add_modifier(Modifier::BOLD)));
+        }
+        if i == browser.selected {
+            selected_line = lines.len();
+        }
+        let style = if i == browser.selected { Style::new().add_modifier(Modifier::REVERSED) } else { Style::new() };
+        lines.push(Line::styled(format!("  {}", path.display()), style));
+    }
+    if browser.entries.is_empty() {
+        lines.push(Line::from("no .orca files here"));
+    }
+    let browser_area = Rect::new(
+        area.x + 2,
+        area.y + 1,
+        area.width.saturating_sub(4),
+        area.height.saturating_sub(3),
+    );
+    // keep the selected file in view
+    let scroll = (selected_line + 1).saturating_sub(browser_area.height.saturating_sub(2) as usize) as u16;
+    let block = Block::bordered().title(" open (enter to open, esc to close) ");
+    frame.render_widget(Clear, browser_area);
+    frame.render_widget(Paragraph::new(lines).block(block).scroll((scroll, 0)), browser_area);
+}
+
+/// Draws the operator picker over the grid: the search typed so far, then the matching operators.
+fn draw_picker(frame: &mut Frame, area: Rect, app: &App, picker: &Picker) {
+    let mut lines = vec![Line::from(format!("search: {}", picker.query)), Line::default()];
+    let matches = picker.matches(&app.tick_operators);
+    for (i, (symbol, operator)) in matches.iter().enumerate() {
+        let style = if i == picker.selected { Style::new().add_modifier(Modifier::REVERSED) } else { Style::new() };
+        lines.push(Line::styled(format!("{} {}: {}", symbol, operator.name(), operator.description()), style));
+    }
+    if matches.is_empty() {
+        lines.push(Line::from("no matching operators"));
+    }
+    let picker_area = Rect::new(
+        area.x + 2,
+        area.y + 1,
+        area.width.saturating_sub(4),
+        area.height.saturating_sub(3),
+    );
+    // keep the selected operator in view, below the search line
+    let scroll = (picker.selected + 3).saturating_sub(picker_area.height.saturating_sub(2) as usize) as u16;
+    let block = Block::bordered().title(" operators (enter to place, tab to place with inputs, esc to close) ");
+    frame.render_widget(Clear, picker_area);
+    frame.render_widget(Paragraph::new(lines).block(block).scroll((scroll, 0)), picker_area);
+}
+
+fn draw_status(buffer: &mut Buffer, area: Rect, app: &App, snapshot: &Snapshot, focused: bool) {
+    let Some(row) = area.height.checked_sub(1) else {
+        return;
+    };
+    let line = Rect::new(area.x, area.y + row, area.width, 1);
+    let style = if focused { Style::new().add_modifier(Modifier::REVERSED) } else { Style::new() };
+    buffer.set_style(line, style);
+    if app.command_line.active {
+        buffer.set_stringn(line.x, line.y, format!(":{}", app.command_line.text), line.width as usize, style);
+        return;
+    }
+    let status = status_text(app, snapshot);
+    // on a narrow terminal the meters give way to the status text
+    if line.width >= 2 * METER_WIDTH {
+        buffer.set_stringn(line.x, line.y, status, (line.width - METER_WIDTH - 1) as usize, style);
+        draw_meters(buffer, line, app);
+    } else {
+        buffer.set_stringn(line.x, line.y, status, line.width as usize, style);
+    }
+}
+
+/// The status bar's text: the file, the editing modes, where playback is, the grid's size, the MIDI
+/// port, the operator under the cursor, what the linter found wrong with it and the last message.
+pub fn status_text(app: &App, snapshot: &Snapshot) -> String {
+    let name = app.path.as_ref().map_or("untitled".to_string(), |path| path.display().to_string());
+    format!(
+        "{}{}  {}{}{}{}{}{}{}{}bpm  {}f  {}  {}  {}x{}  {}  {}{}{}",
+        name,
+        if app.dirty { "*" } else { "" },
+        match &app.vim {
+            Some(vim) if vim.insert => "-- INSERT --  ",
+            Some(_) => "-- NORMAL --  ",
+            None => "",
+        },
+        if app.transport.is_paused() { "paused  " } else { "" },
+        if app.insert_mode { "insert  " } else { "" },
+        if app.cursors.is_empty() { String::new() } else { format!("{} cursors  ", app.cursors.len() + 1) },
+        app.macros.recording.map_or(String::new(), |slot| format!("rec {}  ", slot)),
+        if app.recording_since.is_some() { "recording  " } else { "" },
+        if snapshot.queued == 0 { String::new() } else { format!("{} queued  ", snapshot.queued) },
+        snapshot.tempo,
+        snapshot.ticks,
+        bars_and_beats(snapshot.ticks, snapshot.divisions),
+        clock(app.started.elapsed()),
+        app.cols,
+        app.rows,
+        app.midi_device.as_deref().unwrap_or("no midi"),
+        snapshot.hover.as_ref().map_or(String::new(), |hover| format!("{}  ", hover)),
+        app.problems
+            .get(&(app.cursor_row as i32, app.cursor_col as i32))
+            .map_or(String::new(), |problem| format!("⚠ {}  ", problem)),
+        app.message,
+    )
+}
+
+/// Counts out the frame as `bar:beat`, both from 1.
+fn bars_and_beats(ticks: usize, divisions: u64) -> String {
+    let beats = ticks / divisions.max(1) as usize;
+    format!("{}:{}", beats / BEATS_PER_BAR + 1, beats % BEATS_PER_BAR + 1)
+}
+
+/// Formats a length of time like a clock, as `m:ss`, or `h:mm:ss` past the hour.
+fn clock(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// Draws one bar per MIDI channel at the right end of the status bar, rising with each note
+/// started on the channel and falling away after it. Channels with notes still sounding are
+/// drawn in green.
+fn draw_meters(buffer: &mut Buffer, line: Rect, app: &App) {
+    let left = line.width - METER_WIDTH;
+    for channel in 0..METER_WIDTH as usize {
+        let level = app.midi_monitor.level(channel, METER_DECAY);
+        let bar = METER_BARS[(level * (METER_BARS.len() - 1) as f32).round() as usize];
+        let style = if app.midi_monitor.sounding(channel) > 0 {
+            Style::new().fg(app.theme.meter_sounding).bg(Color::Black)
+        } else {
+            Style::new().fg(app.theme.meter_quiet).bg(Color::Black)
+        };
+        if let Some(cell) = buffer.cell_mut(Position::new(line.x + left + channel as u16, line.y)) {
+            cell.set_char(bar).set_style(style);
+        }
+    }
+}
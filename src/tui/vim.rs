@@ -0,0 +1,121 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use rust_orca::context::Context;
+
+use crate::tui::App;
+
+/// State of the optional vim-style key scheme.
+#[derive(Default)]
+pub struct Vim {
+    /// In insert mode keys type into the grid as usual; in normal mode they are commands.
+    pub insert: bool,
+    /// The count typed before a command, or 0 if none was.
+    pub count: usize,
+    /// Set after a first `g`, waiting for the second.
+    pub pending_g: bool,
+    /// Set after `r`, waiting for the character to put under the cursor.
+    pub pending_replace: bool,
+}
+
+impl App {
+    /// Handles a key in vim mode, returning false for keys that should get their usual meaning:
+    /// everything in insert mode except Esc, keys with control or alt held, and space.
+    pub fn handle_vim_key(&mut self, key: KeyEvent, context: &mut Context) -> bool {
+        let Some(vim) = self.vim.as_mut() else {
+            return false;
+        };
+        if vim.insert {
+            if key.code == KeyCode::Esc {
+                vim.insert = false;
+                return true;
+            }
+            return false;
+        }
+        // vim redoes with ctrl-r, which otherwise resets the frame counter
+        if key.code == KeyCode::Char('r') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.redo(context);
+            return true;
+        }
+        if key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+            return false;
+        }
+        let KeyCode::Char(c) = key.code else {
+            if key.code == KeyCode::Esc {
+                *vim = Vim::default();
+                self.anchor = None;
+                return true;
+            }
+            return false;
+        };
+
+        // space still plays and pauses
+        if c == ' ' && !vim.pending_replace {
+            return false;
+        }
+        if std::mem::take(&mut vim.pending_replace) {
+            let value = if c == '.' { '\0' } else { c };
+            context.edit(self.cursor_row as i32, self.cursor_col as i32, value);
+            self.edited(false);
+            return true;
+        }
+        if c.is_ascii_digit() && (c != '0' || vim.count > 0) {
+            vim.count = vim.count.saturating_mul(10).saturating_add(c as usize - '0' as usize);
+            return true;
+        }
+        let count = std::mem::take(&mut vim.count).max(1) as i32;
+        let pending_g = std::mem::take(&mut vim.pending_g);
+        if pending_g && c == 'g' {
+            self.move_cursor(-(self.rows as i32), 0);
+            return true;
+        }
+        let (row, col) = (self.cursor_row as i32, self.cursor_col as i32);
+        match c {
+            'h' => self.move_cursor(0, -count),
+            'j' => self.move_cursor(count, 0),
+            'k' => self.move_cursor(-count, 0),
+            'l' => self.move_cursor(0, count),
+            '0' => self.move_cursor(0, -(self.cols as i32)),
+            '$' => self.move_cursor(0, self.cols as i32),
+            'g' => self.vim_state().pending_g = true,
+            'G' => self.move_cursor(self.rows as i32, 0),
+            'i' => self.vim_state().insert = true,
+            'r' => self.vim_state().pending_replace = true,
+            ':' => self.command_line.open(),
+            // visual selection: the anchor stays put while the cursor moves
+            'v' if self.anchor.is_some() => self.anchor = None,
+            'v' => self.anchor = Some((self.cursor_row, self.cursor_col)),
+            'x' => {
+                let mut selection = self.selection();
+                if self.anchor.is_none() {
+                    selection.cols = count as usize;
+                }
+                context.erase(selection);
+                self.anchor = None;
+                self.edited(false);
+            }
+            'd' | 'y' => {
+                self.clipboard = if c == 'd' {
+                    context.cut(self.selection())
+                } else {
+                    context.copy(self.selection())
+                };
+                if c == 'd' {
+                    self.edited(false);
+                }
+                self.anchor = None;
+                self.message = format!("{} {}x{}", if c == 'd' { "cut" } else { "copied" }, self.clipboard.cols, self.clipboard.rows);
+            }
+            // p pastes over the grid and P leaves cells under empty clipboard cells alone
+            'p' | 'P' => {
+                context.paste(row, col, &self.clipboard, c == 'P');
+                self.edited(false);
+            }
+            'u' => self.undo(context),
+            _ => {}
+        }
+        true
+    }
+
+    fn vim_state(&mut self) -> &mut Vim {
+        self.vim.get_or_insert_with(Vim::default)
+    }
+}
@@ -0,0 +1,68 @@
+use std::net::UdpSocket;
+
+use crate::error::OrcaError;
+
+/// Sends the text of Message operators to one address over UDP, as it is or as OSC messages.
+#[derive(Debug)]
+pub struct UdpOutput {
+    socket: UdpSocket,
+    osc: bool,
+}
+
+impl UdpOutput {
+    /// Opens a socket sending to `target`, a host and port such as `127.0.0.1:49161`.
+    pub fn open(target: &str, osc: bool) -> Result<UdpOutput, OrcaError> {
+        let socket_error = |source| OrcaError::Socket { address: target.to_string(), source };
+        let socket = UdpSocket::bind("0.0.0.0:0").map_err(socket_error)?;
+        socket.connect(target).map_err(socket_error)?;
+        Ok(UdpOutput { socket, osc })
+    }
+
+    /// Sends one message. Nothing listening on the other end is not an error, since UDP can't
+    /// tell, so the only failures are the system's.
+    pub fn send(&self, text: &str) -> std::io::Result<()> {
+        let packet = if self.osc { osc_message(text) } else { text.as_bytes().to_vec() };
+        match self.socket.send(&packet) {
+            Err(err) if err.kind() == std::io::ErrorKind::ConnectionRefused => Ok(()),
+            result => result.map(|_| ()),
+        }
+    }
+}
+
+/// Encodes text as an OSC message: the first word is the address, with a `/` put in front if it
+/// has none, and each word after it an argument, an integer or a float if it reads as one and a
+/// string otherwise.
+pub fn osc_message(text: &str) -> Vec<u8> {
+    let mut words = text.split_whitespace();
+    let address = words.next().unwrap_or("/");
+    let mut packet = Vec::new();
+    if address.starts_with('/') {
+        push_string(&mut packet, address);
+    } else {
+        push_string(&mut packet, &format!("/{}", address));
+    }
+
+    let mut tags = String::from(",");
+    let mut arguments = Vec::new();
+    for word in words {
+        if let Ok(int) = word.parse::<i32>() {
+            tags.push('i');
+            arguments.extend_from_slice(&int.to_be_bytes());
+        } else if let Ok(float) = word.parse::<f32>() {
+            tags.push('f');
+            arguments.extend_from_slice(&float.to_be_bytes());
+        } else {
+            tags.push('s');
+            push_string(&mut arguments, word);
+        }
+    }
+    push_string(&mut packet, &tags);
+    packet.extend(arguments);
+    packet
+}
+
+// OSC strings end in at least one null and are padded with more to a multiple of four bytes
+fn push_string(packet: &mut Vec<u8>, text: &str) {
+    packet.extend_from_slice(text.as_bytes());
+    packet.extend(core::iter::repeat_n(0, 4 - text.len() % 4));
+}
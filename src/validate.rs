@@ -0,0 +1,106 @@
+use crate::context::Context;
+use crate::fs::grid_lines;
+use crate::operators::{OperatorTable, char_to_base_36};
+use crate::prelude::*;
+
+/// Something likely to be a mistake in a grid, at the cell it concerns.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Problem {
+    pub row: i32,
+    pub col: i32,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(row: i32, col: i32, message: String) -> Problem {
+        Problem { row, col, message }
+    }
+}
+
+/// Looks through a grid for characters no operator or value uses, operators with ports that fall
+/// off the edge of the grid, operators whose outputs a later operator overwrites in the same tick,
+/// operators like `Midi` that only act when banged but have nothing next to them that bangs, and
+/// `Midi` operators whose note can never play. Cells are visited in the order a tick visits them,
+/// and cells another operator reads, writes or locks are skipped just as a tick skips them, so
+/// that values and comments are not mistaken for operators.
+pub fn validate(context: &Context, tick_operators: &OperatorTable, bang_operators: &OperatorTable) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut claimed = HashSet::new();
+    // which operator writes to each cell written so far
+    let mut writers: HashMap<(i32, i32), (i32, i32, &str)> = HashMap::new();
+    let mut bang_only = Vec::new();
+    for row in 0..context.height as i32 {
+        for col in 0..context.width as i32 {
+            let value = context.read(row, col);
+            if value == '\0' || claimed.contains(&(row, col)) {
+                continue;
+            }
+            let Some(operator) = tick_operators.get(value) else {
+                let known = value.is_ascii_alphanumeric() || value == '*' || bang_operators.get(value).is_some();
+                if !known {
+                    problems.push(Problem::new(row, col, format!("unknown character {}", value)));
+                }
+                continue;
+            };
+            let name = operator.name();
+            let ports = operator.ports(context, row, col);
+            let inputs = ports.inputs.iter().map(|port| (port.row, port.col));
+            let outputs = ports.outputs.iter().map(|port| (port.row, port.col));
+            if inputs.clone().any(|(row, col)| !context.contains(row, col)) {
+                problems.push(Problem::new(row, col, format!("{} reads from outside the grid", name)));
+            }
+            if outputs.clone().any(|(row, col)| !context.contains(row, col)) {
+                problems.push(Problem::new(row, col, format!("{} writes outside the grid", name)));
+            }
+            for (out_row, out_col) in outputs.clone().filter(|&(row, col)| context.contains(row, col)) {
+                // the later write wins, as it does in a tick
+                if let Some((other_row, other_col, other)) = writers.insert((out_row, out_col), (row, col, name)) {
+                    problems.push(Problem::new(row, col, format!(
+                        "{} overwrites what the {} at row {} column {} writes to row {} column {}",
+                        name, other, other_row + 1, other_col + 1, out_row + 1, out_col + 1,
+                    )));
+                }
+            }
+            if name == "Midi" {
+                if let Some(note) = ports.inputs.iter().find(|port| port.name == "note") {
+                    // notes are letters; a digit note is ignored
+                    if char_to_base_36(note.value).0 < 10 {
+                        problems.push(Problem::new(row, col, format!("Midi note {} never plays; notes are letters", note.value)));
+                    }
+                }
+            }
+            if operator.is_bang_only() {
+                bang_only.push((row, col, name));
+            }
+            claimed.extend(inputs.chain(outputs).chain(ports.locks.iter().copied()));
+        }
+    }
+    // a bang reaches the cells below, right of and above it, and can come from a bang already in
+    // the grid, from any operator's output, or from an operator that moves and is blocked
+    for (row, col, name) in bang_only {
+        let banged = [(row - 1, col), (row, col - 1), (row + 1, col)].into_iter().any(|(row, col)| {
+            let value = context.read(row, col);
+            value == '*' || writers.contains_key(&(row, col)) || tick_operators.get(value).is_some()
+        });
+        if !banged {
+            problems.push(Problem::new(row, col, format!("{} is never banged; nothing next to it bangs", name)));
+        }
+    }
+    problems.sort_by_key(|problem| (problem.row, problem.col));
+    problems
+}
+
+/// Finds text in an .orca file that falls outside a grid of `rows` by `cols`, which is dropped
+/// when the file is loaded.
+pub fn validate_size(text: &str, rows: usize, cols: usize) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for (row, line) in grid_lines(text).enumerate() {
+        let content = line.trim_end_matches(['.', ' ']);
+        if row >= rows && !content.is_empty() {
+            problems.push(Problem::new(row as i32, 0, format!("text below the grid's {} rows is dropped", rows)));
+        } else if content.chars().count() > cols {
+            problems.push(Problem::new(row as i32, cols as i32, format!("text past the grid's {} columns is dropped", cols)));
+        }
+    }
+    problems
+}
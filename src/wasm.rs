@@ -0,0 +1,102 @@
+use wasm_bindgen::prelude::{JsError, wasm_bindgen};
+
+use crate::context::{Context, fits};
+use crate::engine::tick;
+use crate::error::OrcaError;
+use crate::fs::{grid_to_string, parse_grid};
+use crate::midi::MidiCapture;
+use crate::operators::{OperatorTable, default_operator_config, get_bang_operators, get_tick_operators, parse_operator_config};
+
+/// A grid and the operators it runs with, for driving the engine from JavaScript. Each tick hands
+/// back the MIDI it played for the page to send on, through Web MIDI or a synth of its own.
+#[wasm_bindgen]
+pub struct Orca {
+    context: Context,
+    tick_operators: OperatorTable,
+    bang_operators: OperatorTable,
+    capture: MidiCapture,
+}
+
+#[wasm_bindgen]
+impl Orca {
+    /// An empty grid of `rows` by `cols` cells at `bpm` beats per minute, with the default symbols.
+    #[wasm_bindgen(constructor)]
+    pub fn new(rows: usize, cols: usize, bpm: u32) -> Result<Orca, JsError> {
+        if !fits(cols, rows) {
+            return Err(JsError::new(&OrcaError::GridTooBig { cols, rows }.to_string()));
+        }
+        let operator_map = default_operator_config();
+        Ok(Orca {
+            context: Context::new(vec!['\0'; rows * cols], cols, rows, bpm as u64, 4),
+            tick_operators: get_tick_operators(&operator_map),
+            bang_operators: get_bang_operators(&operator_map),
+            capture: MidiCapture::default(),
+        })
+    }
+
+    /// Replaces the grid with .orca text, cut or padded to the grid's size.
+    pub fn load(&mut self, text: &str) {
+        let grid = parse_grid(text, self.context.height, self.context.width);
+        self.context.replace_grid(grid);
+    }
+
+    /// Gives the operators the symbols from an operator config, one `<symbol> <name>` per line,
+    /// throwing on the first line that could not be read.
+    #[wasm_bindgen(js_name = setOperators)]
+    pub fn set_operators(&mut self, config: &str) -> Result<(), JsError> {
+        let operator_map = parse_operator_config(config).map_err(|err| JsError::new(&err.to_string()))?;
+        self.tick_operators = get_tick_operators(&operator_map);
+        self.bang_operators = get_bang_operators(&operator_map);
+        Ok(())
+    }
+
+    /// Runs one tick, returning the MIDI messages it sent three bytes to a message.
+    pub fn tick(&mut self) -> Vec<u8> {
+        self.capture.tick = self.context.ticks;
+        tick(&mut self.context, &self.tick_operators, &self.bang_operators, &mut self.capture);
+        self.capture.messages.drain(..).flat_map(|(_, message)| message).collect()
+    }
+
+    /// The cell at a row and column, `.` for an empty cell or one outside the grid.
+    pub fn read(&self, row: i32, col: i32) -> char {
+        match self.context.read(row, col) {
+            '\0' => '.',
+            value => value,
+        }
+    }
+
+    /// Sets the cell at a row and column, recorded in the undo history as an edit.
+    pub fn write(&mut self, row: i32, col: i32, value: char) {
+        self.context.edit(row, col, if value == '.' { '\0' } else { value });
+    }
+
+    /// The whole grid as .orca text.
+    pub fn grid(&self) -> String {
+        grid_to_string(self.context.grid(), self.context.width)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn rows(&self) -> usize {
+        self.context.height
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn cols(&self) -> usize {
+        self.context.width
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn ticks(&self) -> usize {
+        self.context.ticks
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn bpm(&self) -> u32 {
+        self.context.tempo as u32
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_bpm(&mut self, bpm: u32) {
+        self.context.set_tempo(bpm as u64);
+    }
+}
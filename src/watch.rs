@@ -0,0 +1,79 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::{JoinHandle, sleep};
+use std::time::{Duration, SystemTime};
+
+use crate::context::Context;
+use crate::fs::read_grid;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata().and_then(|metadata| metadata.modified()).ok()
+}
+
+/// The grid file a watcher follows, shared with whatever saves the grid to it.
+#[derive(Debug, Default)]
+pub struct Watched {
+    /// The file watched; nothing is watched while this is `None`.
+    pub path: Option<PathBuf>,
+    /// When the grid was last written to the file by the program itself, which is no reason to
+    /// load it back.
+    saved: Option<SystemTime>,
+}
+
+impl Watched {
+    /// Notes that the grid was just written to the watched file, so that the watcher leaves the
+    /// grid, and the edits made since, alone.
+    pub fn saved(&mut self) {
+        self.saved = self.path.as_deref().and_then(modified);
+    }
+}
+
+/// Polls the grid file for modifications and reloads it into the shared context whenever it
+/// changes on disk. The engine holds the context lock for an entire tick, so a reload always lands
+/// between two ticks rather than in the middle of one.
+///
+/// The file watched is whichever one `watched` names at the time, so that the editor can switch
+/// files without starting another watcher; nothing is watched while it names none.
+pub fn watch_grid_file(watched: Arc<Mutex<Watched>>, context_arc: Arc<Mutex<Context>>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut path = watched.lock().unwrap().path.clone();
+        let mut last_modified = path.as_deref().and_then(modified);
+        loop {
+            sleep(POLL_INTERVAL);
+            let (current, saved) = {
+                let watched = watched.lock().unwrap();
+                (watched.path.clone(), watched.saved)
+            };
+            if current != path {
+                // a newly named file was just loaded or saved, so it starts out unchanged
+                last_modified = current.as_deref().and_then(modified);
+                path = current;
+                continue;
+            }
+            let Some(path) = &path else {
+                continue;
+            };
+            let current_modified = modified(path);
+            if current_modified == last_modified {
+                continue;
+            }
+            last_modified = current_modified;
+            if current_modified == saved {
+                continue;
+            }
+
+            let (rows, cols) = {
+                let context = context_arc.lock().unwrap();
+                (context.height, context.width)
+            };
+            // a file that is mid-save may fail to read; the next modification will pick it up
+            if let Ok(grid) = read_grid(path, rows, cols) {
+                let mut context = context_arc.lock().unwrap();
+                context.replace_grid(grid);
+            }
+        }
+    })
+}
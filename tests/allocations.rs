@@ -0,0 +1,84 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rust_orca::context::Context;
+use rust_orca::operators::{get_bang_operators, get_tick_operators, grid_tick, default_operator_config};
+
+// counts allocations made by the current thread while counting is switched on, so that the test
+// harness's own threads don't interfere
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.with(|counting| counting.get()) {
+            ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// every other row is left free for operator outputs
+const PATCH: [&str; 16] = [
+    "D4..C4...H...1A2..E....",
+    "",
+    "2I7..3L7..1U8..4M5..1J.",
+    "",
+    "#.a.long.comment.that.spans.the.row.#",
+    "",
+    "4T1234..aV1..Va..R..1Y.",
+    "",
+    "3O12..22Q123..12G12..12X1",
+    "",
+    "1Z8..3K.abc..F2...",
+    "",
+    "D2",
+    "",
+    ":03C..2B9..N..S..W..",
+    "",
+];
+
+#[test]
+fn steady_state_ticks_do_not_allocate() {
+    let (rows, cols) = (24, 60);
+    let mut grid = vec!['\0'; rows * cols];
+    for (r, line) in PATCH.iter().enumerate() {
+        for (c, value) in line.chars().enumerate() {
+            if value != '.' {
+                grid[r * cols + c] = value;
+            }
+        }
+    }
+    let mut context = Context::new(grid, cols, rows, 120, 4);
+    let operator_map = default_operator_config();
+    let tick_operators = get_tick_operators(&operator_map);
+    let bang_operators = get_bang_operators(&operator_map);
+
+    // let buffers such as the variable map and note list reach their working size
+    for _ in 0..64 {
+        grid_tick(&mut context, &tick_operators, &bang_operators);
+        context.notes.clear();
+    }
+
+    COUNTING.with(|counting| counting.set(true));
+    for _ in 0..256 {
+        grid_tick(&mut context, &tick_operators, &bang_operators);
+        context.notes.clear();
+    }
+    COUNTING.with(|counting| counting.set(false));
+
+    assert_eq!(ALLOCATIONS.load(Ordering::SeqCst), 0);
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use bevy_app::App;
+use bevy_ecs::message::Messages;
+use bevy_time::{TimePlugin, TimeUpdateStrategy};
+use rust_orca::bevy_orca::{Orca, OrcaEvent, OrcaPlugin};
+
+// a delay banging a note on channel 0 every tick
+const GRID: &str = ".D1...\n..:03C\n";
+
+fn app(frame: Duration) -> App {
+    let mut app = App::new();
+    app.add_plugins((TimePlugin, OrcaPlugin))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(frame))
+        .insert_resource(Orca::from_text(GRID, 120));
+    app
+}
+
+fn events(app: &mut App) -> Vec<OrcaEvent> {
+    app.world_mut().resource_mut::<Messages<OrcaEvent>>().drain().collect()
+}
+
+#[test]
+fn ticks_at_the_grid_tempo() {
+    // at 120bpm and 4 ticks to a beat, a tick lasts 125ms, and the first frame takes no time
+    let mut app = app(Duration::from_millis(50));
+    let mut ticks = Vec::new();
+    for _ in 0..11 {
+        app.update();
+        ticks.extend(events(&mut app).into_iter().filter_map(|event| match event {
+            OrcaEvent::Tick(tick) => Some(tick),
+            _ => None,
+        }));
+    }
+    assert_eq!(ticks, [0, 1, 2, 3]);
+}
+
+#[test]
+fn sends_what_the_grid_plays() {
+    let mut app = app(Duration::from_millis(125));
+    let mut played = Vec::new();
+    for _ in 0..4 {
+        app.update();
+        played.extend(events(&mut app));
+    }
+    let note_on = |event: &OrcaEvent| matches!(event, OrcaEvent::NoteOn { channel: 0, note: 48, .. });
+    assert!(played.iter().any(note_on), "{:?}", played);
+}
+
+#[test]
+fn a_stall_is_not_played_back_all_at_once() {
+    let mut app = app(Duration::from_secs(10));
+    app.update();
+    app.update();
+    let ticks = events(&mut app).iter().filter(|event| matches!(event, OrcaEvent::Tick(_))).count();
+    assert!(ticks <= 16, "{} ticks", ticks);
+}
@@ -0,0 +1,71 @@
+use rust_orca::clap_plugin::{GridView, HostSync, HostTransport, ViewKey};
+use rust_orca::test_util::context_from;
+
+// a delay banging a note on channel 0 every tick
+const GRID: &str = ".D1...\n..:03C\n";
+
+// at 120bpm and 4 ticks to a beat, a tick lasts 6000 samples at 48kHz
+const SAMPLE_RATE: f64 = 48000.0;
+
+fn playing(beat: f64) -> HostTransport {
+    HostTransport { playing: true, beat, tempo: 120.0 }
+}
+
+fn note_ons(played: &[(u32, [u8; 3])]) -> Vec<u32> {
+    played.iter().filter(|(_, message)| message[0] == 0x90 && message[2] > 0).map(|&(offset, _)| offset).collect()
+}
+
+#[test]
+fn plays_ticks_on_the_samples_they_fall_on() {
+    let mut sync = HostSync::new(context_from(GRID));
+    assert_eq!(note_ons(&sync.process(playing(0.0), SAMPLE_RATE, 12000)), [0, 6000]);
+    // starting a tenth of a beat in, the next tick is 0.6 of a tick away, and the one after a tick later
+    let mut sync = HostSync::new(context_from(GRID));
+    assert_eq!(note_ons(&sync.process(playing(0.1), SAMPLE_RATE, 12000)), [3600, 9600]);
+}
+
+#[test]
+fn follows_the_song_position() {
+    let mut sync = HostSync::new(context_from(GRID));
+    sync.process(playing(10.0), SAMPLE_RATE, 100);
+    assert_eq!(sync.context.ticks, 41);
+    sync.process(HostTransport { tempo: 90.0, ..playing(2.0) }, SAMPLE_RATE, 100);
+    assert_eq!((sync.context.ticks, sync.context.tempo), (9, 90));
+}
+
+#[test]
+fn stops_notes_when_the_host_stops() {
+    let mut sync = HostSync::new(context_from(GRID));
+    assert!(!note_ons(&sync.process(playing(0.0), SAMPLE_RATE, 12000)).is_empty());
+    let stopped = sync.process(HostTransport { playing: false, ..playing(0.5) }, SAMPLE_RATE, 12000);
+    assert!(stopped.iter().any(|(_, message)| message[0] == 0x80), "{:?}", stopped);
+    assert!(sync.process(HostTransport { playing: false, ..playing(0.5) }, SAMPLE_RATE, 12000).is_empty());
+}
+
+#[test]
+fn the_window_edits_the_cell_under_its_cursor() {
+    let mut context = context_from(GRID);
+    let mut view = GridView::default();
+    view.press(ViewKey::Right, &mut context);
+    view.press(ViewKey::Erase, &mut context);
+    view.press(ViewKey::Down, &mut context);
+    view.press(ViewKey::Char('C'), &mut context);
+    assert_eq!(view.lines(&context), ["..1...", ".C:03C"]);
+    view.press(ViewKey::Char('.'), &mut context);
+    assert_eq!(view.lines(&context), ["..1...", "..:03C"]);
+}
+
+#[test]
+fn the_window_keeps_its_cursor_on_the_grid() {
+    let mut context = context_from(GRID);
+    let mut view = GridView::default();
+    view.press(ViewKey::Up, &mut context);
+    view.press(ViewKey::Left, &mut context);
+    assert_eq!((view.row, view.col), (0, 0));
+    view.click(10, 3, &context);
+    assert_eq!((view.row, view.col), (1, 3));
+    for _ in 0..10 {
+        view.press(ViewKey::Right, &mut context);
+    }
+    assert_eq!((view.row, view.col), (1, 5));
+}
@@ -0,0 +1,14 @@
+use rust_orca::test_util::run_default;
+
+#[test]
+fn block_comments_halt_a_rectangle() {
+    // the adds inside the block are halted while the one below it runs
+    assert_eq!(run_default("|1A2..\n.1A2..\n...1A|\n......\n1A2...\n......\n", 1), "|1A2..\n.1A2..\n...1A|\n......\n1A2...\n.3....\n");
+    // operators left of the block's columns still run
+    assert_eq!(run_default("..|....\n1A2....\n.....|.\n", 1), "..|....\n1A2....\n.3...|.\n");
+}
+
+#[test]
+fn block_comments_without_a_close_halt_their_row() {
+    assert_eq!(run_default("|1A2\n1A2.\n....\n", 1), "|1A2\n1A2.\n.3..\n");
+}
@@ -0,0 +1,133 @@
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use rust_orca::test_util::{context_from, default_tables, run_patch_in};
+use rust_orca::{EvaluationMode, MidiCapture, OperatorTable, grid_to_string, run_headless, tick};
+
+// the reference patches, their golden grids and the list of cases, as described in cases.txt
+fn conformance_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("conformance")
+}
+
+fn read(path: &Path) -> String {
+    read_to_string(path).unwrap_or_else(|err| panic!("could not read {}: {}", path.display(), err))
+}
+
+// runs a patch the way run_patch_in does, also writing out what it sent in the format of the
+// .events goldens: a line per note on or message, after the tick it was sent on
+fn run_with_events(
+    evaluation: EvaluationMode,
+    text: &str,
+    ticks: usize,
+    tick_operators: &OperatorTable,
+    bang_operators: &OperatorTable,
+) -> (String, String) {
+    let mut context = context_from(text);
+    context.evaluation = evaluation;
+    let mut capture = MidiCapture::default();
+    let mut events = String::new();
+    for _ in 0..ticks {
+        capture.tick = context.ticks;
+        tick(&mut context, tick_operators, bang_operators, &mut capture);
+        for (_, [status, note, velocity]) in capture.messages.drain(..) {
+            if status & 0xf0 == 0x90 {
+                events += &format!("{} note {} {} {}\n", capture.tick, status & 0x0f, note, velocity);
+            }
+        }
+        for message in &context.messages {
+            events += &format!("{} message {}\n", capture.tick, message);
+        }
+    }
+    (grid_to_string(context.grid(), context.width), events)
+}
+
+#[test]
+fn patches_match_orca_c() {
+    let dir = conformance_dir();
+    let (tick_operators, bang_operators) = default_tables();
+    let mut failures = Vec::new();
+    for line in read(&dir.join("cases.txt")).lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (case, divergence) = match line.split_once(" diverges: ") {
+            Some((case, divergence)) => (case, Some(divergence)),
+            None => (line, None),
+        };
+        let (case, evaluation) = match case.split_once(" in ") {
+            Some((case, mode)) => (case, EvaluationMode::from_name(mode).unwrap_or_else(|err| panic!("{}: {}", line, err))),
+            None => (case, EvaluationMode::default()),
+        };
+        let (name, ticks) = case.split_once(' ').unwrap_or_else(|| panic!("expected a name and ticks: {}", line));
+        let ticks: usize = ticks.parse().unwrap_or_else(|_| panic!("invalid ticks for {}: {}", name, ticks));
+        // run at the size of the patch's text, the way orca-c runs a file
+        let text = read(&dir.join(format!("{}.orca", name)));
+        let (actual, actual_events) = run_with_events(evaluation, &text, ticks, &tick_operators, &bang_operators);
+        let golden = read(&dir.join(format!("{}.golden", name)));
+        // a patch that sends nothing has no events file
+        let events_path = dir.join(format!("{}.events", name));
+        let events = if events_path.exists() { read(&events_path) } else { String::new() };
+        match (actual == golden && actual_events == events, divergence) {
+            (true, None) => {}
+            (false, Some(divergence)) => eprintln!("{} diverges from orca-c: {}", name, divergence),
+            (true, Some(_)) => failures.push(format!("{} matches orca-c now, so it no longer diverges", name)),
+            (false, None) => {
+                failures.push(format!(
+                    "{} after {} ticks:\nexpected\n{}{}got\n{}{}",
+                    name, ticks, golden, events, actual, actual_events,
+                ));
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn evaluation_modes_differ_in_when_bangs_land() {
+    let (tick_operators, bang_operators) = default_tables();
+    let run = |mode, text, ticks| run_patch_in(EvaluationMode::from_name(mode).unwrap(), text, ticks, &tick_operators, &bang_operators);
+
+    // the yump carries the delay's bang under the add after the scan has passed the add
+    let carried = ".D.a1\n..Y..\n";
+    assert_eq!(run("rust-orca", carried, 1), ".D.a1\n.*Y1.\n");
+    assert_eq!(run("orca-c", carried, 1), ".D.a1\n.*Y*.\n");
+    assert_eq!(run("bang-first", carried, 1), ".D.a1\n.*Y*.\n");
+
+    // the delay's bang lands above the add before the scan reaches it
+    let above = ".D...\n.....\n.a1..\n.....\n";
+    assert_eq!(run("rust-orca", above, 1), ".D...\n.*...\n.a1..\n.1...\n");
+    assert_eq!(run("orca-c", above, 1), ".D...\n.*...\n.a1..\n.1...\n");
+    assert_eq!(run("bang-first", above, 1), ".D...\n.*...\n.a1..\n.....\n");
+    assert_eq!(run("bang-first", above, 2), ".D...\n.....\n.a1..\n.1...\n");
+}
+
+// each edge case the orca-c lock flag changes, as (what it is, patch, rust-orca's grid after a tick,
+// orca-c's grid after a tick)
+const LOCK_CASES: [(&str, &str, &str, &str); 5] = [
+    ("track with no length", "00T5\n..E.\n", "00T5\n..5.\n", "00T5\n...E\n"),
+    ("track with a length", "01T5\n..E.\n", "01T5\n..5.\n", "01T5\n..5.\n"),
+    ("push with no length", "00P5\n....\n", "00P5\n..5.\n", "00P5\n....\n"),
+    ("push with a length", "01P5\n....\n", "01P5\n..5.\n", "01P5\n..5.\n"),
+    ("halt", "H.\nE.\n", "H.\nE.\n", "H.\nE.\n"),
+];
+
+#[test]
+fn orca_c_locks_switch_edge_cases() {
+    let (tick_operators, bang_operators) = default_tables();
+    for (case, patch, rust_orca, orca_c) in LOCK_CASES {
+        for (orca_c_locks, expected) in [(false, rust_orca), (true, orca_c)] {
+            let mut context = context_from(patch);
+            context.orca_c_locks = orca_c_locks;
+            context.trace.enabled = true;
+            run_headless(&mut context, &tick_operators, &bang_operators, 1);
+            let actual = grid_to_string(context.grid(), context.width);
+            assert_eq!(actual, expected, "{} with orca-c locks {}", case, if orca_c_locks { "on" } else { "off" });
+            // orca-c's halt only locks the cell below, so it neither reads nor writes it
+            if case == "halt" {
+                let halt = context.trace.visits().iter().find(|visit| visit.name == "Halt").unwrap();
+                assert_eq!(halt.outputs.is_empty(), orca_c_locks);
+            }
+        }
+    }
+}
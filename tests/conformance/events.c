@@ -0,0 +1,49 @@
+// Prints what an orca-c patch sends over a number of timesteps, in the format of the .events
+// goldens described in cases.txt. generate.sh builds it against orca-c's sources.
+#include "field.h"
+#include "gbuffer.h"
+#include "sim.h"
+#include "vmio.h"
+#include <stdio.h>
+#include <stdlib.h>
+
+int main(int argc, char **argv) {
+  if (argc != 3) {
+    fprintf(stderr, "usage: events TICKS FILE\n");
+    return 1;
+  }
+  Usz ticks = (Usz)strtoul(argv[1], NULL, 10);
+  Field field;
+  field_init(&field);
+  if (field_load_file(argv[2], &field) != Field_load_error_ok) {
+    fprintf(stderr, "could not load %s\n", argv[2]);
+    return 1;
+  }
+  Mbuf_reusable mbuf_r;
+  mbuf_reusable_init(&mbuf_r);
+  mbuf_reusable_ensure_size(&mbuf_r, field.height, field.width);
+  Oevent_list events;
+  oevent_list_init(&events);
+  for (Usz tick = 0; tick < ticks; ++tick) {
+    oevent_list_clear(&events);
+    orca_run(field.buffer, mbuf_r.buffer, field.height, field.width, tick, &events, 1);
+    for (Usz i = 0; i < events.count; ++i) {
+      Oevent const *e = events.buffer + i;
+      switch (e->any.oevent_type) {
+      case Oevent_type_midi_note:
+        printf("%zu note %u %u %u\n", (size_t)tick, e->midi_note.channel,
+               12u * e->midi_note.octave + e->midi_note.note, e->midi_note.velocity);
+        break;
+      case Oevent_type_udp_string:
+        printf("%zu message %.*s\n", (size_t)tick, (int)e->udp_string.count, e->udp_string.chars);
+        break;
+      default:
+        break;
+      }
+    }
+  }
+  oevent_list_deinit(&events);
+  mbuf_reusable_deinit(&mbuf_r);
+  field_deinit(&field);
+  return 0;
+}
@@ -0,0 +1,18 @@
+#!/bin/sh
+# Writes the goldens again from orca-c, given the path to its command line tool and to its
+# sources, which events.c is built against to print what each patch sends.
+set -eu
+orca=${1:?usage: generate.sh ORCA_C_BINARY ORCA_C_SOURCES}
+sources=${2:?usage: generate.sh ORCA_C_BINARY ORCA_C_SOURCES}
+cd "$(dirname "$0")"
+events=$(mktemp)
+trap 'rm -f "$events"' EXIT
+cc -std=c99 -O2 -I"$sources" events.c "$sources/field.c" "$sources/gbuffer.c" \
+    "$sources/sim.c" "$sources/vmio.c" -o "$events"
+grep -v '^#' cases.txt | while read -r name ticks _; do
+    [ -n "$name" ] || continue
+    "$orca" -t "$ticks" "$name.orca" > "$name.golden"
+    "$events" "$ticks" "$name.orca" > "$name.events"
+    # a patch that sends nothing has no events file
+    [ -s "$name.events" ] || rm "$name.events"
+done
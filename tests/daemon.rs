@@ -0,0 +1,196 @@
+#![cfg(unix)]
+
+use std::env::temp_dir;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream, UdpSocket};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process;
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle, sleep};
+use std::time::{Duration, Instant};
+
+use rust_orca::daemon::Daemon;
+use rust_orca::metrics::serve_metrics;
+use rust_orca::midi::NoMidi;
+use rust_orca::test_util::{context_from, default_tables};
+use rust_orca::udp::UdpOutput;
+
+// a delay banging a note on channel 0 every tick
+const GRID: &str = ".D1...\n..:03C\n";
+
+struct Client {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Client {
+    fn connect(socket: &PathBuf) -> Client {
+        for _ in 0..200 {
+            if let Ok(stream) = UnixStream::connect(socket) {
+                return Client { reader: BufReader::new(stream.try_clone().unwrap()), writer: stream };
+            }
+            sleep(Duration::from_millis(10));
+        }
+        panic!("the daemon never started listening");
+    }
+
+    fn line(&mut self) -> String {
+        let mut line = String::new();
+        self.reader.read_line(&mut line).unwrap();
+        line.trim_end().to_string()
+    }
+
+    fn send(&mut self, request: &str) {
+        writeln!(self.writer, "{}", request).unwrap();
+    }
+
+    fn request(&mut self, request: &str) -> String {
+        self.send(request);
+        self.line()
+    }
+}
+
+fn serve(name: &str) -> (PathBuf, JoinHandle<()>) {
+    let socket = temp_dir().join(format!("rust-orca-{}-{}.sock", name, process::id()));
+    let (tick_operators, bang_operators) = default_tables();
+    let daemon = Daemon::new(context_from(GRID), tick_operators, bang_operators, Box::new(NoMidi));
+    let path = socket.clone();
+    (socket, thread::spawn(move || daemon.serve(&path).unwrap()))
+}
+
+#[test]
+fn writes_and_reads_cells() {
+    let (socket, daemon) = serve("cells");
+    let mut client = Client::connect(&socket);
+    assert_eq!(client.request(r#"{"cmd": "pause"}"#), r#"{"ok":true}"#);
+    assert_eq!(client.request(r#"{"cmd": "load", "text": "....\n...."}"#), r#"{"ok":true}"#);
+    assert_eq!(client.request(r#"{"cmd": "write", "row": 1, "col": 1, "text": "ab"}"#), r#"{"ok":true}"#);
+    assert_eq!(client.request(r#"{"cmd": "read"}"#), r#"{"grid":"....\n.ab.\n","ok":true}"#);
+    assert!(client.request(r#"{"cmd": "write", "row": 9, "col": 1, "text": "a"}"#).contains(r#""ok":false"#));
+    assert!(client.request(r#"{"cmd": "dance"}"#).contains("invalid request"));
+    assert!(client.request(r#"{"cmd": "operators"}"#).contains(r#""name":"Add""#));
+    assert_eq!(client.request(r#"{"cmd": "shutdown"}"#), r#"{"ok":true}"#);
+    daemon.join().unwrap();
+    assert!(!socket.exists());
+}
+
+#[test]
+fn subscribers_get_ticks_and_notes() {
+    let (socket, daemon) = serve("events");
+    let mut client = Client::connect(&socket);
+    assert_eq!(client.request(r#"{"cmd": "pause"}"#), r#"{"ok":true}"#);
+    // let a tick that was already under way finish before counting
+    sleep(Duration::from_millis(20));
+    let status = client.request(r#"{"cmd": "status"}"#);
+    assert!(status.contains(r#""paused":true"#), "{}", status);
+    assert_eq!(client.request(r#"{"cmd": "subscribe"}"#), r#"{"ok":true}"#);
+    // the ticks can start before the answer goes out
+    client.send(r#"{"cmd": "step", "ticks": 2}"#);
+    let mut events = Vec::new();
+    while events.iter().filter(|event: &&String| event.contains(r#""type": "tick""#)).count() < 2 {
+        events.push(client.line());
+    }
+    assert!(events.iter().any(|event| event.contains(r#""type": "note_on", "channel": 0, "note": 48"#)), "{:?}", events);
+    assert!(events.iter().any(|event| event == r#"{"ok":true}"#), "{:?}", events);
+    client.request(r#"{"cmd": "shutdown"}"#);
+    daemon.join().unwrap();
+}
+
+#[test]
+fn a_huge_step_is_answered_at_once() {
+    let (socket, daemon) = serve("step");
+    let mut client = Client::connect(&socket);
+    client.reader.get_ref().set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    assert_eq!(client.request(r#"{"cmd": "pause"}"#), r#"{"ok":true}"#);
+    let step = format!(r#"{{"cmd": "step", "ticks": {}}}"#, usize::MAX);
+    assert_eq!(client.request(&step), r#"{"ok":true}"#);
+    // more steps on top of as many as there can be are still answered
+    assert_eq!(client.request(r#"{"cmd": "step", "ticks": 2}"#), r#"{"ok":true}"#);
+    assert_eq!(client.request(r#"{"cmd": "shutdown"}"#), r#"{"ok":true}"#);
+    daemon.join().unwrap();
+}
+
+#[test]
+fn sends_messages_over_udp() {
+    let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+    listener.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let socket = temp_dir().join(format!("rust-orca-udp-{}.sock", process::id()));
+    let (tick_operators, bang_operators) = default_tables();
+    let daemon = Daemon::new(context_from(".D1...\n......\n.;hi..\n"), tick_operators, bang_operators, Box::new(NoMidi));
+    daemon.send_messages_to(UdpOutput::open(&listener.local_addr().unwrap().to_string(), false).unwrap());
+    let path = socket.clone();
+    let served = thread::spawn(move || daemon.serve(&path).unwrap());
+    let mut client = Client::connect(&socket);
+
+    let mut packet = [0; 16];
+    let len = listener.recv(&mut packet).unwrap();
+    assert_eq!(&packet[..len], b"hi");
+
+    client.request(r#"{"cmd": "shutdown"}"#);
+    served.join().unwrap();
+}
+
+#[test]
+fn subscribers_that_fall_behind_are_hung_up_on() {
+    let (socket, daemon) = serve("backlog");
+    let mut client = Client::connect(&socket);
+    // as fast a clock as a header allows, with ten notes on every tick
+    let header = r"#@ rust-orca\n#@ size 60x2\n#@ bpm 1000\n#@ frames 64\n#@ seed 0\n#@ operators 0\n";
+    let grid = format!(r"{}{}\n{}\n", header, ".D1...".repeat(10), "..:03C".repeat(10));
+    assert_eq!(client.request(&format!(r#"{{"cmd": "load", "text": "{}"}}"#, grid)), r#"{"ok":true}"#);
+    assert_eq!(client.request(r#"{"cmd": "subscribe"}"#), r#"{"ok":true}"#);
+    // the client reads nothing while its lines pile up
+    sleep(Duration::from_secs(3));
+    client.reader.get_ref().set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let (mut line, started) = (String::new(), Instant::now());
+    while client.reader.read_line(&mut line).expect("the daemon never hung up") > 0 {
+        assert!(started.elapsed() < Duration::from_secs(10), "the daemon never hung up");
+        line.clear();
+    }
+
+    Client::connect(&socket).request(r#"{"cmd": "shutdown"}"#);
+    daemon.join().unwrap();
+}
+
+fn scrape(address: SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn a_quiet_scraper_does_not_hold_up_the_metrics() {
+    let address = serve_metrics("127.0.0.1:0", || "orca_ticks_total 4\n".to_string()).unwrap();
+    // connects and never sends a request
+    let _quiet = TcpStream::connect(address).unwrap();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || sender.send(scrape(address, "/metrics")));
+    let response = receiver.recv_timeout(Duration::from_secs(10)).expect("the scrape was never answered");
+    assert!(response.ends_with("orca_ticks_total 4\n"), "{}", response);
+}
+
+#[test]
+fn serves_metrics_over_http() {
+    let (tick_operators, bang_operators) = default_tables();
+    let daemon = Daemon::new(context_from(GRID), tick_operators, bang_operators, Box::new(NoMidi));
+    let address = daemon.serve_metrics("127.0.0.1:0").unwrap();
+    let socket = temp_dir().join(format!("rust-orca-metrics-{}.sock", process::id()));
+    let path = socket.clone();
+    let serving = daemon.clone();
+    let served = thread::spawn(move || serving.serve(&path).unwrap());
+    let mut client = Client::connect(&socket);
+    sleep(Duration::from_millis(300));
+
+    let response = scrape(address, "/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    assert!(response.contains("# TYPE orca_ticks_total counter"), "{}", response);
+    let notes = response.lines().find_map(|line| line.strip_prefix("orca_notes_total ")).unwrap();
+    assert!(notes.parse::<u64>().unwrap() > 0, "{}", response);
+    assert!(scrape(address, "/").starts_with("HTTP/1.1 404"));
+
+    client.request(r#"{"cmd": "shutdown"}"#);
+    served.join().unwrap();
+}
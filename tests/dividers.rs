@@ -0,0 +1,18 @@
+use rust_orca::test_util::run_default;
+
+#[test]
+fn dividers_halve_the_bangs_they_get_along_the_row() {
+    // the delay above bangs it every frame, and it keeps its count to its left
+    let grids: Vec<String> = (1..=8).map(|ticks| run_default(".D1..\n.....\n./3..\n.....\n", ticks)).collect();
+    let outputs: Vec<&str> = grids.iter().map(|grid| &grid[19..22]).collect();
+    assert_eq!(outputs, ["...", "*..", "...", "**.", "...", "*..", "...", "***"]);
+    let counts: String = grids.iter().map(|grid| &grid[12..13]).collect();
+    assert_eq!(counts, "12345670");
+}
+
+#[test]
+fn dividers_without_bangs_stay_still() {
+    for ticks in 1..=8 {
+        assert_eq!(run_default("3/2\n...\n", ticks), "3/2\n...\n");
+    }
+}
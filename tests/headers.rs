@@ -0,0 +1,36 @@
+use rust_orca::context::{DIVISIONS_RANGE, TEMPO_RANGE};
+use rust_orca::fs::{Header, header_to_string, parse_header};
+use rust_orca::test_util::context_from;
+
+#[test]
+fn headers_keep_the_tempo_and_frame_rate() {
+    let header = Header { rows: 3, cols: 5, bpm: 96, frames: 6, seed: 7, operators: 0xabc };
+    let text = format!("{}.....\n", header_to_string(&header));
+    assert!(text.contains("#@ frames 6\n"), "{}", text);
+    assert_eq!(parse_header(&text), Some(header.clone()));
+
+    let mut context = context_from(".....\n");
+    header.apply(&mut context);
+    assert_eq!((context.tempo, context.divisions, context.seed), (96, 6, 7));
+    assert_eq!(context.tick_time, 60000 / (96 * 6));
+}
+
+#[test]
+fn headers_without_a_frame_rate_play_at_four_frames_a_beat() {
+    let text = "#@ rust-orca\n#@ size 5x1\n#@ bpm 96\n#@ seed 7\n#@ operators 0000000000000abc\n.....\n";
+    assert_eq!(parse_header(text).map(|header| header.frames), Some(4));
+    assert_eq!(parse_header(&text.replace("#@ bpm 96\n", "#@ bpm 96\n#@ frames 0\n")).map(|header| header.frames), Some(4));
+}
+
+#[test]
+fn headers_clamp_the_tempo_and_frame_rate() {
+    let text = "#@ rust-orca\n#@ size 5x1\n#@ bpm 0\n#@ frames 99999999999\n#@ seed 7\n#@ operators 0000000000000abc\n.....\n";
+    let header = parse_header(text).unwrap();
+    assert_eq!((header.bpm, header.frames), (*TEMPO_RANGE.start(), *DIVISIONS_RANGE.end()));
+
+    let mut context = context_from(".....\n");
+    context.set_tempo(u64::MAX);
+    context.set_divisions(u64::MAX);
+    assert_eq!((context.tempo, context.divisions), (*TEMPO_RANGE.end(), *DIVISIONS_RANGE.end()));
+    assert!(context.tick_time > 0);
+}
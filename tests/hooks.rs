@@ -0,0 +1,43 @@
+use std::sync::{Arc, Mutex};
+
+use rust_orca::run_headless;
+use rust_orca::test_util::{context_from, default_tables};
+
+// a delay banging a note every tick
+const GRID: &str = ".D1...\n..:03C\n";
+
+#[test]
+fn hooks_filter_and_transpose_notes() {
+    let (tick_operators, bang_operators) = default_tables();
+    let mut context = context_from(GRID);
+    // only the notes of even ticks are sent, an octave up
+    context.hooks.after_tick(|events| {
+        if events.tick % 2 == 1 {
+            events.notes.retain(|note| note.started);
+        }
+        for note in events.notes.iter_mut().filter(|note| !note.started) {
+            note.note_number += 12;
+        }
+    });
+    let capture = run_headless(&mut context, &tick_operators, &bang_operators, 4);
+    let note_ons: Vec<_> = capture.messages.iter().filter(|(_, message)| message[0] & 0xf0 == 0x90).collect();
+    assert_eq!(note_ons.iter().map(|(tick, _)| *tick).collect::<Vec<_>>(), [0, 2]);
+    assert!(note_ons.iter().all(|(_, message)| message[1] == 60), "{:?}", note_ons);
+}
+
+#[test]
+fn hooks_run_around_every_tick() {
+    let (tick_operators, bang_operators) = default_tables();
+    let mut context = context_from(GRID);
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let before = Arc::clone(&seen);
+    context.hooks.before_tick(move |events| before.lock().unwrap().push(("before", events.tick, events.notes.len())));
+    let after = Arc::clone(&seen);
+    context.hooks.after_tick(move |events| after.lock().unwrap().push(("after", events.tick, events.notes.len())));
+    run_headless(&mut context, &tick_operators, &bang_operators, 2);
+    assert_eq!(*seen.lock().unwrap(), [("before", 0, 0), ("after", 0, 1), ("before", 1, 1), ("after", 1, 1)]);
+
+    context.hooks.clear();
+    run_headless(&mut context, &tick_operators, &bang_operators, 1);
+    assert_eq!(seen.lock().unwrap().len(), 4);
+}
@@ -0,0 +1,56 @@
+use rust_orca::keys::{Key, KeyInput, Modifiers, describe_typed, key_presses, typed_char};
+use rust_orca::test_util::default_tables;
+
+const ALT: Modifiers = Modifiers { shift: false, control: false, alt: true };
+const ALTGR: Modifiers = Modifiers { shift: false, control: true, alt: true };
+
+fn text(text: &str) -> KeyInput<()> {
+    KeyInput::Text(text.to_string())
+}
+
+#[test]
+fn altgr_keeps_the_character_the_layout_typed() {
+    // AltGr+Q on a German layout types @
+    let keys = key_presses([KeyInput::Press(Key::Char('q'), ALTGR), text("@")]);
+    assert_eq!(keys, vec![(Key::Char('@'), Modifiers::NONE)]);
+    assert_eq!(typed_char('@', ALTGR), Some('@'));
+}
+
+#[test]
+fn control_and_alt_without_text_stay_a_chord() {
+    let keys = key_presses([KeyInput::Press(Key::Char('q'), ALTGR), KeyInput::Press(Key::Other(()), Modifiers::NONE)]);
+    assert_eq!(keys, vec![(Key::Char('q'), ALTGR), (Key::Other(()), Modifiers::NONE)]);
+    // letters held with both are shortcuts rather than typed
+    assert_eq!(typed_char('q', ALTGR), None);
+    assert_eq!(key_presses::<()>([KeyInput::Press(Key::Char('q'), ALTGR)]), vec![(Key::Char('q'), ALTGR)]);
+}
+
+#[test]
+fn text_typed_with_alt_is_dropped() {
+    let keys = key_presses([KeyInput::Press(Key::Char('v'), ALT), text("√"), text("a")]);
+    assert_eq!(keys, vec![(Key::Char('v'), ALT), (Key::Char('a'), Modifiers::NONE)]);
+    assert_eq!(typed_char('v', ALT), None);
+}
+
+#[test]
+fn composed_characters_come_through_as_typed() {
+    // a dead key then e, and a capital from an input method
+    let keys = key_presses([KeyInput::<()>::Composed("é".to_string()), KeyInput::Composed("Ü".to_string())]);
+    let shifted = Modifiers { shift: true, ..Modifiers::NONE };
+    assert_eq!(keys, vec![(Key::Char('é'), Modifiers::NONE), (Key::Char('Ü'), shifted)]);
+    assert_eq!(typed_char('Ü', shifted), Some('Ü'));
+}
+
+#[test]
+fn composed_text_after_an_alt_chord_is_kept() {
+    let keys = key_presses::<()>([KeyInput::Press(Key::Char('v'), ALT), KeyInput::Composed("ñ".to_string())]);
+    assert_eq!(keys, vec![(Key::Char('v'), ALT), (Key::Char('ñ'), Modifiers::NONE)]);
+}
+
+#[test]
+fn typed_characters_are_described() {
+    let (tick_operators, _) = default_tables();
+    assert_eq!(describe_typed("ctrl+alt+e", '€', &tick_operators), "ctrl+alt+e types € (U+20AC)");
+    assert_eq!(describe_typed("A", 'A', &tick_operators), "A types A (U+0041), the Add operator");
+    assert_eq!(describe_typed("😀", '😀', &tick_operators), "😀 types 😀 (U+1F600), which is wider than a cell");
+}
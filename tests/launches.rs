@@ -0,0 +1,74 @@
+use rust_orca::history::History;
+use rust_orca::test_util::{context_from, default_tables};
+use rust_orca::{NoMidi, Selection, grid_to_string, tick};
+
+#[test]
+fn held_back_edits_land_on_the_next_launch() {
+    let (tick_operators, bang_operators) = default_tables();
+    let mut context = context_from("1A2\n...\n");
+    context.launch_beats = 1;
+    context.set_divisions(2);
+    context.ticks = 1;
+    context.hold_back(|context| {
+        context.erase(Selection { row: 0, col: 0, rows: 1, cols: 3 });
+        context.set_tempo(140);
+    });
+    // the grid and tempo are untouched until then
+    assert_eq!(grid_to_string(context.grid(), context.width), "1A2\n...\n");
+    assert_eq!((context.tempo, context.launches.len()), (120, 1));
+
+    tick(&mut context, &tick_operators, &bang_operators, &mut NoMidi);
+    assert_eq!(grid_to_string(context.grid(), context.width), "1A2\n.3.\n");
+    tick(&mut context, &tick_operators, &bang_operators, &mut NoMidi);
+    assert_eq!(grid_to_string(context.grid(), context.width), "...\n.3.\n");
+    assert_eq!(context.tempo, 140);
+    assert!(context.launches.is_empty());
+}
+
+#[test]
+fn launched_edits_are_undone_one_at_a_time() {
+    let (tick_operators, bang_operators) = default_tables();
+    let mut context = context_from("....\n");
+    context.history = History::new(64, 16);
+    context.launch_beats = 4;
+    context.hold_back(|context| context.edit(0, 0, 'a'));
+    context.hold_back(|context| context.edit(0, 1, 'b'));
+    // an edit that changes nothing isn't queued
+    context.hold_back(|context| context.edit(0, 2, '\0'));
+    assert_eq!(context.launches.len(), 2);
+    tick(&mut context, &tick_operators, &bang_operators, &mut NoMidi);
+    assert_eq!(grid_to_string(context.grid(), context.width), "ab..\n");
+    assert!(context.undo());
+    assert_eq!(grid_to_string(context.grid(), context.width), "a...\n");
+}
+
+#[test]
+fn fresh_launches_start_the_history_over() {
+    let (tick_operators, bang_operators) = default_tables();
+    let mut context = context_from("....\n");
+    context.history = History::new(64, 16);
+    context.launch_beats = 4;
+    context.edit(0, 3, 'z');
+    context.hold_back(|context| {
+        context.replace_grid("ab..".chars().map(|c| if c == '.' { '\0' } else { c }).collect());
+        context.set_divisions(8);
+    });
+    context.launches[0].fresh = true;
+    assert_eq!((context.divisions, context.launched), (4, 0));
+
+    tick(&mut context, &tick_operators, &bang_operators, &mut NoMidi);
+    assert_eq!(grid_to_string(context.grid(), context.width), "ab..\n");
+    assert_eq!((context.divisions, context.launched), (8, 1));
+    // neither the grid opened nor the edit before it can be undone
+    assert!(!context.undo());
+}
+
+#[test]
+fn launches_too_far_apart_to_count_wait_without_overflowing() {
+    let mut context = context_from("...\n");
+    context.launch_beats = u64::MAX;
+    context.set_divisions(8);
+    assert!(context.is_launch_tick());
+    context.ticks = 8;
+    assert!(!context.is_launch_tick());
+}
@@ -0,0 +1,46 @@
+use rust_orca::test_util::{context_from, default_tables};
+use rust_orca::{Context, grid_to_string, run_headless};
+
+// ticks each grid once, each reading the others as the tick before left them
+fn tick_together(contexts: &mut [Context]) {
+    let (tick_operators, bang_operators) = default_tables();
+    let layers: Vec<_> = contexts.iter().zip(['0', '1']).map(|(context, name)| context.layer(name)).collect();
+    for context in contexts {
+        context.layers.clone_from(&layers);
+        run_headless(context, &tick_operators, &bang_operators, 1);
+    }
+}
+
+#[test]
+fn layers_read_cells_of_another_grid() {
+    let mut contexts = [context_from("...\n.7.\n"), context_from("011@\n....\n")];
+    tick_together(&mut contexts);
+    assert_eq!(grid_to_string(contexts[1].grid(), contexts[1].width), "011@\n...7\n");
+}
+
+#[test]
+fn bangs_in_a_conductor_grid_bang_the_grid_reading_it() {
+    // the conductor bangs every other frame, and the notes grid bangs the tick after it does
+    let mut contexts = [context_from("D2\n..\n"), context_from("001@\n....\n")];
+    let mut banged = Vec::new();
+    for tick in 0..6 {
+        tick_together(&mut contexts);
+        if contexts[1].read(1, 3) == '*' {
+            banged.push(tick);
+        }
+    }
+    assert_eq!(banged, [1, 3, 5]);
+}
+
+#[test]
+fn unknown_layers_and_cells_outside_them_read_as_empty() {
+    let mut contexts = [context_from("7.\n..\n"), context_from("2001@\n.....\n500@.\n.....\n")];
+    tick_together(&mut contexts);
+    assert_eq!(grid_to_string(contexts[1].grid(), contexts[1].width), "2001@\n.....\n500@.\n.....\n");
+
+    // a grid playing alone has no layers to read
+    let mut context = context_from("000@\n....\n");
+    let (tick_operators, bang_operators) = default_tables();
+    run_headless(&mut context, &tick_operators, &bang_operators, 1);
+    assert_eq!(context.read(1, 3), '\0');
+}
@@ -0,0 +1,29 @@
+use rust_orca::run_headless;
+use rust_orca::test_util::{context_from, default_tables};
+use rust_orca::udp::osc_message;
+
+fn messages(patch: &str) -> Vec<String> {
+    let (tick_operators, bang_operators) = default_tables();
+    let mut context = context_from(patch);
+    run_headless(&mut context, &tick_operators, &bang_operators, 1);
+    context.messages.clone()
+}
+
+#[test]
+fn messages_are_sent_when_banged() {
+    // without a length the text runs up to the first empty cell
+    assert_eq!(messages(".D1....\n.......\n.;hi.x.\n"), ["hi"]);
+    assert_eq!(messages(".D1....\n.......\n5;hi.x.\n"), ["hi x "]);
+    assert!(messages(".......\n.......\n.;hi.x.\n").is_empty());
+}
+
+#[test]
+fn messages_substitute_variables() {
+    assert_eq!(messages("aV5D1....\n.........\n..6;note.a\n"), ["note 5"]);
+}
+
+#[test]
+fn osc_messages_are_typed_and_padded() {
+    assert_eq!(osc_message("play 3 x"), b"/play\0\0\0,is\0\0\0\0\x03x\0\0\0");
+    assert_eq!(osc_message("/a 0.5"), b"/a\0\0,f\0\0\x3f\0\0\0");
+}
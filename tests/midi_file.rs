@@ -0,0 +1,19 @@
+use rust_orca::MidiCapture;
+
+#[test]
+fn captures_encode_as_standard_midi_files() {
+    let messages = vec![(0, [0x90, 60, 100]), (200, [0x80, 60, 0]), (16584, [0x90, 62, 1])];
+    let capture = MidiCapture { tick: 0, messages };
+    let mut expected = vec![];
+    // the header: format 0, one track, 4 ticks to a beat
+    expected.extend(b"MThd\0\0\0\x06\0\0\0\x01\0\x04");
+    expected.extend(b"MTrk\0\0\0\x1a");
+    // 500000 microseconds to a beat is 120 bpm
+    expected.extend([0x00, 0xff, 0x51, 0x03, 0x07, 0xa1, 0x20]);
+    expected.extend([0x00, 0x90, 60, 100]);
+    // 200 ticks later takes two bytes, and 16384 takes three
+    expected.extend([0x81, 0x48, 0x80, 60, 0]);
+    expected.extend([0x81, 0x80, 0x00, 0x90, 62, 1]);
+    expected.extend([0x00, 0xff, 0x2f, 0x00]);
+    assert_eq!(capture.to_midi_file(4, 120), expected);
+}
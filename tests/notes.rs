@@ -0,0 +1,22 @@
+use rust_orca::notes::{cell_note_name, cell_note_number, note_name, note_number};
+
+#[test]
+fn cells_name_the_pitches_they_play() {
+    assert_eq!(cell_note_number('3', 'C'), Some(48));
+    assert_eq!(cell_note_name('3', 'C').as_deref(), Some("C3"));
+    // lowercase is sharp, and notes past G climb into the next octave
+    assert_eq!(cell_note_name('4', 'c').as_deref(), Some("C#4"));
+    assert_eq!(cell_note_name('3', 'H').as_deref(), Some("A4"));
+    // a sharp E is an F
+    assert_eq!(cell_note_name('3', 'e').as_deref(), Some("F3"));
+    assert_eq!(cell_note_number('3', '5'), None);
+    assert_eq!(cell_note_number('z', 'Z'), Some(127));
+}
+
+#[test]
+fn note_numbers_are_named_in_scientific_pitch() {
+    assert_eq!(note_name(60), "C4");
+    assert_eq!(note_name(0), "C-1");
+    assert_eq!(note_name(127), "G9");
+    assert_eq!(note_name(note_number(2, 13, true)), "D#2");
+}
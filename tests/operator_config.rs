@@ -0,0 +1,83 @@
+use rust_orca::operators::{default_operator_config, get_bang_operators, get_tick_operators, parse_operator_config};
+use rust_orca::test_util::{assert_grid_intact, context_from, default_tables};
+use rust_orca::{grid_to_string, run_headless};
+
+#[test]
+fn operators_bind_to_unicode_symbols() {
+    let operator_map = parse_operator_config("♥ Add\n· Delay").unwrap();
+    let (tick_operators, bang_operators) = (get_tick_operators(&operator_map), get_bang_operators(&operator_map));
+    assert_eq!(tick_operators.get('♥').map(|operator| operator.name()), Some("Add"));
+    assert_eq!(tick_operators.iter().map(|(symbol, _)| symbol).collect::<String>(), "♥·");
+
+    let mut context = context_from("1♥2\n...");
+    run_headless(&mut context, &tick_operators, &bang_operators, 1);
+    assert_grid_intact(&context);
+    assert_eq!(grid_to_string(context.grid(), context.width), "1♥2\n.3.\n");
+}
+
+#[test]
+fn symbols_wider_than_a_cell_are_refused() {
+    for config in ["漢 Add", "\u{301} Add", ". Add", "* Add"] {
+        let err = parse_operator_config(config).unwrap_err();
+        assert!(err.to_string().contains("can not be an operator's symbol"), "{}: {}", config, err);
+    }
+}
+
+#[test]
+fn inputs_fall_back_on_configured_defaults() {
+    let (mut tick_operators, mut bang_operators) = default_tables();
+    for table in [&mut tick_operators, &mut bang_operators] {
+        table.set_port_default("Add", "b", '5').unwrap();
+    }
+    let mut context = context_from("1A.\n...");
+    run_headless(&mut context, &tick_operators, &bang_operators, 1);
+    assert_eq!(grid_to_string(context.grid(), context.width), "1A.\n.6.\n");
+
+    let ports = tick_operators.get('A').unwrap().ports(&context_from("1A."), 0, 1);
+    assert_eq!(ports.inputs.iter().map(|port| port.value).collect::<String>(), "15");
+    // a value in the cell still wins
+    let mut context = context_from("1A2\n...");
+    run_headless(&mut context, &tick_operators, &bang_operators, 1);
+    assert_eq!(grid_to_string(context.grid(), context.width), "1A2\n.3.\n");
+}
+
+#[test]
+fn defaults_for_unknown_inputs_are_refused() {
+    let (mut tick_operators, _) = default_tables();
+    assert!(tick_operators.set_port_default("Random", "max", 'g').is_ok());
+    assert!(tick_operators.set_port_default("Random", "top", 'g').unwrap_err().to_string().contains("no input top"));
+    assert!(tick_operators.set_port_default("Dance", "max", 'g').unwrap_err().to_string().contains("unknown operator"));
+}
+
+#[test]
+fn operators_bind_to_several_symbols() {
+    let operator_map = parse_operator_config("A Add\n+ Add\nD Delay\nD -\nB Sub\n+ Multiply").unwrap();
+    assert_eq!(operator_map["Add"], ['A']);
+    assert_eq!(operator_map["Multiply"], ['+']);
+    assert!(!operator_map.contains_key("Delay"));
+
+    let operator_map = parse_operator_config("A Add\n+ Add").unwrap();
+    let (tick_operators, bang_operators) = (get_tick_operators(&operator_map), get_bang_operators(&operator_map));
+    assert_eq!(tick_operators.iter().map(|(symbol, operator)| (symbol, operator.name())).collect::<Vec<_>>(), [
+        ('+', "Add"),
+        ('A', "Add"),
+    ]);
+    let mut context = context_from("1A2\n...\n1+2\n...");
+    run_headless(&mut context, &tick_operators, &bang_operators, 1);
+    assert_eq!(grid_to_string(context.grid(), context.width), "1A2\n.3.\n1+2\n.3.\n");
+}
+
+#[test]
+fn the_registry_describes_each_operator_once() {
+    let operator_map = parse_operator_config("A Add\n+ Add\n: Midi").unwrap();
+    let registry = get_tick_operators(&operator_map).registry();
+    assert_eq!(registry.len(), 2);
+    let add = registry.iter().find(|info| info.name == "Add").unwrap();
+    assert_eq!(add.symbols, ['+', 'A']);
+    assert!(!add.bang_only);
+    let inputs: Vec<_> = add.inputs.iter().map(|port| (port.name, port.row, port.col, port.default)).collect();
+    assert_eq!(inputs, [("a", 0, -1, Some('0')), ("b", 0, 1, Some('0'))]);
+    assert_eq!(add.outputs.iter().map(|port| (port.row, port.col, port.default)).collect::<Vec<_>>(), [(1, 0, None)]);
+    assert!(registry.iter().find(|info| info.name == "Midi").unwrap().bang_only);
+    assert_eq!(default_tables().0.registry().len(), default_operator_config().len());
+}
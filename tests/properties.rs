@@ -0,0 +1,72 @@
+use proptest::prelude::*;
+
+use rust_orca::Context;
+use rust_orca::operators::{base_36_to_char, char_to_base_36, default_operator_config, grid_tick};
+use rust_orca::test_util::{assert_grid_intact, assert_tick_is_fresh, default_tables};
+
+// the values operators read, with enough empty cells for operators to move into
+const VALUES: &str = "..........0123456789abcdefz*";
+
+// every operator and the values they read, in the same order every run so that a failing case
+// can be replayed
+fn cells() -> Vec<char> {
+    let mut symbols: Vec<char> = default_operator_config().into_values().flatten().collect();
+    symbols.sort_unstable();
+    VALUES.chars().map(|c| if c == '.' { '\0' } else { c }).chain(symbols).collect()
+}
+
+fn grid() -> impl Strategy<Value = (usize, usize, Vec<char>)> {
+    (1..12usize, 1..12usize).prop_flat_map(|(rows, cols)| {
+        let cell = prop::sample::select(cells());
+        (Just(rows), Just(cols), prop::collection::vec(cell, rows * cols))
+    })
+}
+
+proptest! {
+    #[test]
+    fn writes_stay_inside_the_grid((rows, cols, cells) in grid(), ticks in 0..16usize, seed: u64) {
+        let (tick_operators, bang_operators) = default_tables();
+        let mut context = Context::new(cells, cols, rows, 120, 4);
+        context.reseed(seed);
+        for _ in 0..ticks {
+            grid_tick(&mut context, &tick_operators, &bang_operators);
+            assert_grid_intact(&context);
+            prop_assert_eq!((context.height, context.width), (rows, cols));
+        }
+    }
+
+    #[test]
+    fn locks_do_not_leak_across_ticks((rows, cols, cells) in grid(), ticks in 1..16usize, seed: u64) {
+        let (tick_operators, bang_operators) = default_tables();
+        let mut context = Context::new(cells, cols, rows, 120, 4);
+        context.reseed(seed);
+        for _ in 0..ticks {
+            assert_tick_is_fresh(&mut context, &tick_operators, &bang_operators);
+        }
+    }
+
+    #[test]
+    fn base_36_values_round_trip(value in 0..36u8, upper: bool) {
+        // only letters have a case
+        prop_assert_eq!(char_to_base_36(base_36_to_char(value, upper)), (value, upper && value >= 10));
+    }
+
+    #[test]
+    fn base_36_glyphs_round_trip(glyph in "[0-9a-zA-Z]") {
+        let glyph = glyph.chars().next().unwrap();
+        let (value, upper) = char_to_base_36(glyph);
+        prop_assert_eq!(base_36_to_char(value, upper), glyph);
+    }
+}
+
+// a case the properties once shrank a failure to: a southward mover on a single row, with a
+// westward one moving into its cell
+#[test]
+fn movers_at_the_edge_of_a_single_row() {
+    let (tick_operators, bang_operators) = default_tables();
+    let mut context = Context::new(vec!['S', 'W'], 2, 1, 120, 4);
+    for _ in 0..2 {
+        assert_tick_is_fresh(&mut context, &tick_operators, &bang_operators);
+        assert_grid_intact(&context);
+    }
+}
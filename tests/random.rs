@@ -0,0 +1,60 @@
+use rust_orca::run_headless;
+use rust_orca::test_util::{context_from, default_tables};
+
+// the values a weighted operator draws over many seeds, from 0 to 9
+fn draws(patch: &str, tables: &[(char, &str)]) -> Vec<char> {
+    let (tick_operators, bang_operators) = default_tables();
+    (0..400).map(|seed| {
+        let mut context = context_from(patch);
+        context.random_tables = tables.iter().map(|&(shape, values)| (shape, values.chars().collect())).collect();
+        context.reseed(seed);
+        run_headless(&mut context, &tick_operators, &bang_operators, 1);
+        context.grid()[context.width + 2]
+    }).collect()
+}
+
+fn mean(draws: &[char]) -> f64 {
+    draws.iter().map(|draw| draw.to_digit(36).unwrap() as f64).sum::<f64>() / draws.len() as f64
+}
+
+#[test]
+fn shapes_weight_the_draws() {
+    let (low, even, high) = (draws("l0~a\n....", &[]), draws("e0~a\n....", &[]), draws("h0~a\n....", &[]));
+    for draws in [&low, &even, &high] {
+        assert!(draws.iter().all(|&draw| draw.is_ascii_digit()), "{:?}", draws);
+    }
+    assert!(mean(&low) < mean(&even) - 1.0 && mean(&even) < mean(&high) - 1.0);
+    // an empty shape draws low
+    assert_eq!(draws(".0~a\n....", &[]), low);
+
+    let middle = draws("m0~a\n....", &[]);
+    let inner = middle.iter().filter(|&&draw| ('3'..='6').contains(&draw)).count();
+    assert!(inner > even.iter().filter(|&&draw| ('3'..='6').contains(&draw)).count() + 40);
+}
+
+#[test]
+fn tables_are_drawn_from_by_weight() {
+    let draws = draws("b0~a\n....", &[('b', "0007")]);
+    let zeros = draws.iter().filter(|&&draw| draw == '0').count();
+    assert!(draws.iter().all(|&draw| draw == '0' || draw == '7'));
+    assert!((250..350).contains(&zeros), "{}", zeros);
+}
+
+#[test]
+fn seeded_streams_follow_their_seed() {
+    let (tick_operators, bang_operators) = default_tables();
+    let run = |patch: &str, seed| {
+        let mut context = context_from(patch);
+        context.reseed(seed);
+        (0..8).map(|_| {
+            run_headless(&mut context, &tick_operators, &bang_operators, 1);
+            let grid = context.grid();
+            (grid[context.width + 2], grid[context.width * 3 + 5])
+        }).collect::<Vec<_>>()
+    };
+    // the same seed gives the same stream anywhere on the grid and whatever the grid's seed
+    let streams = run("30&z...\n.......\n...30&z\n.......\n", 1);
+    assert!(streams.iter().all(|(a, b)| a == b), "{:?}", streams);
+    assert_eq!(run("30&z...\n.......\n...30&z\n.......\n", 2), streams);
+    assert_ne!(run("30&z...\n.......\n...40&z\n.......\n", 1), streams);
+}
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use gif::{ColorOutput, DecodeOptions};
+use rust_orca::recording::{Recording, lzw_compress};
+use weezl::decode::Decoder;
+use weezl::BitOrder;
+
+// decodes each frame of a gif to its palette indices, with its delay
+fn decode_gif(bytes: &[u8]) -> Vec<(u16, Vec<u8>)> {
+    let mut options = DecodeOptions::new();
+    options.set_color_output(ColorOutput::Indexed);
+    options.check_frame_consistency(true);
+    let mut decoder = options.read_info(bytes).unwrap();
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        frames.push((frame.delay, frame.buffer.to_vec()));
+    }
+    frames
+}
+
+#[test]
+fn compressed_pixels_decode_back_up_to_the_end_code() {
+    // a cheap generator, so that the codes grow at many different points
+    let mut seed = 7u32;
+    let mut pixels = Vec::new();
+    for _ in 0..40000 {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        // few colors, as drawn frames have, make for long codes
+        pixels.push((seed >> 28) as u8 % 3);
+    }
+    // the longest is long enough for the table to fill up and start over
+    for len in (0..1000).chain([pixels.len()]) {
+        let mut decoded = Vec::new();
+        let result = Decoder::new(BitOrder::Lsb, 3).into_vec(&mut decoded).decode_all(&lzw_compress(&pixels[..len]));
+        // decoding everything fails unless it finishes on an end code of the width it expects
+        assert!(result.status.is_ok(), "{} pixels: {:?}", len, result.status);
+        assert_eq!(decoded, pixels[..len], "{} pixels", len);
+    }
+}
+
+#[test]
+fn gifs_decode_to_their_frames() {
+    let mut recording = Recording::new(1, 1);
+    recording.capture(Duration::ZERO, &['\0']);
+    recording.capture(Duration::from_millis(250), &['*']);
+    let frames = decode_gif(&recording.to_gif(Duration::ZERO, Duration::from_millis(500)));
+    assert_eq!(frames.iter().map(|(delay, _)| *delay).collect::<Vec<_>>(), [25, 25]);
+    // an empty cell is a dot at the bottom of its glyph, two pixels square
+    let (width, dot) = (8, 1);
+    let mut empty = vec![0; width * 12];
+    for (row, col) in [(8, 2), (8, 3), (9, 2), (9, 3)] {
+        empty[row * width + col] = dot;
+    }
+    assert_eq!(frames[0].1, empty);
+    assert_ne!(frames[1].1, empty);
+}
@@ -0,0 +1,117 @@
+#![cfg(unix)]
+
+use std::env::temp_dir;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread::{self, JoinHandle, sleep};
+use std::time::Duration;
+
+use rust_orca::daemon::Daemon;
+use rust_orca::midi::NoMidi;
+use rust_orca::repl::{Repl, parse_command};
+use rust_orca::test_util::{context_from, default_tables};
+use serde_json::json;
+
+fn serve(name: &str) -> (PathBuf, JoinHandle<()>) {
+    let socket = temp_dir().join(format!("rust-orca-repl-{}-{}.sock", name, process::id()));
+    let (tick_operators, bang_operators) = default_tables();
+    let daemon = Daemon::new(context_from("....\n...."), tick_operators, bang_operators, Box::new(NoMidi));
+    let path = socket.clone();
+    (socket, thread::spawn(move || daemon.serve(&path).unwrap()))
+}
+
+fn connect(socket: &Path) -> Repl {
+    for _ in 0..200 {
+        if let Ok(repl) = Repl::connect(socket) {
+            return repl;
+        }
+        sleep(Duration::from_millis(10));
+    }
+    panic!("the daemon never started listening");
+}
+
+// pauses the clock, giving the tick it stopped before once a tick already under way has run
+fn pause(repl: &mut Repl) -> usize {
+    assert_eq!(repl.eval("pause").unwrap(), "ok");
+    sleep(Duration::from_millis(200));
+    repl.eval("status").unwrap().split("ticks ").nth(1).unwrap().parse().unwrap()
+}
+
+// steps run on the clock, after the answer to the step that asked for them
+fn wait_for(repl: &mut Repl, line: &str, expected: &str) {
+    for _ in 0..200 {
+        if repl.eval(line).unwrap() == expected {
+            return;
+        }
+        sleep(Duration::from_millis(10));
+    }
+    panic!("{} never gave {}, it gave {}", line, expected, repl.eval(line).unwrap());
+}
+
+#[test]
+fn commands_become_daemon_requests() {
+    assert_eq!(parse_command("write 1 2 D4").unwrap(), json!({"cmd": "write", "row": 1, "col": 2, "text": "D4"}));
+    assert_eq!(parse_command("  set a 3 ").unwrap(), json!({"cmd": "variable", "name": "a", "value": "3"}));
+    assert_eq!(parse_command("unset a").unwrap(), json!({"cmd": "variable", "name": "a"}));
+    assert_eq!(
+        parse_command("in 4 bpm 140").unwrap(),
+        json!({"cmd": "schedule", "after": 4, "request": {"cmd": "bpm", "bpm": 140}})
+    );
+    assert_eq!(parse_command(r#"{"cmd": "read"}"#).unwrap(), json!({"cmd": "read"}));
+
+    assert_eq!(parse_command("write 1 D4").unwrap_err().to_string(), "usage: write ROW COL TEXT");
+    assert_eq!(parse_command("set ab 3").unwrap_err().to_string(), "usage: set NAME VALUE");
+    assert_eq!(parse_command("at soon play").unwrap_err().to_string(), "usage: at TICK COMMAND");
+    assert_eq!(parse_command("dance").unwrap_err().to_string(), "unknown command dance, try help");
+    assert!(parse_command(r#"{"cmd": "subscribe"}"#).is_err());
+}
+
+#[test]
+fn cells_and_variables_are_livecoded_while_the_grid_plays() {
+    let (socket, daemon) = serve("cells");
+    let mut repl = connect(&socket);
+    pause(&mut repl);
+    assert_eq!(repl.eval("write 0 1 Va").unwrap(), "ok");
+    assert_eq!(repl.eval("read").unwrap(), ".Va.\n....");
+    assert_eq!(repl.eval("read 0 2").unwrap(), "a");
+    assert_eq!(repl.eval("read 9 0").unwrap(), "error: no cell at row 9 col 0");
+
+    // a variable set from outside lasts through every tick, until it is unset
+    assert_eq!(repl.eval("set a 3").unwrap(), "ok");
+    assert_eq!(repl.eval("vars").unwrap(), "a 3");
+    repl.eval("step 2").unwrap();
+    wait_for(&mut repl, "read 1 1", "3");
+    assert_eq!(repl.eval("unset a").unwrap(), "ok");
+    assert_eq!(repl.eval("vars").unwrap(), "no variables");
+    repl.eval("step").unwrap();
+    wait_for(&mut repl, "read 1 1", ".");
+
+    assert_eq!(repl.eval("shutdown").unwrap(), "ok");
+    daemon.join().unwrap();
+}
+
+#[test]
+fn scheduled_commands_wait_for_their_tick() {
+    let (socket, daemon) = serve("schedule");
+    let mut repl = connect(&socket);
+    let now = pause(&mut repl);
+
+    assert_eq!(repl.eval("in 2 write 0 0 7").unwrap(), format!("tick {}", now + 2));
+    assert_eq!(repl.eval(&format!("at {} bpm 90", now + 1)).unwrap(), format!("tick {}", now + 1));
+    assert_eq!(repl.eval("in 5 play").unwrap(), "error: only write, variable and bpm requests can be scheduled");
+    assert!(repl.eval("in 1 write 9 0 7").unwrap().starts_with("error: no cell"));
+    assert!(repl.eval("status").unwrap().contains("scheduled 2"));
+
+    repl.eval("step").unwrap();
+    wait_for(&mut repl, "status", &format!("bpm 90, cols 4, paused true, rows 2, scheduled 1, ticks {}", now + 1));
+    assert_eq!(repl.eval("read 0 0").unwrap(), ".");
+    repl.eval("step").unwrap();
+    wait_for(&mut repl, "read 0 0", "7");
+    assert!(repl.eval(&format!("at {} write 0 0 1", now + 2)).unwrap().contains("has already passed"));
+
+    assert_eq!(repl.eval("in 4 write 0 0 1").unwrap(), format!("tick {}", now + 6));
+    assert_eq!(repl.eval("cancel").unwrap(), "ok");
+    assert!(repl.eval("status").unwrap().contains("scheduled 0"));
+    repl.eval("shutdown").unwrap();
+    daemon.join().unwrap();
+}
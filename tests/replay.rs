@@ -0,0 +1,53 @@
+use rust_orca::replay::{Change, Replayer, SessionRecorder, parse_session, session_to_string};
+use rust_orca::test_util::{context_from, default_tables};
+use rust_orca::tick;
+use rust_orca::NoMidi;
+
+#[test]
+fn frame_changes_and_remaps_replay_on_their_ticks() {
+    let (tick_operators, bang_operators) = default_tables();
+    let mut context = context_from("1A2\n...\n");
+    context.set_divisions(4);
+    let mut recorder = SessionRecorder::new(&context);
+    for ticks in 0..6 {
+        if ticks == 2 {
+            context.set_divisions(8);
+        }
+        if ticks == 4 {
+            recorder.command(context.ticks, "remap Add +");
+        }
+        recorder.before_tick(&context);
+        tick(&mut context, &tick_operators, &bang_operators, &mut NoMidi);
+        recorder.after_tick(&context);
+    }
+
+    let session = parse_session(&session_to_string(&recorder.session)).unwrap();
+    assert_eq!(session, recorder.session);
+    assert_eq!(session.frames, Some(4));
+    assert!(session.changes.contains(&(2, Change::Frames(8))), "{:?}", session.changes);
+    assert!(session.changes.contains(&(4, Change::Command("remap Add +".to_string()))), "{:?}", session.changes);
+
+    // played back at another frame rate, the session still starts at its own
+    let mut replayed = session.to_context(16);
+    assert_eq!(replayed.divisions, 4);
+    let mut replayer = Replayer::new(session);
+    let mut commands = Vec::new();
+    for _ in 0..6 {
+        let due = replayer.before_tick(&mut replayed);
+        commands.extend(due.into_iter().map(|line| (replayed.ticks, line)));
+        tick(&mut replayed, &tick_operators, &bang_operators, &mut NoMidi);
+        if replayed.ticks == 3 {
+            assert_eq!(replayed.divisions, 8);
+        }
+    }
+    assert_eq!(commands, vec![(4, "remap Add +".to_string())]);
+}
+
+#[test]
+fn sessions_without_frames_still_read() {
+    let text = "rust-orca session\nsize 3x1\nbpm 120\nseed 0\ntick 0\ngrid\n1A2\nchanges\n3 frames 6\n";
+    let session = parse_session(text).unwrap();
+    assert_eq!(session.frames, None);
+    assert_eq!(session.changes, vec![(3, Change::Frames(6))]);
+    assert_eq!(session.to_context(4).divisions, 4);
+}
@@ -0,0 +1,36 @@
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use rust_orca::scheduler::Scheduler;
+
+const PERIOD: Duration = Duration::from_millis(4);
+
+#[test]
+fn keeps_time_however_long_ticks_take() {
+    let mut scheduler = Scheduler::new();
+    scheduler.wait(PERIOD);
+    let started = Instant::now();
+    for _ in 0..50 {
+        // a tick that takes most of its period
+        sleep(Duration::from_millis(3));
+        scheduler.wait(PERIOD);
+    }
+    let elapsed = started.elapsed();
+    assert!(elapsed > PERIOD * 49, "{:?}", elapsed);
+    // sleeping a period after each tick would take 350ms; late wakeups don't add up
+    assert!(elapsed < PERIOD * 60, "{:?}", elapsed);
+}
+
+#[test]
+fn starts_over_after_falling_behind() {
+    let mut scheduler = Scheduler::new();
+    scheduler.wait(PERIOD);
+    sleep(PERIOD * 10);
+    let woke = Instant::now();
+    scheduler.wait(PERIOD);
+    // the missed ticks are skipped rather than rushed through
+    assert!(woke.elapsed() < PERIOD, "{:?}", woke.elapsed());
+    let woke = Instant::now();
+    scheduler.wait(PERIOD);
+    assert!(woke.elapsed() > PERIOD / 2, "{:?}", woke.elapsed());
+}
@@ -0,0 +1,34 @@
+use rust_orca::run_headless;
+use rust_orca::test_util::{context_from, default_tables};
+
+#[test]
+fn explains_a_chain_of_writes() {
+    let (tick_operators, bang_operators) = default_tables();
+    let mut context = context_from("1A2.\n..A1\n....");
+    context.trace.enabled = true;
+    run_headless(&mut context, &tick_operators, &bang_operators, 1);
+    assert_eq!(context.trace.explain(2, 2), [
+        "row 3 column 3 on tick 0",
+        "  4 written by Add at row 2 column 3 from a 3 at row 2 column 2, b 1 at row 2 column 4",
+        "    3 written by Add at row 1 column 2 from a 1 at row 1 column 1, b 2 at row 1 column 3",
+        "  nothing read it",
+    ]);
+    assert_eq!(context.trace.explain(1, 1)[1..], [
+        "  3 written by Add at row 1 column 2 from a 1 at row 1 column 1, b 2 at row 1 column 3",
+        "  read by Add at row 2 column 3 as a",
+    ]);
+    assert_eq!(context.trace.explain(0, 3)[1..], ["  nothing wrote to it", "  nothing read it"]);
+}
+
+#[test]
+fn records_nothing_unless_enabled() {
+    let (tick_operators, bang_operators) = default_tables();
+    let mut context = context_from("1A2\n...");
+    run_headless(&mut context, &tick_operators, &bang_operators, 1);
+    assert!(context.trace.visits().is_empty());
+    context.trace.enabled = true;
+    run_headless(&mut context, &tick_operators, &bang_operators, 2);
+    // only the last tick is kept
+    assert_eq!(context.trace.visits().len(), 1);
+    assert_eq!(context.trace.tick, 2);
+}
@@ -0,0 +1,17 @@
+use rust_orca::test_util::run_default;
+
+#[test]
+fn columns_read_downward() {
+    assert_eq!(run_default("12^..\n..7..\n..8..\n", 1), "12^8.\n..7..\n..8..\n");
+    // the key wraps around the length
+    assert_eq!(run_default("22^..\n..7..\n..8..\n", 1), "22^7.\n..7..\n..8..\n");
+    // and matches track reading the same values across
+    assert_eq!(run_default("12T78\n.....\n", 1), "12T78\n..8..\n");
+}
+
+#[test]
+fn columns_lock_their_values() {
+    // the add inside the column is only read, while the one below it runs
+    assert_eq!(run_default("02^....\n..A12..\n.......\n", 1), "02^A...\n..A12..\n.......\n");
+    assert_eq!(run_default("01^....\n..7....\n..A12..\n.......\n", 1), "01^7...\n..7....\n..A12..\n..1....\n");
+}
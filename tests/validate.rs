@@ -0,0 +1,32 @@
+use rust_orca::test_util::{context_from, default_tables};
+use rust_orca::validate::validate;
+
+fn problems(text: &str) -> Vec<String> {
+    let (tick_operators, bang_operators) = default_tables();
+    validate(&context_from(text), &tick_operators, &bang_operators)
+        .into_iter()
+        .map(|problem| format!("{}:{} {}", problem.row + 1, problem.col + 1, problem.message))
+        .collect()
+}
+
+#[test]
+fn clean_grids_have_no_problems() {
+    assert!(problems(".D8.....\n..:03C..\n").is_empty());
+}
+
+#[test]
+fn finds_overwritten_outputs() {
+    // the jymp runs after the add, so the add's sum never shows
+    assert_eq!(problems(".1A2\n1Y..\n"), ["2:2 Jymp overwrites what the Add at row 1 column 3 writes to row 2 column 3"]);
+}
+
+#[test]
+fn finds_operators_nothing_bangs() {
+    assert_eq!(problems("......\n:03C..\n"), ["2:1 Midi is never banged; nothing next to it bangs"]);
+    assert!(problems("*.....\n:03C..\n").is_empty());
+}
+
+#[test]
+fn finds_ports_off_the_grid() {
+    assert_eq!(problems("A1\n.."), ["1:1 Add reads from outside the grid"]);
+}
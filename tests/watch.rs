@@ -0,0 +1,63 @@
+use std::env::temp_dir;
+use std::fs::remove_file;
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::Duration;
+
+use rust_orca::fs::write_grid;
+use rust_orca::history::History;
+use rust_orca::test_util::context_from;
+use rust_orca::watch::{Watched, watch_grid_file};
+
+// long enough for the watcher to have polled the file a few times
+const SETTLE: Duration = Duration::from_millis(800);
+
+#[test]
+fn saving_leaves_the_edits_and_undo_history_alone() {
+    let path = temp_dir().join(format!("rust-orca-watch-save-{}.orca", process::id()));
+    let mut context = context_from("...\n...\n");
+    context.history = History::new(1 << 16, 64);
+    write_grid(&path, context.grid(), context.width).unwrap();
+    let mut watched = Watched::default();
+    watched.path = Some(path.clone());
+    let watched = Arc::new(Mutex::new(watched));
+    let context_arc = Arc::new(Mutex::new(context));
+    watch_grid_file(Arc::clone(&watched), Arc::clone(&context_arc));
+    sleep(SETTLE);
+
+    {
+        let mut context = context_arc.lock().unwrap();
+        context.edit(0, 0, 'A');
+        write_grid(&path, context.grid(), context.width).unwrap();
+        watched.lock().unwrap().saved();
+        context.edit(1, 1, 'B');
+    }
+    sleep(SETTLE);
+
+    let mut context = context_arc.lock().unwrap();
+    assert_eq!(context.read(1, 1), 'B');
+    assert!(context.undo());
+    assert_eq!(context.read(1, 1), '\0');
+    assert!(context.undo());
+    assert_eq!(context.read(0, 0), '\0');
+    let _ = remove_file(&path);
+}
+
+#[test]
+fn changes_made_elsewhere_are_loaded() {
+    let path = temp_dir().join(format!("rust-orca-watch-load-{}.orca", process::id()));
+    let context = context_from("...\n...\n");
+    write_grid(&path, context.grid(), context.width).unwrap();
+    let mut watched = Watched::default();
+    watched.path = Some(path.clone());
+    let context_arc = Arc::new(Mutex::new(context));
+    watch_grid_file(Arc::new(Mutex::new(watched)), Arc::clone(&context_arc));
+    sleep(SETTLE);
+
+    write_grid(&path, &['C', '\0', '\0', '\0', '\0', '\0'], 3).unwrap();
+    sleep(SETTLE);
+
+    assert_eq!(context_arc.lock().unwrap().read(0, 0), 'C');
+    let _ = remove_file(&path);
+}